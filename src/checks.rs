@@ -0,0 +1,723 @@
+//! Checks run before changing the topology, so that the network keeps
+//! satisfying its constraints
+
+use std::collections::{HashMap, HashSet};
+
+use egui_graphs::Graph;
+use petgraph::{graph::EdgeIndex, stable_graph::NodeIndex, Undirected};
+use wg_2024::network::NodeId;
+
+use crate::{
+    format_pairs, is_connected, reachable_servers,
+    reconnect::{self, Candidate, Ranking},
+    validation::{ConnectionRejection, ControllerError},
+    widgets::{NodeWidget, WidgetType},
+    ClientKind, SimulationController,
+};
+
+impl SimulationController {
+    /// Function to check if an existing node can be a neighbor of a drone about to be spawned
+    ///
+    /// Any responsive node can, except the clients that already have 2 connections.
+    pub(crate) fn can_add_spawn_neighbor(
+        &self,
+        neighbor_idx: NodeIndex,
+    ) -> Result<(), ConnectionRejection> {
+        let widget = self.graph.node(neighbor_idx).unwrap().payload();
+        let neighbor_id = widget.get_id_helper();
+        if self.unresponsive_nodes.contains(&neighbor_id) {
+            return Err(ConnectionRejection::Unresponsive(widget.get_label_helper()));
+        }
+        match widget {
+            WidgetType::WebClient(_) => self
+                .can_client_add_sender(ClientKind::Web, neighbor_id)
+                .map(|_| ()),
+            WidgetType::ChatClient(_) => self
+                .can_client_add_sender(ClientKind::Chat, neighbor_id)
+                .map(|_| ()),
+            WidgetType::Drone(_) | WidgetType::Server(_) => Ok(()),
+        }
+    }
+
+    /// Function to validate the input of the user when adding a neighbor to a node
+    ///
+    /// The input should not be empty
+    /// The input should be a valid u8 number
+    /// The input should be a valid id of a node in the graph
+    pub(crate) fn validate_add_sender_input(
+        &self,
+        input_neighbor_id: &str,
+    ) -> Result<NodeIndex, ConnectionRejection> {
+        if input_neighbor_id.is_empty() {
+            return Err(ConnectionRejection::EmptyInput);
+        }
+
+        // Parse the input to u8, return error if parsing goes wrong
+        let Ok(neighbor_id) = input_neighbor_id.parse::<u8>() else {
+            return Err(ConnectionRejection::WrongIdFormat);
+        };
+
+        // From the u8 id, retrieve the corresponding NodeIndex in the graph
+        let Some(neighbor_idx) = self.get_node_idx(neighbor_id) else {
+            return Err(ConnectionRejection::UnknownId(neighbor_id));
+        };
+
+        Ok(neighbor_idx)
+    }
+
+    /// Function used to verify if a client can add a new sender
+    ///
+    /// A client can add a new sender if it has less than 2 connections
+    fn can_client_add_sender(
+        &self,
+        kind: ClientKind,
+        client_id: NodeId,
+    ) -> Result<u8, ConnectionRejection> {
+        let clients = self.clients(kind);
+        if let Some(client_pos) = clients.iter().position(|c| c.id == client_id) {
+            if clients[client_pos].connected_drone_ids.len() == 2 {
+                Err(ConnectionRejection::ClientAtMaxLinks(client_id))
+            } else {
+                Ok(client_id)
+            }
+        } else {
+            Err(ConnectionRejection::ClientNotFound(client_id))
+        }
+    }
+
+    /// Function to check that a node is not flagged as unresponsive
+    ///
+    /// Commands to unresponsive nodes are blocked until the user retries
+    fn check_responsive(&self, idx: NodeIndex) -> Result<(), ControllerError> {
+        let widget = self.graph.node(idx).unwrap().payload();
+        if self.unresponsive_nodes.contains(&widget.get_id_helper()) {
+            Err(ControllerError::Unresponsive(widget.get_label_helper()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Function to check if a sender can be added to a node
+    ///
+    /// It checks if the sender and the neighbor can be connected
+    /// based on the type of the nodes.
+    /// Drones can be connected to drones, clients and servers.
+    /// Clients can be connected only to drones. (max. 2 connections)
+    /// Servers can be connected only to drones.
+    pub(crate) fn can_add_sender(
+        &self,
+        source_idx: NodeIndex,
+        neighbor_idx: NodeIndex,
+    ) -> Result<(NodeIndex, NodeIndex), ConnectionRejection> {
+        for idx in [source_idx, neighbor_idx] {
+            let widget = self.graph.node(idx).unwrap().payload();
+            if self.unresponsive_nodes.contains(&widget.get_id_helper()) {
+                return Err(ConnectionRejection::Unresponsive(widget.get_label_helper()));
+            }
+        }
+
+        // Avoid creating a connection to itself
+        if source_idx == neighbor_idx {
+            let id = self
+                .graph
+                .node(source_idx)
+                .unwrap()
+                .payload()
+                .get_id_helper();
+            return Err(ConnectionRejection::SelfLoop(id));
+        }
+
+        match (
+            self.graph.node(source_idx).unwrap().payload(),
+            self.graph.node(neighbor_idx).unwrap().payload(),
+        ) {
+            (WidgetType::Drone(_), WidgetType::Drone(_)) => Ok((source_idx, neighbor_idx)),
+            // For clients, check if the client has reached its max number of connections (2)
+            (WidgetType::Drone(_), WidgetType::WebClient(web_client_widget))
+            | (WidgetType::WebClient(web_client_widget), WidgetType::Drone(_)) => {
+                let client_id = web_client_widget.id();
+
+                match self.can_client_add_sender(ClientKind::Web, client_id) {
+                    Ok(_) => Ok((source_idx, neighbor_idx)),
+                    Err(e) => Err(e),
+                }
+            }
+            // For clients, check if the client has reached its max number of connections (2)
+            (WidgetType::Drone(_), WidgetType::ChatClient(chat_client_widget))
+            | (WidgetType::ChatClient(chat_client_widget), WidgetType::Drone(_)) => {
+                let client_id = chat_client_widget.id();
+
+                match self.can_client_add_sender(ClientKind::Chat, client_id) {
+                    Ok(_) => Ok((source_idx, neighbor_idx)),
+                    Err(e) => Err(e),
+                }
+            }
+            (WidgetType::Drone(_), WidgetType::Server(_))
+            | (WidgetType::Server(_), WidgetType::Drone(_)) => Ok((source_idx, neighbor_idx)),
+            // Server can be connected to any number of drones, but not to other clients or servers
+            (WidgetType::Server(_), _) => Err(ConnectionRejection::ServerToNonDrone),
+
+            // Here I include all patterns like ChatClient/ChatClient, ChatClient/WebClient, ChatClient/Server.
+            // and all patterns like WebClient/WebClient, WebClient/ChatClient, WebClient/Server.
+            (
+                WidgetType::ChatClient(_) | WidgetType::WebClient(_),
+                WidgetType::ChatClient(_) | WidgetType::WebClient(_),
+            ) => Err(ConnectionRejection::ClientToClient),
+            (WidgetType::ChatClient(_) | WidgetType::WebClient(_), WidgetType::Server(_)) => {
+                Err(ConnectionRejection::ClientToServer)
+            }
+        }
+    }
+
+    /// Function to check every other node as a new neighbor of the node
+    /// `source_id`, with the checks of `TopologyOp::Connect`
+    ///
+    /// Returns the ids sorted, with the reason why each rejected node can't be
+    /// connected, or nothing if the node is not in the network.
+    pub(crate) fn neighbor_candidates(
+        &self,
+        source_id: NodeId,
+    ) -> Vec<(NodeId, Result<(), ConnectionRejection>)> {
+        let Some(source_idx) = self.get_node_idx(source_id) else {
+            return Vec::new();
+        };
+        let mut candidates = self
+            .graph
+            .nodes_iter()
+            .filter(|(idx, _)| *idx != source_idx)
+            .map(|(idx, node)| {
+                let id = node.payload().get_id_helper();
+                let check = if self.graph.edges_connecting(source_idx, idx).count() > 0 {
+                    Err(ConnectionRejection::AlreadyConnected(source_id, id))
+                } else {
+                    self.can_add_sender(source_idx, idx).map(|_| ())
+                };
+                (id, check)
+            })
+            .collect::<Vec<(NodeId, Result<(), ConnectionRejection>)>>();
+        candidates.sort_by_key(|(id, _)| *id);
+        candidates
+    }
+
+    /// Function to get the ids of the nodes that can be connected to the node
+    /// `source_id`, sorted
+    #[must_use]
+    pub fn eligible_neighbors(&self, source_id: NodeId) -> Vec<NodeId> {
+        self.neighbor_candidates(source_id)
+            .into_iter()
+            .filter(|(_, check)| check.is_ok())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Function that checks if the removal of the edge would make some servers/clients unreachable
+    /// Furthermore, it that checks if the graph would become disconnected if the edge is removed.
+    fn check_connectivity(&self, edge_to_remove: EdgeIndex) -> Result<(), ControllerError> {
+        let mut copy_graph = self.graph.clone();
+        copy_graph.remove_edge(edge_to_remove).unwrap();
+
+        self.check_reachability(&copy_graph)
+    }
+
+    /// Function to check that, in `graph`, every client can still reach every
+    /// server and the graph is still connected
+    fn check_reachability(
+        &self,
+        graph: &Graph<WidgetType, (), Undirected>,
+    ) -> Result<(), ControllerError> {
+        for client in self.all_clients() {
+            let client_idx = self.get_node_idx(client.id).unwrap();
+            if reachable_servers(graph, client_idx).len() != self.servers.len() {
+                return Err(ControllerError::ServerUnreachable(client.id));
+            }
+        }
+        if is_connected(graph) {
+            Ok(())
+        } else {
+            Err(ControllerError::WouldDisconnectGraph)
+        }
+    }
+
+    /// Function to check if a node can remove a sender
+    ///
+    /// For drones, they must have at least 1 connection, otherwise the graph becomes disconnected.
+    /// For clients, they must have at least 1 connection to a drone.
+    /// For servers, they must have at least 2 connections to drones.
+    fn can_remove_sender(&self, node_idx: NodeIndex) -> Result<u8, ControllerError> {
+        match self.graph.node(node_idx).unwrap().payload() {
+            // For drones I should check if they have at least 1 connection, otherwise the graph becomes disconnected
+            WidgetType::Drone(drone_widget) => {
+                let drone_id = drone_widget.id();
+                if let Some(pos) = self.drones.iter().position(|d| d.id == drone_id) {
+                    if self.drones.get(pos).unwrap().connected_node_ids.len() == 1 {
+                        Err(ControllerError::DroneMinConnections(drone_id))
+                    } else {
+                        Ok(drone_id)
+                    }
+                } else {
+                    Err(ControllerError::NodeNotFound(drone_id))
+                }
+            }
+            // For clients I should check that they are connected to at least 1 drone
+            WidgetType::WebClient(web_client_widget) => {
+                let client_id = web_client_widget.id();
+                if let Some(pos) = self.web_clients.iter().position(|c| c.id == client_id) {
+                    if self.web_clients.get(pos).unwrap().connected_drone_ids.len() == 1 {
+                        Err(ControllerError::ClientMinConnections(client_id))
+                    } else {
+                        Ok(client_id)
+                    }
+                } else {
+                    Err(ControllerError::NodeNotFound(client_id))
+                }
+            }
+            WidgetType::ChatClient(chat_client_widget) => {
+                let client_id = chat_client_widget.id();
+                if let Some(pos) = self.chat_clients.iter().position(|c| c.id == client_id) {
+                    if self
+                        .chat_clients
+                        .get(pos)
+                        .unwrap()
+                        .connected_drone_ids
+                        .len()
+                        == 1
+                    {
+                        Err(ControllerError::ClientMinConnections(client_id))
+                    } else {
+                        Ok(client_id)
+                    }
+                } else {
+                    Err(ControllerError::NodeNotFound(client_id))
+                }
+            }
+            WidgetType::Server(server_widget) => {
+                let server_id = server_widget.id();
+                if let Some(pos) = self.servers.iter().position(|s| s.id == server_id) {
+                    if self.servers.get(pos).unwrap().connected_drone_ids.len() == 2 {
+                        Err(ControllerError::ServerMinConnections(server_id))
+                    } else {
+                        Ok(server_id)
+                    }
+                } else {
+                    Err(ControllerError::NodeNotFound(server_id))
+                }
+            }
+        }
+    }
+
+    /// This function checks if an edge can be removed
+    /// First it checks if the graph would become disconnected.
+    /// The graph becomes disconnected if the removal of the edge would create more than 1 connected component.
+    /// Or if the removal of the edge would make a client unable to reach every server.
+    /// Then it checks if the nodes (endpoints of the edge) can remove each other.
+    /// For drones, they must have at least 1 connection, otherwise the graph becomes disconnected.
+    /// For clients, they must have at least 1 connection to a drone.
+    /// For servers, they must have at least 2 connections to drones.
+    /// If both endpoints violate a constraint, both errors are returned.
+    pub(crate) fn validate_edge_removal(
+        &mut self,
+        edge: EdgeIndex,
+    ) -> Result<(u8, u8), Vec<ControllerError>> {
+        // Check if without the edge, every client can still reach every server
+        self.check_connectivity(edge).map_err(|e| vec![e])?;
+
+        // Take the 2 endpoints of the edge to be removed
+        let (node_1, node_2) = self.graph.edge_endpoints(edge).unwrap();
+        self.check_responsive(node_1).map_err(|e| vec![e])?;
+        self.check_responsive(node_2).map_err(|e| vec![e])?;
+
+        match (
+            self.can_remove_sender(node_1),
+            self.can_remove_sender(node_2),
+        ) {
+            (Ok(id_1), Ok(id_2)) => Ok((id_1, id_2)),
+            (Ok(_), Err(e)) | (Err(e), Ok(_)) => Err(vec![e]),
+            (Err(e_1), Err(e_2)) => Err(vec![e_1, e_2]),
+        }
+    }
+
+    /// Function to find the clients and servers reachable from `start` through
+    /// the drones, without going through the node `excluded`
+    fn endpoints_reached(&self, start: NodeIndex, excluded: NodeIndex) -> HashSet<NodeId> {
+        let mut visited: HashSet<NodeIndex> = HashSet::from([excluded]);
+        let mut reached: HashSet<NodeId> = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            match self.graph.node(node).unwrap().payload() {
+                WidgetType::Drone(_) => stack.extend(self.graph.g.neighbors(node)),
+                widget => {
+                    reached.insert(widget.get_id_helper());
+                }
+            }
+        }
+        reached
+    }
+
+    /// Function to check if all the edges of a drone can be removed at once
+    ///
+    /// The connectivity is checked once for the isolated drone: the pairs that
+    /// would become unreachable are compared with the ones already unreachable.
+    /// On failure, every blocking edge is reported: the edges whose neighbor
+    /// can't lose a connection, and the edges leading to an endpoint of a pair
+    /// that would be cut.
+    /// Returns the ids of the neighbors to disconnect
+    pub(crate) fn validate_isolation(
+        &self,
+        drone_idx: NodeIndex,
+    ) -> Result<Vec<NodeId>, Vec<String>> {
+        self.check_responsive(drone_idx)
+            .map_err(|e| vec![e.to_string()])?;
+        let drone_id = self
+            .graph
+            .node(drone_idx)
+            .unwrap()
+            .payload()
+            .get_id_helper();
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(drone_idx)
+            .collect::<Vec<NodeIndex>>();
+
+        let already_cut = self
+            .unreachable_pairs(&HashSet::new())
+            .into_iter()
+            .collect::<HashSet<(NodeId, NodeId)>>();
+        let cut = self
+            .unreachable_pairs(&HashSet::from([drone_id]))
+            .into_iter()
+            .filter(|pair| !already_cut.contains(pair))
+            .collect::<Vec<(NodeId, NodeId)>>();
+        let cut_endpoints = cut
+            .iter()
+            .flat_map(|(client, server)| [*client, *server])
+            .collect::<HashSet<NodeId>>();
+
+        let mut blockers = Vec::new();
+        let mut neighbor_ids = Vec::new();
+        for neighbor_idx in neighbors {
+            let neighbor_id = self
+                .graph
+                .node(neighbor_idx)
+                .unwrap()
+                .payload()
+                .get_id_helper();
+            neighbor_ids.push(neighbor_id);
+            if let Err(error) = self
+                .check_responsive(neighbor_idx)
+                .and_then(|()| self.can_remove_sender(neighbor_idx))
+            {
+                blockers.push(format!("Edge {drone_id}-{neighbor_id}: {error}"));
+                continue;
+            }
+            let reached = self.endpoints_reached(neighbor_idx, drone_idx);
+            if !reached.is_disjoint(&cut_endpoints) {
+                let pairs = cut
+                    .iter()
+                    .filter(|(client, server)| reached.contains(client) || reached.contains(server))
+                    .copied()
+                    .collect::<Vec<(NodeId, NodeId)>>();
+                blockers.push(format!(
+                    "Edge {drone_id}-{neighbor_id}: needed by the pairs {}",
+                    format_pairs(&pairs)
+                ));
+            }
+        }
+
+        if blockers.is_empty() {
+            Ok(neighbor_ids)
+        } else {
+            Err(blockers)
+        }
+    }
+
+    /// Function to label the drones with the component they belong to, when
+    /// the paths can only go through drones other than `excluded`
+    fn drone_components(&self, excluded: NodeIndex) -> HashMap<NodeIndex, usize> {
+        let mut components: HashMap<NodeIndex, usize> = HashMap::new();
+        let drones = self
+            .graph
+            .nodes_iter()
+            .filter(|(idx, node)| {
+                *idx != excluded && matches!(node.payload(), WidgetType::Drone(_))
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<NodeIndex>>();
+        for (component, start) in drones.into_iter().enumerate() {
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                if components.contains_key(&node) {
+                    continue;
+                }
+                components.insert(node, component);
+                stack.extend(self.graph.g.neighbors(node).filter(|neighbor| {
+                    *neighbor != excluded
+                        && matches!(
+                            self.graph.node(*neighbor).unwrap().payload(),
+                            WidgetType::Drone(_)
+                        )
+                }));
+            }
+        }
+        components
+    }
+
+    /// Function to propose the edges that would make the crash of a drone legal
+    ///
+    /// The drone neighbors that would end up in different components are
+    /// chained together, then every neighbor that would fall below its minimum
+    /// number of links gets a new link to another drone.
+    /// The proposal is not guaranteed to be enough, so it must be validated
+    /// again when applied.
+    /// Returns `None` if no drone can provide a missing link
+    pub(crate) fn plan_crash_repair(&self, drone_idx: NodeIndex) -> Option<Vec<(NodeId, NodeId)>> {
+        let id_of = |idx: NodeIndex| self.graph.node(idx).unwrap().payload().get_id_helper();
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(drone_idx)
+            .collect::<Vec<NodeIndex>>();
+        let drone_neighbors = neighbors
+            .iter()
+            .copied()
+            .filter(|idx| {
+                matches!(
+                    self.graph.node(*idx).unwrap().payload(),
+                    WidgetType::Drone(_)
+                )
+            })
+            .collect::<Vec<NodeIndex>>();
+        let components = self.drone_components(drone_idx);
+
+        let mut plan: Vec<(NodeIndex, NodeIndex)> = Vec::new();
+        let mut merged: HashSet<usize> = HashSet::new();
+        for neighbor in &drone_neighbors {
+            if merged.insert(components[neighbor]) && merged.len() > 1 {
+                plan.push((drone_neighbors[0], *neighbor));
+            }
+        }
+
+        // Candidates for the missing links, the drone neighbors first
+        let drones = drone_neighbors
+            .iter()
+            .copied()
+            .chain(
+                self.graph
+                    .nodes_iter()
+                    .filter(|(_, node)| matches!(node.payload(), WidgetType::Drone(_)))
+                    .map(|(idx, _)| idx),
+            )
+            .filter(|idx| *idx != drone_idx)
+            .collect::<Vec<NodeIndex>>();
+        for neighbor in neighbors {
+            let min_links = match self.graph.node(neighbor).unwrap().payload() {
+                WidgetType::Drone(_) | WidgetType::WebClient(_) | WidgetType::ChatClient(_) => 1,
+                WidgetType::Server(_) => 2,
+            };
+            let planned = plan
+                .iter()
+                .filter(|(a, b)| *a == neighbor || *b == neighbor)
+                .count();
+            // The link to the crashing drone is lost
+            let links = self.graph.g.neighbors(neighbor).count() - 1 + planned;
+            for _ in links..min_links {
+                let drone = drones.iter().copied().find(|drone| {
+                    *drone != neighbor
+                        && self.graph.edges_connecting(neighbor, *drone).count() == 0
+                        && !plan.contains(&(neighbor, *drone))
+                        && !plan.contains(&(*drone, neighbor))
+                })?;
+                plan.push((neighbor, drone));
+            }
+        }
+
+        Some(
+            plan.into_iter()
+                .map(|(a, b)| (id_of(a), id_of(b)))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn can_drone_crash(&self, drone_id: NodeId) -> Result<(), ControllerError> {
+        let drone_idx = self.get_node_idx(drone_id).unwrap();
+
+        // Check if the neighbors of the drone can remove it
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(drone_idx)
+            .collect::<Vec<NodeIndex>>();
+        for neighbor in neighbors {
+            // The neighbors must be reachable to remove the crashing drone
+            self.check_responsive(neighbor)?;
+            match self.graph.node(neighbor).unwrap().payload() {
+                WidgetType::Drone(drone_widget) => {
+                    let id = drone_widget.id();
+                    if let Some(pos) = self.drones.iter().position(|d| d.id == id) {
+                        if self.drones[pos].connected_node_ids.len() == 1 {
+                            return Err(ControllerError::DroneMinConnections(id));
+                        }
+                    }
+                }
+                WidgetType::WebClient(web_client_widget) => {
+                    let id = web_client_widget.id();
+                    if let Some(pos) = self.web_clients.iter().position(|wc| wc.id == id) {
+                        if self.web_clients[pos].connected_drone_ids.len() == 1 {
+                            return Err(ControllerError::ClientMinConnections(id));
+                        }
+                    }
+                }
+                WidgetType::ChatClient(chat_client_widget) => {
+                    let id = chat_client_widget.id();
+                    if let Some(pos) = self.chat_clients.iter().position(|cc| cc.id == id) {
+                        if self.chat_clients[pos].connected_drone_ids.len() == 1 {
+                            return Err(ControllerError::ClientMinConnections(id));
+                        }
+                    }
+                }
+                WidgetType::Server(server_widget) => {
+                    let id = server_widget.id();
+                    if let Some(pos) = self.servers.iter().position(|s| s.id == id) {
+                        if self.servers[pos].connected_drone_ids.len() == 2 {
+                            return Err(ControllerError::ServerMinConnections(id));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut copy_graph = self.graph.clone();
+        copy_graph.remove_node(drone_idx);
+
+        self.check_reachability(&copy_graph)
+    }
+
+    /// Function to get the drones whose PDR is at least the severing threshold
+    pub(crate) fn severing_drones(&self) -> HashSet<NodeId> {
+        self.drones
+            .iter()
+            .filter(|d| d.pdr >= self.severing_pdr)
+            .map(|d| d.id)
+            .collect()
+    }
+
+    /// Function to find the client-server pairs that cannot reach each other
+    ///
+    /// Paths are searched only through the drones not in `excluded`, since
+    /// clients and servers do not forward packets.
+    pub(crate) fn unreachable_pairs(&self, excluded: &HashSet<NodeId>) -> Vec<(NodeId, NodeId)> {
+        let mut pairs = Vec::new();
+        for client in self.all_clients() {
+            let Some(client_idx) = self.get_node_idx(client.id) else {
+                continue;
+            };
+            let mut visited: HashSet<NodeIndex> = HashSet::new();
+            let mut servers_reached: HashSet<NodeId> = HashSet::new();
+            let mut stack = vec![client_idx];
+            while let Some(node) = stack.pop() {
+                if !visited.insert(node) {
+                    continue;
+                }
+                for neighbor in self.graph.g.neighbors(node) {
+                    match self.graph.node(neighbor).unwrap().payload() {
+                        WidgetType::Server(server_widget) => {
+                            servers_reached.insert(server_widget.id());
+                        }
+                        WidgetType::Drone(drone_widget)
+                            if !excluded.contains(&drone_widget.id()) =>
+                        {
+                            stack.push(neighbor);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            for server in &self.servers {
+                if !servers_reached.contains(&server.id) {
+                    pairs.push((client.id, server.id));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Function to find the clients without links and the servers with less than 2 links
+    ///
+    /// Returns the id of each node with a description of the problem
+    pub(crate) fn under_connected_nodes(&self) -> Vec<(NodeId, String)> {
+        let mut nodes = Vec::new();
+        for client in self.all_clients() {
+            if client.connected_drone_ids.is_empty() {
+                nodes.push((
+                    client.id,
+                    format!("{} has no links", self.node_name(client.id)),
+                ));
+            }
+        }
+        for server in &self.servers {
+            let links = server.connected_drone_ids.len();
+            if links < 2 {
+                nodes.push((
+                    server.id,
+                    format!("{} has {links} link(s)", self.node_name(server.id)),
+                ));
+            }
+        }
+        nodes
+    }
+
+    /// Function to find the drones that can be connected to the node with `target_id`
+    ///
+    /// Drones already connected to the node, and connections that would violate
+    /// a constraint of the network, are not suggested.
+    pub(crate) fn reconnect_candidates(
+        &self,
+        target_id: NodeId,
+        ranking: Ranking,
+    ) -> Vec<Candidate> {
+        let Some(target_idx) = self.get_node_idx(target_id) else {
+            return Vec::new();
+        };
+        let target = self.graph.node(target_idx).unwrap();
+        // Endpoints of the other kind, whose reachability is previewed
+        let others = if let WidgetType::Server(_) = target.payload() {
+            self.all_clients()
+                .map(|c| c.id)
+                .collect::<HashSet<NodeId>>()
+        } else {
+            self.servers
+                .iter()
+                .map(|s| s.id)
+                .collect::<HashSet<NodeId>>()
+        };
+
+        let mut candidates = self
+            .graph
+            .nodes_iter()
+            .filter_map(|(idx, node)| {
+                let WidgetType::Drone(drone_widget) = node.payload() else {
+                    return None;
+                };
+                if self.graph.edges_connecting(target_idx, idx).count() > 0 {
+                    return None;
+                }
+                self.can_add_sender(target_idx, idx).ok()?;
+                let drone_id = drone_widget.id();
+                Some(Candidate {
+                    drone_id,
+                    distance: (node.location() - target.location()).length(),
+                    load: self.stats.counters(drone_id).sent,
+                    reached: self
+                        .endpoints_reached(idx, target_idx)
+                        .intersection(&others)
+                        .count(),
+                })
+            })
+            .collect::<Vec<Candidate>>();
+        reconnect::rank(&mut candidates, ranking);
+        candidates
+    }
+}
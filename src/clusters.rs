@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use egui::Pos2;
+use wg_2024::network::NodeId;
+
+/// User-defined groups of drones, shown as a single meta-node when collapsed
+///
+/// Groups only change how the graph is drawn: the nodes stay in the graph and
+/// commands keep operating on them one by one.
+#[derive(Clone, Debug, Default)]
+pub struct Clusters {
+    groups: BTreeMap<String, BTreeSet<NodeId>>,
+    /// Positions of the members of the collapsed groups, restored on expansion
+    collapsed: BTreeMap<String, HashMap<NodeId, Pos2>>,
+}
+
+impl Clusters {
+    /// Add a drone to the group `name`, creating it if needed
+    ///
+    /// A drone belongs to at most one group, so it leaves its previous group.
+    pub fn add(&mut self, name: &str, id: NodeId) {
+        self.forget(id);
+        self.groups.entry(name.to_string()).or_default().insert(id);
+    }
+
+    /// Remove a drone from its group, e.g. after a crash
+    ///
+    /// Groups left without members are deleted.
+    pub fn forget(&mut self, id: NodeId) {
+        for members in self.groups.values_mut() {
+            members.remove(&id);
+        }
+        for positions in self.collapsed.values_mut() {
+            positions.remove(&id);
+        }
+        let empty = self
+            .groups
+            .iter()
+            .filter(|(_, members)| members.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>();
+        for name in empty {
+            self.delete(&name);
+        }
+    }
+
+    /// Keep only the drones for which `keep` returns true, e.g. after loading
+    /// another network
+    pub fn retain(&mut self, keep: impl Fn(NodeId) -> bool) {
+        let removed = self
+            .groups
+            .values()
+            .flatten()
+            .copied()
+            .filter(|id| !keep(*id))
+            .collect::<Vec<NodeId>>();
+        for id in removed {
+            self.forget(id);
+        }
+    }
+
+    /// Delete the group `name`, returning the saved positions of its members if it was collapsed
+    pub fn delete(&mut self, name: &str) -> Option<HashMap<NodeId, Pos2>> {
+        self.groups.remove(name);
+        self.collapsed.remove(name)
+    }
+
+    /// Groups with their members, ordered by name
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &BTreeSet<NodeId>)> {
+        self.groups.iter()
+    }
+
+    /// Group the drone belongs to, if any
+    #[must_use]
+    pub fn group_of(&self, id: NodeId) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|(_, members)| members.contains(&id))
+            .map(|(name, _)| name.as_str())
+    }
+
+    #[must_use]
+    pub fn is_collapsed(&self, name: &str) -> bool {
+        self.collapsed.contains_key(name)
+    }
+
+    /// Collapse the group `name`, saving the `positions` of its members
+    pub fn collapse(&mut self, name: &str, positions: HashMap<NodeId, Pos2>) {
+        if self.groups.contains_key(name) {
+            self.collapsed.insert(name.to_string(), positions);
+        }
+    }
+
+    /// Expand the group `name`, returning the saved positions of its members
+    pub fn expand(&mut self, name: &str) -> Option<HashMap<NodeId, Pos2>> {
+        self.collapsed.remove(name)
+    }
+
+    /// Member that stands for the collapsed group, the one with the lowest id
+    #[must_use]
+    pub fn representative(&self, name: &str) -> Option<NodeId> {
+        self.groups.get(name)?.first().copied()
+    }
+
+    /// Collapsed groups with their members
+    pub fn collapsed_groups(&self) -> impl Iterator<Item = (&String, &BTreeSet<NodeId>)> {
+        self.groups
+            .iter()
+            .filter(|(name, _)| self.collapsed.contains_key(*name))
+    }
+
+    /// Aggregate the connections of the members of `name` to the nodes outside the group
+    ///
+    /// Returns the number of edges towards each external neighbor.
+    #[must_use]
+    pub fn external_links(
+        &self,
+        name: &str,
+        edges: &BTreeSet<(NodeId, NodeId)>,
+    ) -> BTreeMap<NodeId, usize> {
+        let mut links = BTreeMap::new();
+        let Some(members) = self.groups.get(name) else {
+            return links;
+        };
+        for (a, b) in edges {
+            match (members.contains(a), members.contains(b)) {
+                (true, false) => *links.entry(*b).or_default() += 1,
+                (false, true) => *links.entry(*a).or_default() += 1,
+                _ => {}
+            }
+        }
+        links
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retain_drops_the_groups_left_empty() {
+        let mut clusters = Clusters::default();
+        clusters.add("north", 1);
+        clusters.add("north", 2);
+        clusters.add("south", 3);
+
+        clusters.retain(|id| id != 2 && id != 3);
+
+        let groups = clusters
+            .groups()
+            .map(|(name, members)| (name.as_str(), members.iter().copied().collect()))
+            .collect::<Vec<(&str, Vec<NodeId>)>>();
+        assert_eq!(groups, [("north", vec![1])]);
+        assert_eq!(clusters.group_of(3), None);
+    }
+}
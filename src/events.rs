@@ -0,0 +1,906 @@
+//! Handling of the events received from the nodes, and of the event log
+
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use common::slc_commands::{ChatClientEvent, ServerEvent, WebClientEvent};
+use crossbeam_channel::{SendError, Sender};
+use petgraph::stable_graph::NodeIndex;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+use wg_2024::{
+    controller::DroneEvent,
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+use crate::{
+    drain_channel, format_route, platform,
+    sink::{EventSink, SinkRecord},
+    utils::{EventKind, LogEvent, LogLevel, NodeKind},
+    widgets::{web_client_widget::PendingRequest, WidgetType},
+    ControllerInboundEvent, SimulationController,
+};
+
+impl SimulationController {
+    /// Function to mark a node as unresponsive, after a command could not be sent to it
+    ///
+    /// The node is flagged in the graph and further commands are blocked until
+    /// the user retries.
+    /// Returns the message logged for the failure.
+    pub(crate) fn mark_unresponsive(&mut self, id: NodeId, error: &str) -> String {
+        let event_string = format!("Command to {} failed: {error}", self.node_name(id));
+        self.flag_unresponsive(id, &event_string);
+        event_string
+    }
+
+    /// Helper function to flag a node as unresponsive, logging the given message
+    pub(crate) fn flag_unresponsive(&mut self, id: NodeId, event_string: &str) {
+        let node_kind = self.get_node_idx(id).map(|idx| {
+            self.graph
+                .node(idx)
+                .unwrap()
+                .payload()
+                .get_node_kind_helper()
+        });
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind: EventKind::Unresponsive,
+                session_id: None,
+                source: Some(id),
+                node_kind,
+                packet_type: None,
+                destination: None,
+                level: LogLevel::Error,
+                text: event_string.to_string(),
+            },
+            None,
+        );
+        self.unresponsive_nodes.insert(id);
+        if let Some(idx) = self.get_node_idx(id) {
+            self.refresh_label(idx);
+        }
+    }
+
+    /// Function to probe the health of the nodes
+    ///
+    /// The probe runs every `probe_interval` and never blocks the UI thread.
+    /// The sending side of the event channel is owned by the node, so a
+    /// disconnected event channel (detected while draining the events) means
+    /// that the node terminated.
+    /// Command channels can't be probed without sending a command, so their
+    /// failures are detected when a command is sent.
+    pub(crate) fn probe_health(&mut self) {
+        let Some(interval) = self.probe_interval else {
+            return;
+        };
+        if self.last_probe.elapsed() < interval {
+            return;
+        }
+        self.last_probe = Instant::now();
+
+        let mut disconnected = self
+            .disconnected_nodes
+            .iter()
+            .filter(|id| !self.unresponsive_nodes.contains(*id))
+            .filter(|id| self.get_node_idx(**id).is_some())
+            .copied()
+            .collect::<Vec<NodeId>>();
+        disconnected.sort_unstable();
+        for id in disconnected {
+            let event_string = format!(
+                "{} stopped responding: event channel disconnected",
+                self.node_name(id)
+            );
+            self.flag_unresponsive(id, &event_string);
+        }
+    }
+
+    /// Helper function to push an event to the event log, streaming it if the sink is enabled
+    ///
+    /// The route of `packet` only appears in the stream.
+    fn push_event(&mut self, event: LogEvent, packet: Option<&Packet>) {
+        if let Some(sink) = &self.event_sink {
+            sink.send(&SinkRecord {
+                kind: event.kind.name(),
+                node_id: event.source,
+                node_kind: event.node_kind,
+                destination: event.destination,
+                severity: event.level,
+                packet: packet.map(|packet| {
+                    format!(
+                        "{} {}",
+                        SimulationController::get_pack_type(packet),
+                        format_route(&packet.routing_header)
+                    )
+                }),
+                session_id: event.session_id,
+                text: event.text.clone(),
+            });
+        }
+        self.write_log_file(&event.text);
+        match &mut self.paused_events {
+            Some(buffer) => {
+                if buffer.len() >= self.events.capacity() {
+                    if let Some(oldest) = buffer.pop_front() {
+                        self.events.discard(&oldest);
+                    }
+                }
+                buffer.push_back(event);
+            }
+            None => self.events.push(event),
+        }
+    }
+
+    /// Function to pause the event log, or to resume it appending the events
+    /// received in the meantime
+    ///
+    /// Only the log is paused: the events are still handled and streamed.
+    pub fn set_log_paused(&mut self, paused: bool) {
+        if paused {
+            self.paused_events.get_or_insert_with(VecDeque::new);
+        } else if let Some(buffer) = self.paused_events.take() {
+            for event in buffer {
+                self.events.push(event);
+            }
+        }
+    }
+
+    /// Function to stream the structured events as JSON Lines to `sink`, or to stop
+    /// streaming with `None`
+    ///
+    /// The previous sink is flushed and closed.
+    pub fn set_event_sink(&mut self, sink: Option<EventSink>) {
+        self.event_sink = sink;
+    }
+
+    /// Function to append the entries of the event log to the file at `path`, one
+    /// line each with the time in milliseconds since the Unix epoch and the text
+    ///
+    /// The file is created if missing, and the previous log file is flushed and closed.
+    /// If a line can't be written, the log file is disabled and the error is logged.
+    ///
+    /// # Errors
+    /// If the file can't be opened
+    pub fn set_log_file(&mut self, path: &Path) -> Result<(), io::Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.close_log_file();
+        self.log_file = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    /// Function to stop appending the entries of the event log to the log file, if any
+    pub fn close_log_file(&mut self) {
+        if let Some(mut file) = self.log_file.take() {
+            let _ = file.flush();
+        }
+    }
+
+    /// Helper function to append the text of an entry of the log to the log file, if enabled
+    fn write_log_file(&mut self, text: &str) {
+        let Some(file) = &mut self.log_file else {
+            return;
+        };
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        if let Err(error) = writeln!(file, "{timestamp_ms} {text}") {
+            // Disabled first, so that the error itself is not written
+            self.log_file = None;
+            self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Log file disabled: {error}"),
+            );
+        }
+    }
+
+    /// Helper function to push an event of the controller to the event log
+    pub(crate) fn log(&mut self, level: LogLevel, text: impl Into<String>) {
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind: EventKind::Controller,
+                session_id: None,
+                source: None,
+                node_kind: None,
+                packet_type: None,
+                destination: None,
+                level,
+                text: text.into(),
+            },
+            None,
+        );
+    }
+
+    /// Helper function to push an event about a packet handled by `source` to the event log
+    ///
+    /// `kind` tells whether the packet was sent, dropped or injected, so that the
+    /// stats and the filters don't depend on the level.
+    /// Events about fragments, acks and nacks are tagged with the session id of
+    /// the packet, so that the log can be grouped by session.
+    pub(crate) fn log_packet(
+        &mut self,
+        source: (NodeId, NodeKind),
+        packet: &Packet,
+        kind: EventKind,
+        level: LogLevel,
+        text: String,
+    ) {
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind,
+                session_id: SimulationController::get_session_id(packet),
+                source: Some(source.0),
+                node_kind: Some(source.1),
+                packet_type: Some(SimulationController::get_pack_type(packet)),
+                destination: None,
+                level,
+                text,
+            },
+            Some(packet),
+        );
+    }
+
+    /// Helper function to push an event about a shortcut requested by `source` to the event log
+    ///
+    /// The event is tagged as a shortcut, so that the controller-mediated
+    /// deliveries can be filtered in the log.
+    fn log_shortcut(&mut self, source: (NodeId, NodeKind), packet: &Packet, text: String) {
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind: EventKind::Shortcut,
+                session_id: SimulationController::get_session_id(packet),
+                source: Some(source.0),
+                node_kind: Some(source.1),
+                packet_type: Some(SimulationController::get_pack_type(packet)),
+                destination: packet.routing_header.destination(),
+                level: LogLevel::Info,
+                text,
+            },
+            Some(packet),
+        );
+    }
+
+    /// Helper function to push an event about a request of `client` that is not supported
+    fn log_unsupported_request(&mut self, client: (NodeId, NodeKind), text: String) {
+        let (client_id, node_kind) = client;
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind: EventKind::UnsupportedRequest,
+                session_id: None,
+                source: Some(client_id),
+                node_kind: Some(node_kind),
+                packet_type: None,
+                destination: None,
+                level: LogLevel::Warn,
+                text,
+            },
+            None,
+        );
+    }
+
+    /// Utility function to get the session id of the `Packet`, if it belongs to a session
+    /// Used to group the events of the log
+    fn get_session_id(packet: &Packet) -> Option<u64> {
+        match packet.pack_type {
+            PacketType::MsgFragment(_) | PacketType::Ack(_) | PacketType::Nack(_) => {
+                Some(packet.session_id)
+            }
+            PacketType::FloodRequest(_) | PacketType::FloodResponse(_) => None,
+        }
+    }
+
+    /// Utility function to get the type of the `Packet`
+    /// Used for logging purposes
+    pub(crate) fn get_pack_type(packet: &Packet) -> String {
+        match &packet.pack_type {
+            wg_2024::packet::PacketType::MsgFragment(_) => String::from("MsgFragment"),
+            wg_2024::packet::PacketType::Ack(_) => String::from("Ack"),
+            wg_2024::packet::PacketType::Nack(_) => String::from("Nack"),
+            wg_2024::packet::PacketType::FloodRequest(_) => String::from("FloodRequest"),
+            wg_2024::packet::PacketType::FloodResponse(_) => String::from("FloodResponse"),
+        }
+    }
+
+    /// Function to handle the shortcut of a packet
+    /// The packet is sent to the corresponding node
+    ///
+    /// If the destination is unknown or its channel is closed, e.g. because it
+    /// crashed, the failed delivery is logged as an error.
+    fn handle_shortcut(&mut self, id: NodeId, packet: Packet) {
+        let channel = self
+            .drones_channels
+            .get(&id)
+            .map(|ch| &ch.2)
+            .or_else(|| self.web_clients_channels.get(&id).map(|ch| &ch.2))
+            .or_else(|| self.chat_clients_channels.get(&id).map(|ch| &ch.2))
+            .or_else(|| self.servers_channels.get(&id).map(|ch| &ch.2));
+        let (packet, reason) = match channel {
+            Some(channel) => match channel.send(packet) {
+                Ok(()) => return,
+                Err(SendError(packet)) => (packet, "channel closed"),
+            },
+            None => (packet, "unknown destination"),
+        };
+        let packet_type = SimulationController::get_pack_type(&packet);
+        let text = format!(
+            "[CONTROLLER] Shortcut delivery of {packet_type} to {} failed: {reason}",
+            self.node_name(id)
+        );
+        self.push_event(
+            LogEvent {
+                time: Instant::now(),
+                kind: EventKind::Shortcut,
+                session_id: SimulationController::get_session_id(&packet),
+                source: None,
+                node_kind: None,
+                packet_type: Some(packet_type),
+                destination: Some(id),
+                level: LogLevel::Error,
+                text,
+            },
+            Some(&packet),
+        );
+    }
+
+    /// Function to handle all the incoming events
+    ///
+    /// Each time the GUI is refreshed, this function is called.
+    /// It listens to all the channels of the drones, web clients, chat clients and servers,
+    /// storing the received events in a queue.
+    /// Then for each event in the queue, it calls the corresponding handler function.
+    pub(crate) fn handle_event(&mut self) {
+        let mut event_queue: Vec<(NodeId, ControllerInboundEvent)> = Vec::new();
+        self.disconnected_nodes.clear();
+        // The entries of the producers wait in their channel while the log is paused
+        if self.paused_events.is_none() {
+            self.events.drain_producers();
+        }
+
+        // The events left by the drones crashed in the last frame are handled
+        // once, then their receivers are dropped
+        for (drone_id, receiver) in std::mem::take(&mut self.crashed_receivers) {
+            for event in receiver.try_iter() {
+                self.handle_late_drone_event(drone_id, event);
+            }
+        }
+
+        let max = self.max_events_per_frame;
+        for (drone_id, drone_ch) in &self.drones_channels {
+            let (events, disconnected) = drain_channel(&drone_ch.1, max);
+            event_queue.extend(
+                events
+                    .into_iter()
+                    .map(|event| (*drone_id, ControllerInboundEvent::Drone(event))),
+            );
+            if disconnected {
+                self.disconnected_nodes.insert(*drone_id);
+            }
+            self.intake.observe(*drone_id, drone_ch.1.len());
+        }
+
+        for (client_id, client_ch) in &self.web_clients_channels {
+            let (events, disconnected) = drain_channel(&client_ch.1, max);
+            event_queue.extend(
+                events
+                    .into_iter()
+                    .map(|event| (*client_id, ControllerInboundEvent::WebClient(event))),
+            );
+            if disconnected {
+                self.disconnected_nodes.insert(*client_id);
+            }
+            self.intake.observe(*client_id, client_ch.1.len());
+        }
+
+        for (client_id, client_ch) in &self.chat_clients_channels {
+            let (events, disconnected) = drain_channel(&client_ch.1, max);
+            event_queue.extend(
+                events
+                    .into_iter()
+                    .map(|event| (*client_id, ControllerInboundEvent::ChatClient(event))),
+            );
+            if disconnected {
+                self.disconnected_nodes.insert(*client_id);
+            }
+            self.intake.observe(*client_id, client_ch.1.len());
+        }
+
+        for (server_id, server_ch) in &self.servers_channels {
+            let (events, disconnected) = drain_channel(&server_ch.1, max);
+            event_queue.extend(
+                events
+                    .into_iter()
+                    .map(|event| (*server_id, ControllerInboundEvent::Server(event))),
+            );
+            if disconnected {
+                self.disconnected_nodes.insert(*server_id);
+            }
+            self.intake.observe(*server_id, server_ch.1.len());
+        }
+
+        for (id, event) in event_queue {
+            self.dispatch_event(id, event);
+        }
+
+        self.transfers.expire();
+    }
+
+    /// Function to set the max number of events received from each channel at each frame
+    ///
+    /// The events past the cap stay in the channel for the next frames.
+    pub fn set_max_events_per_frame(&mut self, max: usize) {
+        self.max_events_per_frame = max.max(1);
+    }
+
+    /// Function to set the number of entries kept in the event log
+    ///
+    /// The entries are kept when the log grows; when it shrinks, the entries
+    /// past the new capacity are evicted following the overflow policy, and
+    /// counted as discarded.
+    /// A capacity of 0 is raised to 1.
+    pub fn set_log_capacity(&mut self, capacity: usize) {
+        self.events.resize_capacity(capacity);
+    }
+
+    /// Function to handle an event as if it was received from the node with `node_id`
+    ///
+    /// The event goes through the same handlers as the events received from
+    /// the channels, so that synthetic events can be injected without a
+    /// running network.
+    pub fn inject_event(&mut self, node_id: NodeId, event: impl Into<ControllerInboundEvent>) {
+        self.dispatch_event(node_id, event.into());
+    }
+
+    /// Function to receive the pending events from the channels and handle them,
+    /// as done at each frame of the GUI
+    pub fn process_events(&mut self) {
+        self.handle_event();
+    }
+
+    /// Get a handle to append entries to the event log from another thread
+    ///
+    /// Sending never blocks, the entries are added to the log by the next
+    /// `process_events` (or frame, with the GUI).
+    #[must_use]
+    pub fn log_producer(&self) -> Sender<LogEvent> {
+        self.events.producer()
+    }
+
+    /// Function to get the text of the entries of the event log, oldest first
+    #[must_use]
+    pub fn log_lines(&self) -> Vec<String> {
+        self.events
+            .get()
+            .into_iter()
+            .map(|event| event.text.clone())
+            .collect()
+    }
+
+    /// Function to get the number of entries discarded from the event log so far
+    #[must_use]
+    pub fn log_discarded(&self) -> usize {
+        self.events.discarded()
+    }
+
+    /// Utility function to call the handler of the given event
+    fn dispatch_event(&mut self, id: NodeId, event: ControllerInboundEvent) {
+        match event {
+            ControllerInboundEvent::Drone(event) => self.handle_drone_event(id, event),
+            ControllerInboundEvent::WebClient(event) => self.handle_web_client_event(id, event),
+            ControllerInboundEvent::ChatClient(event) => self.handle_chat_client_event(id, event),
+            ControllerInboundEvent::Server(event) => self.handle_server_event(id, event),
+        }
+    }
+
+    /// Handler function for the drone events
+    fn handle_drone_event(&mut self, drone_id: NodeId, event: DroneEvent) {
+        let tag = self.with_alias(drone_id, drone_id.to_string());
+        match event {
+            DroneEvent::PacketSent(packet) => {
+                self.transfers.record_sent(&packet);
+                self.tracer.record(drone_id, &packet);
+                self.stats.record_sent(drone_id);
+                self.stats.record_drone_sent(drone_id, &packet);
+                self.pdr_history.record_sent(drone_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string = format!("[DRONE: {tag}] Sent {packet_type} packet");
+                self.log_packet(
+                    (drone_id, NodeKind::Drone),
+                    &packet,
+                    EventKind::Packet,
+                    LogLevel::Debug,
+                    event_string,
+                );
+            }
+            DroneEvent::PacketDropped(packet) => {
+                self.transfers.record_dropped(&packet);
+                self.stats.record_dropped(drone_id);
+                self.pdr_history.record_dropped(drone_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string = format!("[DRONE: {tag}] Dropped {packet_type} packet");
+                self.log_packet(
+                    (drone_id, NodeKind::Drone),
+                    &packet,
+                    EventKind::PacketDropped,
+                    LogLevel::Warn,
+                    event_string,
+                );
+            }
+            DroneEvent::ControllerShortcut(packet) => {
+                self.stats.record_shortcut(drone_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let destination_id = packet.routing_header.destination();
+                match destination_id {
+                    Some(id) => {
+                        let event_string = format!(
+                            "[DRONE: {tag}] Requested shortcut for packet {packet_type} to {id}, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log_shortcut((drone_id, NodeKind::Drone), &packet, event_string);
+                        self.handle_shortcut(id, packet);
+                    }
+                    None => {
+                        let event_string = format!(
+                            "[DRONE: {tag}] Requested shortcut for packet {packet_type} without destination, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log(LogLevel::Error, event_string);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handler function for the events left in the channel of a crashed drone
+    ///
+    /// The events are only logged, so that they don't bring back the state of
+    /// the crashed drone. Shortcuts are still delivered, since a crashing drone
+    /// hands over the packets that can't be dropped.
+    fn handle_late_drone_event(&mut self, drone_id: NodeId, event: DroneEvent) {
+        let suffix = "(late, node crashed)";
+        match event {
+            DroneEvent::PacketSent(packet) => {
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string =
+                    format!("[DRONE: {drone_id}] Sent {packet_type} packet {suffix}");
+                self.log_packet(
+                    (drone_id, NodeKind::Drone),
+                    &packet,
+                    EventKind::Packet,
+                    LogLevel::Debug,
+                    event_string,
+                );
+            }
+            DroneEvent::PacketDropped(packet) => {
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string =
+                    format!("[DRONE: {drone_id}] Dropped {packet_type} packet {suffix}");
+                self.log_packet(
+                    (drone_id, NodeKind::Drone),
+                    &packet,
+                    EventKind::PacketDropped,
+                    LogLevel::Warn,
+                    event_string,
+                );
+            }
+            DroneEvent::ControllerShortcut(packet) => {
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let route = format_route(&packet.routing_header);
+                match packet.routing_header.destination() {
+                    Some(id) => {
+                        let event_string = format!(
+                            "[DRONE: {drone_id}] Requested shortcut for packet {packet_type} to {id}, {route} {suffix}"
+                        );
+                        self.log_shortcut((drone_id, NodeKind::Drone), &packet, event_string);
+                        self.handle_shortcut(id, packet);
+                    }
+                    None => {
+                        let event_string = format!(
+                            "[DRONE: {drone_id}] Requested shortcut for packet {packet_type} without destination, {route} {suffix}"
+                        );
+                        self.log(LogLevel::Error, event_string);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handler function for the web client events
+    fn handle_web_client_event(&mut self, client_id: NodeId, event: WebClientEvent) {
+        let tag = self.with_alias(client_id, client_id.to_string());
+        match event {
+            WebClientEvent::PacketSent(packet) => {
+                self.transfers.record_sent(&packet);
+                self.tracer.record(client_id, &packet);
+                self.stats.record_sent(client_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string = format!("[WEB CLIENT: {tag}] Sent {packet_type} packet");
+                self.log_packet(
+                    (client_id, NodeKind::WebClient),
+                    &packet,
+                    EventKind::Packet,
+                    LogLevel::Debug,
+                    event_string,
+                );
+            }
+            WebClientEvent::Shortcut(packet) => {
+                self.stats.record_shortcut(client_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let destination_id = packet.routing_header.destination();
+                match destination_id {
+                    Some(id) => {
+                        let event_string = format!(
+                            "[WEB CLIENT: {tag}] Requested shortcut for packet {packet_type} to {id}, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log_shortcut((client_id, NodeKind::WebClient), &packet, event_string);
+                        self.handle_shortcut(id, packet);
+                    }
+                    None => {
+                        let event_string = format!(
+                            "[WEB CLIENT: {tag}] Requested shortcut for packet {packet_type} without destination, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log(LogLevel::Error, event_string);
+                    }
+                }
+            }
+            WebClientEvent::ListOfFiles(files, server_id) => {
+                let Some(client_idx) = self.known_node_idx(client_id, "ListOfFiles") else {
+                    return;
+                };
+                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
+
+                if let WidgetType::WebClient(client_widget) = client {
+                    let rtt = client_widget.add_list_of_files(server_id, files);
+                    let request = PendingRequest::ListOfFiles(server_id);
+                    self.record_round_trip(client_id, &request, rtt);
+                }
+            }
+            WebClientEvent::FileFromClient(response, server_id) => {
+                // The page of an unknown client is dropped before anything is written
+                let Some(client_idx) = self.known_node_idx(client_id, "FileFromClient") else {
+                    return;
+                };
+                let request = PendingRequest::File(server_id);
+                let rtt = match self.graph.node_mut(client_idx).unwrap().payload_mut() {
+                    WidgetType::WebClient(client_widget) => client_widget.resolve_request(&request),
+                    _ => None,
+                };
+                self.record_round_trip(client_id, &request, rtt);
+
+                let media_folder = self.output_dir.join("media");
+                let (filename, html_file) = response.get_html_file();
+                let file_path = self.output_dir.join(filename);
+                let mut result = platform::save_file(&file_path, html_file);
+                let mut media = Vec::new();
+                for (media_name, media_content) in response.get_media_files() {
+                    let media_path = media_folder.join(media_name);
+                    result = result.and_then(|()| platform::save_file(&media_path, media_content));
+                    media.push((media_name.to_string(), media_path, media_content.to_vec()));
+                }
+                if let WidgetType::WebClient(client_widget) =
+                    self.graph.node_mut(client_idx).unwrap().payload_mut()
+                {
+                    for (name, path, content) in media {
+                        client_widget.add_media(name, path, content);
+                    }
+                }
+
+                if let Err(error) = result.and_then(|()| platform::open_page(&file_path, html_file))
+                {
+                    let event_string = format!(
+                        "[CONTROLLER] Cannot open the file received by client {client_id}: {error}"
+                    );
+                    self.log(LogLevel::Error, event_string);
+                }
+            }
+            WebClientEvent::ServersTypes(types) => {
+                let Some(client_idx) = self.known_node_idx(client_id, "ServersTypes") else {
+                    return;
+                };
+                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
+
+                if let WidgetType::WebClient(client_widget) = client {
+                    let rtt = client_widget.add_server_type(types);
+                    self.record_round_trip(client_id, &PendingRequest::ServersTypes, rtt);
+                }
+            }
+            WebClientEvent::UnsupportedRequest => {
+                let Some(client_idx) = self.known_node_idx(client_id, "UnsupportedRequest") else {
+                    return;
+                };
+                self.log_unsupported_request(
+                    (client_id, NodeKind::WebClient),
+                    format!("[WEB CLIENT: {tag}] Unsupported request"),
+                );
+                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
+
+                if let WidgetType::WebClient(client_widget) = client {
+                    client_widget.add_unsupported_request_error("Unsupported request".to_string());
+                }
+            }
+        }
+    }
+
+    /// Function to record the round-trip time of a web client request, if it was matched
+    ///
+    /// The time is added to the aggregate statistics and to the running stress test.
+    fn record_round_trip(
+        &mut self,
+        client_id: NodeId,
+        request: &PendingRequest,
+        rtt: Option<Duration>,
+    ) {
+        let Some(rtt) = rtt else {
+            return;
+        };
+        self.stats.record_round_trip(rtt);
+        if let Some(test) = &mut self.stress_test {
+            test.record_response(client_id, request, rtt);
+        }
+    }
+
+    /// Function to drop the web client requests waiting for longer than the timeout
+    ///
+    /// The dropped requests are counted as losses in the statistics.
+    pub(crate) fn expire_requests(&mut self) {
+        let web_clients = self
+            .graph
+            .nodes_iter()
+            .filter(|(_, node)| matches!(node.payload(), WidgetType::WebClient(_)))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<NodeIndex>>();
+        let mut lost = 0;
+        for idx in web_clients {
+            if let Some(WidgetType::WebClient(client_widget)) =
+                self.graph.node_mut(idx).map(|node| node.payload_mut())
+            {
+                lost += client_widget.expire_requests(self.request_timeout);
+            }
+        }
+        if lost > 0 {
+            self.stats.record_lost_requests(lost);
+        }
+    }
+
+    /// Function to discard the log entries older than the configured max age, if any
+    ///
+    /// The capacity of the log still applies, whichever limit is hit first wins.
+    pub(crate) fn prune_log(&mut self) {
+        if self.limit_log_age {
+            self.events
+                .prune_older_than(Duration::from_secs(self.log_max_age_minutes * 60));
+        }
+    }
+
+    /// Handler function for the chat client events
+    fn handle_chat_client_event(&mut self, chat_client_id: NodeId, event: ChatClientEvent) {
+        let tag = self.with_alias(chat_client_id, chat_client_id.to_string());
+        match event {
+            ChatClientEvent::PacketSent(packet) => {
+                self.transfers.record_sent(&packet);
+                self.tracer.record(chat_client_id, &packet);
+                self.stats.record_sent(chat_client_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string = format!("[CHAT CLIENT: {tag}] Sent {packet_type} packet");
+                self.log_packet(
+                    (chat_client_id, NodeKind::ChatClient),
+                    &packet,
+                    EventKind::Packet,
+                    LogLevel::Debug,
+                    event_string,
+                );
+            }
+            ChatClientEvent::Shortcut(packet) => {
+                self.stats.record_shortcut(chat_client_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let destination_id = packet.routing_header.destination();
+                match destination_id {
+                    Some(id) => {
+                        let event_string = format!(
+                            "[CHAT CLIENT: {tag}] Requested shortcut for packet {packet_type} to {id}, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log_shortcut(
+                            (chat_client_id, NodeKind::ChatClient),
+                            &packet,
+                            event_string,
+                        );
+                        self.handle_shortcut(id, packet);
+                    }
+                    None => {
+                        let event_string = format!(
+                            "[CHAT CLIENT: {tag}] Requested shortcut for packet {packet_type} without destination, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log(LogLevel::Error, event_string);
+                    }
+                }
+            }
+            ChatClientEvent::ServersTypes(types) => {
+                let Some(client_idx) = self.known_node_idx(chat_client_id, "ServersTypes") else {
+                    return;
+                };
+                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
+
+                if let WidgetType::ChatClient(client_widget) = client {
+                    client_widget.add_server_type(&types);
+                }
+            }
+            ChatClientEvent::UnsupportedRequest => {
+                if self
+                    .known_node_idx(chat_client_id, "UnsupportedRequest")
+                    .is_some()
+                {
+                    self.log_unsupported_request(
+                        (chat_client_id, NodeKind::ChatClient),
+                        format!("[CHAT CLIENT: {tag}] Unsupported request"),
+                    );
+                }
+            }
+            ChatClientEvent::MessageReceived(msg) => {
+                let Some(client_idx) = self.known_node_idx(chat_client_id, "MessageReceived")
+                else {
+                    return;
+                };
+                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
+
+                if let WidgetType::ChatClient(client_widget) = client {
+                    client_widget.update_chat(msg);
+                }
+            }
+        }
+    }
+
+    /// Handler function for the server events
+    fn handle_server_event(&mut self, server_id: NodeId, event: ServerEvent) {
+        let tag = self.with_alias(server_id, server_id.to_string());
+        match event {
+            ServerEvent::PacketSent(packet) => {
+                self.transfers.record_sent(&packet);
+                self.tracer.record(server_id, &packet);
+                self.stats.record_sent(server_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let event_string = format!("[SERVER: {tag}] Sent {packet_type} packet");
+                self.log_packet(
+                    (server_id, NodeKind::Server),
+                    &packet,
+                    EventKind::Packet,
+                    LogLevel::Debug,
+                    event_string,
+                );
+            }
+            ServerEvent::ShortCut(packet) => {
+                self.stats.record_shortcut(server_id);
+                let packet_type = SimulationController::get_pack_type(&packet);
+                let destination_id = packet.routing_header.destination();
+                match destination_id {
+                    Some(id) => {
+                        let event_string = format!(
+                            "[SERVER: {tag}] Requested shortcut for packet {packet_type} to {id}, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log_shortcut((server_id, NodeKind::Server), &packet, event_string);
+                        self.handle_shortcut(id, packet);
+                    }
+                    None => {
+                        let event_string = format!(
+                            "[SERVER: {tag}] Requested shortcut for packet {packet_type} without destination, {}",
+                            format_route(&packet.routing_header)
+                        );
+                        self.log(LogLevel::Error, event_string);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,684 @@
+//! Exports and imports of the event log, of the statistics and of the topology
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use rand::Rng;
+use web_time::Instant;
+use wg_2024::{
+    config::{Client, Config},
+    network::NodeId,
+    packet::Packet,
+};
+
+use crate::{
+    build_node, check_bidirectional, generate_graph,
+    history::PdrHistory,
+    import::{self, ImportError, ImportPlan, LoadError, SaveError},
+    platform,
+    stats::{self, csv_field, NodeInfo},
+    topology::{OperationLog, TopologyOp},
+    utils::{LogLevel, NodeKind},
+    validation::ConstraintPolicy,
+    widgets::{NodeWidget, WidgetType},
+    CCChannels, DChannels, SChannels, SimulationController, WCChannels, DRONE_FACTORY,
+};
+
+impl SimulationController {
+    /// Function to export the changes to the topology since startup as a text file
+    pub(crate) fn export_topology_changes(&self) -> Result<PathBuf, String> {
+        let mut text = String::from("Changes to the topology since startup\n");
+        for (change, secs) in self.topology_changes() {
+            match secs {
+                Some(secs) => text.push_str(&format!("[+{secs:.1}s] {change}\n")),
+                None => text.push_str(&format!("{change}\n")),
+            }
+        }
+        let path = self.output_dir.join("topology_diff.txt");
+        platform::save_file(&path, text.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Function to export the entries of the event log as CSV to `path`, oldest first
+    ///
+    /// The file has the header `timestamp_secs,node_id,node_type,event_type,detail`,
+    /// with the time of each entry in seconds since the controller started.
+    ///
+    /// # Errors
+    /// If the file can't be written
+    pub fn export_csv(&self, path: &Path) -> Result<(), io::Error> {
+        platform::save_file(path, self.events_csv().as_bytes()).map_err(io::Error::other)
+    }
+
+    /// Helper function to log the outcome of an export of the event log to `path`
+    pub(crate) fn log_export_result(&mut self, path: &Path, result: Result<(), io::Error>) {
+        match result {
+            Ok(()) => self.log(
+                LogLevel::Info,
+                format!("[CONTROLLER] Event log exported to {}", path.display()),
+            ),
+            Err(error) => self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Event log export failed: {error}"),
+            ),
+        }
+    }
+
+    /// Helper function to build the CSV of the entries of the event log, see `export_csv`
+    fn events_csv(&self) -> String {
+        let mut csv = String::from("timestamp_secs,node_id,node_type,event_type,detail\n");
+        for event in self.events.get() {
+            csv.push_str(&format!(
+                "{:.3},{},{},{},{}\n",
+                event.time.duration_since(self.start_time).as_secs_f64(),
+                event.source.map(|id| id.to_string()).unwrap_or_default(),
+                event.node_kind.map_or("", NodeKind::name),
+                event.kind.name(),
+                csv_field(&event.text)
+            ));
+        }
+        csv
+    }
+
+    /// Function to export the entries of the event log as JSON to `path`, oldest first
+    ///
+    /// The file holds an array with an object per entry, e.g.
+    /// `{"elapsed_secs":12.345,"node_id":3,"event_type":"packet","message":"..."}`:
+    /// - `elapsed_secs`: time of the entry in seconds since the controller started
+    /// - `node_id`: node that reported the entry, `null` for the controller
+    /// - `event_type`: `packet`, `packet_dropped`, `packet_injected`, `shortcut`,
+    ///   `controller`, `unresponsive` or `unsupported_request`, as in the event stream
+    /// - `message`: text of the entry as shown in the log
+    ///
+    /// # Errors
+    /// If the file can't be written
+    pub fn export_json(&self, path: &Path) -> Result<(), io::Error> {
+        platform::save_file(path, self.events_json().as_bytes()).map_err(io::Error::other)
+    }
+
+    /// Helper function to build the JSON of the entries of the event log, see `export_json`
+    fn events_json(&self) -> String {
+        let entries = self
+            .events
+            .get()
+            .into_iter()
+            .map(|event| {
+                serde_json::json!({
+                    "elapsed_secs": event.time.duration_since(self.start_time).as_secs_f64(),
+                    "node_id": event.source,
+                    "event_type": event.kind.name(),
+                    "message": event.text,
+                })
+            })
+            .collect::<Vec<serde_json::Value>>();
+        serde_json::Value::Array(entries).to_string()
+    }
+
+    /// Function to get the configuration of the running network, in the layout of
+    /// the network initialization files
+    ///
+    /// It reflects the changes applied at runtime, e.g. spawned drones, removed
+    /// edges and new PDRs. The nodes are sorted by id, so that exports can be diffed.
+    #[must_use]
+    pub fn export_config(&self) -> Config {
+        let mut drone = self.drones.clone();
+        drone.sort_by_key(|d| d.id);
+        let mut client = self
+            .web_clients
+            .iter()
+            .chain(&self.chat_clients)
+            .cloned()
+            .collect::<Vec<Client>>();
+        client.sort_by_key(|c| c.id);
+        let mut server = self.servers.clone();
+        server.sort_by_key(|s| s.id);
+        Config {
+            drone,
+            client,
+            server,
+        }
+    }
+
+    /// Function to save the configuration of the running network, see `export_config`,
+    /// as a TOML file at `path`
+    ///
+    /// The file can be given back to the network initializer, or dropped on the window.
+    ///
+    /// # Errors
+    /// If the configuration can't be serialized or the file can't be written
+    pub fn save_topology(&self, path: &Path) -> Result<(), SaveError> {
+        let content = toml::to_string(&self.export_config())?;
+        platform::save_file(path, content.as_bytes()).map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Function to export the graph of the network to `path` in the Graphviz DOT format
+    ///
+    /// Each node is labeled with its kind and id, e.g. `5 [label="Drone 5", shape=circle];`,
+    /// with a circle for the drones, a diamond for the clients and a box for the
+    /// servers. Nodes and edges are sorted by id, so that exports can be diffed.
+    ///
+    /// # Errors
+    /// If the file can't be written
+    pub fn export_dot(&self, path: &Path) -> Result<(), io::Error> {
+        platform::save_file(path, self.graph_dot().as_bytes()).map_err(io::Error::other)
+    }
+
+    /// Helper function to build the DOT graph of the network, see `export_dot`
+    fn graph_dot(&self) -> String {
+        let mut nodes = self
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload())
+            .collect::<Vec<&WidgetType>>();
+        nodes.sort_by_key(|widget| widget.get_id_helper());
+
+        let mut dot = String::from("graph G {\n");
+        for widget in nodes {
+            dot.push_str(&format!(
+                "    {} [label=\"{}\", shape={}];\n",
+                widget.get_id_helper(),
+                widget.get_label_helper(),
+                widget.get_dot_shape_helper()
+            ));
+        }
+        for (a, b) in self.topology_snapshot().edges {
+            dot.push_str(&format!("    {a} -- {b};\n"));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Function to export the topology of the network to `path` as a JSON adjacency list
+    ///
+    /// The file is independent of the network initialization format, e.g.
+    /// `{"nodes": [{"id":5,"type":"Drone","pdr":0.1},...], "edges": [[5,6],[5,7],...]}`:
+    /// the type of a node is `Drone`, `WebClient`, `ChatClient` or `Server`, and
+    /// only drones have a PDR. Nodes and edges are sorted by id.
+    ///
+    /// # Errors
+    /// If the file can't be written
+    pub fn export_json_topology(&self, path: &Path) -> Result<(), io::Error> {
+        platform::save_file(path, self.topology_json().to_string().as_bytes())
+            .map_err(io::Error::other)
+    }
+
+    /// Helper function to build the JSON adjacency list of the network, see `export_json_topology`
+    fn topology_json(&self) -> serde_json::Value {
+        let mut nodes = self
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload())
+            .collect::<Vec<&WidgetType>>();
+        nodes.sort_by_key(|widget| widget.get_id_helper());
+        let nodes = nodes
+            .into_iter()
+            .map(|widget| match widget {
+                WidgetType::Drone(drone_widget) => serde_json::json!({
+                    "id": drone_widget.id(),
+                    "type": "Drone",
+                    "pdr": self.drone_pdr(drone_widget.id()).unwrap_or_default(),
+                }),
+                WidgetType::WebClient(_) => {
+                    serde_json::json!({"id": widget.get_id_helper(), "type": "WebClient"})
+                }
+                WidgetType::ChatClient(_) => {
+                    serde_json::json!({"id": widget.get_id_helper(), "type": "ChatClient"})
+                }
+                WidgetType::Server(_) => {
+                    serde_json::json!({"id": widget.get_id_helper(), "type": "Server"})
+                }
+            })
+            .collect::<Vec<serde_json::Value>>();
+        let edges = self
+            .topology_snapshot()
+            .edges
+            .into_iter()
+            .map(|(a, b)| [a, b])
+            .collect::<Vec<[NodeId; 2]>>();
+        serde_json::json!({"nodes": nodes, "edges": edges})
+    }
+
+    /// Function to replace the running network with the one of the JSON
+    /// adjacency list at `path`, see `export_json_topology`
+    ///
+    /// Like `load_topology`, the running network is left intact on error.
+    ///
+    /// # Errors
+    /// If the file can't be read or is not a valid topology, or if a node can't be built
+    pub fn import_json_topology(&mut self, path: &Path) -> Result<(), ImportError> {
+        let content = std::fs::read_to_string(path)?;
+        let topology = import::parse_json_topology(&content)?;
+        Ok(self.replace_topology(topology.config, &topology.chat_clients, path)?)
+    }
+
+    /// Function to replace the running network with the one of the network
+    /// initialization file at `path`
+    ///
+    /// The load is transactional: the file is parsed and validated, and every node
+    /// is built before anything changes, so that on error the running network is
+    /// left intact. Then the drones are sent the crash command, the old channels
+    /// are dropped and the new nodes are started.
+    /// Drones get a random implementation, while clients and servers are built by
+    /// the factories given to `SimulationControllerBuilder`: a client is a chat
+    /// client if it was one before, or if there is no web client factory.
+    ///
+    /// # Errors
+    /// If the file can't be read or is not a valid configuration, or if a node can't be built
+    pub fn load_topology(&mut self, path: &Path) -> Result<(), LoadError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| LoadError::Read(format!("Cannot read {}: {e}", path.display())))?;
+        let config = import::load_config(&content)?;
+        let chat_clients = self.chat_clients_channels.keys().copied().collect();
+        self.replace_topology(config, &chat_clients, path)
+    }
+
+    /// Helper function to replace the running network with the one of `config`,
+    /// loaded from `source`, see `load_topology`
+    ///
+    /// The `chat_clients` are built by the chat client factory, the other
+    /// clients by the web client factory if given.
+    #[allow(clippy::too_many_lines)]
+    fn replace_topology(
+        &mut self,
+        mut config: Config,
+        chat_clients: &BTreeSet<NodeId>,
+        source: &Path,
+    ) -> Result<(), LoadError> {
+        check_bidirectional(
+            &mut config.drone,
+            &mut config.client,
+            &mut config.server,
+            ConstraintPolicy::Strict,
+        )
+        .map_err(LoadError::Invalid)?;
+        if !platform::CAN_SPAWN_DRONES {
+            return Err(LoadError::Spawn(
+                "Nodes can't be spawned on this platform".to_string(),
+            ));
+        }
+
+        // The packet channels are created first, so that each node is built with
+        // the senders of its neighbors
+        let mut packet_channels = config
+            .drone
+            .iter()
+            .map(|d| d.id)
+            .chain(config.client.iter().map(|c| c.id))
+            .chain(config.server.iter().map(|s| s.id))
+            .map(|id| (id, crossbeam_channel::unbounded()))
+            .collect::<HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)>>();
+        let senders = packet_channels
+            .iter()
+            .map(|(id, (sender, _))| (*id, sender.clone()))
+            .collect::<HashMap<NodeId, Sender<Packet>>>();
+        let neighbor_senders = |neighbors: &[NodeId]| {
+            neighbors
+                .iter()
+                .filter_map(|id| senders.get(id).map(|sender| (*id, sender.clone())))
+                .collect::<HashMap<NodeId, Sender<Packet>>>()
+        };
+
+        let mut drones_channels = DChannels::new();
+        let mut new_drones = Vec::new();
+        for drone in &config.drone {
+            let nbrs = neighbor_senders(&drone.connected_node_ids);
+            let Some((packet_send, packet_recv)) = packet_channels.remove(&drone.id) else {
+                return Err(LoadError::Invalid(format!(
+                    "ID {} is used more than once",
+                    drone.id
+                )));
+            };
+            let (name, drone_factory) =
+                DRONE_FACTORY[rand::rng().random_range(0..DRONE_FACTORY.len())];
+            let (sender_command, receiver_command) = crossbeam_channel::unbounded();
+            let (send_event, receive_event) = crossbeam_channel::unbounded();
+            let new_drone = drone_factory(
+                drone.id,
+                send_event,
+                receiver_command,
+                packet_recv.clone(),
+                nbrs,
+                drone.pdr,
+            );
+            drones_channels.insert(
+                drone.id,
+                (sender_command, receive_event, packet_send, packet_recv),
+            );
+            new_drones.push((drone.id, name, new_drone));
+        }
+
+        let mut web_clients_channels = WCChannels::new();
+        let mut chat_clients_channels = CCChannels::new();
+        let mut servers_channels = SChannels::new();
+        let mut runners = Vec::new();
+        for client in &config.client {
+            let nbrs = neighbor_senders(&client.connected_drone_ids);
+            let Some(packet) = packet_channels.remove(&client.id) else {
+                return Err(LoadError::Invalid(format!(
+                    "ID {} is used more than once",
+                    client.id
+                )));
+            };
+            let chat_factory = self
+                .chat_client_factory
+                .as_ref()
+                .filter(|_| chat_clients.contains(&client.id) || self.web_client_factory.is_none());
+            if let Some(factory) = chat_factory {
+                let (channels, runner) = build_node(factory, client.id, packet, nbrs);
+                chat_clients_channels.insert(client.id, channels);
+                runners.push(runner);
+            } else if let Some(factory) = &self.web_client_factory {
+                let (channels, runner) = build_node(factory, client.id, packet, nbrs);
+                web_clients_channels.insert(client.id, channels);
+                runners.push(runner);
+            } else {
+                return Err(LoadError::Spawn(format!(
+                    "No factory was given for client {}",
+                    client.id
+                )));
+            }
+        }
+        for server in &config.server {
+            let nbrs = neighbor_senders(&server.connected_drone_ids);
+            let Some(packet) = packet_channels.remove(&server.id) else {
+                return Err(LoadError::Invalid(format!(
+                    "ID {} is used more than once",
+                    server.id
+                )));
+            };
+            let Some(factory) = &self.server_factory else {
+                return Err(LoadError::Spawn(format!(
+                    "No factory was given for server {}",
+                    server.id
+                )));
+            };
+            let (channels, runner) = build_node(factory, server.id, packet, nbrs);
+            servers_channels.insert(server.id, channels);
+            runners.push(runner);
+        }
+
+        let graph = generate_graph(
+            &drones_channels,
+            &web_clients_channels,
+            &chat_clients_channels,
+            &servers_channels,
+            &config.drone,
+            &config.client,
+            &config.server,
+        )
+        .map_err(LoadError::Invalid)?;
+
+        // From here on the load can't fail: the running network is stopped and replaced
+        for (_, node) in self.graph.nodes_iter() {
+            if let WidgetType::Drone(drone_widget) = node.payload() {
+                let _ = drone_widget.send_crash_command();
+            }
+        }
+        // Clients and servers have no command to stop them: they are cut off from
+        // their neighbors, then see their channels close when they are replaced
+        let old_nodes = self
+            .all_clients()
+            .map(|c| (c.id, c.connected_drone_ids.clone()))
+            .chain(
+                self.servers
+                    .iter()
+                    .map(|s| (s.id, s.connected_drone_ids.clone())),
+            )
+            .collect::<Vec<(NodeId, Vec<NodeId>)>>();
+        for (id, neighbors) in old_nodes {
+            if let Some(idx) = self.get_node_idx(id) {
+                let widget = self.graph.node(idx).unwrap().payload();
+                for neighbor in neighbors {
+                    let _ = widget.rm_neighbor_helper(neighbor);
+                }
+            }
+        }
+        let running = self.drone_threads.keys().copied().collect::<Vec<NodeId>>();
+        for drone_id in running {
+            self.retire_drone_thread(drone_id);
+        }
+        let (web_clients, chat_clients): (Vec<Client>, Vec<Client>) = config
+            .client
+            .into_iter()
+            .partition(|c| web_clients_channels.contains_key(&c.id));
+        self.drones_channels = drones_channels;
+        self.web_clients_channels = web_clients_channels;
+        self.chat_clients_channels = chat_clients_channels;
+        self.servers_channels = servers_channels;
+        self.drones = config.drone;
+        self.web_clients = web_clients;
+        self.chat_clients = chat_clients;
+        self.servers = config.server;
+        self.graph = graph;
+        self.node_indices = self
+            .graph
+            .nodes_iter()
+            .map(|(idx, node)| (node.payload().get_id_helper(), idx))
+            .collect();
+        self.selected_node = None;
+        self.selected_nodes.clear();
+        self.selected_edge = None;
+        self.unresponsive_nodes.clear();
+        self.disconnected_nodes.clear();
+        self.crashed_receivers.clear();
+        self.retired_ids.clear();
+        // The state kept by id only applies to the nodes of the new network
+        let ids = self
+            .node_indices
+            .keys()
+            .copied()
+            .collect::<HashSet<NodeId>>();
+        self.notes.retain(|id, _| ids.contains(id));
+        self.aliases.retain(|id, _| ids.contains(id));
+        self.pinned_nodes.retain(|id| ids.contains(id));
+        self.muted_nodes.retain(|id| ids.contains(id));
+        self.log_hover.retain(|id| ids.contains(id));
+        self.clusters.retain(|id| ids.contains(&id));
+        if self.log_node_filter.is_some_and(|id| !ids.contains(&id)) {
+            self.log_node_filter = None;
+        }
+        if self.reconnect_target.is_some_and(|id| !ids.contains(&id)) {
+            self.reconnect_target = None;
+        }
+        let now = Instant::now();
+        self.started_at = self.node_indices.keys().map(|id| (*id, now)).collect();
+        self.pdr_history = PdrHistory::default();
+        for drone in &self.drones {
+            self.pdr_history.record_pdr(drone.id, drone.pdr);
+        }
+        // The changes to the topology are now relative to the loaded file
+        self.initial_topology = self.topology_snapshot();
+        self.operations = OperationLog::default();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.start_time = now;
+
+        for (drone_id, name, new_drone) in new_drones {
+            if let Some(idx) = self.get_node_idx(drone_id) {
+                if let WidgetType::Drone(drone_widget) =
+                    self.graph.node_mut(idx).unwrap().payload_mut()
+                {
+                    drone_widget.set_implementation(name);
+                }
+            }
+            self.track_drone_thread(drone_id, platform::run_drone(new_drone));
+        }
+        for runner in runners {
+            platform::run_node(runner);
+        }
+        self.refresh_labels();
+        self.topology_changed();
+        self.log(
+            LogLevel::Info,
+            format!(
+                "[CONTROLLER] Topology loaded from {}: {} drones, {} clients, {} servers",
+                source.display(),
+                self.drones.len(),
+                self.web_clients.len() + self.chat_clients.len(),
+                self.servers.len()
+            ),
+        );
+        Ok(())
+    }
+
+    /// Function to export the statistics of the nodes as CSV files
+    ///
+    /// The files are written by a worker thread into `tmp/stats`, so that the
+    /// UI does not stall; the outcome is logged once `poll_stats_export` sees it.
+    pub(crate) fn export_stats(&mut self) {
+        if self.stats_export.is_some() {
+            self.log(LogLevel::Warn, "[CONTROLLER] Stats export already running");
+            return;
+        }
+
+        let nodes = self
+            .graph
+            .nodes_iter()
+            .map(|(idx, node)| {
+                let id = node.payload().get_id_helper();
+                NodeInfo {
+                    id,
+                    alias: self.aliases.get(&id).cloned(),
+                    kind: node.payload().get_kind_helper(),
+                    configured_pdr: self.drones.iter().find(|d| d.id == id).map(|d| d.pdr),
+                    neighbors: self.graph.g.neighbors(idx).count(),
+                    uptime: self
+                        .started_at
+                        .get(&id)
+                        .map(Instant::elapsed)
+                        .unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let snapshot = self.stats.snapshot(self.start_time.elapsed());
+
+        let dir = self.output_dir.join("stats");
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        self.stats_export = Some(receiver);
+        platform::run_in_background(move || {
+            let result = stats::export_csv(&dir, &nodes, &snapshot).map(|()| dir);
+            let _ = sender.send(result);
+        });
+    }
+
+    /// Function to log the outcome of the stats export, once the worker thread is done
+    pub(crate) fn poll_stats_export(&mut self) {
+        let Some(receiver) = &self.stats_export else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("worker thread stopped".to_string()),
+        };
+        self.stats_export = None;
+        match result {
+            Ok(dir) => self.log(
+                LogLevel::Info,
+                format!("[CONTROLLER] Stats exported to {}", dir.display()),
+            ),
+            Err(error) => self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Stats export failed: {error}"),
+            ),
+        }
+    }
+
+    /// Function to queue the configuration files dropped on the window for import
+    ///
+    /// Each file is parsed and validated right away, the files that can't be
+    /// imported are reported in a toast.
+    pub(crate) fn receive_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        let existing = self
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload().get_id_helper())
+            .collect::<BTreeSet<NodeId>>();
+        let existing_edges = self.topology_snapshot().edges;
+        let mut errors = Vec::new();
+        for file in dropped {
+            let name = file
+                .path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map_or(file.name.clone(), |name| name.to_string_lossy().to_string());
+            if !Path::new(&name)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+            {
+                errors.push(format!("{name} is not a TOML file"));
+                continue;
+            }
+            // The content is provided on the web, the path on native
+            let content = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string()),
+                (None, Some(path)) => std::fs::read_to_string(path).map_err(|e| e.to_string()),
+                (None, None) => Err("No content".to_string()),
+            };
+            match content.and_then(|content| {
+                import::plan_import(name.clone(), &content, &existing, &existing_edges)
+            }) {
+                Ok(plan) => self.pending_imports.push_back(plan),
+                Err(error) => errors.push(format!("Cannot import {name}: {error}")),
+            }
+        }
+        if !errors.is_empty() {
+            self.toast = Some((errors.join("\n"), Instant::now()));
+        }
+    }
+
+    /// Function to apply an import confirmed by the user: the drones are spawned
+    /// first, then the edges are added
+    ///
+    /// Each operation goes through `apply_op`, so the rejected ones are skipped
+    /// and reported without undoing the others.
+    pub(crate) fn apply_import(&mut self, plan: ImportPlan) {
+        let mut failures = Vec::new();
+        let mut spawned = 0;
+        for (drone_id, pdr) in &plan.drones {
+            let op = TopologyOp::Spawn {
+                drone_id: *drone_id,
+                implementation: None,
+                pdr: *pdr,
+                neighbors: Vec::new(),
+            };
+            match self.apply_op(op) {
+                Ok(()) => spawned += 1,
+                Err(error) => failures.push(format!("Drone {drone_id}: {error}")),
+            }
+        }
+        let mut connected = 0;
+        for (a, b) in &plan.edges {
+            match self.apply_op(TopologyOp::Connect(*a, *b)) {
+                Ok(()) => connected += 1,
+                Err(error) => failures.push(format!("Edge {a}-{b}: {error}")),
+            }
+        }
+        self.log(
+            LogLevel::Info,
+            format!(
+                "[IMPORT] {}: {spawned} drones and {connected} edges added",
+                plan.file
+            ),
+        );
+        if !failures.is_empty() {
+            self.toast = Some((
+                format!(
+                    "Import of {} partially failed:\n{}",
+                    plan.file,
+                    failures.join("\n")
+                ),
+                Instant::now(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{
+        Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType,
+        FRAGMENT_DSIZE,
+    },
+};
+
+/// Type of the packet built by the packet forge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgeKind {
+    MsgFragment,
+    Ack,
+    Nack,
+    FloodRequest,
+    FloodResponse,
+}
+
+impl ForgeKind {
+    pub const ALL: [ForgeKind; 5] = [
+        ForgeKind::MsgFragment,
+        ForgeKind::Ack,
+        ForgeKind::Nack,
+        ForgeKind::FloodRequest,
+        ForgeKind::FloodResponse,
+    ];
+
+    /// Name of the packet type, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ForgeKind::MsgFragment => "MsgFragment",
+            ForgeKind::Ack => "Ack",
+            ForgeKind::Nack => "Nack",
+            ForgeKind::FloodRequest => "FloodRequest",
+            ForgeKind::FloodResponse => "FloodResponse",
+        }
+    }
+}
+
+/// Type of the `Nack` built by the packet forge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgeNack {
+    ErrorInRouting,
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient,
+}
+
+impl ForgeNack {
+    pub const ALL: [ForgeNack; 4] = [
+        ForgeNack::ErrorInRouting,
+        ForgeNack::DestinationIsDrone,
+        ForgeNack::Dropped,
+        ForgeNack::UnexpectedRecipient,
+    ];
+
+    /// Name of the nack type, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ForgeNack::ErrorInRouting => "ErrorInRouting",
+            ForgeNack::DestinationIsDrone => "DestinationIsDrone",
+            ForgeNack::Dropped => "Dropped",
+            ForgeNack::UnexpectedRecipient => "UnexpectedRecipient",
+        }
+    }
+}
+
+/// Inputs of the packet forge
+#[derive(Clone, Debug)]
+pub struct PacketForm {
+    pub kind: ForgeKind,
+    pub session_id: u64,
+    /// Hops of the routing header, built by adding the selected nodes in order
+    pub route: Vec<NodeId>,
+    pub hop_index: usize,
+    /// Flag to accept routes whose consecutive hops are not adjacent
+    pub allow_invalid_route: bool,
+    /// Node whose packet channel receives the packet, the current hop if `None`
+    pub target: Option<NodeId>,
+    pub fragment_index: u64,
+    pub total_n_fragments: u64,
+    /// Payload of the fragment, truncated to `FRAGMENT_DSIZE` bytes
+    pub data: String,
+    pub nack: ForgeNack,
+    /// Node reported by `ErrorInRouting` and `UnexpectedRecipient`
+    pub nack_node: NodeId,
+    pub flood_id: u64,
+    pub initiator_id: NodeId,
+}
+
+impl Default for PacketForm {
+    fn default() -> Self {
+        Self {
+            kind: ForgeKind::MsgFragment,
+            session_id: 0,
+            route: Vec::new(),
+            hop_index: 1,
+            allow_invalid_route: false,
+            target: None,
+            fragment_index: 0,
+            total_n_fragments: 1,
+            data: String::default(),
+            nack: ForgeNack::Dropped,
+            nack_node: 0,
+            flood_id: 0,
+            initiator_id: 0,
+        }
+    }
+}
+
+impl PacketForm {
+    /// Node whose packet channel receives the packet
+    #[must_use]
+    pub fn target(&self) -> Option<NodeId> {
+        self.target
+            .or_else(|| self.route.get(self.hop_index).copied())
+    }
+
+    /// Build the packet described by the form
+    ///
+    /// `path_trace` is used by the flood packets, it's the route with the type
+    /// of each node.
+    ///
+    /// # Errors
+    /// If the hop index is outside the route, or the fragment fields are inconsistent
+    pub fn build(&self, path_trace: Vec<(NodeId, NodeType)>) -> Result<Packet, String> {
+        if !self.route.is_empty() && self.hop_index >= self.route.len() {
+            return Err(format!(
+                "Hop index {} outside a route of {} hops",
+                self.hop_index,
+                self.route.len()
+            ));
+        }
+        let pack_type = match self.kind {
+            ForgeKind::MsgFragment => {
+                if self.fragment_index >= self.total_n_fragments {
+                    return Err("The fragment index must be less than the fragments".to_string());
+                }
+                let bytes = self.data.as_bytes();
+                let length = bytes.len().min(FRAGMENT_DSIZE);
+                let mut data = [0; FRAGMENT_DSIZE];
+                data[..length].copy_from_slice(&bytes[..length]);
+                PacketType::MsgFragment(Fragment {
+                    fragment_index: self.fragment_index,
+                    total_n_fragments: self.total_n_fragments,
+                    length: u8::try_from(length).map_err(|_| "Fragment too long".to_string())?,
+                    data,
+                })
+            }
+            ForgeKind::Ack => PacketType::Ack(Ack {
+                fragment_index: self.fragment_index,
+            }),
+            ForgeKind::Nack => PacketType::Nack(Nack {
+                fragment_index: self.fragment_index,
+                nack_type: match self.nack {
+                    ForgeNack::ErrorInRouting => NackType::ErrorInRouting(self.nack_node),
+                    ForgeNack::DestinationIsDrone => NackType::DestinationIsDrone,
+                    ForgeNack::Dropped => NackType::Dropped,
+                    ForgeNack::UnexpectedRecipient => NackType::UnexpectedRecipient(self.nack_node),
+                },
+            }),
+            ForgeKind::FloodRequest => PacketType::FloodRequest(FloodRequest {
+                flood_id: self.flood_id,
+                initiator_id: self.initiator_id,
+                path_trace,
+            }),
+            ForgeKind::FloodResponse => PacketType::FloodResponse(FloodResponse {
+                flood_id: self.flood_id,
+                path_trace,
+            }),
+        };
+        Ok(Packet {
+            routing_header: SourceRoutingHeader {
+                hop_index: self.hop_index,
+                hops: self.route.clone(),
+            },
+            session_id: self.session_id,
+            pack_type,
+        })
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeSet;
+
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+use wg_2024::{
+    config::{Client, Drone, Server},
+    network::NodeId,
+};
+
+use crate::stats::edge_key;
+
+/// Number of nodes of each kind in a generated topology
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TopologyCounts {
+    pub drones: usize,
+    pub web_clients: usize,
+    pub chat_clients: usize,
+    pub servers: usize,
+}
+
+/// Configuration of a generated topology
+///
+/// Web clients and chat clients are kept apart, since the controller tells
+/// them apart only by the channels they are spawned with.
+#[derive(Clone, Debug)]
+pub struct GeneratedTopology {
+    pub drones: Vec<Drone>,
+    pub web_clients: Vec<Client>,
+    pub chat_clients: Vec<Client>,
+    pub servers: Vec<Server>,
+}
+
+/// Generate a random topology satisfying the constraints of the network
+///
+/// The drones form a random spanning tree with some extra edges, so the
+/// network is connected and every client can reach every server through the
+/// drones. Each client is connected to 1 or 2 drones, each server to at least
+/// 2, and the PDR of the drones is between 0 and 0.3.
+/// Ids are assigned in order: drones first, then web clients, chat clients and servers.
+/// The same `seed` always produces the same topology.
+///
+/// # Errors
+/// If the counts can't satisfy the constraints, e.g. a server with less than
+/// 2 drones, or there are more nodes than available ids
+pub fn generate_topology(counts: TopologyCounts, seed: u64) -> Result<GeneratedTopology, String> {
+    let total = counts.drones + counts.web_clients + counts.chat_clients + counts.servers;
+    if total > usize::from(NodeId::MAX) + 1 {
+        return Err(format!("Too many nodes: {total}"));
+    }
+    if counts.drones == 0 && total > 0 {
+        return Err("At least 1 drone is needed to connect the clients and servers".to_string());
+    }
+    if counts.servers > 0 && counts.drones < 2 {
+        return Err("At least 2 drones are needed to connect a server".to_string());
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let ids = (0..=NodeId::MAX).take(total).collect::<Vec<NodeId>>();
+    let (drone_ids, ids) = ids.split_at(counts.drones);
+    let (web_client_ids, ids) = ids.split_at(counts.web_clients);
+    let (chat_client_ids, server_ids) = ids.split_at(counts.chat_clients);
+
+    // Random spanning tree of the drones, plus some extra edges
+    let mut edges: BTreeSet<(NodeId, NodeId)> = BTreeSet::new();
+    for (i, id) in drone_ids.iter().enumerate().skip(1) {
+        let parent = drone_ids[rng.random_range(0..i)];
+        edges.insert(edge_key(*id, parent));
+    }
+    for _ in 0..counts.drones / 2 {
+        let a = *drone_ids.choose(&mut rng).unwrap();
+        let b = *drone_ids.choose(&mut rng).unwrap();
+        if a != b {
+            edges.insert(edge_key(a, b));
+        }
+    }
+
+    let mut attach = |ids: &[NodeId], min: usize, max: usize, rng: &mut StdRng| {
+        for id in ids {
+            let links = rng.random_range(min..=max.min(counts.drones));
+            for drone_id in drone_ids.choose_multiple(rng, links) {
+                edges.insert(edge_key(*id, *drone_id));
+            }
+        }
+    };
+    attach(web_client_ids, 1, 2, &mut rng);
+    attach(chat_client_ids, 1, 2, &mut rng);
+    attach(server_ids, 2, 3, &mut rng);
+
+    let neighbors = |id: NodeId| -> Vec<NodeId> {
+        edges
+            .iter()
+            .filter_map(|(a, b)| match (*a == id, *b == id) {
+                (true, _) => Some(*b),
+                (_, true) => Some(*a),
+                _ => None,
+            })
+            .collect()
+    };
+    let clients = |ids: &[NodeId]| -> Vec<Client> {
+        ids.iter()
+            .map(|id| Client {
+                id: *id,
+                connected_drone_ids: neighbors(*id),
+            })
+            .collect()
+    };
+
+    Ok(GeneratedTopology {
+        drones: drone_ids
+            .iter()
+            .map(|id| Drone {
+                id: *id,
+                connected_node_ids: neighbors(*id),
+                pdr: f32::from(rng.random_range(0..=30_u8)) / 100.0,
+            })
+            .collect(),
+        web_clients: clients(web_client_ids),
+        chat_clients: clients(chat_client_ids),
+        servers: server_ids
+            .iter()
+            .map(|id| Server {
+                id: *id,
+                connected_drone_ids: neighbors(*id),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNTS: TopologyCounts = TopologyCounts {
+        drones: 10,
+        web_clients: 3,
+        chat_clients: 2,
+        servers: 2,
+    };
+
+    /// Ids of the drones reachable from the first drone through the drones
+    fn reachable_drones(topology: &GeneratedTopology) -> BTreeSet<NodeId> {
+        let mut reached = BTreeSet::from([topology.drones[0].id]);
+        let mut stack = vec![topology.drones[0].id];
+        while let Some(id) = stack.pop() {
+            let drone = topology.drones.iter().find(|d| d.id == id).unwrap();
+            for neighbor in &drone.connected_node_ids {
+                let is_drone = topology.drones.iter().any(|d| d.id == *neighbor);
+                if is_drone && reached.insert(*neighbor) {
+                    stack.push(*neighbor);
+                }
+            }
+        }
+        reached
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_topology() {
+        let first = generate_topology(COUNTS, 42).unwrap();
+        let second = generate_topology(COUNTS, 42).unwrap();
+
+        assert_eq!(format!("{first:?}"), format!("{second:?}"));
+    }
+
+    #[test]
+    fn generated_topologies_satisfy_the_constraints() {
+        for seed in 0..20 {
+            let topology = generate_topology(COUNTS, seed).unwrap();
+
+            let ids = topology
+                .drones
+                .iter()
+                .map(|d| d.id)
+                .chain(topology.web_clients.iter().map(|c| c.id))
+                .chain(topology.chat_clients.iter().map(|c| c.id))
+                .chain(topology.servers.iter().map(|s| s.id))
+                .collect::<Vec<NodeId>>();
+            assert_eq!(ids, (0..17).collect::<Vec<NodeId>>());
+            assert_eq!(reachable_drones(&topology).len(), COUNTS.drones);
+            for drone in &topology.drones {
+                assert!((0.0..=0.3).contains(&drone.pdr), "seed {seed}");
+            }
+            for client in topology.web_clients.iter().chain(&topology.chat_clients) {
+                let links = client.connected_drone_ids.len();
+                assert!((1..=2).contains(&links), "seed {seed}");
+                assert!(client.connected_drone_ids.iter().all(|id| *id < 10));
+            }
+            for server in &topology.servers {
+                assert!(server.connected_drone_ids.len() >= 2, "seed {seed}");
+                assert!(server.connected_drone_ids.iter().all(|id| *id < 10));
+            }
+        }
+    }
+
+    #[test]
+    fn the_edges_are_listed_at_both_ends() {
+        let topology = generate_topology(COUNTS, 7).unwrap();
+
+        for server in &topology.servers {
+            for drone_id in &server.connected_drone_ids {
+                let drone = topology.drones.iter().find(|d| d.id == *drone_id).unwrap();
+                assert!(drone.connected_node_ids.contains(&server.id));
+            }
+        }
+    }
+
+    #[test]
+    fn impossible_counts_are_rejected() {
+        let counts = |drones, servers| TopologyCounts {
+            drones,
+            web_clients: 1,
+            chat_clients: 0,
+            servers,
+        };
+
+        assert!(generate_topology(counts(0, 0), 1).is_err());
+        assert!(generate_topology(counts(1, 1), 1).is_err());
+        assert!(generate_topology(counts(256, 0), 1).is_err());
+        assert!(generate_topology(counts(1, 0), 1).is_ok());
+    }
+
+    #[test]
+    fn an_empty_topology_is_generated() {
+        let counts = TopologyCounts {
+            drones: 0,
+            web_clients: 0,
+            chat_clients: 0,
+            servers: 0,
+        };
+
+        let topology = generate_topology(counts, 1).unwrap();
+
+        assert!(topology.drones.is_empty() && topology.servers.is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Groups of drones collapsed into a single node of the graph
+
+use std::collections::HashMap;
+
+use wg_2024::network::NodeId;
+
+use crate::SimulationController;
+
+impl SimulationController {
+    /// Function to collapse a group of drones into a single node
+    ///
+    /// The members are moved on top of the representative of the group, their
+    /// positions are saved to be restored when the group is expanded.
+    pub(crate) fn collapse_group(&mut self, name: &str) {
+        let Some((_, members)) = self.clusters.groups().find(|(group, _)| *group == name) else {
+            return;
+        };
+        let positions = members
+            .iter()
+            .filter_map(|id| {
+                let idx = self.get_node_idx(*id)?;
+                Some((*id, self.graph.node(idx)?.location()))
+            })
+            .collect::<HashMap<NodeId, egui::Pos2>>();
+        self.clusters.collapse(name, positions);
+        self.stack_collapsed_groups();
+        self.refresh_labels();
+    }
+
+    /// Function to expand a collapsed group, restoring the positions of its members
+    pub(crate) fn expand_group(&mut self, name: &str) {
+        let Some(positions) = self.clusters.expand(name) else {
+            return;
+        };
+        self.restore_positions(&positions);
+        self.refresh_labels();
+    }
+
+    /// Function to delete a group, expanding it first if collapsed
+    pub(crate) fn delete_group(&mut self, name: &str) {
+        if let Some(positions) = self.clusters.delete(name) {
+            self.restore_positions(&positions);
+        }
+        self.refresh_labels();
+    }
+
+    /// Helper function to move the nodes back to the given positions
+    fn restore_positions(&mut self, positions: &HashMap<NodeId, egui::Pos2>) {
+        for (id, pos) in positions {
+            if let Some(idx) = self.get_node_idx(*id) {
+                self.graph.node_mut(idx).unwrap().set_location(*pos);
+            }
+        }
+    }
+
+    /// Function to move the members of each collapsed group on top of its representative
+    ///
+    /// Dragging the representative drags the whole group.
+    pub(crate) fn stack_collapsed_groups(&mut self) {
+        let stacks = self
+            .clusters
+            .collapsed_groups()
+            .filter_map(|(name, members)| {
+                let representative = self.clusters.representative(name)?;
+                let location = self
+                    .graph
+                    .node(self.get_node_idx(representative)?)?
+                    .location();
+                Some((members.clone(), location))
+            })
+            .collect::<Vec<_>>();
+        for (members, location) in stacks {
+            for id in members {
+                if let Some(idx) = self.get_node_idx(id) {
+                    self.graph.node_mut(idx).unwrap().set_location(location);
+                }
+            }
+        }
+    }
+}
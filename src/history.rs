@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use web_time::Instant;
+use wg_2024::network::NodeId;
+
+/// How long the history of each drone is kept
+pub const HISTORY_RETENTION: Duration = Duration::from_secs(5 * 60);
+
+/// Width of the time buckets of the observed drop rate
+const BUCKET_WIDTH: Duration = Duration::from_secs(5);
+
+/// Packets handled by a drone in a time bucket
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    start: Instant,
+    sent: u64,
+    dropped: u64,
+}
+
+/// History of a single drone
+#[derive(Clone, Debug, Default)]
+struct DroneHistory {
+    /// Configured PDR, with the time it was applied
+    pdr_changes: VecDeque<(Instant, f32)>,
+    buckets: VecDeque<Bucket>,
+}
+
+impl DroneHistory {
+    /// Get the bucket of the current time, opening a new one if needed
+    fn current_bucket(&mut self, now: Instant) -> &mut Bucket {
+        let open = self
+            .buckets
+            .back()
+            .is_some_and(|bucket| now.duration_since(bucket.start) < BUCKET_WIDTH);
+        if !open {
+            self.buckets.push_back(Bucket {
+                start: now,
+                sent: 0,
+                dropped: 0,
+            });
+        }
+        self.buckets.back_mut().unwrap()
+    }
+
+    /// Drop the entries older than the retention, keeping the PDR in effect at the cutoff
+    fn prune(&mut self, now: Instant) {
+        let expired = |time: Instant| now.duration_since(time) > HISTORY_RETENTION;
+        while self.buckets.front().is_some_and(|b| expired(b.start)) {
+            self.buckets.pop_front();
+        }
+        while self.pdr_changes.len() > 1 && self.pdr_changes.get(1).is_some_and(|c| expired(c.0)) {
+            self.pdr_changes.pop_front();
+        }
+    }
+}
+
+/// Series to plot for a drone, with the time in seconds relative to now (negative)
+#[derive(Clone, Debug, Default)]
+pub struct HistorySeries {
+    /// Configured PDR as a step function
+    pub pdr: Vec<[f64; 2]>,
+    /// Observed drop rate of each bucket
+    pub drop_rate: Vec<[f64; 2]>,
+    /// Times the PDR was changed
+    pub changes: Vec<f64>,
+}
+
+/// Configured PDR and observed drop rate of the drones over the last minutes
+#[derive(Clone, Debug, Default)]
+pub struct PdrHistory {
+    drones: HashMap<NodeId, DroneHistory>,
+}
+
+impl PdrHistory {
+    /// Record the PDR applied to a drone, including the one it starts with
+    pub fn record_pdr(&mut self, drone_id: NodeId, pdr: f32) {
+        let now = Instant::now();
+        let history = self.drones.entry(drone_id).or_default();
+        history.pdr_changes.push_back((now, pdr));
+        history.prune(now);
+    }
+
+    /// Record a packet sent by the drone
+    pub fn record_sent(&mut self, drone_id: NodeId) {
+        let now = Instant::now();
+        let history = self.drones.entry(drone_id).or_default();
+        history.current_bucket(now).sent += 1;
+        history.prune(now);
+    }
+
+    /// Record a packet dropped by the drone
+    pub fn record_dropped(&mut self, drone_id: NodeId) {
+        let now = Instant::now();
+        let history = self.drones.entry(drone_id).or_default();
+        history.current_bucket(now).dropped += 1;
+        history.prune(now);
+    }
+
+    /// Forget the history of a drone, e.g. after a crash
+    pub fn forget(&mut self, drone_id: NodeId) {
+        self.drones.remove(&drone_id);
+    }
+
+    /// Build the series to plot for the drone, if it has a history
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn series(&self, drone_id: NodeId) -> Option<HistorySeries> {
+        let history = self.drones.get(&drone_id)?;
+        let now = Instant::now();
+        let x = |time: Instant| -now.duration_since(time).as_secs_f64();
+        let cutoff = -HISTORY_RETENTION.as_secs_f64();
+
+        let mut series = HistorySeries::default();
+        for (i, (time, pdr)) in history.pdr_changes.iter().enumerate() {
+            let start = x(*time).max(cutoff);
+            if i > 0 {
+                // Close the previous step
+                if let Some(last) = series.pdr.last().copied() {
+                    series.pdr.push([start, last[1]]);
+                }
+                series.changes.push(start);
+            }
+            series.pdr.push([start, f64::from(*pdr)]);
+        }
+        if let Some(last) = series.pdr.last().copied() {
+            series.pdr.push([0.0, last[1]]);
+        }
+
+        series.drop_rate = history
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.sent + bucket.dropped > 0)
+            .map(|bucket| {
+                let rate = bucket.dropped as f64 / (bucket.sent + bucket.dropped) as f64;
+                let middle = bucket.start + BUCKET_WIDTH / 2;
+                [x(middle.min(now)), rate]
+            })
+            .collect();
+        Some(series)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drones_without_events_have_no_series() {
+        let mut history = PdrHistory::default();
+        assert!(history.series(1).is_none());
+
+        history.record_sent(1);
+        history.forget(1);
+
+        assert!(history.series(1).is_none());
+    }
+
+    #[test]
+    fn the_pdr_is_plotted_as_a_step_function() {
+        let mut history = PdrHistory::default();
+        history.record_pdr(1, 0.1);
+        history.record_pdr(1, 0.5);
+
+        let series = history.series(1).unwrap();
+
+        let values = series.pdr.iter().map(|point| point[1]).collect::<Vec<_>>();
+        let low = f64::from(0.1_f32);
+        assert_eq!(values, [low, low, 0.5, 0.5]);
+        assert_eq!(series.changes.len(), 1);
+        assert_eq!(series.pdr[1][0], series.changes[0]);
+        assert_eq!(series.pdr[3][0], 0.0);
+        assert!(series.pdr.windows(2).all(|pair| pair[0][0] <= pair[1][0]));
+    }
+
+    #[test]
+    fn the_drop_rate_is_observed_per_bucket() {
+        let mut history = PdrHistory::default();
+        for _ in 0..3 {
+            history.record_sent(2);
+        }
+        history.record_dropped(2);
+
+        let series = history.series(2).unwrap();
+
+        assert_eq!(series.drop_rate.len(), 1);
+        assert_eq!(series.drop_rate[0][1], 0.25);
+        assert!(series.drop_rate[0][0] <= 0.0);
+        assert!(series.pdr.is_empty());
+    }
+
+    #[test]
+    fn old_entries_are_pruned_keeping_the_pdr_in_effect() {
+        let mut history = DroneHistory::default();
+        let start = Instant::now();
+        history.pdr_changes.push_back((start, 0.1));
+        history
+            .pdr_changes
+            .push_back((start + Duration::from_secs(1), 0.2));
+        history.current_bucket(start).sent += 1;
+
+        history.prune(start + HISTORY_RETENTION + Duration::from_secs(2));
+
+        assert!(history.buckets.is_empty());
+        assert_eq!(history.pdr_changes.len(), 1);
+        assert_eq!(history.pdr_changes[0].1, 0.2);
+    }
+}
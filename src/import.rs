@@ -0,0 +1,580 @@
+use std::{collections::BTreeSet, error::Error, fmt, io};
+
+use wg_2024::{
+    config::{Client, Config, Drone, Server},
+    network::NodeId,
+};
+
+use crate::{stats::edge_key, validation::ControllerError};
+
+/// What importing a network initialization file adds to the running network
+///
+/// Only drones can be spawned by the controller: the clients and servers of
+/// the file that are not in the network yet are skipped, with their edges.
+#[derive(Clone, Debug)]
+pub struct ImportPlan {
+    /// Name of the imported file
+    pub file: String,
+    /// Drones to spawn, with their PDR
+    pub drones: Vec<(NodeId, f32)>,
+    /// Edges to add, normalized with `edge_key`
+    pub edges: BTreeSet<(NodeId, NodeId)>,
+    /// Nodes of the file that are not imported, with the reason
+    pub skipped: Vec<String>,
+}
+
+impl ImportPlan {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.drones.is_empty() && self.edges.is_empty()
+    }
+}
+
+/// Reason why a network initialization file could not be loaded, see
+/// `SimulationController::load_topology`
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadError {
+    /// The file can't be read
+    Read(String),
+    /// The file is not a valid TOML configuration
+    Parse(String),
+    /// The configuration breaks a rule of the network, e.g. a duplicated id
+    Invalid(String),
+    /// A node of the configuration can't be created, e.g. without a client factory
+    Spawn(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Read(error) => write!(f, "{error}"),
+            LoadError::Parse(error) => write!(f, "Invalid TOML: {error}"),
+            LoadError::Invalid(error) => write!(f, "Invalid configuration: {error}"),
+            LoadError::Spawn(error) => write!(f, "Cannot create the nodes: {error}"),
+        }
+    }
+}
+
+/// Reason why a JSON adjacency list could not be imported, see
+/// `SimulationController::import_json_topology`
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file can't be read
+    Read(io::Error),
+    /// The file is not valid JSON, or not in the adjacency list format
+    Parse(String),
+    /// The topology breaks a rule of the network, e.g. an edge between two clients
+    InvalidTopology(String),
+    /// A node of the topology can't be created, e.g. without a client factory
+    Spawn(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Read(error) => write!(f, "Cannot read the file: {error}"),
+            ImportError::Parse(error) => write!(f, "Invalid JSON topology: {error}"),
+            ImportError::InvalidTopology(error) => write!(f, "Invalid topology: {error}"),
+            ImportError::Spawn(error) => write!(f, "Cannot create the nodes: {error}"),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Read(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(error: io::Error) -> Self {
+        ImportError::Read(error)
+    }
+}
+
+/// The network is replaced as by `load_topology`, whose errors are mapped to
+/// the ones of the import
+impl From<LoadError> for ImportError {
+    fn from(error: LoadError) -> Self {
+        match error {
+            LoadError::Read(error) => ImportError::Read(io::Error::other(error)),
+            LoadError::Parse(error) => ImportError::Parse(error),
+            LoadError::Invalid(error) => ImportError::InvalidTopology(error),
+            LoadError::Spawn(error) => ImportError::Spawn(error),
+        }
+    }
+}
+
+/// Reason why the running network could not be saved as a network initialization
+/// file, see `SimulationController::save_topology`
+#[derive(Debug)]
+pub enum SaveError {
+    /// The configuration can't be serialized as TOML
+    Serialize(toml::ser::Error),
+    /// The file can't be written
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Serialize(error) => write!(f, "Cannot serialize the topology: {error}"),
+            SaveError::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SaveError::Serialize(error) => Some(error),
+            SaveError::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(error: toml::ser::Error) -> Self {
+        SaveError::Serialize(error)
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+/// Parse the content of a network initialization file
+///
+/// # Errors
+/// If the content is not a valid TOML configuration
+pub fn parse_config(content: &str) -> Result<Config, String> {
+    toml::from_str(content).map_err(|e| e.to_string())
+}
+
+/// Check the file on its own: unique ids, PDR between 0 and 1 and
+/// connections towards nodes of the file or of the network
+fn validate_config(config: &Config, existing: &BTreeSet<NodeId>) -> Result<(), String> {
+    let mut ids = BTreeSet::new();
+    let nodes = config
+        .drone
+        .iter()
+        .map(|d| (d.id, &d.connected_node_ids))
+        .chain(config.client.iter().map(|c| (c.id, &c.connected_drone_ids)))
+        .chain(config.server.iter().map(|s| (s.id, &s.connected_drone_ids)));
+    for (id, _) in nodes.clone() {
+        if !ids.insert(id) {
+            return Err(format!("ID {id} is used more than once"));
+        }
+    }
+    if let Some(drone) = config.drone.iter().find(|d| !(0.0..=1.0).contains(&d.pdr)) {
+        return Err(format!(
+            "PDR {} of drone {} is not between 0 and 1",
+            drone.pdr, drone.id
+        ));
+    }
+    for (id, neighbors) in nodes {
+        if neighbors.contains(&id) {
+            return Err(format!("Node {id} is connected to itself"));
+        }
+        if let Some(neighbor) = neighbors
+            .iter()
+            .find(|n| !ids.contains(n) && !existing.contains(n))
+        {
+            return Err(format!("Node {id} is connected to unknown node {neighbor}"));
+        }
+    }
+    Ok(())
+}
+
+/// Parse and validate a network initialization file on its own, to replace the network
+///
+/// # Errors
+/// If the content can't be parsed or is not a valid configuration
+pub fn load_config(content: &str) -> Result<Config, LoadError> {
+    let config = parse_config(content).map_err(LoadError::Parse)?;
+    validate_config(&config, &BTreeSet::new()).map_err(LoadError::Invalid)?;
+    Ok(config)
+}
+
+/// A topology in the JSON adjacency list format, converted to a configuration
+#[derive(Clone, Debug)]
+pub struct JsonTopology {
+    pub config: Config,
+    /// Clients of the configuration that are chat clients, the others are web clients
+    pub chat_clients: BTreeSet<NodeId>,
+}
+
+/// Parse and validate a topology in the JSON adjacency list format, e.g.
+/// `{"nodes": [{"id":5,"type":"Drone","pdr":0.1},...], "edges": [[5,6],...]}`
+///
+/// The type of a node is `Drone`, `WebClient`, `ChatClient` or `Server`, and
+/// only drones have a PDR. Besides the checks of the network initialization
+/// files, every edge must have a drone on one side, clients can have at most 2
+/// connections and servers need at least 2.
+///
+/// # Errors
+/// If the content can't be parsed or is not a valid topology
+pub fn parse_json_topology(content: &str) -> Result<JsonTopology, ImportError> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| ImportError::Parse(e.to_string()))?;
+    let parse_id = |value: &serde_json::Value| {
+        value
+            .as_u64()
+            .and_then(|id| NodeId::try_from(id).ok())
+            .ok_or_else(|| ImportError::Parse(format!("Invalid node ID {value}")))
+    };
+
+    let mut drone = Vec::new();
+    let mut client = Vec::new();
+    let mut server = Vec::new();
+    let mut chat_clients = BTreeSet::new();
+    let nodes = value["nodes"]
+        .as_array()
+        .ok_or_else(|| ImportError::Parse("Missing array `nodes`".to_string()))?;
+    for node in nodes {
+        let id = parse_id(&node["id"])?;
+        match node["type"].as_str() {
+            Some("Drone") => {
+                let pdr = match node.get("pdr") {
+                    None => 0.0,
+                    Some(pdr) => pdr.as_f64().ok_or_else(|| {
+                        ImportError::Parse(format!("Invalid PDR {pdr} of drone {id}"))
+                    })?,
+                };
+                #[allow(clippy::cast_possible_truncation)]
+                let pdr = pdr as f32;
+                drone.push(Drone {
+                    id,
+                    connected_node_ids: Vec::new(),
+                    pdr,
+                });
+            }
+            Some(kind @ ("WebClient" | "ChatClient")) => {
+                if kind == "ChatClient" {
+                    chat_clients.insert(id);
+                }
+                client.push(Client {
+                    id,
+                    connected_drone_ids: Vec::new(),
+                });
+            }
+            Some("Server") => server.push(Server {
+                id,
+                connected_drone_ids: Vec::new(),
+            }),
+            _ => {
+                return Err(ImportError::Parse(format!(
+                    "Invalid type {} of node {id}",
+                    node["type"]
+                )))
+            }
+        }
+    }
+
+    let mut edges = BTreeSet::new();
+    let pairs = value["edges"]
+        .as_array()
+        .ok_or_else(|| ImportError::Parse("Missing array `edges`".to_string()))?;
+    for pair in pairs {
+        match pair.as_array().map(Vec::as_slice) {
+            Some([a, b]) => edges.insert(edge_key(parse_id(a)?, parse_id(b)?)),
+            _ => return Err(ImportError::Parse(format!("Invalid edge {pair}"))),
+        };
+    }
+    let mut config = Config {
+        drone,
+        client,
+        server,
+    };
+    for (a, b) in edges {
+        for (id, neighbor) in [(a, b), (b, a)] {
+            if !add_neighbor(&mut config, id, neighbor) {
+                return Err(ImportError::InvalidTopology(format!(
+                    "Edge {a}-{b} references unknown node {id}"
+                )));
+            }
+        }
+        if !config.drone.iter().any(|d| d.id == a || d.id == b) {
+            return Err(ImportError::InvalidTopology(format!(
+                "Edge {a}-{b} does not connect a drone"
+            )));
+        }
+    }
+
+    validate_config(&config, &BTreeSet::new()).map_err(ImportError::InvalidTopology)?;
+    if let Some(c) = config
+        .client
+        .iter()
+        .find(|c| c.connected_drone_ids.len() > 2)
+    {
+        return Err(ImportError::InvalidTopology(
+            ControllerError::ClientMaxConnections(c.id).to_string(),
+        ));
+    }
+    if let Some(s) = config
+        .server
+        .iter()
+        .find(|s| s.connected_drone_ids.len() < 2)
+    {
+        return Err(ImportError::InvalidTopology(
+            ControllerError::ServerMinConnections(s.id).to_string(),
+        ));
+    }
+    Ok(JsonTopology {
+        config,
+        chat_clients,
+    })
+}
+
+/// Helper function to add `neighbor` to the connections of the node `id`,
+/// returns false if the configuration has no such node
+fn add_neighbor(config: &mut Config, id: NodeId, neighbor: NodeId) -> bool {
+    let neighbors = if let Some(d) = config.drone.iter_mut().find(|d| d.id == id) {
+        &mut d.connected_node_ids
+    } else if let Some(c) = config.client.iter_mut().find(|c| c.id == id) {
+        &mut c.connected_drone_ids
+    } else if let Some(s) = config.server.iter_mut().find(|s| s.id == id) {
+        &mut s.connected_drone_ids
+    } else {
+        return false;
+    };
+    neighbors.push(neighbor);
+    true
+}
+
+/// Parse and validate the file `file`, then compute what it adds to the network
+/// made of the `existing` nodes and edges
+///
+/// Nodes already in the network keep their state, only their missing edges
+/// towards the imported drones are added.
+///
+/// # Errors
+/// If the file can't be parsed or is not a valid configuration
+pub fn plan_import(
+    file: String,
+    content: &str,
+    existing: &BTreeSet<NodeId>,
+    existing_edges: &BTreeSet<(NodeId, NodeId)>,
+) -> Result<ImportPlan, String> {
+    let config = parse_config(content)?;
+    validate_config(&config, existing)?;
+
+    let drones = config
+        .drone
+        .iter()
+        .filter(|d| !existing.contains(&d.id))
+        .map(|d| (d.id, d.pdr))
+        .collect::<Vec<(NodeId, f32)>>();
+    let mut skipped = Vec::new();
+    for (kind, id) in config
+        .client
+        .iter()
+        .map(|c| ("Client", c.id))
+        .chain(config.server.iter().map(|s| ("Server", s.id)))
+    {
+        if !existing.contains(&id) {
+            skipped.push(format!("{kind} {id}: only drones can be spawned"));
+        }
+    }
+
+    // The nodes the network will have after the import
+    let available = existing
+        .iter()
+        .copied()
+        .chain(drones.iter().map(|(id, _)| *id))
+        .collect::<BTreeSet<NodeId>>();
+    let edges = config
+        .drone
+        .iter()
+        .map(|d| (d.id, &d.connected_node_ids))
+        .chain(config.client.iter().map(|c| (c.id, &c.connected_drone_ids)))
+        .chain(config.server.iter().map(|s| (s.id, &s.connected_drone_ids)))
+        .flat_map(|(id, neighbors)| neighbors.iter().map(move |n| edge_key(id, *n)))
+        .filter(|(a, b)| available.contains(a) && available.contains(b))
+        .filter(|edge| !existing_edges.contains(edge))
+        .collect();
+
+    Ok(ImportPlan {
+        file,
+        drones,
+        edges,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file with 2 drones, a client and a server
+    const FILE: &str = r#"
+        [[drone]]
+        id = 1
+        connected_node_ids = [2, 10, 20]
+        pdr = 0.1
+
+        [[drone]]
+        id = 2
+        connected_node_ids = [1, 20]
+        pdr = 0.2
+
+        [[client]]
+        id = 10
+        connected_drone_ids = [1]
+
+        [[server]]
+        id = 20
+        connected_drone_ids = [1, 2]
+    "#;
+
+    #[test]
+    fn a_valid_file_is_loaded() {
+        let config = load_config(FILE).unwrap();
+
+        assert_eq!(config.drone.len(), 2);
+        assert_eq!(config.client[0].connected_drone_ids, [1]);
+        assert_eq!(config.server[0].id, 20);
+    }
+
+    #[test]
+    fn invalid_files_are_rejected_with_the_reason() {
+        assert!(matches!(load_config("drone = 1"), Err(LoadError::Parse(_))));
+        let cases = [
+            (
+                FILE.replace("id = 2\n", "id = 1\n"),
+                "ID 1 is used more than once",
+            ),
+            (
+                FILE.replace("pdr = 0.2", "pdr = 1.5"),
+                "PDR 1.5 of drone 2 is not between 0 and 1",
+            ),
+            (
+                FILE.replace("[1, 20]", "[2, 20]"),
+                "Node 2 is connected to itself",
+            ),
+            (
+                FILE.replace("[1, 20]", "[1, 30]"),
+                "Node 2 is connected to unknown node 30",
+            ),
+        ];
+        for (content, error) in cases {
+            assert_eq!(
+                load_config(&content).unwrap_err(),
+                LoadError::Invalid(error.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_new_drones_and_edges_are_planned() {
+        let existing = BTreeSet::from([1, 10, 30]);
+        let existing_edges = BTreeSet::from([(1, 10)]);
+
+        let plan = plan_import("net.toml".to_string(), FILE, &existing, &existing_edges).unwrap();
+
+        assert_eq!(plan.file, "net.toml");
+        assert_eq!(plan.drones, [(2, 0.2)]);
+        // The server is skipped, and so are its edges
+        assert_eq!(plan.edges, BTreeSet::from([(1, 2)]));
+        assert_eq!(plan.skipped, ["Server 20: only drones can be spawned"]);
+        assert!(!plan.is_empty());
+    }
+
+    #[test]
+    fn a_file_already_in_the_network_plans_nothing() {
+        let existing = BTreeSet::from([1, 2, 10, 20]);
+        let existing_edges = BTreeSet::from([(1, 2), (1, 10), (1, 20), (2, 20)]);
+
+        let plan = plan_import("net.toml".to_string(), FILE, &existing, &existing_edges).unwrap();
+
+        assert!(plan.is_empty());
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn connections_to_the_running_network_are_accepted() {
+        let content = "[[drone]]\nid = 5\nconnected_node_ids = [1]\npdr = 0.0\n";
+
+        assert!(plan_import(
+            "a.toml".to_string(),
+            content,
+            &BTreeSet::new(),
+            &BTreeSet::new()
+        )
+        .is_err());
+        let plan = plan_import(
+            "a.toml".to_string(),
+            content,
+            &BTreeSet::from([1]),
+            &BTreeSet::new(),
+        )
+        .unwrap();
+        assert_eq!(plan.edges, BTreeSet::from([(1, 5)]));
+    }
+
+    #[test]
+    fn json_nodes_are_converted_to_a_configuration() {
+        let content = r#"{"nodes": [
+            {"id": 1, "type": "Drone", "pdr": 0.25}, {"id": 2, "type": "Drone"},
+            {"id": 10, "type": "WebClient"}, {"id": 11, "type": "ChatClient"},
+            {"id": 20, "type": "Server"}
+        ], "edges": [[1, 2], [10, 1], [11, 2], [20, 1], [2, 20]]}"#;
+
+        let topology = parse_json_topology(content).unwrap();
+
+        let config = topology.config;
+        assert_eq!(config.drone[0].pdr, 0.25);
+        assert_eq!(config.drone[1].pdr, 0.0);
+        assert_eq!(config.drone[1].connected_node_ids, [1, 11, 20]);
+        assert_eq!(config.client.len(), 2);
+        assert_eq!(config.server[0].connected_drone_ids, [1, 2]);
+        assert_eq!(topology.chat_clients, BTreeSet::from([11]));
+    }
+
+    #[test]
+    fn json_topologies_break_the_connection_limits() {
+        let nodes = r#"{"id": 1, "type": "Drone"}, {"id": 2, "type": "Drone"},
+            {"id": 3, "type": "Drone"}"#;
+        let client = format!(
+            r#"{{"nodes": [{nodes}, {{"id": 10, "type": "WebClient"}}],
+            "edges": [[10, 1], [10, 2], [10, 3]]}}"#
+        );
+        let server = format!(
+            r#"{{"nodes": [{nodes}, {{"id": 20, "type": "Server"}}], "edges": [[20, 1]]}}"#
+        );
+
+        let error = parse_json_topology(&client).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid topology: {}",
+                ControllerError::ClientMaxConnections(10)
+            )
+        );
+        let error = parse_json_topology(&server).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid topology: {}",
+                ControllerError::ServerMinConnections(20)
+            )
+        );
+    }
+
+    #[test]
+    fn load_errors_map_to_import_errors() {
+        let error = ImportError::from(LoadError::Read("gone".to_string()));
+        assert!(matches!(&error, ImportError::Read(_)));
+        assert!(error.source().is_some());
+        let error = ImportError::from(LoadError::Invalid("bad".to_string()));
+        assert_eq!(error.to_string(), "Invalid topology: bad");
+        assert!(error.source().is_none());
+        let error = ImportError::from(LoadError::Spawn("no factory".to_string()));
+        assert_eq!(error.to_string(), "Cannot create the nodes: no factory");
+    }
+}
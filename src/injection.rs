@@ -0,0 +1,140 @@
+//! Traffic injected by the controller: forged packets and stress tests
+
+use wg_2024::{network::NodeId, packet::NodeType};
+
+use crate::{
+    format_route,
+    utils::{EventKind, LogLevel},
+    widgets::{NodeWidget, WidgetType},
+    SimulationController,
+};
+
+impl SimulationController {
+    /// Function to send the requests of the running stress test that are due
+    ///
+    /// It is called every frame, so the UI is never blocked between two sends.
+    /// Each request is tracked as pending in the web client widget, so that its
+    /// response can be correlated when the event arrives.
+    pub(crate) fn step_stress_test(&mut self, ctx: &egui::Context) {
+        let Some(test) = &mut self.stress_test else {
+            return;
+        };
+        let client_id = test.client_id;
+        let client_idx = self
+            .graph
+            .nodes_iter()
+            .find_map(|(idx, node)| match node.payload() {
+                WidgetType::WebClient(widget) if widget.id() == client_id => Some(idx),
+                _ => None,
+            });
+        let mut client_widget = client_idx.and_then(|idx| {
+            match self.graph.node_mut(idx).map(|node| node.payload_mut()) {
+                Some(WidgetType::WebClient(widget)) => Some(widget),
+                _ => None,
+            }
+        });
+        let mut failure = None;
+        while test.due() {
+            let (Some(channels), Some(client_widget)) = (
+                self.web_clients_channels.get(&client_id),
+                client_widget.as_mut(),
+            ) else {
+                failure = Some("web client not found".to_string());
+                break;
+            };
+            if self.unresponsive_nodes.contains(&client_id) {
+                failure = Some("web client is unresponsive".to_string());
+                break;
+            }
+            if channels.0.send(test.command()).is_err() {
+                failure = Some("channel closed".to_string());
+                break;
+            }
+            client_widget.track_request(test.pending_request());
+            test.mark_sent();
+        }
+        if let Some(delay) = test.time_to_next_send() {
+            ctx.request_repaint_after(delay);
+        }
+
+        if let Some(error) = failure {
+            test.cancel();
+            self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Stress test cancelled: {error}"),
+            );
+        }
+    }
+
+    /// Function to check that the consecutive hops of a route are adjacent
+    ///
+    /// # Errors
+    /// If a hop is not in the graph or two consecutive hops are not connected
+    fn check_route(&self, route: &[NodeId]) -> Result<(), String> {
+        for hops in route.windows(2) {
+            let (Some(a), Some(b)) = (self.get_node_idx(hops[0]), self.get_node_idx(hops[1]))
+            else {
+                return Err(format!("Hop {} or {} not found", hops[0], hops[1]));
+            };
+            if self.graph.edges_connecting(a, b).count() == 0 {
+                return Err(format!("Hops {} and {} are not adjacent", hops[0], hops[1]));
+            }
+        }
+        Ok(())
+    }
+
+    /// Function to build the packet described by the forge form and inject it
+    /// into the packet channel of the target node
+    ///
+    /// The injection is logged apart from the packets sent by the nodes.
+    pub(crate) fn inject_forged_packet(&mut self) -> Result<(), String> {
+        let form = &self.forge_form;
+        if !form.allow_invalid_route {
+            self.check_route(&form.route)?;
+        }
+        let target = form
+            .target()
+            .ok_or("Select the node to inject the packet into")?;
+        let target_idx = self
+            .get_node_idx(target)
+            .ok_or(format!("Node {target} not found"))?;
+        let path_trace = form
+            .route
+            .iter()
+            .filter_map(|id| {
+                let node_type = match self.graph.node(self.get_node_idx(*id)?)?.payload() {
+                    WidgetType::Drone(_) => NodeType::Drone,
+                    WidgetType::WebClient(_) | WidgetType::ChatClient(_) => NodeType::Client,
+                    WidgetType::Server(_) => NodeType::Server,
+                };
+                Some((*id, node_type))
+            })
+            .collect();
+        let packet = form.build(path_trace)?;
+
+        let (_, packet_ch) = self.get_sender_channel(target_idx);
+        let packet_type = SimulationController::get_pack_type(&packet);
+        let route = format_route(&packet.routing_header);
+        let event_string = format!(
+            "[FORGE] Injected {packet_type} into {}, {route}",
+            self.node_name(target)
+        );
+        let target_kind = self
+            .graph
+            .node(target_idx)
+            .unwrap()
+            .payload()
+            .get_node_kind_helper();
+        if packet_ch.send(packet.clone()).is_err() {
+            return Err(self.mark_unresponsive(target, "channel closed"));
+        }
+        self.log_packet(
+            (target, target_kind),
+            &packet,
+            EventKind::PacketInjected,
+            LogLevel::Info,
+            event_string,
+        );
+        Ok(())
+    }
+}
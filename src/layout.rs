@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+use egui::{Pos2, Vec2};
+use rand::Rng;
+use wg_2024::network::NodeId;
+
+/// Space reserved to each node by the layouts
+const NODE_SPACING: f32 = 100.0;
+
+/// Number of iterations of the force-directed layout
+const FORCE_ITERATIONS: usize = 200;
+
+/// Layout algorithm used to place the nodes of the graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutKind {
+    Random,
+    ForceDirected,
+}
+
+impl LayoutKind {
+    /// Name of the layout, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutKind::Random => "Random",
+            LayoutKind::ForceDirected => "Force-directed",
+        }
+    }
+}
+
+/// Side of the square the nodes are placed in, growing with their number
+#[allow(clippy::cast_precision_loss)]
+fn side(nodes: usize) -> f32 {
+    NODE_SPACING * (nodes as f32).sqrt().max(1.0)
+}
+
+/// Place the nodes not in `pinned` at random positions
+pub fn random(positions: &mut HashMap<NodeId, Pos2>, pinned: &HashSet<NodeId>) {
+    let side = side(positions.len());
+    let mut rng = rand::rng();
+    for (id, pos) in positions.iter_mut() {
+        if !pinned.contains(id) {
+            *pos = Pos2::new(rng.random_range(0.0..side), rng.random_range(0.0..side));
+        }
+    }
+}
+
+/// Place the nodes not in `pinned` with the Fruchterman-Reingold algorithm
+///
+/// Nodes repel each other, while `edges` pull their endpoints together.
+/// The free nodes start from random positions, so that the result does not
+/// depend on the previous layout; pinned nodes keep their position but still
+/// exert forces on the others.
+#[allow(clippy::cast_precision_loss)]
+pub fn force_directed(
+    positions: &mut HashMap<NodeId, Pos2>,
+    edges: &[(NodeId, NodeId)],
+    pinned: &HashSet<NodeId>,
+) {
+    random(positions, pinned);
+    let ids = positions.keys().copied().collect::<Vec<NodeId>>();
+    let k = NODE_SPACING;
+    let mut temperature = side(ids.len()) / 10.0;
+    let cooling = temperature / FORCE_ITERATIONS as f32;
+
+    for _ in 0..FORCE_ITERATIONS {
+        let mut displacement: HashMap<NodeId, Vec2> =
+            ids.iter().map(|id| (*id, Vec2::ZERO)).collect();
+
+        for (i, a) in ids.iter().enumerate() {
+            for b in &ids[i + 1..] {
+                let delta = positions[a] - positions[b];
+                let distance = delta.length().max(0.01);
+                let force = delta / distance * (k * k / distance);
+                *displacement.get_mut(a).unwrap() += force;
+                *displacement.get_mut(b).unwrap() -= force;
+            }
+        }
+
+        for (a, b) in edges {
+            let (Some(pos_a), Some(pos_b)) = (positions.get(a), positions.get(b)) else {
+                continue;
+            };
+            let delta = *pos_a - *pos_b;
+            let distance = delta.length().max(0.01);
+            let force = delta / distance * (distance * distance / k);
+            *displacement.get_mut(a).unwrap() -= force;
+            *displacement.get_mut(b).unwrap() += force;
+        }
+
+        for id in &ids {
+            if pinned.contains(id) {
+                continue;
+            }
+            let delta = displacement[id];
+            let length = delta.length();
+            if length > 0.0 {
+                *positions.get_mut(id).unwrap() += delta / length * length.min(temperature);
+            }
+        }
+        temperature -= cooling;
+    }
+}
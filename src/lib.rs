@@ -1,71 +1,137 @@
 #![warn(clippy::pedantic)]
 
 use ap2024_rustinpeace_nosounddrone::NoSoundDroneRIP;
+use clusters::Clusters;
 use common::slc_commands::{
     ChatClientCommand, ChatClientEvent, ServerCommand, ServerEvent, WebClientCommand,
     WebClientEvent,
 };
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use drone_bettercalldrone::BetterCallDrone;
 use eframe::egui;
-use egui::{
-    Button, CentralPanel, Color32, Layout, RichText, ScrollArea, SidePanel, TextStyle, TopBottomPanel
-};
-use egui_graphs::{
-    Graph, GraphView, LayoutRandom, LayoutStateRandom, SettingsInteraction, SettingsNavigation,
-    SettingsStyle,
-};
+use egui::{CentralPanel, Color32, RichText};
+use egui_graphs::Graph;
+use forge::PacketForm;
 use getdroned::GetDroned;
+use history::PdrHistory;
+use import::ImportPlan;
+use layout::LayoutKind;
 use petgraph::{
     graph::EdgeIndex,
     stable_graph::{NodeIndex, StableUnGraph},
     Undirected,
 };
-use rand::Rng;
+use reconnect::Ranking;
 use rolling_drone::RollingDrone;
 use rust_do_it::RustDoIt;
 use rust_roveri::RustRoveri;
 use rustafarian_drone::RustafarianDrone;
 use rusteze_drone::RustezeDrone;
 use rusty_drones::RustyDrone;
+use sink::EventSink;
+use stats::{IntakeMonitor, Stats};
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     fs::File,
-    io::Write,
-    path::Path,
+    io::BufWriter,
+    path::PathBuf,
+    thread::JoinHandle,
+    time::Duration,
 };
-use utils::EventQueue;
+use stress::{StressTest, StressTestForm};
+use topology::{Change, OperationLog, TopologyOperation, TopologySnapshot};
+use traces::{PacketTracer, TraceKey};
+use transfers::TransferTracker;
+use utils::{EventQueue, LogCategory, LogEvent, LogLevel, NodeKind};
+use validation::{ConnectionRejection, ConstraintPolicy};
+use web_time::Instant;
 use wg_2024::{
     config::{Client, Drone, Server},
     controller::{DroneCommand, DroneEvent},
     drone::Drone as DroneTrait,
-    network::NodeId,
+    network::{NodeId, SourceRoutingHeader},
     packet::Packet,
 };
 pub mod widgets;
 use widgets::{
     chat_client_widget::ChatClientWidget, drone_widget::DroneWidget, server_widget::ServerWidget,
-    web_client_widget::WebClientWidget, WidgetType,
+    web_client_widget::WebClientWidget, NodeWidget, WidgetType,
 };
+pub mod clusters;
+pub mod forge;
+pub mod generator;
+pub mod history;
+pub mod import;
+pub mod layout;
+pub mod platform;
+pub mod reconnect;
+pub mod sink;
+pub mod stats;
+pub mod stress;
+pub mod topology;
+pub mod traces;
+pub mod transfers;
 pub mod utils;
+pub mod validation;
+
+mod checks;
+mod events;
+mod export;
+mod groups;
+mod injection;
+mod ops;
+mod render;
+mod spawning;
 
 use dr_ones::Drone as DrDrone;
 
+/// Event received by the controller from a node of the network
 #[derive(Clone, Debug)]
-enum Events {
+pub enum ControllerInboundEvent {
     Drone(DroneEvent),
     WebClient(WebClientEvent),
     ChatClient(ChatClientEvent),
     Server(ServerEvent),
 }
 
+impl From<DroneEvent> for ControllerInboundEvent {
+    fn from(event: DroneEvent) -> Self {
+        ControllerInboundEvent::Drone(event)
+    }
+}
+
+impl From<WebClientEvent> for ControllerInboundEvent {
+    fn from(event: WebClientEvent) -> Self {
+        ControllerInboundEvent::WebClient(event)
+    }
+}
+
+impl From<ChatClientEvent> for ControllerInboundEvent {
+    fn from(event: ChatClientEvent) -> Self {
+        ControllerInboundEvent::ChatClient(event)
+    }
+}
+
+impl From<ServerEvent> for ControllerInboundEvent {
+    fn from(event: ServerEvent) -> Self {
+        ControllerInboundEvent::Server(event)
+    }
+}
+
 enum UpdateType {
     Add,
     Remove,
 }
 
+/// Kind of a client, used to look up the right configuration vector
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ClientKind {
+    Web,
+    Chat,
+}
+
 // Type aliases for the channels
-type DChannels = HashMap<
+pub type DChannels = HashMap<
     NodeId,
     (
         Sender<DroneCommand>,
@@ -74,7 +140,7 @@ type DChannels = HashMap<
         Receiver<Packet>,
     ),
 >;
-type WCChannels = HashMap<
+pub type WCChannels = HashMap<
     NodeId,
     (
         Sender<WebClientCommand>,
@@ -83,7 +149,7 @@ type WCChannels = HashMap<
         Receiver<Packet>,
     ),
 >;
-type CCChannels = HashMap<
+pub type CCChannels = HashMap<
     NodeId,
     (
         Sender<ChatClientCommand>,
@@ -92,7 +158,7 @@ type CCChannels = HashMap<
         Receiver<Packet>,
     ),
 >;
-type SChannels = HashMap<
+pub type SChannels = HashMap<
     NodeId,
     (
         Sender<ServerCommand>,
@@ -102,10 +168,59 @@ type SChannels = HashMap<
     ),
 >;
 
+/// Helper function to create the channels of a client or server and build it with `factory`,
+/// given its packet channel
+///
+/// Returns the channels of the node, as stored by the controller, and the function running it.
+fn build_node<C, E>(
+    factory: &NodeFactory<C, E>,
+    id: NodeId,
+    (packet_send, packet_recv): (Sender<Packet>, Receiver<Packet>),
+    neighbors: HashMap<NodeId, Sender<Packet>>,
+) -> (
+    (Sender<C>, Receiver<E>, Sender<Packet>, Receiver<Packet>),
+    Box<dyn FnOnce() + Send>,
+) {
+    let (sender_command, receiver_command) = crossbeam_channel::unbounded();
+    let (send_event, receive_event) = crossbeam_channel::unbounded();
+    let node = factory(
+        id,
+        send_event,
+        receiver_command,
+        packet_recv.clone(),
+        neighbors,
+    );
+    (
+        (sender_command, receive_event, packet_send, packet_recv),
+        node,
+    )
+}
+
+/// Helper function to receive up to `max` events from `receiver` without blocking
+///
+/// Returns the events, and a flag set if the channel is empty and disconnected.
+fn drain_channel<T>(receiver: &Receiver<T>, max: usize) -> (Vec<T>, bool) {
+    let mut events = Vec::new();
+    while events.len() < max {
+        match receiver.try_recv() {
+            Ok(event) => events.push(event),
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => return (events, true),
+        }
+    }
+    (events, false)
+}
+
 /// Function to run the simulation controller
 ///
+/// Only available on native targets, see `run_web` for the browser.
+/// The configuration is validated with the `ConstraintPolicy::Strict` policy.
+///
+/// An invalid configuration is reported in a window instead of the controller.
+///
 /// # Panics
 /// The function panics if the GUI fails to run
+#[cfg(not(target_arch = "wasm32"))]
 pub fn run(
     drones_channels: DChannels,
     web_clients_channels: WCChannels,
@@ -115,26 +230,498 @@ pub fn run(
     clients: Vec<Client>,
     servers: Vec<Server>,
 ) {
+    run_with_policy(
+        drones_channels,
+        web_clients_channels,
+        chat_clients_channels,
+        servers_channels,
+        drones,
+        clients,
+        servers,
+        ConstraintPolicy::Strict,
+    );
+}
+
+/// Function to run the simulation controller, validating the configuration with `policy`
+///
+/// An invalid configuration is reported in a window instead of the controller.
+///
+/// # Panics
+/// The function panics if the GUI fails to run
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn run_with_policy(
+    drones_channels: DChannels,
+    web_clients_channels: WCChannels,
+    chat_clients_channels: CCChannels,
+    servers_channels: SChannels,
+    drones: Vec<Drone>,
+    clients: Vec<Client>,
+    servers: Vec<Server>,
+    policy: ConstraintPolicy,
+) {
+    run_with_options(
+        drones_channels,
+        web_clients_channels,
+        chat_clients_channels,
+        servers_channels,
+        drones,
+        clients,
+        servers,
+        RunOptions {
+            policy,
+            ..RunOptions::default()
+        },
+    );
+}
+
+/// Options of the simulation controller, see `run_with_options`
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    /// Policy used to validate the configuration
+    pub policy: ConstraintPolicy,
+    /// File the controller events are streamed to as JSON Lines, if any
+    pub event_stream: Option<PathBuf>,
+    /// Folder of the received files and of the exports, `tmp` if not set
+    pub output_dir: Option<PathBuf>,
+    /// Number of entries of the event log, 100 if not set
+    pub log_capacity: Option<usize>,
+}
+
+/// Factory of the clients or servers spawned from the GUI
+///
+/// It gets the id of the node, its event sender and command receiver, its packet
+/// receiver and the packet senders of its neighbors, and returns the function
+/// running the node, which the controller starts in its own thread.
+pub type NodeFactory<C, E> = Box<
+    dyn Fn(
+        NodeId,
+        Sender<E>,
+        Receiver<C>,
+        Receiver<Packet>,
+        HashMap<NodeId, Sender<Packet>>,
+    ) -> Box<dyn FnOnce() + Send>,
+>;
+
+/// Builder of a `SimulationController` that can also spawn clients and servers
+///
+/// The drone implementations are known to the controller, while the clients
+/// and servers are not: their factories are given here. Without a factory,
+/// the nodes of that kind can't be spawned from the GUI.
+pub struct SimulationControllerBuilder {
+    drones_channels: DChannels,
+    web_clients_channels: WCChannels,
+    chat_clients_channels: CCChannels,
+    servers_channels: SChannels,
+    drones: Vec<Drone>,
+    clients: Vec<Client>,
+    servers: Vec<Server>,
+    policy: ConstraintPolicy,
+    web_client_factory: Option<NodeFactory<WebClientCommand, WebClientEvent>>,
+    chat_client_factory: Option<NodeFactory<ChatClientCommand, ChatClientEvent>>,
+    server_factory: Option<NodeFactory<ServerCommand, ServerEvent>>,
+}
+
+impl SimulationControllerBuilder {
+    /// Start building a controller from the channels and the configuration of the nodes
+    #[must_use]
+    pub fn new(
+        drones_channels: DChannels,
+        web_clients_channels: WCChannels,
+        chat_clients_channels: CCChannels,
+        servers_channels: SChannels,
+        drones: Vec<Drone>,
+        clients: Vec<Client>,
+        servers: Vec<Server>,
+    ) -> Self {
+        Self {
+            drones_channels,
+            web_clients_channels,
+            chat_clients_channels,
+            servers_channels,
+            drones,
+            clients,
+            servers,
+            policy: ConstraintPolicy::default(),
+            web_client_factory: None,
+            chat_client_factory: None,
+            server_factory: None,
+        }
+    }
+
+    /// Set the policy used to validate the configuration, `ConstraintPolicy::Strict` by default
+    #[must_use]
+    pub fn policy(mut self, policy: ConstraintPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    #[must_use]
+    pub fn web_client_factory(
+        mut self,
+        factory: NodeFactory<WebClientCommand, WebClientEvent>,
+    ) -> Self {
+        self.web_client_factory = Some(factory);
+        self
+    }
+
+    #[must_use]
+    pub fn chat_client_factory(
+        mut self,
+        factory: NodeFactory<ChatClientCommand, ChatClientEvent>,
+    ) -> Self {
+        self.chat_client_factory = Some(factory);
+        self
+    }
+
+    #[must_use]
+    pub fn server_factory(mut self, factory: NodeFactory<ServerCommand, ServerEvent>) -> Self {
+        self.server_factory = Some(factory);
+        self
+    }
+
+    /// Build the controller, see `SimulationController::new`
+    ///
+    /// # Errors
+    /// If the configuration is invalid
+    pub fn build(self) -> Result<SimulationController, String> {
+        let mut controller = SimulationController::new(
+            self.drones_channels,
+            self.web_clients_channels,
+            self.chat_clients_channels,
+            self.servers_channels,
+            self.drones,
+            self.clients,
+            self.servers,
+            self.policy,
+        )?;
+        controller.web_client_factory = self.web_client_factory;
+        controller.chat_client_factory = self.chat_client_factory;
+        controller.server_factory = self.server_factory;
+        Ok(controller)
+    }
+}
+
+/// Function to run the simulation controller with the given `options`
+///
+/// If the event stream can't be created, the controller runs without it and
+/// the error is reported in the log.
+/// If the configuration is invalid, a window with the error is shown instead of
+/// the controller.
+///
+/// # Panics
+/// The function panics if the GUI fails to run
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn run_with_options(
+    drones_channels: DChannels,
+    web_clients_channels: WCChannels,
+    chat_clients_channels: CCChannels,
+    servers_channels: SChannels,
+    drones: Vec<Drone>,
+    clients: Vec<Client>,
+    servers: Vec<Server>,
+    options: RunOptions,
+) {
+    run_with_builder(
+        SimulationControllerBuilder::new(
+            drones_channels,
+            web_clients_channels,
+            chat_clients_channels,
+            servers_channels,
+            drones,
+            clients,
+            servers,
+        ),
+        options,
+    );
+}
+
+/// Function to run the simulation controller built by `builder`, with the given `options`
+///
+/// The policy of `options` replaces the one of the builder. As in `run_with_options`,
+/// an invalid configuration is reported in a window instead of the controller.
+///
+/// # Panics
+/// The function panics if the GUI fails to run
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_with_builder(builder: SimulationControllerBuilder, options: RunOptions) {
+    let mut controller = match builder.policy(options.policy).build() {
+        Ok(controller) => controller,
+        Err(error) => {
+            eframe::run_native(
+                "Simulation Controller",
+                eframe::NativeOptions::default(),
+                Box::new(|_cc| Ok(Box::new(StartupError(error)))),
+            )
+            .expect("Failed to run simulation controller");
+            return;
+        }
+    };
+    if let Some(dir) = options.output_dir {
+        controller.set_output_dir(dir);
+    }
+    if let Some(capacity) = options.log_capacity {
+        controller.set_log_capacity(capacity);
+    }
+    if let Some(path) = &options.event_stream {
+        match EventSink::to_file(path) {
+            Ok(sink) => controller.set_event_sink(Some(sink)),
+            Err(error) => controller.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Event stream disabled: {error}"),
+            ),
+        }
+    }
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Simulation Controller",
         options,
-        Box::new(|_cc| {
-            Ok(Box::new(SimulationController::new(
-                drones_channels,
-                web_clients_channels,
-                chat_clients_channels,
-                servers_channels,
-                drones,
-                clients,
-                servers,
-            )))
-        }),
+        Box::new(|_cc| Ok(Box::new(controller))),
     )
     .expect("Failed to run simulation controller");
 }
 
+/// Window shown instead of the controller when the configuration is invalid
+#[cfg(not(target_arch = "wasm32"))]
+struct StartupError(String);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl eframe::App for StartupError {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Invalid configuration");
+            ui.label(RichText::new(&self.0).color(Color32::RED));
+            if ui.button("Close").clicked() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        });
+    }
+}
+
+/// Function to run the simulation controller in the given canvas of a web page
+///
+/// The features unavailable on the web are listed in the `platform` module.
+///
+/// # Errors
+/// The function returns an error if the configuration is invalid or if the GUI fails to start
+#[cfg(target_arch = "wasm32")]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_web(
+    canvas: web_sys::HtmlCanvasElement,
+    drones_channels: DChannels,
+    web_clients_channels: WCChannels,
+    chat_clients_channels: CCChannels,
+    servers_channels: SChannels,
+    drones: Vec<Drone>,
+    clients: Vec<Client>,
+    servers: Vec<Server>,
+    policy: ConstraintPolicy,
+) -> Result<(), String> {
+    let controller = SimulationController::new(
+        drones_channels,
+        web_clients_channels,
+        chat_clients_channels,
+        servers_channels,
+        drones,
+        clients,
+        servers,
+        policy,
+    )
+    .map_err(|error| format!("Invalid configuration: {error}"))?;
+    eframe::WebRunner::new()
+        .start(
+            canvas,
+            eframe::WebOptions::default(),
+            Box::new(|_cc| Ok(Box::new(controller))),
+        )
+        .await
+        .map_err(|error| format!("Failed to run simulation controller: {error:?}"))
+}
+
+/// This function checks that every `NodeId` is used by a single node
+///
+/// The same id could be present in more than one channel map (e.g. both as a
+/// drone and as a server), in which case the graph would be wired wrongly.
+/// The error lists every duplicated id with its conflicting kinds.
+fn check_unique_ids(
+    dh: &DChannels,
+    wch: &WCChannels,
+    cch: &CCChannels,
+    sh: &SChannels,
+) -> Result<(), String> {
+    let mut kinds: BTreeMap<NodeId, Vec<&str>> = BTreeMap::new();
+    for id in dh.keys() {
+        kinds.entry(*id).or_default().push("drone");
+    }
+    for id in wch.keys() {
+        kinds.entry(*id).or_default().push("web client");
+    }
+    for id in cch.keys() {
+        kinds.entry(*id).or_default().push("chat client");
+    }
+    for id in sh.keys() {
+        kinds.entry(*id).or_default().push("server");
+    }
+
+    let duplicates = kinds
+        .iter()
+        .filter(|(_, kinds)| kinds.len() > 1)
+        .map(|(id, kinds)| format!("{id} ({})", kinds.join(", ")))
+        .collect::<Vec<String>>();
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Duplicated ids: {}", duplicates.join("; ")))
+    }
+}
+
+/// This function finds the servers that the client at `client_idx` can reach in `graph`
+///
+/// The search only goes through drones: packets can't be forwarded by other
+/// clients or by servers.
+fn reachable_servers(
+    graph: &Graph<WidgetType, (), Undirected>,
+    client_idx: NodeIndex,
+) -> HashSet<NodeId> {
+    let mut visited: HashSet<NodeIndex> = HashSet::new();
+    let mut servers_visited: HashSet<NodeId> = HashSet::new();
+    let mut stack: VecDeque<NodeIndex> = VecDeque::new();
+    stack.push_back(client_idx);
+
+    while let Some(node) = stack.pop_front() {
+        if visited.insert(node) {
+            for neighbor in graph.g.neighbors(node) {
+                match graph.node(neighbor).unwrap().payload() {
+                    WidgetType::Server(server_widget) => {
+                        servers_visited.insert(server_widget.id());
+                    }
+                    WidgetType::ChatClient(_) | WidgetType::WebClient(_) => {}
+                    WidgetType::Drone(_) => stack.push_front(neighbor),
+                }
+            }
+        }
+    }
+    servers_visited
+}
+
+/// This function checks that `graph` has a single connected component
+fn is_connected(graph: &Graph<WidgetType, (), Undirected>) -> bool {
+    petgraph::algo::tarjan_scc(&graph.g).len() <= 1
+}
+
+/// Thread of a drone crashed or replaced by the controller, waiting to finish
+struct RetiredThread {
+    drone_id: NodeId,
+    /// Time the drone was retired, after being sent the crash command
+    retired_at: Instant,
+    thread: JoinHandle<()>,
+    /// Flag set once the thread was reported as failing to terminate
+    reported: bool,
+}
+
+/// This function formats a list of pairs of nodes, e.g. `4-7, 5-7`
+fn format_pairs(pairs: &[(NodeId, NodeId)]) -> String {
+    pairs
+        .iter()
+        .map(|(client, server)| format!("{client}-{server}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// This function formats the route of a packet with the node that originated it,
+/// marking the current hop, e.g. `originated by 1, route 1 -> [3] -> 5`
+fn format_route(header: &SourceRoutingHeader) -> String {
+    let route = header
+        .hops
+        .iter()
+        .enumerate()
+        .map(|(i, hop)| {
+            if i == header.hop_index {
+                format!("[{hop}]")
+            } else {
+                hop.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" -> ");
+    match header.hops.first() {
+        Some(origin) => format!("originated by {origin}, route {route}"),
+        None => "empty route".to_string(),
+    }
+}
+
+/// This function checks that every edge of the configuration is declared on both endpoints
+///
+/// With `ConstraintPolicy::Strict` a one-sided declaration is an error, while with
+/// `ConstraintPolicy::Sandbox` the missing entry is mirrored and a warning is
+/// returned for it.
+/// Declarations of unknown nodes are an error with both policies.
+fn check_bidirectional(
+    drones: &mut [Drone],
+    clients: &mut [Client],
+    servers: &mut [Server],
+    policy: ConstraintPolicy,
+) -> Result<Vec<String>, String> {
+    let adjacency: HashMap<NodeId, Vec<NodeId>> = drones
+        .iter()
+        .map(|d| (d.id, d.connected_node_ids.clone()))
+        .chain(
+            clients
+                .iter()
+                .map(|c| (c.id, c.connected_drone_ids.clone())),
+        )
+        .chain(
+            servers
+                .iter()
+                .map(|s| (s.id, s.connected_drone_ids.clone())),
+        )
+        .collect();
+
+    // (node missing the entry, node declaring the edge)
+    let mut missing: Vec<(NodeId, NodeId)> = Vec::new();
+    for (id, neighbors) in &adjacency {
+        for neighbor in neighbors {
+            match adjacency.get(neighbor) {
+                None => return Err(format!("Node {id} lists unknown node {neighbor}")),
+                Some(list) if !list.contains(id) => missing.push((*neighbor, *id)),
+                Some(_) => {}
+            }
+        }
+    }
+    missing.sort_unstable();
+
+    if policy == ConstraintPolicy::Strict {
+        if missing.is_empty() {
+            return Ok(Vec::new());
+        }
+        let edges = missing
+            .iter()
+            .map(|(a, b)| format!("{b} lists {a} but {a} does not list {b}"))
+            .collect::<Vec<String>>();
+        return Err(format!("One-sided edges: {}", edges.join("; ")));
+    }
+
+    let mut warnings = Vec::new();
+    for (a, b) in missing {
+        if let Some(drone) = drones.iter_mut().find(|d| d.id == a) {
+            drone.connected_node_ids.push(b);
+        } else if let Some(client) = clients.iter_mut().find(|c| c.id == a) {
+            client.connected_drone_ids.push(b);
+        } else if let Some(server) = servers.iter_mut().find(|s| s.id == a) {
+            server.connected_drone_ids.push(b);
+        }
+        warnings.push(format!(
+            "[CONTROLLER] Edge {b}-{a} was declared only by {b}, mirrored on {a}"
+        ));
+    }
+    Ok(warnings)
+}
+
 /// This function generate the graph from the channels and the nodes
+///
+/// Returns an error, before creating any node, if an id is used by more than
+/// one node of the configuration, or if a node of the configuration has no channels.
 fn generate_graph(
     dh: &DChannels,
     wch: &WCChannels,
@@ -143,7 +730,26 @@ fn generate_graph(
     drones: &Vec<Drone>,
     clients: &Vec<Client>,
     servers: &Vec<Server>,
-) -> Graph<WidgetType, (), Undirected> {
+) -> Result<Graph<WidgetType, (), Undirected>, String> {
+    let mut ids = HashSet::new();
+    for id in drones
+        .iter()
+        .map(|d| d.id)
+        .chain(clients.iter().map(|c| c.id))
+        .chain(servers.iter().map(|s| s.id))
+    {
+        if !ids.insert(id) {
+            return Err(format!("ID {id} is used by more than one node"));
+        }
+        if !dh.contains_key(&id)
+            && !wch.contains_key(&id)
+            && !cch.contains_key(&id)
+            && !sh.contains_key(&id)
+        {
+            return Err(format!("Node {id} has no channels"));
+        }
+    }
+
     let mut g = StableUnGraph::default();
     let mut h: HashMap<u8, NodeIndex> = HashMap::new();
     let mut edges: HashSet<(u8, u8)> = HashSet::new();
@@ -151,7 +757,12 @@ fn generate_graph(
     
     // Create drone widgets
     for (id, channels) in dh {
-        let idx = g.add_node(WidgetType::Drone(DroneWidget::new(*id, channels.0.clone())));
+        let pdr = drones.iter().find(|d| d.id == *id).map_or(0.0, |d| d.pdr);
+        let idx = g.add_node(WidgetType::Drone(DroneWidget::new(
+            *id,
+            channels.0.clone(),
+            pdr,
+        )));
         h.insert(*id, idx);
     }
     // Create web client widgets
@@ -179,11 +790,17 @@ fn generate_graph(
         h.insert(*id, idx);
     }
 
+    let node = |id: &u8| {
+        h.get(id)
+            .copied()
+            .ok_or_else(|| format!("Node {id} has no channels"))
+    };
+
     // Add edges
     for dr in drones {
         for n in &dr.connected_node_ids {
             if !edges.contains(&(dr.id, *n)) && !edges.contains(&(*n, dr.id)) {
-                g.add_edge(h[&dr.id], h[n], ());
+                g.add_edge(node(&dr.id)?, node(n)?, ());
                 edges.insert((dr.id, *n));
             }
         }
@@ -192,7 +809,7 @@ fn generate_graph(
     for cl in clients {
         for n in &cl.connected_drone_ids {
             if !edges.contains(&(cl.id, *n)) && !edges.contains(&(*n, cl.id)) {
-                g.add_edge(h[&cl.id], h[n], ());
+                g.add_edge(node(&cl.id)?, node(n)?, ());
                 edges.insert((cl.id, *n));
             }
         }
@@ -201,7 +818,7 @@ fn generate_graph(
     for srv in servers {
         for n in &srv.connected_drone_ids {
             if !edges.contains(&(srv.id, *n)) && !edges.contains(&(*n, srv.id)) {
-                g.add_edge(h[&srv.id], h[n], ());
+                g.add_edge(node(&srv.id)?, node(n)?, ());
                 edges.insert((srv.id, *n));
             }
         }
@@ -211,22 +828,14 @@ fn generate_graph(
     // Since graph library is beatiful, first iterate over the nodes to construct the labels for each node
     let temp: Vec<(NodeIndex, String)> = eg_graph
         .nodes_iter()
-        .map(|(idx, node)| {
-            let widget = node.payload();
-            match widget {
-                WidgetType::Drone(d) => (idx, format!("Drone {}", d.get_id())),
-                WidgetType::WebClient(wc) => (idx, format!("Web Client {}", wc.get_id())),
-                WidgetType::ChatClient(cc) => (idx, format!("Chat Client {}", cc.get_id())),
-                WidgetType::Server(s) => (idx, format!("Server {}", s.get_id())),
-            }
-        })
+        .map(|(idx, node)| (idx, node.payload().get_label_helper()))
         .collect();
     // Then iterate over the nodes again to set the labels
     for (idx, label) in &temp {
         eg_graph.node_mut(*idx).unwrap().set_label(label.clone());
     }
 
-    eg_graph
+    Ok(eg_graph)
 }
 
 type DroneFactory = fn(
@@ -237,47 +846,280 @@ type DroneFactory = fn(
     HashMap<u8, Sender<Packet>>,
     f32,
 ) -> Box<dyn DroneTrait>;
-const DRONE_FACTORY: [DroneFactory; 10] = [
-    create_boxed_drone!(DrDrone),
-    create_boxed_drone!(RustDoIt),
-    create_boxed_drone!(RustRoveri),
-    create_boxed_drone!(RollingDrone),
-    create_boxed_drone!(RustafarianDrone),
-    create_boxed_drone!(RustezeDrone),
-    create_boxed_drone!(RustyDrone),
-    create_boxed_drone!(GetDroned),
-    create_boxed_drone!(NoSoundDroneRIP),
-    create_boxed_drone!(BetterCallDrone),
+/// The available drone implementations, with the name of their crate
+const DRONE_FACTORY: [(&str, DroneFactory); 10] = [
+    ("dr_ones", create_boxed_drone!(DrDrone)),
+    ("rust_do_it", create_boxed_drone!(RustDoIt)),
+    ("rust_roveri", create_boxed_drone!(RustRoveri)),
+    ("rolling_drone", create_boxed_drone!(RollingDrone)),
+    ("rustafarian_drone", create_boxed_drone!(RustafarianDrone)),
+    ("rusteze_drone", create_boxed_drone!(RustezeDrone)),
+    ("rusty_drones", create_boxed_drone!(RustyDrone)),
+    ("getdroned", create_boxed_drone!(GetDroned)),
+    (
+        "ap2024_rustinpeace_nosounddrone",
+        create_boxed_drone!(NoSoundDroneRIP),
+    ),
+    (
+        "drone_bettercalldrone",
+        create_boxed_drone!(BetterCallDrone),
+    ),
 ];
 
-struct SimulationController {
+/// Default number of entries of the event log
+const LOG_CAPACITY: usize = 100;
+
+/// Default max number of events received from each channel at each frame, so
+/// that a flooding node can't freeze the UI
+const MAX_EVENTS_PER_FRAME: usize = 512;
+
+/// Number of entries the event log can grow to with `OverflowPolicy::Grow`
+const LOG_MAX_GROWTH: usize = 10_000;
+
+/// Max lines of a wrapped log entry, the rest is elided and shown on hover
+const LOG_WRAPPED_ROWS: usize = 2;
+
+/// Default folder of the received files and of the exports
+const OUTPUT_DIR: &str = "tmp";
+
+/// Time the drone threads are given to stop after the crash command, before being
+/// reported as failing to terminate, and when the window is closed
+const DRONE_JOIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// State of the simulation controller
+///
+/// It's driven by the GUI through `run`, but it can also be built with `new`
+/// and driven without a GUI, through the public operations below.
+pub struct SimulationController {
     drones_channels: DChannels,
     web_clients_channels: WCChannels,
     chat_clients_channels: CCChannels,
     servers_channels: SChannels,
     drones: Vec<Drone>,
-    clients: Vec<Client>,
+    web_clients: Vec<Client>,
+    chat_clients: Vec<Client>,
     servers: Vec<Server>,
     graph: Graph<WidgetType, (), Undirected>,
+    /// Index in the graph of each node, kept in sync when nodes are added or removed
+    node_indices: HashMap<NodeId, NodeIndex>,
     selected_node: Option<NodeIndex>,
+    /// Nodes selected in the graph, in the order they were selected: Shift+Click
+    /// toggles a node, Shift+drag selects the nodes in a rectangle
+    selected_nodes: Vec<NodeIndex>,
+    /// Screen position where the selection rectangle was started, while dragging it
+    selection_rect_start: Option<egui::Pos2>,
+    /// Input field of the PDR applied to the selected drones, with the outcome and
+    /// the errors of the last action on the selection
+    batch_pdr_input: String,
+    batch_summary: String,
+    batch_errors: Vec<String>,
     selected_edge: Option<EdgeIndex>,
     add_neighbor_input: String,
-    add_neighbor_error: String,
-    rm_neighbor_error: String,
+    /// Flag to type the id of the new neighbor instead of picking it, e.g. for automation
+    type_neighbor_id: bool,
+    add_neighbor_error: Option<ConnectionRejection>,
+    /// Errors of the last edge removal, one per violated constraint
+    rm_neighbor_error: Vec<String>,
+    /// Errors of the last removal from the neighbor list of the side panel, by neighbor
+    neighbor_errors: HashMap<NodeId, Vec<String>>,
     drone_crash_error: String,
-    events: EventQueue<RichText>,
+    /// Edges proposed to make the last rejected crash legal, with the id of the drone
+    crash_repair: Option<(NodeId, Vec<(NodeId, NodeId)>)>,
+    /// Errors of the last isolation, e.g. the edges blocking it
+    isolate_errors: Vec<String>,
+    /// Index in `DRONE_FACTORY` of the implementation selected to swap or duplicate a drone
+    selected_implementation: usize,
+    /// Index in `DRONE_FACTORY` of the implementation of the drones added with
+    /// "Add Drone", `None` to pick a random one each time
+    spawn_implementation: Option<usize>,
+    /// Input fields of the PDR and of the comma-separated neighbor ids of the drones
+    /// added with "Add Drone"
+    spawn_pdr_input: String,
+    spawn_neighbors_input: String,
+    /// Kind of the node added by the spawn form
+    spawn_kind: NodeKind,
+    /// Error of the last spawn form that could not be validated
+    spawn_error: String,
+    /// Factories of the clients and servers, given through `SimulationControllerBuilder`
+    web_client_factory: Option<NodeFactory<WebClientCommand, WebClientEvent>>,
+    chat_client_factory: Option<NodeFactory<ChatClientCommand, ChatClientEvent>>,
+    server_factory: Option<NodeFactory<ServerCommand, ServerEvent>>,
+    implementation_error: String,
+    events: EventQueue<LogEvent>,
+    /// Free-text notes attached by the user to the nodes
+    notes: HashMap<NodeId, String>,
+    /// Aliases given by the user to the nodes, used in labels and logs
+    aliases: HashMap<NodeId, String>,
+    /// Flag to show the notes as a second line under the graph labels
+    show_notes_in_labels: bool,
+    /// Delivery status of the sessions observed in the events
+    transfers: TransferTracker,
+    /// Flag to show the transfers window
+    show_transfers: bool,
+    /// Statistics collected from the events
+    stats: Stats,
+    /// Monitor of the event channels, to warn when the log falls behind
+    intake: IntakeMonitor,
+    /// Nodes whose command channel is closed, commands to them are blocked
+    unresponsive_nodes: HashSet<NodeId>,
+    /// Nodes whose event channel was found disconnected in the last frame
+    disconnected_nodes: HashSet<NodeId>,
+    /// Event receivers of the drones crashed in the last frame, drained once before being dropped
+    crashed_receivers: Vec<(NodeId, Receiver<DroneEvent>)>,
+    /// Threads of the drones spawned by the controller
+    drone_threads: HashMap<NodeId, JoinHandle<()>>,
+    /// Threads of the crashed or replaced drones, joined once they finish
+    retired_threads: Vec<RetiredThread>,
+    /// Max number of events received from each channel at each frame
+    max_events_per_frame: usize,
+    /// Ids of the crashed drones, not reassigned unless explicitly requested
+    retired_ids: BTreeSet<NodeId>,
+    /// Interval of the health probe, `None` if the probe is disabled
+    probe_interval: Option<Duration>,
+    /// Last time the health probe ran
+    last_probe: Instant,
+    /// Paths taken by the fragments across the network
+    tracer: PacketTracer,
+    /// Flag to show the traces window
+    show_traces: bool,
+    /// Nodes of the log entry under the pointer, highlighted in the graph labels
+    log_hover: Vec<NodeId>,
+    /// Trace whose hops are highlighted in the graph labels
+    highlighted_trace: Option<TraceKey>,
+    /// Flag to show the event log grouped by session
+    group_log_by_session: bool,
+    /// Flag to show the age of each log entry, e.g. `12s ago`
+    show_event_age: bool,
+    /// Flag to show the time of each log entry since startup, e.g. `[+12.3s]`
+    show_event_offset: bool,
+    /// Flag to show only the shortcuts in the event log
+    show_only_shortcuts: bool,
+    /// Nodes whose info events are hidden from the log
+    muted_nodes: BTreeSet<NodeId>,
+    /// Node whose events are the only ones shown in the log, if any
+    log_node_filter: Option<NodeId>,
+    /// Flag to filter the log by the node selected in the graph
+    log_filter_follows_selection: bool,
+    /// Categories of events hidden from the log
+    hidden_log_categories: BTreeSet<LogCategory>,
+    /// Flag to wrap the long log entries on `LOG_WRAPPED_ROWS` lines instead of truncating them
+    wrap_log_lines: bool,
+    /// Flag to discard the log entries older than `log_max_age_minutes`
+    limit_log_age: bool,
+    /// Max age of the log entries, in minutes
+    log_max_age_minutes: u64,
+    /// Events received while the log is paused, appended to the log on resume
+    ///
+    /// The buffer holds at most the capacity of the log, the oldest events past
+    /// it are counted as discarded.
+    paused_events: Option<VecDeque<LogEvent>>,
+    /// Time each node joined the simulation, used to compute its uptime
+    started_at: HashMap<NodeId, Instant>,
+    /// Configured PDR and observed drop rate of the drones over the last minutes
+    pdr_history: PdrHistory,
+    /// Result of the stats export running on the worker thread, if any
+    stats_export: Option<Receiver<Result<PathBuf, String>>>,
+    /// Flag to show the stress test dialog
+    show_stress_test: bool,
+    /// Inputs of the stress test dialog
+    stress_form: StressTestForm,
+    /// Error of the stress test dialog
+    stress_error: String,
+    /// Last stress test started, kept after it ends to show its report
+    stress_test: Option<StressTest>,
+    /// Time after which a web client request without response is counted as lost
+    request_timeout: Duration,
+    /// Flag to show the stats window
+    show_stats: bool,
+    /// PDR from which a drone is considered as not forwarding packets at all
+    severing_pdr: f32,
+    /// Warning shown at the bottom of the window, with the time it was raised
+    toast: Option<(String, Instant)>,
+    /// Configuration files dropped on the window, waiting for confirmation in order
+    pending_imports: VecDeque<ImportPlan>,
+    /// Stream of the structured events, if enabled
+    event_sink: Option<EventSink>,
+    /// File the entries of the log are appended to as plain text, if enabled
+    log_file: Option<BufWriter<File>>,
+    /// Input field of the path of the log file, with the error of the last attempt to open it
+    log_file_input: String,
+    log_file_error: String,
+    /// Input field of the capacity of the log, with the error of the last attempt to apply it
+    log_capacity_input: String,
+    log_capacity_error: String,
+    /// Error of the last load of a topology file
+    topology_error: String,
+    /// Folder of the received files and of the exports
+    output_dir: PathBuf,
+    /// Topology of the network when the controller started
+    initial_topology: TopologySnapshot,
+    /// Time the controller started
+    start_time: Instant,
+    /// Changes applied to the topology by the controller
+    operations: OperationLog,
+    /// Operations on the topology that can be undone, the last one on top
+    undo_stack: Vec<TopologyOperation>,
+    /// Operations undone, that can be redone until a new operation is applied
+    redo_stack: Vec<TopologyOperation>,
+    /// Flag to show the changes window
+    show_changes: bool,
+    /// Flag to show the reconnect wizard
+    show_reconnect: bool,
+    /// Groups of drones that can be collapsed into a single node
+    clusters: Clusters,
+    show_clusters: bool,
+    cluster_input: String,
+    /// Flag to enable the debug tools, e.g. the packet forge
+    debug_tools: bool,
+    show_forge: bool,
+    forge_form: PacketForm,
+    forge_error: String,
+    /// Under-connected node the reconnect wizard is suggesting drones for
+    reconnect_target: Option<NodeId>,
+    reconnect_ranking: Ranking,
+    reconnect_error: String,
+    /// Layout algorithm run by the "Re-layout" button
+    layout_kind: LayoutKind,
+    /// Nodes dragged by the user, kept in place by the layout if `keep_pinned` is set
+    pinned_nodes: HashSet<NodeId>,
+    /// Flag to keep the pinned nodes in place when re-running the layout
+    keep_pinned: bool,
+    /// Flag to re-run the layout after every topology change
+    auto_relayout: bool,
 }
 
 impl SimulationController {
+    /// Creates a new `SimulationController` from the channels and the configuration of the nodes
+    ///
+    /// The clients are split between web and chat clients, based on the channel
+    /// map containing their id.
+    /// Returns an error if an id appears in more than one channel map or more than
+    /// once in the configuration, if a node has no channels at all, or if an edge
+    /// is declared on one endpoint only and `policy` is `ConstraintPolicy::Strict`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         drones_channels: DChannels,
         web_clients_channels: WCChannels,
         chat_clients_channels: CCChannels,
         servers_channels: SChannels,
-        drones: Vec<Drone>,
-        clients: Vec<Client>,
-        servers: Vec<Server>,
-    ) -> Self {
+        mut drones: Vec<Drone>,
+        mut clients: Vec<Client>,
+        mut servers: Vec<Server>,
+        policy: ConstraintPolicy,
+    ) -> Result<Self, String> {
+        check_unique_ids(
+            &drones_channels,
+            &web_clients_channels,
+            &chat_clients_channels,
+            &servers_channels,
+        )?;
+        if let Some(client) = clients.iter().find(|c| {
+            !web_clients_channels.contains_key(&c.id) && !chat_clients_channels.contains_key(&c.id)
+        }) {
+            return Err(format!("Client {} has no channels", client.id));
+        }
+        let warnings = check_bidirectional(&mut drones, &mut clients, &mut servers, policy)?;
+
         let graph = generate_graph(
             &drones_channels,
             &web_clients_channels,
@@ -286,1096 +1128,435 @@ impl SimulationController {
             &drones,
             &clients,
             &servers,
-        );
-        SimulationController {
+        )?;
+        let now = Instant::now();
+        let started_at = graph
+            .nodes_iter()
+            .map(|(_, node)| (node.payload().get_id_helper(), now))
+            .collect();
+        let node_indices = graph
+            .nodes_iter()
+            .map(|(idx, node)| (node.payload().get_id_helper(), idx))
+            .collect();
+        let (web_clients, chat_clients): (Vec<Client>, Vec<Client>) = clients
+            .into_iter()
+            .partition(|c| web_clients_channels.contains_key(&c.id));
+        let mut controller = SimulationController {
             drones_channels,
             web_clients_channels,
             chat_clients_channels,
             servers_channels,
             drones,
-            clients,
+            web_clients,
+            chat_clients,
             servers,
             graph,
+            node_indices,
             selected_node: Option::default(),
+            selected_nodes: Vec::new(),
+            selection_rect_start: None,
+            batch_pdr_input: String::new(),
+            batch_summary: String::new(),
+            batch_errors: Vec::new(),
             selected_edge: Option::default(),
             add_neighbor_input: String::default(),
-            add_neighbor_error: String::default(),
-            rm_neighbor_error: String::default(),
+            type_neighbor_id: false,
+            add_neighbor_error: None,
+            rm_neighbor_error: Vec::default(),
+            neighbor_errors: HashMap::new(),
             drone_crash_error: String::default(),
-            events: EventQueue::new(100),
-        }
-    }
-
-    /// Helper function to get the index of a node given its id
-    ///
-    /// The `NodeIndex` is the index used by the graph library to identify a node
-    fn get_node_idx(&self, id: NodeId) -> Option<NodeIndex> {
-        for (node_idx, widget) in self.graph.nodes_iter() {
-            match widget.payload() {
-                WidgetType::Drone(drone_widget) => {
-                    if drone_widget.get_id() == id {
-                        return Some(node_idx);
-                    }
-                }
-                WidgetType::WebClient(web_client_widget) => {
-                    if web_client_widget.get_id() == id {
-                        return Some(node_idx);
-                    }
-                }
-                WidgetType::ChatClient(chat_client_widget) => {
-                    if chat_client_widget.get_id() == id {
-                        return Some(node_idx);
-                    }
-                }
-                WidgetType::Server(server_widget) => {
-                    if server_widget.get_id() == id {
-                        return Some(node_idx);
-                    }
-                }
-            }
+            crash_repair: None,
+            isolate_errors: Vec::new(),
+            selected_implementation: 0,
+            spawn_implementation: None,
+            spawn_pdr_input: "0.0".to_string(),
+            spawn_neighbors_input: String::new(),
+            spawn_kind: NodeKind::Drone,
+            spawn_error: String::new(),
+            web_client_factory: None,
+            chat_client_factory: None,
+            server_factory: None,
+            implementation_error: String::default(),
+            events: EventQueue::new(LOG_CAPACITY),
+            notes: HashMap::default(),
+            aliases: HashMap::default(),
+            show_notes_in_labels: false,
+            transfers: TransferTracker::new(Duration::from_secs(60)),
+            show_transfers: false,
+            stats: Stats::default(),
+            intake: IntakeMonitor::default(),
+            unresponsive_nodes: HashSet::default(),
+            disconnected_nodes: HashSet::default(),
+            crashed_receivers: Vec::new(),
+            drone_threads: HashMap::new(),
+            retired_threads: Vec::new(),
+            max_events_per_frame: MAX_EVENTS_PER_FRAME,
+            retired_ids: BTreeSet::new(),
+            probe_interval: Some(Duration::from_secs(5)),
+            last_probe: Instant::now(),
+            tracer: PacketTracer::new(200),
+            show_traces: false,
+            highlighted_trace: None,
+            log_hover: Vec::new(),
+            group_log_by_session: false,
+            show_event_age: false,
+            show_event_offset: true,
+            show_only_shortcuts: false,
+            muted_nodes: BTreeSet::new(),
+            log_node_filter: None,
+            log_filter_follows_selection: false,
+            hidden_log_categories: BTreeSet::new(),
+            wrap_log_lines: false,
+            limit_log_age: false,
+            log_max_age_minutes: 10,
+            paused_events: None,
+            started_at,
+            pdr_history: PdrHistory::default(),
+            stats_export: None,
+            show_stress_test: false,
+            stress_form: StressTestForm::default(),
+            stress_error: String::default(),
+            stress_test: None,
+            request_timeout: Duration::from_secs(10),
+            show_stats: false,
+            severing_pdr: 0.9,
+            toast: None,
+            pending_imports: VecDeque::new(),
+            event_sink: None,
+            log_file: None,
+            log_file_input: String::new(),
+            log_file_error: String::new(),
+            log_capacity_input: String::new(),
+            log_capacity_error: String::new(),
+            topology_error: String::new(),
+            output_dir: PathBuf::from(OUTPUT_DIR),
+            initial_topology: TopologySnapshot::default(),
+            start_time: now,
+            operations: OperationLog::default(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            show_changes: false,
+            show_reconnect: false,
+            clusters: Clusters::default(),
+            show_clusters: false,
+            cluster_input: String::default(),
+            debug_tools: false,
+            show_forge: false,
+            forge_form: PacketForm::default(),
+            forge_error: String::default(),
+            reconnect_target: None,
+            reconnect_ranking: Ranking::Nearest,
+            reconnect_error: String::default(),
+            layout_kind: LayoutKind::Random,
+            pinned_nodes: HashSet::default(),
+            keep_pinned: true,
+            auto_relayout: false,
+        };
+        controller.initial_topology = controller.topology_snapshot();
+        for drone in &controller.drones {
+            controller.pdr_history.record_pdr(drone.id, drone.pdr);
         }
-        None
-    }
-
-    /// Utility function to get the type of the `Packet`
-    /// Used for logging purposes
-    fn get_pack_type(packet: &Packet) -> String {
-        match &packet.pack_type {
-            wg_2024::packet::PacketType::MsgFragment(_) => String::from("MsgFragment"),
-            wg_2024::packet::PacketType::Ack(_) => String::from("Ack"),
-            wg_2024::packet::PacketType::Nack(_) => String::from("Nack"),
-            wg_2024::packet::PacketType::FloodRequest(_) => String::from("FloodRequest"),
-            wg_2024::packet::PacketType::FloodResponse(_) => String::from("FloodResponse"),
+        for warning in warnings {
+            controller.log(LogLevel::Warn, warning);
         }
+        Ok(controller)
     }
 
-    /// Function to handle the shortcut of a packet
-    /// The packet is sent to the corresponding node
-    fn handle_shortcut(&self, id: NodeId, packet: Packet) {
-        if let Some(ch) = self.drones_channels.get(&id) {
-            ch.2.send(packet).unwrap();
-        } else if let Some(ch) = self.web_clients_channels.get(&id) {
-            ch.2.send(packet).unwrap();
-        } else if let Some(ch) = self.servers_channels.get(&id) {
-            ch.2.send(packet).unwrap();
-        }
+    /// Function to get the PDR of the drone `id` as last applied by the controller,
+    /// `None` if the drone is not in the network
+    #[must_use]
+    pub fn drone_pdr(&self, id: NodeId) -> Option<f32> {
+        self.drones.iter().find(|d| d.id == id).map(|d| d.pdr)
     }
 
-    /// Function to handle all the incoming events
-    ///
-    /// Each time the GUI is refreshed, this function is called.
-    /// It listens to all the channels of the drones, web clients, chat clients and servers,
-    /// storing the received events in a queue.
-    /// Then for each event in the queue, it calls the corresponding handler function.
-    fn handle_event(&mut self) {
-        let mut event_queue: Vec<(NodeId, Events)> = Vec::new();
-        for (drone_id, drone_ch) in &self.drones_channels {
-            if let Ok(event) = drone_ch.1.try_recv() {
-                event_queue.push((*drone_id, Events::Drone(event)));
-            }
-        }
-
-        for (client_id, client_ch) in &self.web_clients_channels {
-            if let Ok(event) = client_ch.1.try_recv() {
-                event_queue.push((*client_id, Events::WebClient(event)));
+    /// Function to take a snapshot of the current topology of the network
+    #[must_use]
+    pub fn topology_snapshot(&self) -> TopologySnapshot {
+        let mut snapshot = TopologySnapshot::default();
+        for (_, node) in self.graph.nodes_iter() {
+            if let WidgetType::Drone(drone_widget) = node.payload() {
+                let id = drone_widget.id();
+                snapshot
+                    .drones
+                    .insert(id, self.drone_pdr(id).unwrap_or_default());
             }
         }
-
-        for (client_id, client_ch) in &self.chat_clients_channels {
-            if let Ok(event) = client_ch.1.try_recv() {
-                event_queue.push((*client_id, Events::ChatClient(event)));
+        for edge in self.graph.g.edge_indices() {
+            if let Some((a, b)) = self.graph.g.edge_endpoints(edge) {
+                let a = self.graph.node(a).unwrap().payload().get_id_helper();
+                let b = self.graph.node(b).unwrap().payload().get_id_helper();
+                snapshot.edges.insert(stats::edge_key(a, b));
             }
         }
+        snapshot
+    }
 
-        for (server_id, server_ch) in &self.servers_channels {
-            if let Ok(event) = server_ch.1.try_recv() {
-                event_queue.push((*server_id, Events::Server(event)));
-            }
-        }
+    /// Function to list the changes to the topology since startup, with the
+    /// seconds from startup of the last operation on each changed drone or edge
+    fn topology_changes(&self) -> Vec<(Change, Option<f32>)> {
+        topology::diff(&self.initial_topology, &self.topology_snapshot())
+            .into_iter()
+            .map(|change| {
+                let secs = self
+                    .operations
+                    .last_time(&change)
+                    .map(|time| time.duration_since(self.start_time).as_secs_f32());
+                (change, secs)
+            })
+            .collect()
+    }
 
-        for (id, event) in event_queue {
-            match event {
-                Events::Drone(event) => self.handle_drone_event(id, event),
-                Events::WebClient(event) => self.handle_web_client_event(id, event),
-                Events::ChatClient(event) => self.handle_chat_client_event(id, event),
-                Events::Server(event) => self.handle_server_event(id, event),
+    /// Function to re-run the selected layout over the current graph
+    ///
+    /// If `keep_pinned` is set, the nodes dragged by the user keep their position.
+    fn relayout(&mut self) {
+        let mut positions = self
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| (node.payload().get_id_helper(), node.location()))
+            .collect::<HashMap<NodeId, egui::Pos2>>();
+        let no_pins = HashSet::new();
+        let pinned = if self.keep_pinned {
+            &self.pinned_nodes
+        } else {
+            &no_pins
+        };
+        match self.layout_kind {
+            LayoutKind::Random => layout::random(&mut positions, pinned),
+            LayoutKind::ForceDirected => {
+                let edges = self
+                    .topology_snapshot()
+                    .edges
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                layout::force_directed(&mut positions, &edges, pinned);
             }
         }
-    }
 
-    /// Handler function for the drone events
-    fn handle_drone_event(&mut self, drone_id: NodeId, event: DroneEvent) {
-        match event {
-            DroneEvent::PacketSent(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let event_string = format!("[DRONE: {drone_id}] Sent {packet_type} packet");
-                let event_label = RichText::new(event_string);
-                self.events.push(event_label);
-            }
-            DroneEvent::PacketDropped(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let event_string = format!("[DRONE: {drone_id}] Dropped {packet_type} packet");
-                let event_label = RichText::new(event_string).color(Color32::RED);
-                self.events.push(event_label);
-            }
-            DroneEvent::ControllerShortcut(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let destination_id = packet.routing_header.destination();
-                match destination_id {
-                    Some(id) => {
-                        let event_string = format!("[DRONE: {drone_id}] Requested shortcut for packet {packet_type} to {id}");
-                        let event_label = RichText::new(event_string).color(Color32::ORANGE);
-                        self.events.push(event_label);
-                        self.handle_shortcut(id, packet);
-                    }
-                    None => unreachable!("Is it possible????"),
-                }
+        let indices = self
+            .graph
+            .nodes_iter()
+            .map(|(idx, node)| (idx, node.payload().get_id_helper()))
+            .collect::<Vec<_>>();
+        for (idx, id) in indices {
+            if let Some(pos) = positions.get(&id) {
+                self.graph.node_mut(idx).unwrap().set_location(*pos);
             }
         }
     }
 
-    /// Handler function for the web client events
-    fn handle_web_client_event(&mut self, client_id: NodeId, event: WebClientEvent) {
-        match event {
-            WebClientEvent::PacketSent(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let event_string = format!("[WEB CLIENT: {client_id}] Sent {packet_type} packet");
-                let event_label = RichText::new(event_string);
-                self.events.push(event_label);
-            }
-            WebClientEvent::Shortcut(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let destination_id = packet.routing_header.destination();
-                match destination_id {
-                    Some(id) => {
-                        let event_string = format!("[WEB CLIENT: {client_id}] Requested shortcut for packet {packet_type} to {id}");
-                        let event_label = RichText::new(event_string).color(Color32::ORANGE);
-                        self.events.push(event_label);
-                        self.handle_shortcut(id, packet);
-                    }
-                    None => unreachable!("Is it possible????"),
-                }
-            }
-            WebClientEvent::ListOfFiles(files, server_id) => {
-                let client_idx = self.get_node_idx(client_id).unwrap();
-                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
-
-                if let WidgetType::WebClient(client_widget) = client {
-                    client_widget.add_list_of_files(server_id, files);
-                }
-            }
-            WebClientEvent::FileFromClient(response, _) => {
-                let folder = Path::new("tmp");
-                let media_folder = Path::new("tmp/media");
-                let (filename, html_file) = response.get_html_file();
-
-                if !folder.exists() {
-                    std::fs::create_dir_all(folder).unwrap();
-                }
-
-                if !media_folder.exists() {
-                    std::fs::create_dir_all(media_folder).unwrap();
-                }
-
-                let file_path = folder.join(filename);
-                let mut file = File::create(&file_path).unwrap();
-                file.write_all(html_file).unwrap();
-
-                for (media_name, media_content) in response.get_media_files() {
-                    let media_path = media_folder.join(media_name);
-                    let mut media_file = File::create(&media_path).unwrap();
-                    media_file.write_all(media_content).unwrap();
-                }
-
-                if webbrowser::open(file_path.to_str().unwrap()).is_err() {
-                    println!("Failed to open the file in the browser");
-                }
-            }
-            WebClientEvent::ServersTypes(types) => {
-                let client_idx = self.get_node_idx(client_id).unwrap();
-                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
-
-                if let WidgetType::WebClient(client_widget) = client {
-                    client_widget.add_server_type(types);
-                }
-            }
-            WebClientEvent::UnsupportedRequest => {
-                let client_idx = self.get_node_idx(client_id).unwrap();
-                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
-
-                if let WidgetType::WebClient(client_widget) = client {
-                    client_widget.add_unsupported_request_error("Unsupported request".to_string());
-                }
-            }
+    /// Function to re-run the layout after a topology change, if enabled
+    fn topology_changed(&mut self) {
+        if self.auto_relayout {
+            self.relayout();
         }
     }
 
-    /// Handler function for the chat client events
-    fn handle_chat_client_event(&mut self, chat_client_id: NodeId, event: ChatClientEvent) {
-        match event {
-            ChatClientEvent::PacketSent(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let event_string =
-                    format!("[CHAT CLIENT: {chat_client_id}] Sent {packet_type} packet");
-                let event_label = RichText::new(event_string);
-                self.events.push(event_label);
-            }
-            ChatClientEvent::Shortcut(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let destination_id = packet.routing_header.destination();
-                match destination_id {
-                    Some(id) => {
-                        let event_string = format!("[CHAT CLIENT: {chat_client_id}] Requested shortcut for packet {packet_type} to {id}");
-                        let event_label = RichText::new(event_string).color(Color32::ORANGE);
-                        self.events.push(event_label);
-                        self.handle_shortcut(id, packet);
-                    }
-                    None => unreachable!("Is it possible????"),
-                }
-            }
-            ChatClientEvent::ServersTypes(types) => {
-                let client_idx = self.get_node_idx(chat_client_id).unwrap();
-                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
-
-                if let WidgetType::ChatClient(client_widget) = client {
-                    client_widget.add_server_type(&types);
-                }
-            }
-            ChatClientEvent::UnsupportedRequest => {}
-            ChatClientEvent::MessageReceived(msg) => {
-                let client_idx = self.get_node_idx(chat_client_id).unwrap();
-                let client = self.graph.node_mut(client_idx).unwrap().payload_mut();
-
-                if let WidgetType::ChatClient(client_widget) = client {
-                    client_widget.update_chat(msg);
-                }
-            }
+    /// Helper function to get the configuration vector of the clients of the given kind
+    fn clients(&self, kind: ClientKind) -> &[Client] {
+        match kind {
+            ClientKind::Web => &self.web_clients,
+            ClientKind::Chat => &self.chat_clients,
         }
     }
 
-    /// Handler function for the server events
-    fn handle_server_event(&mut self, server_id: NodeId, event: ServerEvent) {
-        match event {
-            ServerEvent::PacketSent(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let event_string = format!("[SERVER: {server_id}] Sent {packet_type} packet");
-                let event_label = RichText::new(event_string);
-                self.events.push(event_label);
-            }
-            ServerEvent::ShortCut(packet) => {
-                let packet_type = SimulationController::get_pack_type(&packet);
-                let destination_id = packet.routing_header.destination();
-                match destination_id {
-                    Some(id) => {
-                        let event_string = format!("[SERVER: {server_id}] Requested shortcut for packet {packet_type} to {id}");
-                        let event_label = RichText::new(event_string).color(Color32::ORANGE);
-                        self.events.push(event_label);
-                        self.handle_shortcut(id, packet);
-                    }
-                    None => unreachable!("Is it possible????"),
-                }
-            }
+    /// Helper function to get the mutable configuration vector of the clients of the given kind
+    fn clients_mut(&mut self, kind: ClientKind) -> &mut Vec<Client> {
+        match kind {
+            ClientKind::Web => &mut self.web_clients,
+            ClientKind::Chat => &mut self.chat_clients,
         }
     }
 
-    /// Function used to update the neighborhood of a node
-    ///
-    /// The neighborhood of a node is the set of nodes that are connected to it.
-    /// This function handles the addition and removal of nodes from the neighborhood,
-    /// by using the `UpdateType` enum to distinguish between the two cases.
-    fn update_neighborhood(
-        &mut self,
-        update_type: &UpdateType,
-        source_id: u8,
-        source_idx: NodeIndex,
-        n_id: u8,
-    ) {
-        match update_type {
-            UpdateType::Add => match self.graph.node(source_idx).unwrap().payload() {
-                WidgetType::Drone(_) => {
-                    if let Some(pos) = self.drones.iter().position(|d| d.id == source_id) {
-                        self.drones[pos].connected_node_ids.push(n_id);
-                    }
-                }
-                WidgetType::Server(_) => {
-                    if let Some(pos) = self.servers.iter().position(|d| d.id == source_id) {
-                        self.servers[pos].connected_drone_ids.push(n_id);
-                    }
-                }
-                _ => {
-                    if let Some(pos) = self.clients.iter().position(|d| d.id == source_id) {
-                        self.clients[pos].connected_drone_ids.push(n_id);
-                    }
-                }
-            },
-            UpdateType::Remove => match self.graph.node(source_idx).unwrap().payload() {
-                WidgetType::Drone(_) => {
-                    if let Some(pos) = self.drones.iter().position(|d| d.id == source_id) {
-                        if let Some(to_remove) = self.drones[pos]
-                            .connected_node_ids
-                            .iter()
-                            .position(|id| *id == n_id)
-                        {
-                            self.drones[pos].connected_node_ids.remove(to_remove);
-                        }
-                    }
-                }
-                WidgetType::Server(_) => {
-                    if let Some(pos) = self.servers.iter().position(|s| s.id == source_id) {
-                        if let Some(to_remove) = self.servers[pos]
-                            .connected_drone_ids
-                            .iter()
-                            .position(|id| *id == n_id)
-                        {
-                            self.servers[pos].connected_drone_ids.remove(to_remove);
-                        }
-                    }
-                }
-                _ => {
-                    if let Some(pos) = self.clients.iter().position(|c| c.id == source_id) {
-                        if let Some(to_remove) = self.clients[pos]
-                            .connected_drone_ids
-                            .iter()
-                            .position(|id| *id == n_id)
-                        {
-                            self.clients[pos].connected_drone_ids.remove(to_remove);
-                        }
-                    }
-                }
-            },
-        }
+    /// Helper function to iterate over every client, regardless of its kind
+    fn all_clients(&self) -> impl Iterator<Item = &Client> {
+        self.web_clients.iter().chain(self.chat_clients.iter())
     }
 
-    /// Function to validate the input of the user when adding a neighbor to a node
-    ///
-    /// The input should not be empty
-    /// The input should be a valid u8 number
-    /// The input should be a valid id of a node in the graph
-    fn validate_add_sender_input(&self, input_neighbor_id: &str) -> Result<NodeIndex, String> {
-        if input_neighbor_id.is_empty() {
-            return Err("The input field cannot be empty".to_string());
+    /// Helper function to add `n_id` to the neighbors of the client with `client_id`
+    fn add_client_neighbor(&mut self, kind: ClientKind, client_id: NodeId, n_id: NodeId) {
+        let clients = self.clients_mut(kind);
+        if let Some(pos) = clients.iter().position(|c| c.id == client_id) {
+            clients[pos].connected_drone_ids.push(n_id);
         }
-
-        // Parse the input to u8, return error if parsing goes wrong
-        let Ok(neighbor_id) = input_neighbor_id.parse::<u8>() else {
-            return Err("Wrong ID format".to_string());
-        };
-
-        // From the u8 id, retrieve the corresponding NodeIndex in the graph
-        let Some(neighbor_idx) = self.get_node_idx(neighbor_id) else {
-            return Err("ID not found in te graph".to_string());
-        };
-
-        Ok(neighbor_idx)
     }
 
-    /// Function used to verify if a client can add a new sender
-    ///
-    /// A client can add a new sender if it has less than 2 connections
-    fn can_client_add_sender(&self, client_id: NodeId) -> Result<u8, String> {
-        if let Some(client_pos) = self.clients.iter().position(|c| c.id == client_id) {
-            if self.clients[client_pos].connected_drone_ids.len() == 2 {
-                Err(format!("Client {client_id} reached its max connections"))
-            } else {
-                Ok(client_id)
+    /// Helper function to remove `n_id` from the neighbors of the client with `client_id`
+    fn remove_client_neighbor(&mut self, kind: ClientKind, client_id: NodeId, n_id: NodeId) {
+        let clients = self.clients_mut(kind);
+        if let Some(pos) = clients.iter().position(|c| c.id == client_id) {
+            if let Some(to_remove) = clients[pos]
+                .connected_drone_ids
+                .iter()
+                .position(|id| *id == n_id)
+            {
+                clients[pos].connected_drone_ids.remove(to_remove);
             }
-        } else {
-            Err("Client not found".to_string())
         }
     }
 
-    /// Function to check if a sender can be added to a node
+    /// Helper function to get the index of a node given its id
     ///
-    /// It checks if the sender and the neighbor can be connected
-    /// based on the type of the nodes.
-    /// Drones can be connected to drones, clients and servers.
-    /// Clients can be connected only to drones. (max. 2 connections)
-    /// Servers can be connected only to drones.
-    fn can_add_sender(
-        &self,
-        source_idx: NodeIndex,
-        neighbor_idx: NodeIndex,
-    ) -> Result<(NodeIndex, NodeIndex), String> {
-        match (
-            self.graph.node(source_idx).unwrap().payload(),
-            self.graph.node(neighbor_idx).unwrap().payload(),
-        ) {
-            (WidgetType::Drone(_), WidgetType::Drone(_)) => {
-                // Avoid creating a connection to itself
-                if source_idx == neighbor_idx {
-                    return Err("Can't create a connection to itself".to_string());
-                }
-                Ok((source_idx, neighbor_idx))
-            }
-            // For clients, check if the client has reached its max number of connections (2)
-            (WidgetType::Drone(_), WidgetType::WebClient(web_client_widget))
-            | (WidgetType::WebClient(web_client_widget), WidgetType::Drone(_)) => {
-                let client_id = web_client_widget.get_id();
-
-                match self.can_client_add_sender(client_id) {
-                    Ok(_) => Ok((source_idx, neighbor_idx)),
-                    Err(e) => Err(e),
-                }
-            }
-            // For clients, check if the client has reached its max number of connections (2)
-            (WidgetType::Drone(_), WidgetType::ChatClient(chat_client_widget))
-            | (WidgetType::ChatClient(chat_client_widget), WidgetType::Drone(_)) => {
-                let client_id = chat_client_widget.get_id();
-
-                match self.can_client_add_sender(client_id) {
-                    Ok(_) => Ok((source_idx, neighbor_idx)),
-                    Err(e) => Err(e),
-                }
-            }
-            (WidgetType::Drone(_), WidgetType::Server(_))
-            | (WidgetType::Server(_), WidgetType::Drone(_)) => Ok((source_idx, neighbor_idx)),
-            // Server can be connected to any number of drones, but not to other clients or servers
-            (WidgetType::Server(_), _) => {
-                Err("Server cannot be connected directly to other client nor server".to_string())
-            }
-
-            // Here I include all patterns like ChatClient/ChatClient, ChatClient/WebClient, ChatClient/Server.
-            // and all patterns like WebClient/WebClient, WebClient/ChatClient, WebClient/Server.
-            (WidgetType::ChatClient(_) | WidgetType::WebClient(_), _) => {
-                Err("Client cannot be connected directly to other client nor server".to_string())
-            }
+    /// The `NodeIndex` is the index used by the graph library to identify a node
+    fn get_node_idx(&self, id: NodeId) -> Option<NodeIndex> {
+        let idx = self.node_indices.get(&id).copied();
+        if let Some(idx) = idx {
+            debug_assert!(
+                self.graph
+                    .node(idx)
+                    .is_some_and(|node| node.payload().get_id_helper() == id),
+                "Stale index of node {id}"
+            );
         }
+        idx
     }
 
-    /// This function checks if an edge can be added between two nodes
+    /// Helper function to refresh the label of a node in the graph
     ///
-    /// First, it checks if the input is valid, calling the `validate_add_sender_input` function.
-    /// Then, it checks if the nodes can be connected, calling the `can_add_sender` function.
-    fn validate_add_sender(
-        &mut self,
-        source_idx: NodeIndex,
-        input_neighbor_id: &str,
-    ) -> Result<(NodeIndex, NodeIndex), String> {
-        let neighbor_idx = self.validate_add_sender_input(input_neighbor_id)?;
-        
-        // check if the two nodes are already connected
-        if self.graph.edges_connecting(source_idx, neighbor_idx).count() > 0 {
-            return Err("Nodes are already connected".to_string());
+    /// If the user enabled it, the note attached to the node is shown as a second line
+    fn refresh_label(&mut self, idx: NodeIndex) {
+        let Some(node) = self.graph.node(idx) else {
+            return;
+        };
+        let widget = node.payload();
+
+        // The members of a collapsed group are drawn as a single node
+        if let Some(name) = self.clusters.group_of(widget.get_id_helper()) {
+            if self.clusters.is_collapsed(name) {
+                let label = if self.clusters.representative(name) == Some(widget.get_id_helper()) {
+                    let members = self
+                        .clusters
+                        .groups()
+                        .find(|(group, _)| *group == name)
+                        .map_or(0, |(_, members)| members.len());
+                    let links = self
+                        .clusters
+                        .external_links(name, &self.topology_snapshot().edges);
+                    format!(
+                        "{name} [{members} drones, {} links]",
+                        links.values().sum::<usize>()
+                    )
+                } else {
+                    String::new()
+                };
+                self.graph.node_mut(idx).unwrap().set_label(label);
+                return;
+            }
         }
-        
-        self.can_add_sender(source_idx, neighbor_idx)
-    }
 
-    /// Helper function to get the sender channel of a node and the corresponding `NodeId`
-    fn get_sender_channel(&self, idx: NodeIndex) -> (NodeId, Sender<Packet>) {
-        match self.graph.node(idx).unwrap().payload() {
-            WidgetType::Drone(dw) => (dw.get_id(), self.drones_channels[&dw.get_id()].2.clone()),
-            WidgetType::WebClient(wcw) => (
-                wcw.get_id(),
-                self.web_clients_channels[&wcw.get_id()].2.clone(),
-            ),
-            WidgetType::ChatClient(ccw) => (
-                ccw.get_id(),
-                self.chat_clients_channels[&ccw.get_id()].2.clone(),
-            ),
-            WidgetType::Server(sw) => (sw.get_id(), self.servers_channels[&sw.get_id()].2.clone()),
+        let mut label = self.with_alias(widget.get_id_helper(), widget.get_label_helper());
+        if self.unresponsive_nodes.contains(&widget.get_id_helper()) {
+            label.push_str(" (unresponsive)");
         }
-    }
-
-    /// Function that checks if the removal of the edge would make some servers/clients unreachable
-    /// Furthermore, it that checks if the graph would become disconnected if the edge is removed.
-    fn check_connectivity(&self, edge_to_remove: EdgeIndex) -> Result<(), String> {
-        let mut copy_graph = self.graph.clone();
-        copy_graph.remove_edge(edge_to_remove).unwrap();
-
-        // For each client, perform a DFS to check if it can reach every server
-        for client in &self.clients {
-            let client_idx = self.get_node_idx(client.id).unwrap();
-            let mut visited: HashSet<NodeIndex> = HashSet::new();
-            let mut servers_visited: HashSet<NodeId> = HashSet::new();
-            let mut stack: VecDeque<NodeIndex> = VecDeque::new();
-            stack.push_back(client_idx);
-
-            while let Some(node) = stack.pop_front() {
-                if visited.insert(node) {
-                    let neighbors = copy_graph.g.neighbors(node).collect::<Vec<NodeIndex>>();
-                    for neighbor in neighbors {
-                        if let WidgetType::Server(server_widget) =
-                            copy_graph.node(neighbor).unwrap().payload()
-                        {
-                            servers_visited.insert(server_widget.get_id());
-                        } else if let WidgetType::ChatClient(_) | WidgetType::WebClient(_) =
-                            copy_graph.node(neighbor).unwrap().payload()
-                        {
-                            continue;
-                        } else {
-                            stack.push_front(neighbor);
-                        }
-                    }
-                }
-            }
-
-            // Check if the client can reach every server
-            if servers_visited.len() != self.servers.len() {
-                return Err(format!(
-                    "By removing edge {}, client {} wouldn't reach every server",
-                    edge_to_remove.index(),
-                    client.id
-                ));
+        if let Some(trace) = self.highlighted_trace.and_then(|key| self.tracer.get(key)) {
+            let hops = trace
+                .hops
+                .iter()
+                .enumerate()
+                .filter(|(_, (hop, _))| *hop == widget.get_id_helper())
+                .map(|(i, _)| i.to_string())
+                .collect::<Vec<String>>();
+            if !hops.is_empty() {
+                label.push_str(&format!(" [hop {}]", hops.join(", ")));
             }
         }
-
-        // Check if graph is still connected
-        let cc = petgraph::algo::tarjan_scc(&copy_graph.g);
-        if cc.len() > 1 {
-            return Err("By removing the edge, the graph would become disconnected".to_string());
+        if self.log_hover.contains(&widget.get_id_helper()) {
+            label.push_str(" [log]");
         }
-
-        Ok(())
-    }
-
-    /// Function to check if a node can remove a sender
-    ///
-    /// For drones, they must have at least 1 connection, otherwise the graph becomes disconnected.
-    /// For clients, they must have at least 1 connection to a drone.
-    /// For servers, they must have at least 2 connections to drones.
-    fn can_remove_sender(&self, node_idx: NodeIndex) -> Result<u8, String> {
-        match self.graph.node(node_idx).unwrap().payload() {
-            // For drones I should check if they have at least 1 connection, otherwise the graph becomes disconnected
-            WidgetType::Drone(drone_widget) => {
-                let drone_id = drone_widget.get_id();
-                if let Some(pos) = self.drones.iter().position(|d| d.id == drone_id) {
-                    if self.drones.get(pos).unwrap().connected_node_ids.len() == 1 {
-                        Err(format!("Cant remove last connection of drone {drone_id}!"))
-                    } else {
-                        Ok(drone_id)
-                    }
-                } else {
-                    Err("Drone not found".to_string())
-                }
-            }
-            // For clients I should check that they are connected to at least 1 drone
-            WidgetType::WebClient(web_client_widget) => {
-                let client_id = web_client_widget.get_id();
-                if let Some(pos) = self.clients.iter().position(|c| c.id == client_id) {
-                    if self.clients.get(pos).unwrap().connected_drone_ids.len() == 1 {
-                        Err(format!(
-                            "Client {client_id} must have at least 1 connection!"
-                        ))
-                    } else {
-                        Ok(client_id)
-                    }
-                } else {
-                    Err("Client not found".to_string())
-                }
-            }
-            WidgetType::ChatClient(chat_client_widget) => {
-                let client_id = chat_client_widget.get_id();
-                if let Some(pos) = self.clients.iter().position(|c| c.id == client_id) {
-                    if self.clients.get(pos).unwrap().connected_drone_ids.len() == 1 {
-                        Err(format!(
-                            "Client {client_id} must have at least 1 connection!"
-                        ))
-                    } else {
-                        Ok(client_id)
-                    }
-                } else {
-                    Err("Client not found".to_string())
-                }
-            }
-            WidgetType::Server(server_widget) => {
-                let server_id = server_widget.get_id();
-                if let Some(pos) = self.servers.iter().position(|s| s.id == server_id) {
-                    if self.servers.get(pos).unwrap().connected_drone_ids.len() == 2 {
-                        Err(format!(
-                            "Server {server_id} must have at least 2 connections"
-                        ))
-                    } else {
-                        Ok(server_id)
-                    }
-                } else {
-                    Err("Server not found".to_string())
-                }
+        if self.show_notes_in_labels {
+            if let Some(note) = self.notes.get(&widget.get_id_helper()) {
+                label.push('\n');
+                label.push_str(note);
             }
         }
+        self.graph.node_mut(idx).unwrap().set_label(label);
     }
 
-    /// This function checks if an edge can be removed
-    /// First it checks if the graph would become disconnected.
-    /// The graph becomes disconnected if the removal of the edge would create more than 1 connected component.
-    /// Or if the removal of the edge would make a client unable to reach every server.
-    /// Then it checks if the nodes (endpoints of the edge) can remove each other.
-    /// For drones, they must have at least 1 connection, otherwise the graph becomes disconnected.
-    /// For clients, they must have at least 1 connection to a drone.
-    /// For servers, they must have at least 2 connections to drones.
-    fn validate_edge_removal(&mut self, edge: EdgeIndex) -> Result<(u8, u8), String> {
-        // Check if without the edge, every client can still reach every server
-        self.check_connectivity(edge)?;
-
-        // Take the 2 endpoints of the edge to be removed
-        let (node_1, node_2) = self.graph.edge_endpoints(edge).unwrap();
-
-        match (
-            self.can_remove_sender(node_1),
-            self.can_remove_sender(node_2),
-        ) {
-            (Ok(id_1), Ok(id_2)) => Ok((id_1, id_2)),
-            (Ok(_), Err(e)) | (Err(e), Ok(_)) => Err(e),
-            (Err(_), Err(_)) => Err("Either nodes can't remove each other".to_string()),
-        }
+    /// Helper function to get the name of a node, as shown in its label
+    fn node_name(&self, id: NodeId) -> String {
+        let name = self.get_node_idx(id).map_or(format!("node {id}"), |idx| {
+            self.graph.node(idx).unwrap().payload().get_label_helper()
+        });
+        self.with_alias(id, name)
     }
 
-    fn can_drone_crash(&self, drone_id: NodeId) -> Result<(), String> {
-        let drone_idx = self.get_node_idx(drone_id).unwrap();
-
-        // Check if the neighbors of the drone can remove it
-        let neighbors = self
-            .graph
-            .g
-            .neighbors(drone_idx)
-            .collect::<Vec<NodeIndex>>();
-        for neighbor in neighbors {
-            match self.graph.node(neighbor).unwrap().payload() {
-                WidgetType::Drone(drone_widget) => {
-                    let id = drone_widget.get_id();
-                    if let Some(pos) = self.drones.iter().position(|d| d.id == id) {
-                        if self.drones[pos].connected_node_ids.len() == 1 {
-                            return Err(format!("Drone {id} must have at least 1 connection"));
-                        }
-                    }
-                }
-                WidgetType::WebClient(web_client_widget) => {
-                    let id = web_client_widget.get_id();
-                    if let Some(pos) = self.clients.iter().position(|wc| wc.id == id) {
-                        if self.clients[pos].connected_drone_ids.len() == 1 {
-                            return Err(format!("Client {id} must have at least 1 connection"));
-                        }
-                    }
-                }
-                WidgetType::ChatClient(chat_client_widget) => {
-                    let id = chat_client_widget.get_id();
-                    if let Some(pos) = self.clients.iter().position(|cc| cc.id == id) {
-                        if self.clients[pos].connected_drone_ids.len() == 1 {
-                            return Err(format!("Client {id} must have at least 1 connection"));
-                        }
-                    }
-                }
-                WidgetType::Server(server_widget) => {
-                    let id = server_widget.get_id();
-                    if let Some(pos) = self.servers.iter().position(|s| s.id == id) {
-                        if self.servers[pos].connected_drone_ids.len() == 2 {
-                            return Err(format!("Server {id} must have at least 2 connections"));
-                        }
-                    }
-                }
-            }
-        }
-
-        let mut copy_graph = self.graph.clone();
-        copy_graph.remove_node(drone_idx);
-
-        // check connectivity between clients and servers
-        for client in &self.clients {
-            let client_idx = self.get_node_idx(client.id).unwrap();
-            let mut visited: HashSet<NodeIndex> = HashSet::new();
-            let mut servers_visited: HashSet<NodeId> = HashSet::new();
-            let mut stack: VecDeque<NodeIndex> = VecDeque::new();
-            stack.push_back(client_idx);
-
-            while let Some(node) = stack.pop_front() {
-                if visited.insert(node) {
-                    let neighbors = copy_graph.g.neighbors(node).collect::<Vec<NodeIndex>>();
-                    for neighbor in neighbors {
-                        if let WidgetType::Server(server_widget) =
-                            copy_graph.node(neighbor).unwrap().payload()
-                        {
-                            servers_visited.insert(server_widget.get_id());
-                        } else if let WidgetType::ChatClient(_) | WidgetType::WebClient(_) =
-                            copy_graph.node(neighbor).unwrap().payload()
-                        {
-                            continue;
-                        } else {
-                            stack.push_front(neighbor);
-                        }
-                    }
-                }
-            }
-
-            // Check if the client can reach every server
-            if servers_visited.len() != self.servers.len() {
-                return Err(format!(
-                    "By removing drone {}, client {} wouldn't reach every server",
-                    drone_idx.index(),
-                    client.id
-                ));
-            }
-        }
-
-        // check if graph is still connected
-        let cc = petgraph::algo::tarjan_scc(&copy_graph.g);
-        if cc.len() > 1 {
-            return Err(format!(
-                "By removing drone {}, the graph would become disconnected",
-                drone_idx.index()
-            ));
+    /// Helper function to prefix the alias of a node, if any, to `name`
+    ///
+    /// E.g. if node 4 has alias "gateway", "Drone 4" becomes "gateway (Drone 4)"
+    fn with_alias(&self, id: NodeId, name: String) -> String {
+        match self.aliases.get(&id) {
+            Some(alias) => format!("{alias} ({name})"),
+            None => name,
         }
-
-        Ok(())
     }
 
-    /// Function to crash a drone
-    ///
-    /// When a drone crashes, it sends a crash command to the mimicked drone.
-    /// Then, it removes the drone from the graph and updates the neighbors of the drone.
-    fn crash_drone(&mut self, crashing_drone: NodeIndex) {
-        let drone = self.graph.node(crashing_drone).unwrap().payload();
-        let neighbors = self
+    /// Helper function to refresh the labels of every node in the graph
+    fn refresh_labels(&mut self) {
+        let indices = self
             .graph
-            .g
-            .neighbors(crashing_drone)
+            .nodes_iter()
+            .map(|(idx, _)| idx)
             .collect::<Vec<NodeIndex>>();
-        match drone {
-            WidgetType::Drone(drone_widget) => {
-                drone_widget.send_crash_command();
-                let crashing_drone_id = drone_widget.get_id();
-                for neighbor in neighbors {
-                    match self.graph.node(neighbor).unwrap().payload() {
-                        WidgetType::Drone(neighbor_widget) => {
-                            let id = neighbor_widget.get_id();
-                            if let Some(pos) = self.drones.iter().position(|d| d.id == id) {
-                                if let Some(to_remove) = self.drones[pos]
-                                    .connected_node_ids
-                                    .iter()
-                                    .position(|id| *id == crashing_drone_id)
-                                {
-                                    self.drones[pos].connected_node_ids.remove(to_remove);
-                                }
-                            }
-                            neighbor_widget.remove_neighbor(drone_widget.get_id());
-                        }
-                        WidgetType::WebClient(neighbor_widget) => {
-                            let id = neighbor_widget.get_id();
-                            if let Some(pos) = self.clients.iter().position(|c| c.id == id) {
-                                if let Some(to_remove) = self.clients[pos]
-                                    .connected_drone_ids
-                                    .iter()
-                                    .position(|id| *id == crashing_drone_id)
-                                {
-                                    self.clients[pos].connected_drone_ids.remove(to_remove);
-                                }
-                            }
-                            neighbor_widget.remove_neighbor(drone_widget.get_id());
-                        }
-                        WidgetType::ChatClient(neighbor_widget) => {
-                            let id = neighbor_widget.get_id();
-                            if let Some(pos) = self.clients.iter().position(|c| c.id == id) {
-                                if let Some(to_remove) = self.clients[pos]
-                                    .connected_drone_ids
-                                    .iter()
-                                    .position(|id| *id == crashing_drone_id)
-                                {
-                                    self.clients[pos].connected_drone_ids.remove(to_remove);
-                                }
-                            }
-                            neighbor_widget.remove_neighbor(drone_widget.get_id());
-                        }
-                        WidgetType::Server(neighbor_widget) => {
-                            let id = neighbor_widget.get_id();
-                            if let Some(pos) = self.servers.iter().position(|s| s.id == id) {
-                                if let Some(to_remove) = self.servers[pos]
-                                    .connected_drone_ids
-                                    .iter()
-                                    .position(|id| *id == crashing_drone_id)
-                                {
-                                    self.servers[pos].connected_drone_ids.remove(to_remove);
-                                }
-                            }
-                            neighbor_widget.remove_neighbor(drone_widget.get_id());
-                        }
-                    }
-                }
-            }
-            _ => {
-                unreachable!("Only drones can crash")
-            }
+        for idx in indices {
+            self.refresh_label(idx);
         }
-        self.graph.remove_node(crashing_drone);
-        self.selected_node = None;
     }
 
-    /// Function to spawn a new drone
-    fn spawn_drone(&mut self) {
-        let rand_drone_id = rand::rng().random_range(0..10);
-        let drone_factory = DRONE_FACTORY[rand_drone_id];
-        let new_id = 100;
-        let (sender_command, receiver_command): (Sender<DroneCommand>, Receiver<DroneCommand>) =
-            crossbeam_channel::unbounded();
-        let (send_event, receive_event): (Sender<DroneEvent>, Receiver<DroneEvent>) =
-            crossbeam_channel::unbounded();
-        let (packet_send, packet_recv): (Sender<Packet>, Receiver<Packet>) =
-            crossbeam_channel::unbounded();
-        let nbrs = HashMap::new();
-        let pdr = 0.0;
-        let mut new_drone = drone_factory(
-            new_id,
-            send_event,
-            receiver_command,
-            packet_recv.clone(),
-            nbrs,
-            pdr,
-        );
-
-        self.drones_channels.insert(
-            new_id,
-            (
-                sender_command.clone(),
-                receive_event,
-                packet_send,
-                packet_recv,
-            ),
-        );
-        self.drones.push(Drone {
-            id: new_id,
-            connected_node_ids: vec![],
-            pdr,
-        });
-        let drone_idx = self.graph.add_node(WidgetType::Drone(DroneWidget::new(
-            new_id,
-            sender_command.clone(),
-        )));
-        self.graph
-            .node_mut(drone_idx)
-            .unwrap()
-            .set_label(format!("Drone {new_id}"));
-        std::thread::spawn(move || {
-            new_drone.run();
-        });
+    /// Function to set the folder the received files and the exports are saved to
+    ///
+    /// The folder is created when the first file is saved.
+    pub fn set_output_dir(&mut self, dir: PathBuf) {
+        self.output_dir = dir;
     }
 
-    fn read_data(&mut self) {
-        if !self.graph.selected_nodes().is_empty() {
-            let idx = self.graph.selected_nodes().first().unwrap();
-            self.selected_node = Some(*idx);
+    /// Function to undo and redo the operations on the topology with Ctrl+Z and Ctrl+Y
+    ///
+    /// The shortcuts are left to the text fields while one has the focus.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
         }
+        let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        if ctx.input_mut(|input| input.consume_shortcut(&undo)) {
+            self.undo();
+        } else if ctx.input_mut(|input| input.consume_shortcut(&redo)) {
+            self.redo();
+        }
+    }
 
-        if !self.graph.selected_edges().is_empty() {
-            let edge_idx = self.graph.selected_edges().first().unwrap();
-            self.selected_edge = Some(*edge_idx);
+    /// Function to get the index of the node that sent an event, if it's in the graph
+    ///
+    /// Events from ids missing from the graph (e.g. mis-wired channels) are
+    /// dropped, logging a warning instead of panicking.
+    fn known_node_idx(&mut self, id: NodeId, event_name: &str) -> Option<NodeIndex> {
+        let idx = self.get_node_idx(id);
+        if idx.is_none() {
+            let event_string =
+                format!("[CONTROLLER] Dropped {event_name} event from unknown node {id}");
+            self.log(LogLevel::Warn, event_string);
         }
+        idx
     }
 
-    #[allow(clippy::too_many_lines)]
-    fn render(&mut self, ctx: &egui::Context) {
-        SidePanel::right("Panel").show(ctx, |ui| {
-            if let Some(idx) = self.selected_node {
-                let node = self.graph.node_mut(idx).unwrap().payload_mut().clone();
-                match node {
-                    WidgetType::Drone(drone_widget) => {
-                        let drone_id = drone_widget.get_id();
-                        ui.vertical(|ui| {
-                            ui.add(drone_widget);
-                            ui.separator();
-                            ui.label("Crash the drone");
-                            let red_btn = ui.add(
-                                Button::new(RichText::new("Crash").color(Color32::BLACK))
-                                    .fill(Color32::RED),
-                            );
-                            if red_btn.clicked() {
-                                // check if the drone can crash
-                                match self.can_drone_crash(drone_id) {
-                                    Ok(()) => self.crash_drone(idx),
-                                    Err(error) => self.drone_crash_error = error,
-                                }
-                            }
-
-                            if !self.drone_crash_error.is_empty() {
-                                ui.label(
-                                    RichText::new(&self.drone_crash_error)
-                                        .color(egui::Color32::RED),
-                                );
-                            }
-                        })
-                        .response
-                    }
-                    WidgetType::WebClient(web_client_widget) => ui.add(web_client_widget),
-                    WidgetType::ChatClient(chat_client_widget) => ui.add(chat_client_widget),
-                    WidgetType::Server(server_widget) => ui.add(server_widget),
-                };
-            } else {
-                ui.label("No node selected");
-            }
+    /// Helper function to get the sender channel of a node and the corresponding `NodeId`
+    fn get_sender_channel(&self, idx: NodeIndex) -> (NodeId, Sender<Packet>) {
+        let widget = self.graph.node(idx).unwrap().payload();
+        let id = widget.get_id_helper();
+        let channel = match widget {
+            WidgetType::Drone(_) => &self.drones_channels[&id].2,
+            WidgetType::WebClient(_) => &self.web_clients_channels[&id].2,
+            WidgetType::ChatClient(_) => &self.chat_clients_channels[&id].2,
+            WidgetType::Server(_) => &self.servers_channels[&id].2,
+        };
+        (id, channel.clone())
+    }
+}
 
-            ui.with_layout(Layout::bottom_up(egui::Align::Center), |ui| {
-                ui.add_space(10.0);
-                if ui.button("Add Drone").clicked() {
-                    self.spawn_drone();
-                }
-            });
-        });
-        TopBottomPanel::bottom("Bottom_panel")
-            .resizable(true)
-            .show(ctx, |ui| {
-                let text_style = TextStyle::Body;
-                let row_height = ui.text_style_height(&text_style);
-                ui.columns_const(|[left, right]| {
-                    // Left column should containt the add sender and remove edge buttons
-                    left.horizontal(|ui| {
-                        if let Some(idx) = self.selected_node {
-                            ui.vertical(|ui| {
-                                ui.label(format!(
-                                    "Selected node: {:?}",
-                                    self.graph.node(idx).unwrap().payload().get_id_helper()
-                                ));
-                                ui.set_max_width(71.0); // Width of the add button
-                                ui.text_edit_singleline(&mut self.add_neighbor_input);
-                                let add_btn = ui.add(Button::new("Add sender"));
-                                if add_btn.clicked() {
-                                    match self
-                                        .validate_add_sender(idx, &self.add_neighbor_input.clone())
-                                    {
-                                        Ok((source_idx, neighbor_idx)) => {
-                                            let (neighbor_id, neighbor_ch) =
-                                                self.get_sender_channel(neighbor_idx);
-                                            let (current_node_id, current_node_ch) =
-                                                self.get_sender_channel(source_idx);
-
-                                            let current_node_widget =
-                                                self.graph.node_mut(idx).unwrap().payload_mut();
-                                            current_node_widget
-                                                .add_neighbor_helper(neighbor_id, neighbor_ch);
-
-                                            let neighbor_widget = self
-                                                .graph
-                                                .node_mut(neighbor_idx)
-                                                .unwrap()
-                                                .payload_mut();
-                                            neighbor_widget.add_neighbor_helper(
-                                                current_node_id,
-                                                current_node_ch,
-                                            );
-
-                                            self.update_neighborhood(
-                                                &UpdateType::Add,
-                                                current_node_id,
-                                                idx,
-                                                neighbor_id,
-                                            );
-                                            self.update_neighborhood(
-                                                &UpdateType::Add,
-                                                neighbor_id,
-                                                neighbor_idx,
-                                                current_node_id,
-                                            );
-                                            self.graph.add_edge(idx, neighbor_idx, ());
-                                        }
-                                        Err(error) => self.add_neighbor_error = error,
-                                    }
-                                }
-
-                                if !self.add_neighbor_error.is_empty() {
-                                    ui.label(
-                                        RichText::new(&self.add_neighbor_error)
-                                            .color(egui::Color32::RED),
-                                    );
-                                }
-                            });
-                        }
-
-                        ui.add_space(15.0);
-
-                        // Remove edge area
-                        if let Some(edge_idx) = self.selected_edge {
-                            ui.vertical(|ui| {
-                                ui.label(format!("Selected edge: {edge_idx:?}"));
-                                let remove_btn = ui.add(Button::new("Remove edge"));
-                                if remove_btn.clicked() {
-                                    match self.validate_edge_removal(edge_idx) {
-                                        Ok((node_1, node_2)) => {
-                                            self.rm_neighbor_error = String::new();
-
-                                            let node_1_idx = self.get_node_idx(node_1).unwrap();
-                                            let node_1_widget = self
-                                                .graph
-                                                .node_mut(node_1_idx)
-                                                .unwrap()
-                                                .payload_mut();
-                                            // Send command to source to remove neighbor
-                                            node_1_widget.rm_neighbor_helper(node_2);
-
-                                            let node_2_idx = self.get_node_idx(node_2).unwrap();
-                                            let node_2_widget = self
-                                                .graph
-                                                .node_mut(node_2_idx)
-                                                .unwrap()
-                                                .payload_mut();
-                                            // Send command to neighbor to remove source
-                                            node_2_widget.rm_neighbor_helper(node_1);
-
-                                            // Update state of SCL
-                                            self.update_neighborhood(
-                                                &UpdateType::Remove,
-                                                node_1,
-                                                node_1_idx,
-                                                node_2,
-                                            );
-                                            self.update_neighborhood(
-                                                &UpdateType::Remove,
-                                                node_2,
-                                                node_2_idx,
-                                                node_1,
-                                            );
-                                            // Deselect the edge
-                                            self.selected_edge = None;
-                                            // Update graph visualization
-                                            self.graph.remove_edges_between(node_1_idx, node_2_idx);
-                                        }
-                                        Err(error) => self.rm_neighbor_error = error,
-                                    }
-                                }
-
-                                // Display the error label
-                                if !self.rm_neighbor_error.is_empty() {
-                                    ui.label(
-                                        RichText::new(&self.rm_neighbor_error)
-                                            .color(egui::Color32::RED),
-                                    );
-                                }
-                            });
-                        }
-                        // ui.add(Separator::default().vertical());
-                    }); // End of left column
-
-                    // Right column should contain the event logger
-                    ScrollArea::vertical().stick_to_bottom(true).show_rows(
-                        right,
-                        row_height,
-                        self.events.len(),
-                        |ui, row_range| {
-                            let events = self.events.get();
-                            for row in row_range {
-                                ui.label(events[row].clone());
-                            }
-                        },
-                    );
-                });
-            });
-        CentralPanel::default().show(ctx, |ui| {
-            let graph_widget: &mut GraphView<
-                '_,
-                WidgetType,
-                (),
-                petgraph::Undirected,
-                u32,
-                egui_graphs::DefaultNodeShape,
-                egui_graphs::DefaultEdgeShape,
-                LayoutStateRandom,
-                LayoutRandom,
-            > = &mut GraphView::new(&mut self.graph)
-                .with_interactions(
-                    &SettingsInteraction::new()
-                        .with_node_selection_enabled(true)
-                        .with_dragging_enabled(true)
-                        .with_edge_selection_enabled(true),
-                )
-                .with_styles(&SettingsStyle::new().with_labels_always(true))
-                .with_navigations(&SettingsNavigation::new().with_zoom_and_pan_enabled(true));
-            ui.add(graph_widget);
-        });
+impl Drop for SimulationController {
+    fn drop(&mut self) {
+        // The controllers driven without a GUI never see `on_exit`
+        self.close_log_file();
     }
 }
 
@@ -1392,7 +1573,19 @@ impl eframe::App for SimulationController {
      */
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.handle_event();
+        self.probe_health();
+        self.poll_stats_export();
+        self.step_stress_test(ctx);
+        self.expire_requests();
+        self.prune_log();
+        self.reap_drone_threads();
+        self.receive_dropped_files(ctx);
+        self.handle_shortcuts(ctx);
         self.read_data();
         self.render(ctx);
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.shutdown();
+    }
 }
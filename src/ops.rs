@@ -0,0 +1,816 @@
+//! Operations changing the topology, with their undo and redo
+
+use std::collections::HashMap;
+
+use crossbeam_channel::{Receiver, Sender};
+use petgraph::stable_graph::NodeIndex;
+use web_time::Instant;
+use wg_2024::{
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+use crate::{
+    format_pairs, platform,
+    topology::{Change, TopologyOp, TopologyOperation},
+    utils::{LogLevel, NodeKind},
+    validation::{ConnectionRejection, ControllerError, ValidationError},
+    widgets::{NodeWidget, WidgetType},
+    ClientKind, SimulationController, UpdateType, DRONE_FACTORY,
+};
+
+impl SimulationController {
+    /// Helper function to record an operation applied to the topology, so that it
+    /// can be undone
+    ///
+    /// A new operation drops the operations undone so far.
+    fn record_undoable(&mut self, op: TopologyOperation) {
+        self.undo_stack.push(op);
+        self.redo_stack.clear();
+    }
+
+    /// Function to undo the last operation on the topology: add edge, remove edge
+    /// or drone crash
+    ///
+    /// A crashed drone is spawned again with the same id, PDR and implementation
+    /// (a random one if unknown), connected to its neighbors still in the network.
+    /// The inverse operation goes through the usual checks: if it is rejected,
+    /// the error is logged and the operation is dropped from the history.
+    pub fn undo(&mut self) {
+        let Some(op) = self.undo_stack.pop() else {
+            return;
+        };
+        let (result, _) = self.replay(|controller| match &op {
+            TopologyOperation::AddEdge(a, b) => controller.apply_disconnect(*a, *b),
+            TopologyOperation::RemoveEdge(a, b) => controller.apply_connect(*a, *b),
+            TopologyOperation::CrashDrone {
+                drone_id,
+                pdr,
+                implementation,
+                neighbors,
+            } => {
+                let neighbors = neighbors
+                    .iter()
+                    .copied()
+                    .filter(|id| controller.get_node_idx(*id).is_some())
+                    .collect::<Vec<NodeId>>();
+                controller.apply_spawn(*drone_id, *implementation, *pdr, &neighbors)
+            }
+        });
+        match result {
+            Ok(()) => {
+                self.log(LogLevel::Info, format!("[CONTROLLER] Undo: {op}"));
+                self.redo_stack.push(op);
+            }
+            Err(error) => self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Cannot undo {op}: {error}"),
+            ),
+        }
+    }
+
+    /// Function to apply again the last operation undone
+    ///
+    /// Like `undo`, a rejected operation is logged and dropped from the history.
+    pub fn redo(&mut self) {
+        let Some(op) = self.redo_stack.pop() else {
+            return;
+        };
+        let (result, recorded) = self.replay(|controller| match &op {
+            TopologyOperation::AddEdge(a, b) => controller.apply_connect(*a, *b),
+            TopologyOperation::RemoveEdge(a, b) => controller.apply_disconnect(*a, *b),
+            TopologyOperation::CrashDrone { drone_id, .. } => controller.apply_crash(*drone_id),
+        });
+        match result {
+            Ok(()) => {
+                self.log(LogLevel::Info, format!("[CONTROLLER] Redo: {op}"));
+                // As recorded again, e.g. a crash with the neighbors at this time
+                self.undo_stack.extend(recorded);
+            }
+            Err(error) => self.log(
+                LogLevel::Error,
+                format!("[CONTROLLER] Cannot redo {op}: {error}"),
+            ),
+        }
+    }
+
+    /// Helper function to apply an operation of `undo` or `redo`, keeping the
+    /// history unchanged
+    ///
+    /// Returns the operations recorded while applying it, taken out of the history.
+    fn replay(
+        &mut self,
+        apply: impl FnOnce(&mut Self) -> Result<(), ValidationError>,
+    ) -> (Result<(), ValidationError>, Vec<TopologyOperation>) {
+        let undo_len = self.undo_stack.len();
+        let redo_stack = std::mem::take(&mut self.redo_stack);
+        let result = apply(self);
+        let recorded = self.undo_stack.split_off(undo_len);
+        self.redo_stack = redo_stack;
+        (result, recorded)
+    }
+
+    /// Function to apply an operation on the topology, with the same checks as the GUI
+    ///
+    /// The buttons of the GUI go through this function too.
+    /// A rejected operation leaves the state of the controller unchanged.
+    ///
+    /// # Errors
+    /// If the operation violates a constraint of the network, or a command can't be sent
+    pub fn apply_op(&mut self, op: TopologyOp) -> Result<(), ValidationError> {
+        match op {
+            TopologyOp::Connect(node_1, node_2) => self.apply_connect(node_1, node_2),
+            TopologyOp::Disconnect(node_1, node_2) => self.apply_disconnect(node_1, node_2),
+            TopologyOp::Crash(drone_id) => self.apply_crash(drone_id),
+            TopologyOp::Spawn {
+                drone_id,
+                implementation,
+                pdr,
+                neighbors,
+            } => self.apply_spawn(drone_id, implementation, pdr, &neighbors),
+            TopologyOp::SetPdr { drone_id, pdr } => {
+                if !(0.0..=1.0).contains(&pdr) {
+                    return Err(ValidationError::Violations(vec![
+                        ControllerError::PdrOutOfRange(pdr),
+                    ]));
+                }
+                self.set_pdr(drone_id, pdr).map_err(ValidationError::Failed)
+            }
+            TopologyOp::SpawnNode {
+                node_id,
+                kind: NodeKind::Drone,
+                neighbors,
+            } => self.apply_spawn(node_id, None, 0.0, &neighbors),
+            TopologyOp::SpawnNode {
+                node_id,
+                kind,
+                neighbors,
+            } => self.apply_spawn_node(node_id, kind, &neighbors),
+        }
+    }
+
+    /// Helper function to validate and apply `TopologyOp::Connect`
+    fn apply_connect(&mut self, node_1: NodeId, node_2: NodeId) -> Result<(), ValidationError> {
+        let idx_1 = self.get_node_idx(node_1).ok_or(ValidationError::Rejected(
+            ConnectionRejection::UnknownId(node_1),
+        ))?;
+        let idx_2 = self.get_node_idx(node_2).ok_or(ValidationError::Rejected(
+            ConnectionRejection::UnknownId(node_2),
+        ))?;
+        if self.graph.edges_connecting(idx_1, idx_2).count() > 0 {
+            return Err(ValidationError::Rejected(
+                ConnectionRejection::AlreadyConnected(node_1, node_2),
+            ));
+        }
+        let (idx_1, idx_2) = self
+            .can_add_sender(idx_1, idx_2)
+            .map_err(ValidationError::Rejected)?;
+        self.connect_nodes(idx_1, idx_2)
+            .map_err(ValidationError::Failed)
+    }
+
+    /// Helper function to validate and apply `TopologyOp::Disconnect`
+    fn apply_disconnect(&mut self, node_1: NodeId, node_2: NodeId) -> Result<(), ValidationError> {
+        let edge = self
+            .get_node_idx(node_1)
+            .zip(self.get_node_idx(node_2))
+            .and_then(|(idx_1, idx_2)| self.graph.edges_connecting(idx_1, idx_2).next())
+            .map(|(edge, _)| edge)
+            .ok_or(ValidationError::Violations(vec![ControllerError::NoEdge(
+                node_1, node_2,
+            )]))?;
+        let (node_1, node_2) = self
+            .validate_edge_removal(edge)
+            .map_err(ValidationError::Violations)?;
+        self.disconnect_nodes(node_1, node_2)
+            .map_err(ValidationError::Failed)
+    }
+
+    /// Function to remove the edge between two nodes from the GUI, with the
+    /// checks of `TopologyOp::Disconnect`
+    ///
+    /// The edge is deselected once removed. Returns the reasons why the edge
+    /// can't be removed, one per violated constraint.
+    pub(crate) fn remove_edge(
+        &mut self,
+        node_1: NodeId,
+        node_2: NodeId,
+    ) -> Result<(), Vec<String>> {
+        match self.apply_op(TopologyOp::Disconnect(node_1, node_2)) {
+            Ok(()) => {
+                if self
+                    .selected_edge
+                    .is_some_and(|edge| self.graph.edge_endpoints(edge).is_none())
+                {
+                    self.selected_edge = None;
+                }
+                Ok(())
+            }
+            Err(ValidationError::Violations(errors)) => {
+                Err(errors.iter().map(ToString::to_string).collect())
+            }
+            Err(error) => Err(vec![error.to_string()]),
+        }
+    }
+
+    /// Helper function to validate and apply `TopologyOp::Crash`
+    fn apply_crash(&mut self, drone_id: NodeId) -> Result<(), ValidationError> {
+        let drone_idx = self
+            .get_node_idx(drone_id)
+            .filter(|idx| {
+                matches!(
+                    self.graph.node(*idx).unwrap().payload(),
+                    WidgetType::Drone(_)
+                )
+            })
+            .ok_or(ValidationError::Violations(vec![
+                ControllerError::DroneNotFound(drone_id),
+            ]))?;
+        self.can_drone_crash(drone_id)
+            .map_err(|error| ValidationError::Violations(vec![error]))?;
+        self.crash_drone(drone_idx);
+        Ok(())
+    }
+
+    /// Function used to update the neighborhood of a node
+    ///
+    /// The neighborhood of a node is the set of nodes that are connected to it.
+    /// This function handles the addition and removal of nodes from the neighborhood,
+    /// by using the `UpdateType` enum to distinguish between the two cases.
+    pub(crate) fn update_neighborhood(
+        &mut self,
+        update_type: &UpdateType,
+        source_id: u8,
+        source_idx: NodeIndex,
+        n_id: u8,
+    ) {
+        match update_type {
+            UpdateType::Add => match self.graph.node(source_idx).unwrap().payload() {
+                WidgetType::Drone(_) => {
+                    if let Some(pos) = self.drones.iter().position(|d| d.id == source_id) {
+                        self.drones[pos].connected_node_ids.push(n_id);
+                    }
+                }
+                WidgetType::Server(_) => {
+                    if let Some(pos) = self.servers.iter().position(|d| d.id == source_id) {
+                        self.servers[pos].connected_drone_ids.push(n_id);
+                    }
+                }
+                WidgetType::WebClient(_) => {
+                    self.add_client_neighbor(ClientKind::Web, source_id, n_id);
+                }
+                WidgetType::ChatClient(_) => {
+                    self.add_client_neighbor(ClientKind::Chat, source_id, n_id);
+                }
+            },
+            UpdateType::Remove => match self.graph.node(source_idx).unwrap().payload() {
+                WidgetType::Drone(_) => {
+                    if let Some(pos) = self.drones.iter().position(|d| d.id == source_id) {
+                        if let Some(to_remove) = self.drones[pos]
+                            .connected_node_ids
+                            .iter()
+                            .position(|id| *id == n_id)
+                        {
+                            self.drones[pos].connected_node_ids.remove(to_remove);
+                        }
+                    }
+                }
+                WidgetType::Server(_) => {
+                    if let Some(pos) = self.servers.iter().position(|s| s.id == source_id) {
+                        if let Some(to_remove) = self.servers[pos]
+                            .connected_drone_ids
+                            .iter()
+                            .position(|id| *id == n_id)
+                        {
+                            self.servers[pos].connected_drone_ids.remove(to_remove);
+                        }
+                    }
+                }
+                WidgetType::WebClient(_) => {
+                    self.remove_client_neighbor(ClientKind::Web, source_id, n_id);
+                }
+                WidgetType::ChatClient(_) => {
+                    self.remove_client_neighbor(ClientKind::Chat, source_id, n_id);
+                }
+            },
+        }
+    }
+
+    /// Function to connect the node `source_id` to the neighbor whose id is in
+    /// `input_neighbor_id`, keeping the outcome for the error label of the bottom panel
+    pub(crate) fn add_sender(&mut self, source_id: NodeId, input_neighbor_id: &str) {
+        let result = self
+            .validate_add_sender_input(input_neighbor_id)
+            .map_err(ValidationError::Rejected)
+            .and_then(|neighbor_idx| {
+                let neighbor_id = self
+                    .graph
+                    .node(neighbor_idx)
+                    .unwrap()
+                    .payload()
+                    .get_id_helper();
+                self.apply_op(TopologyOp::Connect(source_id, neighbor_id))
+            });
+        self.add_neighbor_error = match result {
+            Ok(()) => None,
+            Err(ValidationError::Rejected(rejection)) => Some(rejection),
+            Err(error) => Some(ConnectionRejection::CommandFailed(error.to_string())),
+        };
+    }
+
+    /// Function to connect two nodes, after the connection has been validated
+    ///
+    /// It sends the `AddSender` command to both nodes, updates their configuration
+    /// and adds the edge to the graph.
+    /// If a command can't be sent, the node is marked as unresponsive and the
+    /// connection is rolled back.
+    pub(crate) fn connect_nodes(
+        &mut self,
+        source_idx: NodeIndex,
+        neighbor_idx: NodeIndex,
+    ) -> Result<(), String> {
+        let (neighbor_id, neighbor_ch) = self.get_sender_channel(neighbor_idx);
+        let (source_id, source_ch) = self.get_sender_channel(source_idx);
+
+        let source_widget = self.graph.node_mut(source_idx).unwrap().payload_mut();
+        if let Err(error) = source_widget.add_neighbor_helper(neighbor_id, neighbor_ch) {
+            return Err(self.mark_unresponsive(source_id, &error));
+        }
+
+        let neighbor_widget = self.graph.node_mut(neighbor_idx).unwrap().payload_mut();
+        if let Err(error) = neighbor_widget.add_neighbor_helper(source_id, source_ch) {
+            // Roll back the first half of the connection
+            let source_widget = self.graph.node(source_idx).unwrap().payload();
+            let _ = source_widget.rm_neighbor_helper(neighbor_id);
+            return Err(self.mark_unresponsive(neighbor_id, &error));
+        }
+
+        self.update_neighborhood(&UpdateType::Add, source_id, source_idx, neighbor_id);
+        self.update_neighborhood(&UpdateType::Add, neighbor_id, neighbor_idx, source_id);
+        self.graph.add_edge(source_idx, neighbor_idx, ());
+        self.operations
+            .record(Change::EdgeAdded(source_id, neighbor_id));
+        self.record_undoable(TopologyOperation::AddEdge(source_id, neighbor_id));
+        self.topology_changed();
+        Ok(())
+    }
+
+    /// Function to disconnect two nodes, after the removal has been validated
+    ///
+    /// It sends the `RemoveSender` command to both nodes, updates their configuration
+    /// and removes the edge from the graph.
+    /// If a command can't be sent, the node is marked as unresponsive and the
+    /// edge is kept.
+    fn disconnect_nodes(&mut self, node_1: NodeId, node_2: NodeId) -> Result<(), String> {
+        let node_1_idx = self.get_node_idx(node_1).unwrap();
+        let node_2_idx = self.get_node_idx(node_2).unwrap();
+
+        // Send command to source to remove neighbor
+        let node_1_widget = self.graph.node(node_1_idx).unwrap().payload();
+        if let Err(error) = node_1_widget.rm_neighbor_helper(node_2) {
+            return Err(self.mark_unresponsive(node_1, &error));
+        }
+
+        // Send command to neighbor to remove source
+        let node_2_widget = self.graph.node(node_2_idx).unwrap().payload();
+        if let Err(error) = node_2_widget.rm_neighbor_helper(node_1) {
+            return Err(self.mark_unresponsive(node_2, &error));
+        }
+
+        // Update state of SCL
+        self.update_neighborhood(&UpdateType::Remove, node_1, node_1_idx, node_2);
+        self.update_neighborhood(&UpdateType::Remove, node_2, node_2_idx, node_1);
+        // Update graph visualization
+        self.graph.remove_edges_between(node_1_idx, node_2_idx);
+        self.operations.record(Change::EdgeRemoved(node_1, node_2));
+        self.record_undoable(TopologyOperation::RemoveEdge(node_1, node_2));
+        self.topology_changed();
+        Ok(())
+    }
+
+    /// Function to remove all the edges of a drone, after the isolation has been validated
+    ///
+    /// Each edge is removed like a single edge removal; if a removal fails,
+    /// the remaining edges are still removed and the errors are returned.
+    pub(crate) fn isolate_drone(
+        &mut self,
+        drone_id: NodeId,
+        neighbors: &[NodeId],
+    ) -> Result<(), Vec<String>> {
+        let errors = neighbors
+            .iter()
+            .filter_map(|neighbor_id| self.disconnect_nodes(drone_id, *neighbor_id).err())
+            .collect::<Vec<String>>();
+        if errors.is_empty() {
+            self.log(
+                LogLevel::Info,
+                format!("[CONTROLLER] Drone {drone_id} isolated"),
+            );
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Function to add the proposed edges, then crash the drone
+    ///
+    /// Each edge goes through the same validation as a manual connection, and
+    /// the crash is checked again once they are added.
+    /// If any step fails, the edges added so far are removed.
+    pub(crate) fn repair_and_crash(
+        &mut self,
+        drone_id: NodeId,
+        plan: &[(NodeId, NodeId)],
+    ) -> Result<(), String> {
+        let mut added: Vec<(NodeId, NodeId)> = Vec::new();
+        let mut failure = None;
+        for (a, b) in plan {
+            let result = match (self.get_node_idx(*a), self.get_node_idx(*b)) {
+                (Some(a_idx), Some(b_idx)) => self
+                    .can_add_sender(a_idx, b_idx)
+                    .map_err(|rejection| rejection.to_string())
+                    .and_then(|(source_idx, neighbor_idx)| {
+                        self.connect_nodes(source_idx, neighbor_idx)
+                    }),
+                _ => Err("node not found".to_string()),
+            };
+            if let Err(error) = result {
+                failure = Some(format!("Cannot add edge {a}-{b}: {error}"));
+                break;
+            }
+            added.push((*a, *b));
+        }
+        if failure.is_none() {
+            if let Err(error) = self.can_drone_crash(drone_id) {
+                failure = Some(format!("The crash is still blocked: {error}"));
+            }
+        }
+
+        if let Some(error) = failure {
+            for (a, b) in added.iter().rev() {
+                let _ = self.disconnect_nodes(*a, *b);
+            }
+            return Err(error);
+        }
+        let drone_idx = self.get_node_idx(drone_id).unwrap();
+        self.crash_drone(drone_idx);
+        Ok(())
+    }
+
+    /// Function to crash a drone
+    ///
+    /// When a drone crashes, it sends a crash command to the mimicked drone.
+    /// Then, it removes the drone from the graph and updates the neighbors of the drone.
+    fn crash_drone(&mut self, crashing_drone: NodeIndex) {
+        let mut failed_sends: Vec<(NodeId, String)> = Vec::new();
+        let drone = self.graph.node(crashing_drone).unwrap().payload();
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(crashing_drone)
+            .collect::<Vec<NodeIndex>>();
+        let neighbor_ids = neighbors
+            .iter()
+            .map(|idx| self.graph.node(*idx).unwrap().payload().get_id_helper())
+            .collect::<Vec<NodeId>>();
+        match drone {
+            WidgetType::Drone(drone_widget) => {
+                let crashing_drone_id = drone_widget.id();
+                if let Err(error) = drone_widget.send_crash_command() {
+                    failed_sends.push((crashing_drone_id, error));
+                }
+                for neighbor in neighbors {
+                    let neighbor_widget = self.graph.node(neighbor).unwrap().payload();
+                    let id = neighbor_widget.get_id_helper();
+                    let connections = match neighbor_widget {
+                        WidgetType::Drone(_) => self
+                            .drones
+                            .iter_mut()
+                            .find(|d| d.id == id)
+                            .map(|d| &mut d.connected_node_ids),
+                        WidgetType::WebClient(_) => self
+                            .web_clients
+                            .iter_mut()
+                            .find(|c| c.id == id)
+                            .map(|c| &mut c.connected_drone_ids),
+                        WidgetType::ChatClient(_) => self
+                            .chat_clients
+                            .iter_mut()
+                            .find(|c| c.id == id)
+                            .map(|c| &mut c.connected_drone_ids),
+                        WidgetType::Server(_) => self
+                            .servers
+                            .iter_mut()
+                            .find(|s| s.id == id)
+                            .map(|s| &mut s.connected_drone_ids),
+                    };
+                    if let Some(connections) = connections {
+                        if let Some(to_remove) =
+                            connections.iter().position(|id| *id == crashing_drone_id)
+                        {
+                            connections.remove(to_remove);
+                        }
+                    }
+                    if let Err(error) = neighbor_widget.rm_neighbor_helper(crashing_drone_id) {
+                        failed_sends.push((id, error));
+                    }
+                }
+            }
+            _ => {
+                unreachable!("Only drones can crash")
+            }
+        }
+        let crashed_id = drone.get_id_helper();
+        self.notes.remove(&crashed_id);
+        self.aliases.remove(&crashed_id);
+        self.started_at.remove(&crashed_id);
+        self.pdr_history.forget(crashed_id);
+        self.intake.forget(crashed_id);
+        self.clusters.forget(crashed_id);
+        // The drone may have left events in its channel, handled in the next frame
+        if let Some((_, event_receiver, _, _)) = self.drones_channels.remove(&crashed_id) {
+            self.crashed_receivers.push((crashed_id, event_receiver));
+        }
+        self.retire_drone_thread(crashed_id);
+        let crashed_pdr = self.drone_pdr(crashed_id).unwrap_or_default();
+        self.drones.retain(|d| d.id != crashed_id);
+        self.retired_ids.insert(crashed_id);
+        for (id, error) in failed_sends {
+            self.mark_unresponsive(id, &error);
+        }
+        self.unresponsive_nodes.remove(&crashed_id);
+        self.disconnected_nodes.remove(&crashed_id);
+        self.muted_nodes.remove(&crashed_id);
+        if self.log_node_filter == Some(crashed_id) {
+            self.log_node_filter = None;
+        }
+        self.log_hover.retain(|id| *id != crashed_id);
+        if self.reconnect_target == Some(crashed_id) {
+            self.reconnect_target = None;
+        }
+        self.operations.record(Change::DroneCrashed(crashed_id));
+        let WidgetType::Drone(drone_widget) = self.graph.node(crashing_drone).unwrap().payload()
+        else {
+            unreachable!("Only drones can crash")
+        };
+        let undo_op = TopologyOperation::CrashDrone {
+            drone_id: crashed_id,
+            pdr: crashed_pdr,
+            implementation: drone_widget
+                .implementation()
+                .and_then(|name| DRONE_FACTORY.iter().position(|(n, _)| *n == name)),
+            neighbors: neighbor_ids,
+        };
+        self.record_undoable(undo_op);
+        self.graph.remove_node(crashing_drone);
+        self.node_indices.remove(&crashed_id);
+        self.pinned_nodes.remove(&crashed_id);
+        self.selected_node = None;
+        self.selected_nodes.clear();
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] Drone {crashed_id} crashed"),
+        );
+        self.topology_changed();
+    }
+
+    /// Function to replace the implementation of a drone, keeping its id, PDR and neighbors
+    ///
+    /// The old drone goes through the crash flow without removing its node from
+    /// the graph, then the drone built by the chosen factory takes its place with
+    /// new channels and is connected again to the former neighbors.
+    /// A neighbor that can't be reconnected is disconnected from the drone and
+    /// reported, so that the configuration still matches the network.
+    pub(crate) fn swap_drone(
+        &mut self,
+        drone_idx: NodeIndex,
+        implementation: usize,
+    ) -> Result<(), String> {
+        let drone_id = self
+            .graph
+            .node(drone_idx)
+            .unwrap()
+            .payload()
+            .get_id_helper();
+        let Some(pdr) = self.drones.iter().find(|d| d.id == drone_id).map(|d| d.pdr) else {
+            return Err(format!("Drone {drone_id} not found"));
+        };
+        if !platform::CAN_SPAWN_DRONES {
+            return Err("Drones can't be spawned on this platform".to_string());
+        }
+        let Some((name, drone_factory)) = DRONE_FACTORY.get(implementation).copied() else {
+            return Err(format!("Unknown drone implementation {implementation}"));
+        };
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(drone_idx)
+            .collect::<Vec<NodeIndex>>();
+        let neighbor_channels = neighbors
+            .iter()
+            .map(|idx| self.get_sender_channel(*idx))
+            .collect::<HashMap<NodeId, Sender<Packet>>>();
+
+        // Crash flow of the old drone, its node stays in the graph
+        let mut failed_sends: Vec<(NodeId, String)> = Vec::new();
+        if let WidgetType::Drone(drone_widget) = self.graph.node(drone_idx).unwrap().payload() {
+            // The old drone may already be gone, the swap goes on anyway
+            let _ = drone_widget.send_crash_command();
+        }
+        for idx in &neighbors {
+            let neighbor_widget = self.graph.node(*idx).unwrap().payload();
+            if let Err(error) = neighbor_widget.rm_neighbor_helper(drone_id) {
+                failed_sends.push((neighbor_widget.get_id_helper(), error));
+            }
+        }
+
+        // Spawn the new implementation with the same id and PDR
+        let (sender_command, receiver_command): (Sender<DroneCommand>, Receiver<DroneCommand>) =
+            crossbeam_channel::unbounded();
+        let (send_event, receive_event): (Sender<DroneEvent>, Receiver<DroneEvent>) =
+            crossbeam_channel::unbounded();
+        let (packet_send, packet_recv): (Sender<Packet>, Receiver<Packet>) =
+            crossbeam_channel::unbounded();
+        let new_drone = drone_factory(
+            drone_id,
+            send_event,
+            receiver_command,
+            packet_recv.clone(),
+            neighbor_channels,
+            pdr,
+        );
+        self.drones_channels.insert(
+            drone_id,
+            (
+                sender_command.clone(),
+                receive_event,
+                packet_send.clone(),
+                packet_recv,
+            ),
+        );
+        if let WidgetType::Drone(drone_widget) =
+            self.graph.node_mut(drone_idx).unwrap().payload_mut()
+        {
+            drone_widget.set_command_channel(sender_command);
+            drone_widget.set_implementation(name);
+            drone_widget.set_pdr(pdr);
+        }
+        self.track_drone_thread(drone_id, platform::run_drone(new_drone));
+        self.started_at.insert(drone_id, Instant::now());
+        self.unresponsive_nodes.remove(&drone_id);
+
+        // Give the former neighbors the sender of the new drone
+        let mut lost_neighbors: Vec<(NodeIndex, NodeId)> = Vec::new();
+        for idx in neighbors {
+            let neighbor_widget = self.graph.node_mut(idx).unwrap().payload_mut();
+            let neighbor_id = neighbor_widget.get_id_helper();
+            if let Err(error) = neighbor_widget.add_neighbor_helper(drone_id, packet_send.clone()) {
+                failed_sends.push((neighbor_id, error));
+                lost_neighbors.push((idx, neighbor_id));
+            }
+        }
+
+        // Cut off the neighbors that could not be reconnected
+        for (idx, neighbor_id) in &lost_neighbors {
+            let drone_widget = self.graph.node(drone_idx).unwrap().payload();
+            let _ = drone_widget.rm_neighbor_helper(*neighbor_id);
+            self.update_neighborhood(&UpdateType::Remove, drone_id, drone_idx, *neighbor_id);
+            self.update_neighborhood(&UpdateType::Remove, *neighbor_id, *idx, drone_id);
+            self.graph.remove_edges_between(drone_idx, *idx);
+            self.operations
+                .record(Change::EdgeRemoved(drone_id, *neighbor_id));
+        }
+        for (id, error) in failed_sends {
+            self.mark_unresponsive(id, &error);
+        }
+        self.refresh_label(drone_idx);
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] Drone {drone_id} swapped to {name}"),
+        );
+
+        if lost_neighbors.is_empty() {
+            Ok(())
+        } else {
+            self.topology_changed();
+            let lost = lost_neighbors
+                .iter()
+                .map(|(_, id)| id.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(format!(
+                "Drone {drone_id} swapped to {name}, but could not be reconnected to {lost}"
+            ))
+        }
+    }
+
+    /// Function to change the PDR of a drone
+    ///
+    /// It sends the `DroneCommand::SetPacketDropRate` command to the drone,
+    /// then updates the configuration of the drone, so that the state of the
+    /// controller stays in sync, refreshes its label and logs the change.
+    fn set_pdr(&mut self, drone_id: NodeId, pdr: f32) -> Result<(), String> {
+        let Some(channels) = self.drones_channels.get(&drone_id) else {
+            return Err(format!("Drone {drone_id} not found"));
+        };
+        if channels
+            .0
+            .send(DroneCommand::SetPacketDropRate(pdr))
+            .is_err()
+        {
+            return Err(self.mark_unresponsive(drone_id, "channel closed"));
+        }
+
+        if let Some(drone) = self.drones.iter_mut().find(|d| d.id == drone_id) {
+            self.operations.record(Change::PdrChanged {
+                drone_id,
+                from: drone.pdr,
+                to: pdr,
+            });
+            drone.pdr = pdr;
+        }
+        self.pdr_history.record_pdr(drone_id, pdr);
+        if let Some(drone_idx) = self.get_node_idx(drone_id) {
+            if let WidgetType::Drone(drone_widget) =
+                self.graph.node_mut(drone_idx).unwrap().payload_mut()
+            {
+                drone_widget.set_pdr(pdr);
+            }
+            self.refresh_label(drone_idx);
+        }
+
+        let event_string = format!("[CONTROLLER] Set PDR of drone {drone_id} to {pdr}");
+        self.log(LogLevel::Info, event_string);
+
+        // The change is applied anyway, the user is only warned about its effect
+        if pdr >= self.severing_pdr {
+            let pairs = self.unreachable_pairs(&self.severing_drones());
+            if !pairs.is_empty() {
+                let warning = format!(
+                    "[CONTROLLER] Drones with PDR >= {} cut client-server pairs: {}",
+                    self.severing_pdr,
+                    format_pairs(&pairs)
+                );
+                self.log(LogLevel::Warn, &warning);
+                self.toast = Some((warning, Instant::now()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper function to get the ids of the selected drones
+    pub(crate) fn selected_drone_ids(&self) -> Vec<NodeId> {
+        self.selected_nodes
+            .iter()
+            .filter_map(|idx| match self.graph.node(*idx)?.payload() {
+                WidgetType::Drone(drone_widget) => Some(drone_widget.id()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Function to add the nodes with the given ids to the selection, as with
+    /// Shift+Click; the unknown ids are ignored
+    pub fn select_nodes(&mut self, ids: &[NodeId]) {
+        for idx in ids.iter().filter_map(|id| self.get_node_idx(*id)) {
+            self.graph.node_mut(idx).unwrap().set_selected(true);
+            if !self.selected_nodes.contains(&idx) {
+                self.selected_nodes.push(idx);
+            }
+        }
+    }
+
+    /// Function to set the PDR of the selected drones, the other selected nodes
+    /// are skipped
+    ///
+    /// Returns the summary shown in the panel; the drones that reject the PDR
+    /// are listed below it.
+    pub fn set_selection_pdr(&mut self, pdr: f32) -> &str {
+        let drone_ids = self.selected_drone_ids();
+        let skipped = self.selected_nodes.len() - drone_ids.len();
+        self.batch_errors =
+            self.apply_to_drones(&drone_ids, |drone_id| TopologyOp::SetPdr { drone_id, pdr });
+        self.batch_summary = format!(
+            "PDR set on {} drones, {skipped} non-drone nodes skipped",
+            drone_ids.len() - self.batch_errors.len()
+        );
+        &self.batch_summary
+    }
+
+    /// Helper function to apply the operation built by `op` to each drone, returns
+    /// the errors of the rejected ones
+    pub(crate) fn apply_to_drones(
+        &mut self,
+        drone_ids: &[NodeId],
+        op: impl Fn(NodeId) -> TopologyOp,
+    ) -> Vec<String> {
+        let mut errors = Vec::new();
+        for drone_id in drone_ids {
+            if let Err(error) = self.apply_op(op(*drone_id)) {
+                errors.push(format!("Drone {drone_id}: {error}"));
+            }
+        }
+        errors
+    }
+}
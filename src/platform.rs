@@ -0,0 +1,185 @@
+//! Platform-specific operations, so that the GUI also builds for `wasm32-unknown-unknown`
+//!
+//! On the web:
+//! - files are not written to disk, each saved file is offered as a download
+//! - downloaded pages are opened in a new tab, without their media
+//! - drones can't be spawned, since they need their own thread: spawning
+//!   requires the `native-spawn` feature on a native target
+//! - background tasks, e.g. the stats export, run on the UI thread
+//! - there is no save dialog, the file is downloaded with the default name
+
+use std::{
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+};
+
+use wg_2024::drone::Drone as DroneTrait;
+
+/// Flag to indicate that the controller can spawn drones in their own thread
+pub const CAN_SPAWN_DRONES: bool = cfg!(all(feature = "native-spawn", not(target_arch = "wasm32")));
+
+/// Run a drone in its own thread, returning the handle of the thread
+///
+/// Callers check `CAN_SPAWN_DRONES` first: without it, the drone is dropped.
+#[cfg(all(feature = "native-spawn", not(target_arch = "wasm32")))]
+pub fn run_drone(mut drone: Box<dyn DroneTrait>) -> Option<JoinHandle<()>> {
+    Some(std::thread::spawn(move || {
+        drone.run();
+    }))
+}
+
+/// Run a drone in its own thread, returning the handle of the thread
+///
+/// Callers check `CAN_SPAWN_DRONES` first: without it, the drone is dropped.
+#[cfg(not(all(feature = "native-spawn", not(target_arch = "wasm32"))))]
+pub fn run_drone(drone: Box<dyn DroneTrait>) -> Option<JoinHandle<()>> {
+    drop(drone);
+    None
+}
+
+/// Run a client or server in its own thread
+///
+/// The thread is detached: clients and servers have no command to stop them.
+/// Callers check `CAN_SPAWN_DRONES` first: without it, the node is dropped.
+#[cfg(all(feature = "native-spawn", not(target_arch = "wasm32")))]
+pub fn run_node(node: Box<dyn FnOnce() + Send>) {
+    std::thread::spawn(node);
+}
+
+/// Run a client or server in its own thread
+///
+/// The thread is detached: clients and servers have no command to stop them.
+/// Callers check `CAN_SPAWN_DRONES` first: without it, the node is dropped.
+#[cfg(not(all(feature = "native-spawn", not(target_arch = "wasm32"))))]
+pub fn run_node(node: Box<dyn FnOnce() + Send>) {
+    drop(node);
+}
+
+/// Run a task without blocking the UI, in a worker thread where available
+pub fn run_in_background(task: impl FnOnce() + Send + 'static) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(task);
+    #[cfg(target_arch = "wasm32")]
+    task();
+}
+
+/// Save `content` to `path`, creating the missing folders
+///
+/// # Errors
+/// If the file can't be written
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder)
+            .map_err(|e| format!("Cannot create {}: {e}", folder.display()))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Cannot write {}: {e}", path.display()))
+}
+
+/// Save `content` to `path`, offering it as a download named after the file
+///
+/// # Errors
+/// If the download can't be started
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("download");
+    let url = blob_url(content, "application/octet-stream")?;
+    let anchor = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document")?
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| "Cannot create the download link".to_string())?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)
+}
+
+/// Ask the user where to save a file, starting in `folder` with the name `file_name`
+///
+/// Returns `None` if the dialog was cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use]
+pub fn save_dialog(folder: &Path, file_name: &str) -> Option<PathBuf> {
+    // The folder may not exist yet, the dialog then opens in its default folder
+    rfd::FileDialog::new()
+        .set_directory(folder)
+        .set_file_name(file_name)
+        .save_file()
+}
+
+/// Ask the user where to save a file: on the web the file is downloaded as
+/// `file_name`, so no dialog is shown
+#[cfg(target_arch = "wasm32")]
+#[must_use]
+pub fn save_dialog(folder: &Path, file_name: &str) -> Option<PathBuf> {
+    Some(folder.join(file_name))
+}
+
+/// Ask the user for a file to open, starting in `folder` and showing the files
+/// with the given `extensions`
+///
+/// Returns `None` if the dialog was cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use]
+pub fn open_dialog(folder: &Path, extensions: &[&str]) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_directory(folder)
+        .add_filter("Topology", extensions)
+        .pick_file()
+}
+
+/// Ask the user for a file to open: the files of the web page can't be read,
+/// so this always returns `None`
+#[cfg(target_arch = "wasm32")]
+#[must_use]
+pub fn open_dialog(_folder: &Path, _extensions: &[&str]) -> Option<PathBuf> {
+    None
+}
+
+/// Open the page saved at `path` in the default browser
+///
+/// # Errors
+/// If the browser can't be opened
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_page(path: &Path, _content: &[u8]) -> Result<(), String> {
+    let path = path.to_str().ok_or("Invalid path")?;
+    webbrowser::open(path).map_err(|e| format!("Cannot open {path}: {e}"))
+}
+
+/// Open the page with the given `content` in a new tab
+///
+/// # Errors
+/// If the tab can't be opened
+#[cfg(target_arch = "wasm32")]
+pub fn open_page(_path: &Path, content: &[u8]) -> Result<(), String> {
+    let url = blob_url(content, "text/html")?;
+    web_sys::window()
+        .ok_or("No window")?
+        .open_with_url_and_target(&url, "_blank")
+        .map_err(js_error)?;
+    Ok(())
+}
+
+/// Helper function to wrap `content` in a blob, returning its URL
+#[cfg(target_arch = "wasm32")]
+fn blob_url(content: &[u8], mime: &str) -> Result<String, String> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(content));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(js_error)?;
+    web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(error: wasm_bindgen::JsValue) -> String {
+    format!("{error:?}")
+}
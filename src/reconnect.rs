@@ -0,0 +1,48 @@
+use wg_2024::network::NodeId;
+
+/// Criterion used to rank the drones suggested to an under-connected node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ranking {
+    /// Closest drones in the layout first
+    Nearest,
+    /// Drones that sent the fewest packets first
+    LeastLoaded,
+}
+
+impl Ranking {
+    /// Name of the ranking, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Ranking::Nearest => "Nearest",
+            Ranking::LeastLoaded => "Least loaded",
+        }
+    }
+}
+
+/// A drone that can be connected to an under-connected node
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub drone_id: NodeId,
+    /// Distance from the node in the layout
+    pub distance: f32,
+    /// Number of packets sent by the drone so far
+    pub load: u64,
+    /// Number of endpoints of the other kind (servers for a client, clients
+    /// for a server) the node would reach through the drone
+    pub reached: usize,
+}
+
+/// Sort the candidates by `ranking`, breaking ties by id
+pub fn rank(candidates: &mut [Candidate], ranking: Ranking) {
+    match ranking {
+        Ranking::Nearest => candidates.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then(a.drone_id.cmp(&b.drone_id))
+        }),
+        Ranking::LeastLoaded => {
+            candidates.sort_by_key(|candidate| (candidate.load, candidate.drone_id));
+        }
+    }
+}
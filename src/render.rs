@@ -0,0 +1,2073 @@
+//! Panels and windows of the GUI
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use egui::{
+    Button, CentralPanel, Color32, Layout, RichText, ScrollArea, SidePanel, TextStyle,
+    TopBottomPanel,
+};
+use egui_graphs::{
+    GraphView, LayoutRandom, LayoutStateRandom, SettingsInteraction, SettingsNavigation,
+    SettingsStyle,
+};
+use petgraph::stable_graph::NodeIndex;
+use web_time::Instant;
+use wg_2024::network::NodeId;
+
+use crate::{
+    forge::{ForgeKind, ForgeNack},
+    format_pairs, history,
+    layout::LayoutKind,
+    platform,
+    reconnect::Ranking,
+    stress::StressTest,
+    topology::TopologyOp,
+    utils::{
+        format_age, format_count, format_hms, EventKind, LogCategory, LogEvent, LogLevel, NodeKind,
+        OverflowPolicy,
+    },
+    validation::{ConnectionRejection, ValidationError},
+    widgets::{drone_widget::DroneWidget, NodeWidget, WidgetType},
+    SimulationController, DRONE_FACTORY, LOG_MAX_GROWTH, LOG_WRAPPED_ROWS,
+};
+
+impl SimulationController {
+    /// Function to render the window with the changes to the topology since startup
+    fn render_changes(&mut self, ctx: &egui::Context) {
+        if !self.show_changes {
+            return;
+        }
+        let mut export = false;
+        let changes = self.topology_changes();
+        egui::Window::new("Changes")
+            .open(&mut self.show_changes)
+            .show(ctx, |ui| {
+                if changes.is_empty() {
+                    ui.label("No changes since startup");
+                }
+                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (change, secs) in &changes {
+                        match secs {
+                            Some(secs) => ui.label(format!("[+{secs:.1}s] {change}")),
+                            None => ui.label(change.to_string()),
+                        };
+                    }
+                });
+                ui.separator();
+                if ui.button("Export diff").clicked() {
+                    export = true;
+                }
+            });
+
+        if export {
+            match self.export_topology_changes() {
+                Ok(path) => self.log(
+                    LogLevel::Info,
+                    format!("[CONTROLLER] Topology diff exported to {}", path.display()),
+                ),
+                Err(error) => self.log(
+                    LogLevel::Error,
+                    format!("[CONTROLLER] Topology diff export failed: {error}"),
+                ),
+            }
+        }
+    }
+
+    /// Function to render the reconnect wizard
+    ///
+    /// It lists the under-connected nodes and, for the one being fixed, the
+    /// drones it can be connected to, with the number of endpoints it would
+    /// reach through each of them.
+    /// The chosen connection goes through the same validation as the manual one.
+    fn render_reconnect(&mut self, ctx: &egui::Context) {
+        if !self.show_reconnect {
+            return;
+        }
+        let under_connected = self.under_connected_nodes();
+        let candidates = self
+            .reconnect_target
+            .map(|target_id| self.reconnect_candidates(target_id, self.reconnect_ranking))
+            .unwrap_or_default();
+        let mut fix = None;
+        let mut connect = None;
+        let target = self.reconnect_target;
+        let ranking = &mut self.reconnect_ranking;
+        let error = &self.reconnect_error;
+        egui::Window::new("Reconnect")
+            .open(&mut self.show_reconnect)
+            .show(ctx, |ui| {
+                if under_connected.is_empty() {
+                    ui.label("Every client and server is connected enough");
+                }
+                for (node_id, problem) in &under_connected {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(problem).color(Color32::ORANGE));
+                        if ui.button("Fix…").clicked() {
+                            fix = Some(*node_id);
+                        }
+                    });
+                }
+
+                let Some(target_id) = target else {
+                    return;
+                };
+                ui.separator();
+                ui.label(format!("Drones to connect to node {target_id}"));
+                egui::ComboBox::from_label("Rank by")
+                    .selected_text(ranking.name())
+                    .show_ui(ui, |ui| {
+                        for option in [Ranking::Nearest, Ranking::LeastLoaded] {
+                            ui.selectable_value(ranking, option, option.name());
+                        }
+                    });
+                if candidates.is_empty() {
+                    ui.label(
+                        RichText::new("No drone can be connected without violating a constraint")
+                            .color(Color32::RED),
+                    );
+                }
+                for candidate in &candidates {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Drone {}: reaches {} endpoint(s), {} packets sent, {:.0} px away",
+                            candidate.drone_id,
+                            candidate.reached,
+                            candidate.load,
+                            candidate.distance
+                        ));
+                        if ui.button("Connect").clicked() {
+                            connect = Some((target_id, candidate.drone_id));
+                        }
+                    });
+                }
+                if !error.is_empty() {
+                    ui.label(RichText::new(error).color(Color32::RED));
+                }
+            });
+
+        if let Some(node_id) = fix {
+            self.reconnect_target = Some(node_id);
+            self.reconnect_error.clear();
+        }
+        if let Some((target_id, drone_id)) = connect {
+            let (Some(target_idx), Some(drone_idx)) =
+                (self.get_node_idx(target_id), self.get_node_idx(drone_id))
+            else {
+                return;
+            };
+            let result = self
+                .can_add_sender(target_idx, drone_idx)
+                .map_err(|rejection| rejection.to_string())
+                .and_then(|(source_idx, neighbor_idx)| {
+                    self.connect_nodes(source_idx, neighbor_idx)
+                });
+            match result {
+                Ok(()) => {
+                    self.reconnect_error.clear();
+                    // Keep fixing the node only while it is still under-connected
+                    if self
+                        .under_connected_nodes()
+                        .iter()
+                        .all(|(id, _)| *id != target_id)
+                    {
+                        self.reconnect_target = None;
+                    }
+                }
+                Err(error) => self.reconnect_error = error,
+            }
+        }
+    }
+
+    /// Function to select the nodes in the rectangle dragged on the graph background
+    /// with Shift held
+    ///
+    /// The nodes are added to the selection, like with Shift+Click. The node
+    /// positions are mapped to the screen with the zoom and pan of the graph view.
+    fn handle_selection_rect(&mut self, ui: &egui::Ui, response: &egui::Response, shift: bool) {
+        if response.drag_started() && shift && self.graph.hovered_node().is_none() {
+            self.selection_rect_start = ui.input(|i| i.pointer.press_origin());
+        }
+        let Some(start) = self.selection_rect_start else {
+            return;
+        };
+        let Some(end) = ui.input(|i| i.pointer.latest_pos()) else {
+            return;
+        };
+        let rect = egui::Rect::from_two_pos(start, end);
+
+        if response.drag_stopped() {
+            self.selection_rect_start = None;
+            let meta = egui_graphs::Metadata::load(ui);
+            let inside = self
+                .graph
+                .nodes_iter()
+                .filter(|(_, node)| rect.contains(meta.canvas_to_screen_pos(node.location())))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<NodeIndex>>();
+            for idx in inside {
+                self.graph.node_mut(idx).unwrap().set_selected(true);
+            }
+        } else if response.dragged() {
+            let color = ui.visuals().selection.bg_fill;
+            ui.painter().rect(
+                rect,
+                0.0,
+                color.gamma_multiply(0.25),
+                egui::Stroke::new(1.0, color),
+            );
+        } else {
+            self.selection_rect_start = None;
+        }
+    }
+
+    /// Function to render the actions on the nodes selected in the graph: PDR
+    /// change and crash of the selected drones
+    ///
+    /// Each drone goes through `apply_op`, so a rejected one is reported without
+    /// stopping the others.
+    fn render_batch_actions(&mut self, ui: &mut egui::Ui) {
+        let drone_ids = self.selected_drone_ids();
+        ui.label(format!("{} nodes selected", self.selected_nodes.len()));
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.batch_pdr_input).desired_width(40.0));
+            let set_pdr = ui
+                .add_enabled(!drone_ids.is_empty(), Button::new("Apply to Selection"))
+                .on_hover_text("Set the PDR of the selected drones, the other nodes are skipped");
+            if set_pdr.clicked() {
+                match DroneWidget::validate_parse_pdr(self.batch_pdr_input.trim()) {
+                    Ok(pdr) => {
+                        self.set_selection_pdr(pdr);
+                    }
+                    Err(error) => {
+                        self.batch_summary.clear();
+                        self.batch_errors = vec![error];
+                    }
+                }
+            }
+        });
+        let crash = ui.add_enabled(
+            !drone_ids.is_empty(),
+            Button::new(
+                RichText::new(format!("Crash {} drones", drone_ids.len())).color(Color32::BLACK),
+            )
+            .fill(Color32::RED),
+        );
+        if crash.clicked() {
+            self.batch_summary.clear();
+            self.batch_errors = self.apply_to_drones(&drone_ids, TopologyOp::Crash);
+        }
+        if !self.batch_summary.is_empty() {
+            ui.label(&self.batch_summary);
+        }
+        for error in &self.batch_errors {
+            ui.label(RichText::new(error).color(Color32::RED));
+        }
+    }
+
+    /// Function to render the neighbors of the node at `idx` in the side panel, each
+    /// with a button to remove the edge
+    ///
+    /// The errors of a removal are shown under the neighbor it failed for.
+    fn render_neighbors(&mut self, ui: &mut egui::Ui, idx: NodeIndex) {
+        let node_id = self.graph.node(idx).unwrap().payload().get_id_helper();
+        let mut neighbors = self
+            .graph
+            .g
+            .neighbors(idx)
+            .map(|neighbor| {
+                let widget = self.graph.node(neighbor).unwrap().payload();
+                (widget.get_id_helper(), widget.get_label_helper())
+            })
+            .collect::<Vec<(NodeId, String)>>();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+
+        ui.separator();
+        ui.label(format!("Neighbors ({})", neighbors.len()));
+        for (neighbor_id, label) in neighbors {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                if ui.small_button("Disconnect").clicked() {
+                    match self.remove_edge(node_id, neighbor_id) {
+                        Ok(()) => {
+                            self.neighbor_errors.remove(&neighbor_id);
+                        }
+                        Err(errors) => {
+                            self.neighbor_errors.insert(neighbor_id, errors);
+                        }
+                    }
+                }
+            });
+            for error in self.neighbor_errors.get(&neighbor_id).into_iter().flatten() {
+                ui.label(RichText::new(error).color(Color32::RED));
+            }
+        }
+    }
+
+    /// Function to render the settings of the side panel: the log file, the
+    /// topology save and load and the graph export
+    fn render_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("Log file");
+        ui.text_edit_singleline(&mut self.log_file_input)
+            .on_hover_text("The entries of the log are appended to the file");
+        ui.horizontal(|ui| {
+            if ui.button("Enable Logging").clicked() {
+                let path = PathBuf::from(self.log_file_input.trim());
+                match self.set_log_file(&path) {
+                    Ok(()) => {
+                        self.log_file_error.clear();
+                        self.log(
+                            LogLevel::Info,
+                            format!("[CONTROLLER] Logging to {}", path.display()),
+                        );
+                    }
+                    Err(error) => {
+                        self.log_file_error = format!("Cannot open {}: {error}", path.display());
+                    }
+                }
+            }
+            if ui
+                .add_enabled(self.log_file.is_some(), Button::new("Disable"))
+                .clicked()
+            {
+                self.close_log_file();
+            }
+        });
+        if !self.log_file_error.is_empty() {
+            ui.label(RichText::new(&self.log_file_error).color(Color32::RED));
+        }
+
+        ui.separator();
+        ui.label("Topology file");
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save Topology…")
+                .on_hover_text("Save the running network as a network initialization file")
+                .clicked()
+            {
+                if let Some(path) = platform::save_dialog(&self.output_dir, "topology.toml") {
+                    match self.save_topology(&path) {
+                        Ok(()) => self.log(
+                            LogLevel::Info,
+                            format!("[CONTROLLER] Topology saved to {}", path.display()),
+                        ),
+                        Err(error) => self.log(
+                            LogLevel::Error,
+                            format!("[CONTROLLER] Topology save failed: {error}"),
+                        ),
+                    }
+                }
+            }
+            if ui
+                .add_enabled(platform::CAN_SPAWN_DRONES, Button::new("Load Topology…"))
+                .on_hover_text("Replace the running network with the one of the file")
+                .clicked()
+            {
+                if let Some(path) = platform::open_dialog(&self.output_dir, &["toml"]) {
+                    match self.load_topology(&path) {
+                        Ok(()) => self.topology_error.clear(),
+                        Err(error) => {
+                            self.log(
+                                LogLevel::Error,
+                                format!("[CONTROLLER] Topology load failed: {error}"),
+                            );
+                            self.topology_error = error.to_string();
+                        }
+                    }
+                }
+            }
+        });
+        if !self.topology_error.is_empty() {
+            ui.label(RichText::new(&self.topology_error).color(Color32::RED));
+        }
+        if ui
+            .button("Export DOT…")
+            .on_hover_text("Save the graph in the DOT format, for Graphviz")
+            .clicked()
+        {
+            if let Some(path) = platform::save_dialog(&self.output_dir, "topology.dot") {
+                match self.export_dot(&path) {
+                    Ok(()) => self.log(
+                        LogLevel::Info,
+                        format!("[CONTROLLER] Graph exported to {}", path.display()),
+                    ),
+                    Err(error) => self.log(
+                        LogLevel::Error,
+                        format!("[CONTROLLER] Graph export failed: {error}"),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Function to render the form to spawn a node with its neighbors, with the
+    /// implementation and PDR of the drones
+    ///
+    /// The clients and servers can be picked only if their factory was given to
+    /// `SimulationControllerBuilder`.
+    fn render_spawn_form(&mut self, ui: &mut egui::Ui) {
+        ui.label("Spawn node");
+        let kinds = [
+            (NodeKind::Drone, "Drone", true),
+            (
+                NodeKind::WebClient,
+                "Web client",
+                self.web_client_factory.is_some(),
+            ),
+            (
+                NodeKind::ChatClient,
+                "Chat client",
+                self.chat_client_factory.is_some(),
+            ),
+            (NodeKind::Server, "Server", self.server_factory.is_some()),
+        ];
+        let kind_name = |kind: NodeKind| {
+            kinds
+                .iter()
+                .find(|(k, _, _)| *k == kind)
+                .map_or("Drone", |(_, name, _)| *name)
+        };
+        egui::ComboBox::from_id_salt("Spawn_kind")
+            .selected_text(kind_name(self.spawn_kind))
+            .show_ui(ui, |ui| {
+                for (kind, name, available) in kinds {
+                    let label = ui
+                        .add_enabled(
+                            available,
+                            egui::SelectableLabel::new(self.spawn_kind == kind, name),
+                        )
+                        .on_disabled_hover_text("No factory was given for this kind of node");
+                    if label.clicked() {
+                        self.spawn_kind = kind;
+                    }
+                }
+            });
+        if self.spawn_kind == NodeKind::Drone {
+            ui.horizontal(|ui| {
+                ui.label("PDR");
+                ui.text_edit_singleline(&mut self.spawn_pdr_input);
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Neighbors");
+            ui.text_edit_singleline(&mut self.spawn_neighbors_input)
+                .on_hover_text("Comma-separated ids, e.g. 1, 4, 7");
+        });
+        let add_node = ui
+            .horizontal(|ui| {
+                let add_node = ui.button(format!("Add {}", kind_name(self.spawn_kind)));
+                if self.spawn_kind == NodeKind::Drone {
+                    egui::ComboBox::from_id_salt("Spawn_implementation")
+                        .selected_text(
+                            self.spawn_implementation
+                                .map_or("Random", |i| DRONE_FACTORY[i].0),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.spawn_implementation, None, "Random");
+                            for (i, (name, _)) in DRONE_FACTORY.iter().enumerate() {
+                                ui.selectable_value(&mut self.spawn_implementation, Some(i), *name);
+                            }
+                        });
+                }
+                add_node
+            })
+            .inner;
+        if add_node.clicked() {
+            match self.validate_spawn_form() {
+                Ok((pdr, neighbors)) => {
+                    self.spawn_error.clear();
+                    let kind = self.spawn_kind;
+                    let implementation = self.spawn_implementation;
+                    let spawned = self
+                        .allocate_node_id(false)
+                        .map_err(ValidationError::Failed)
+                        .and_then(|node_id| {
+                            self.apply_op(match kind {
+                                NodeKind::Drone => TopologyOp::Spawn {
+                                    drone_id: node_id,
+                                    implementation,
+                                    pdr,
+                                    neighbors,
+                                },
+                                _ => TopologyOp::SpawnNode {
+                                    node_id,
+                                    kind,
+                                    neighbors,
+                                },
+                            })
+                        });
+                    if let Err(error) = spawned {
+                        let message =
+                            format!("Cannot add a {}: {error}", kind_name(kind).to_lowercase());
+                        self.log(LogLevel::Error, &message);
+                        self.toast = Some((message, Instant::now()));
+                    }
+                }
+                Err(error) => self.spawn_error = error,
+            }
+        }
+        if !self.spawn_error.is_empty() {
+            ui.label(RichText::new(&self.spawn_error).color(Color32::RED));
+        }
+    }
+
+    /// Function to parse the PDR and the neighbor ids of the spawn form
+    ///
+    /// The PDR is only parsed for the drones. The rules on the connections
+    /// are checked later, by `apply_spawn` or `apply_spawn_node`.
+    fn validate_spawn_form(&self) -> Result<(f32, Vec<NodeId>), String> {
+        let pdr = if self.spawn_kind == NodeKind::Drone {
+            DroneWidget::validate_parse_pdr(self.spawn_pdr_input.trim())?
+        } else {
+            0.0
+        };
+        let mut neighbors = Vec::new();
+        for input in self
+            .spawn_neighbors_input
+            .split(',')
+            .map(str::trim)
+            .filter(|input| !input.is_empty())
+        {
+            let neighbor_idx = self
+                .validate_add_sender_input(input)
+                .map_err(|rejection| format!("Neighbor {input}: {rejection}"))?;
+            neighbors.push(
+                self.graph
+                    .node(neighbor_idx)
+                    .unwrap()
+                    .payload()
+                    .get_id_helper(),
+            );
+        }
+        Ok((pdr, neighbors))
+    }
+
+    /// Function to render the summary of the next queued import, applied on confirmation
+    fn render_import(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            egui::Area::new(egui::Id::new("Drop_hint"))
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label("Drop a TOML configuration to import it");
+                    });
+                });
+        }
+
+        let Some(plan) = self.pending_imports.front() else {
+            return;
+        };
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new(format!("Import {}", plan.file))
+            .id(egui::Id::new("Import"))
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if plan.is_empty() {
+                    ui.label("The file adds nothing to the network");
+                }
+                if !plan.drones.is_empty() {
+                    ui.label(format!("{} drones to spawn:", plan.drones.len()));
+                    for (drone_id, pdr) in &plan.drones {
+                        ui.label(format!("  Drone {drone_id}, PDR {pdr}"));
+                    }
+                }
+                if !plan.edges.is_empty() {
+                    let edges = plan
+                        .edges
+                        .iter()
+                        .map(|(a, b)| format!("{a}-{b}"))
+                        .collect::<Vec<String>>();
+                    ui.label(format!(
+                        "{} edges to add: {}",
+                        edges.len(),
+                        edges.join(", ")
+                    ));
+                }
+                for skipped in &plan.skipped {
+                    ui.label(RichText::new(format!("Skipped {skipped}")).color(Color32::ORANGE));
+                }
+                if self.pending_imports.len() > 1 {
+                    ui.label(
+                        RichText::new(format!(
+                            "{} more files queued",
+                            self.pending_imports.len() - 1
+                        ))
+                        .weak(),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    apply = ui
+                        .add_enabled(!plan.is_empty(), Button::new("Apply"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if apply {
+            if let Some(plan) = self.pending_imports.pop_front() {
+                self.apply_import(plan);
+            }
+        } else if cancel {
+            self.pending_imports.pop_front();
+        }
+    }
+
+    /// Function to render the packet forge, available only with the debug tools
+    #[allow(clippy::too_many_lines)]
+    fn render_forge(&mut self, ctx: &egui::Context) {
+        if !self.debug_tools || !self.show_forge {
+            return;
+        }
+        let mut inject = false;
+        let selected = self
+            .selected_node
+            .and_then(|idx| self.graph.node(idx))
+            .map(|node| node.payload().get_id_helper());
+        let form = &mut self.forge_form;
+        let error = &self.forge_error;
+        egui::Window::new("Packet forge")
+            .open(&mut self.show_forge)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Type")
+                    .selected_text(form.kind.name())
+                    .show_ui(ui, |ui| {
+                        for kind in ForgeKind::ALL {
+                            ui.selectable_value(&mut form.kind, kind, kind.name());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Session id");
+                    ui.add(egui::DragValue::new(&mut form.session_id));
+                });
+
+                match form.kind {
+                    ForgeKind::MsgFragment => {
+                        ui.horizontal(|ui| {
+                            ui.label("Fragment");
+                            ui.add(egui::DragValue::new(&mut form.fragment_index));
+                            ui.label("of");
+                            ui.add(
+                                egui::DragValue::new(&mut form.total_n_fragments)
+                                    .range(1..=u64::MAX),
+                            );
+                        });
+                        ui.label("Data");
+                        ui.text_edit_singleline(&mut form.data);
+                    }
+                    ForgeKind::Ack => {
+                        ui.horizontal(|ui| {
+                            ui.label("Fragment");
+                            ui.add(egui::DragValue::new(&mut form.fragment_index));
+                        });
+                    }
+                    ForgeKind::Nack => {
+                        ui.horizontal(|ui| {
+                            ui.label("Fragment");
+                            ui.add(egui::DragValue::new(&mut form.fragment_index));
+                        });
+                        egui::ComboBox::from_label("Nack type")
+                            .selected_text(form.nack.name())
+                            .show_ui(ui, |ui| {
+                                for nack in ForgeNack::ALL {
+                                    ui.selectable_value(&mut form.nack, nack, nack.name());
+                                }
+                            });
+                        if matches!(
+                            form.nack,
+                            ForgeNack::ErrorInRouting | ForgeNack::UnexpectedRecipient
+                        ) {
+                            ui.horizontal(|ui| {
+                                ui.label("Node");
+                                ui.add(egui::DragValue::new(&mut form.nack_node));
+                            });
+                        }
+                    }
+                    ForgeKind::FloodRequest | ForgeKind::FloodResponse => {
+                        ui.horizontal(|ui| {
+                            ui.label("Flood id");
+                            ui.add(egui::DragValue::new(&mut form.flood_id));
+                        });
+                        if form.kind == ForgeKind::FloodRequest {
+                            ui.horizontal(|ui| {
+                                ui.label("Initiator");
+                                ui.add(egui::DragValue::new(&mut form.initiator_id));
+                            });
+                        }
+                        ui.label("The path trace is taken from the route");
+                    }
+                }
+
+                ui.separator();
+                ui.label(format!("Route: {:?}", form.route));
+                ui.horizontal(|ui| {
+                    let add_label = selected.map_or("Select a node to add it".to_string(), |id| {
+                        format!("Add node {id}")
+                    });
+                    if ui
+                        .add_enabled(selected.is_some(), Button::new(add_label))
+                        .clicked()
+                    {
+                        form.route.extend(selected);
+                    }
+                    if ui.button("Undo").clicked() {
+                        form.route.pop();
+                    }
+                    if ui.button("Clear").clicked() {
+                        form.route.clear();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Hop index");
+                    ui.add(egui::DragValue::new(&mut form.hop_index));
+                });
+                ui.checkbox(&mut form.allow_invalid_route, "Allow non-adjacent hops");
+
+                let mut custom_target = form.target.is_some();
+                ui.checkbox(
+                    &mut custom_target,
+                    "Inject into another node than the current hop",
+                );
+                if custom_target {
+                    let mut target = form.target.unwrap_or_default();
+                    ui.add(egui::DragValue::new(&mut target));
+                    form.target = Some(target);
+                } else {
+                    form.target = None;
+                }
+                ui.label(
+                    form.target()
+                        .map_or("No target".to_string(), |id| format!("Target: node {id}")),
+                );
+
+                ui.separator();
+                if ui.button("Inject").clicked() {
+                    inject = true;
+                }
+                if !error.is_empty() {
+                    ui.label(RichText::new(error).color(Color32::RED));
+                }
+            });
+
+        if inject {
+            match self.inject_forged_packet() {
+                Ok(()) => self.forge_error.clear(),
+                Err(error) => self.forge_error = error,
+            }
+        }
+    }
+
+    /// Function to render the window to group drones and collapse the groups
+    fn render_clusters(&mut self, ctx: &egui::Context) {
+        if !self.show_clusters {
+            return;
+        }
+        let selected_drone = self
+            .selected_node
+            .and_then(|idx| self.graph.node(idx))
+            .filter(|node| matches!(node.payload(), WidgetType::Drone(_)))
+            .map(|node| node.payload().get_id_helper());
+        let groups = self
+            .clusters
+            .groups()
+            .map(|(name, members)| {
+                (
+                    name.clone(),
+                    members.clone(),
+                    self.clusters.is_collapsed(name),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut add_to = None;
+        let mut toggle = None;
+        let mut delete = None;
+        egui::Window::new("Groups")
+            .open(&mut self.show_clusters)
+            .show(ctx, |ui| {
+                ui.label("Groups only change how the graph is drawn");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.cluster_input);
+                    let label = selected_drone
+                        .map_or("Select a drone".to_string(), |id| format!("Add drone {id}"));
+                    let enabled = selected_drone.is_some() && !self.cluster_input.is_empty();
+                    if ui.add_enabled(enabled, Button::new(label)).clicked() {
+                        add_to = selected_drone.map(|id| (self.cluster_input.clone(), id));
+                    }
+                });
+                ui.separator();
+                if groups.is_empty() {
+                    ui.label("No groups");
+                }
+                for (name, members, collapsed) in &groups {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{name}: {members:?}"));
+                        let action = if *collapsed { "Expand" } else { "Collapse" };
+                        if ui.button(action).clicked() {
+                            toggle = Some((name.clone(), *collapsed));
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete = Some(name.clone());
+                        }
+                    });
+                }
+            });
+
+        if let Some((name, id)) = add_to {
+            // A collapsed group is expanded before it changes
+            if let Some(group) = self.clusters.group_of(id).map(str::to_string) {
+                self.expand_group(&group);
+            }
+            self.expand_group(&name);
+            self.clusters.add(&name, id);
+            self.refresh_labels();
+        }
+        match toggle {
+            Some((name, true)) => self.expand_group(&name),
+            Some((name, false)) => self.collapse_group(&name),
+            None => {}
+        }
+        if let Some(name) = delete {
+            self.delete_group(&name);
+        }
+    }
+
+    /// Function to render the PDR history of a drone, in a collapsible header
+    ///
+    /// The configured PDR is drawn as a step function, with a vertical line at
+    /// each change, over the drop rate observed in the events of the drone.
+    fn render_pdr_history(&self, ui: &mut egui::Ui, drone_id: NodeId) {
+        let Some(series) = self.pdr_history.series(drone_id) else {
+            return;
+        };
+        egui::CollapsingHeader::new("PDR history")
+            .id_salt(("pdr_history", drone_id))
+            .show(ui, |ui| {
+                egui_plot::Plot::new(("pdr_history_plot", drone_id))
+                    .height(120.0)
+                    .include_x(-history::HISTORY_RETENTION.as_secs_f64())
+                    .include_x(0.0)
+                    .include_y(0.0)
+                    .include_y(1.0)
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .x_axis_label("Seconds ago")
+                    .legend(egui_plot::Legend::default())
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(egui_plot::Line::new(series.pdr).name("Configured PDR"));
+                        plot_ui.line(
+                            egui_plot::Line::new(series.drop_rate).name("Observed drop rate"),
+                        );
+                        for change in series.changes {
+                            plot_ui.vline(egui_plot::VLine::new(change).color(Color32::GRAY));
+                        }
+                    });
+            });
+    }
+
+    /// Function to render the stats window
+    fn render_stats(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Stats")
+            .open(&mut self.show_stats)
+            .show(ctx, |ui| {
+                ui.label("Web client requests");
+                let (answered, lost) = self.stats.request_outcomes();
+                ui.label(format!("Answered: {answered}, lost: {lost}"));
+                match self.stats.round_trip_summary() {
+                    Some((min, avg, max)) => ui.label(format!(
+                        "Round-trip: min {} ms, avg {} ms, max {} ms",
+                        min.as_millis(),
+                        avg.as_millis(),
+                        max.as_millis()
+                    )),
+                    None => ui.label("Round-trip: -"),
+                };
+
+                let mut timeout_secs = self.request_timeout.as_secs();
+                ui.horizontal(|ui| {
+                    ui.label("Timeout");
+                    ui.add(
+                        egui::DragValue::new(&mut timeout_secs)
+                            .range(1..=300)
+                            .suffix(" s"),
+                    );
+                });
+                self.request_timeout = Duration::from_secs(timeout_secs);
+
+                ui.separator();
+                ui.label("Event intake");
+                ui.label(format!(
+                    "Lagging nodes: {}, lag episodes: {}",
+                    self.intake.lagging().len(),
+                    self.intake.lag_episodes()
+                ));
+            });
+    }
+
+    /// Function to render the stress test dialog
+    fn render_stress_test(&mut self, ctx: &egui::Context) {
+        let mut start = false;
+        let mut cancel = false;
+        let form = &mut self.stress_form;
+        let running = self
+            .stress_test
+            .as_ref()
+            .is_some_and(StressTest::is_running);
+        egui::Window::new("Stress test")
+            .open(&mut self.show_stress_test)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Web client")
+                    .selected_text(form.client_id.map_or("-".to_string(), |id| id.to_string()))
+                    .show_ui(ui, |ui| {
+                        for client in &self.web_clients {
+                            ui.selectable_value(
+                                &mut form.client_id,
+                                Some(client.id),
+                                client.id.to_string(),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Server")
+                    .selected_text(form.server_id.map_or("-".to_string(), |id| id.to_string()))
+                    .show_ui(ui, |ui| {
+                        for server in &self.servers {
+                            ui.selectable_value(
+                                &mut form.server_id,
+                                Some(server.id),
+                                server.id.to_string(),
+                            );
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut form.request_file, false, "AskListOfFiles");
+                    ui.radio_value(&mut form.request_file, true, "RequestFile");
+                });
+                if form.request_file {
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.text_edit_singleline(&mut form.file);
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Requests");
+                    ui.add(egui::DragValue::new(&mut form.count).range(1..=1000));
+                    ui.label("every");
+                    ui.add(
+                        egui::DragValue::new(&mut form.interval_ms)
+                            .range(0..=10_000)
+                            .suffix(" ms"),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!running, Button::new("Start")).clicked() {
+                        start = true;
+                    }
+                    if ui.add_enabled(running, Button::new("Cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+                if !self.stress_error.is_empty() {
+                    ui.label(RichText::new(&self.stress_error).color(Color32::RED));
+                }
+
+                if let Some(test) = &self.stress_test {
+                    ui.separator();
+                    ui.label(format!(
+                        "Web client {} -> server {}",
+                        test.client_id, test.server_id
+                    ));
+                    ui.label(format!("Sent: {}/{}", test.sent, test.count));
+                    ui.label(format!("Responses: {}", test.latencies.len()));
+                    if let Some((min, avg, max)) = test.latency_summary() {
+                        ui.label(format!(
+                            "Round-trip: min {} ms, avg {} ms, max {} ms",
+                            min.as_millis(),
+                            avg.as_millis(),
+                            max.as_millis()
+                        ));
+                    }
+                    if test.cancelled {
+                        ui.label(RichText::new("Cancelled").color(Color32::ORANGE));
+                    }
+                }
+            });
+
+        if start {
+            match self.stress_form.build() {
+                Ok(test) => {
+                    self.stress_error.clear();
+                    self.log(LogLevel::Info, format!(
+                        "[CONTROLLER] Stress test started: {} requests from web client {} to server {}",
+                        test.count, test.client_id, test.server_id
+                    ));
+                    self.stress_test = Some(test);
+                }
+                Err(error) => self.stress_error = error,
+            }
+        }
+        if cancel {
+            if let Some(test) = &mut self.stress_test {
+                test.cancel();
+            }
+        }
+    }
+
+    pub(crate) fn read_data(&mut self) {
+        let previous_selection = (self.selected_node, self.selected_edge);
+        self.selected_nodes = self.graph.selected_nodes().to_vec();
+        if !self.graph.selected_nodes().is_empty() {
+            let idx = self.graph.selected_nodes().first().unwrap();
+            self.selected_node = Some(*idx);
+            if self.log_filter_follows_selection {
+                self.log_node_filter =
+                    Some(self.graph.node(*idx).unwrap().payload().get_id_helper());
+            }
+        }
+
+        if !self.graph.selected_edges().is_empty() {
+            let edge_idx = self.graph.selected_edges().first().unwrap();
+            self.selected_edge = Some(*edge_idx);
+        }
+
+        // The errors refer to the previous selection
+        if (self.selected_node, self.selected_edge) != previous_selection {
+            self.clear_panel_errors();
+        }
+    }
+
+    /// Function to clear the errors shown in the side panel
+    fn clear_panel_errors(&mut self) {
+        self.add_neighbor_error = None;
+        self.rm_neighbor_error.clear();
+        self.neighbor_errors.clear();
+        self.batch_summary.clear();
+        self.batch_errors.clear();
+        self.drone_crash_error.clear();
+        self.isolate_errors.clear();
+        self.implementation_error.clear();
+    }
+
+    /// Function to render the status bar, showing the health of the nodes
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
+        TopBottomPanel::top("Status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Uptime: {}", format_hms(self.start_time.elapsed())));
+                ui.separator();
+                let nodes = self.graph.nodes_iter().count();
+                ui.label(format!("Nodes: {nodes}"));
+                if self.unresponsive_nodes.is_empty() {
+                    ui.label(RichText::new("All nodes responsive").color(Color32::GREEN));
+                } else {
+                    let mut unresponsive = self
+                        .unresponsive_nodes
+                        .iter()
+                        .copied()
+                        .collect::<Vec<NodeId>>();
+                    unresponsive.sort_unstable();
+                    ui.label(
+                        RichText::new(format!("Unresponsive: {unresponsive:?}"))
+                            .color(Color32::RED),
+                    );
+                }
+
+                ui.separator();
+                let mut probe_enabled = self.probe_interval.is_some();
+                let mut probe_secs = self.probe_interval.map_or(5, |i| i.as_secs());
+                ui.checkbox(&mut probe_enabled, "Health probe every");
+                ui.add(
+                    egui::DragValue::new(&mut probe_secs)
+                        .range(1..=60)
+                        .suffix(" s"),
+                );
+                self.probe_interval = probe_enabled.then_some(Duration::from_secs(probe_secs));
+
+                // Connectivity when the drones dropping (almost) everything are ignored
+                ui.separator();
+                let pairs = self.unreachable_pairs(&self.severing_drones());
+                if pairs.is_empty() {
+                    ui.label(RichText::new("Functionally connected").color(Color32::GREEN));
+                } else {
+                    ui.label(
+                        RichText::new(format!("{} client-server pairs cut", pairs.len()))
+                            .color(Color32::ORANGE),
+                    )
+                    .on_hover_text(format_pairs(&pairs));
+                }
+                ui.label("PDR threshold");
+                ui.add(
+                    egui::DragValue::new(&mut self.severing_pdr)
+                        .range(0.0..=1.0)
+                        .speed(0.01),
+                );
+            });
+        });
+        // Keep the uptime and the ages of the log entries ticking
+        ctx.request_repaint_after(Duration::from_secs(1));
+
+        if !self.intake.lagging().is_empty() {
+            TopBottomPanel::top("Lag_banner").show(ctx, |ui| {
+                for (node_id, queued) in self.intake.lagging() {
+                    ui.label(
+                        RichText::new(format!(
+                            "Event intake lagging for {} ({} queued)",
+                            self.node_name(*node_id),
+                            format_count(*queued)
+                        ))
+                        .color(Color32::YELLOW),
+                    );
+                }
+            });
+        }
+    }
+
+    /// Function to render the last warning as a toast, until it expires
+    fn render_toast(&mut self, ctx: &egui::Context) {
+        const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+        let Some((message, raised)) = &self.toast else {
+            return;
+        };
+        let elapsed = raised.elapsed();
+        if elapsed > TOAST_DURATION {
+            self.toast = None;
+            return;
+        }
+        egui::Area::new(egui::Id::new("Toast"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(RichText::new(message).color(Color32::ORANGE));
+                });
+            });
+        ctx.request_repaint_after(TOAST_DURATION - elapsed);
+    }
+
+    /// Function to render an entry of the event log, with its time and age if enabled
+    ///
+    /// The time and age are formatted when the row is rendered, so only the
+    /// visible rows pay for it.
+    ///
+    /// Returns true if the entry is under the pointer.
+    fn render_log_event(&self, ui: &mut egui::Ui, event: &LogEvent) -> bool {
+        let muted = event
+            .source
+            .is_some_and(|id| self.muted_nodes.contains(&id));
+        if self.show_event_offset || self.show_event_age || muted {
+            ui.horizontal(|ui| {
+                if self.show_event_offset {
+                    let offset = event.time.duration_since(self.start_time);
+                    ui.label(
+                        RichText::new(format!("[+{:.1}s]", offset.as_secs_f32()))
+                            .monospace()
+                            .weak(),
+                    );
+                }
+                if self.show_event_age {
+                    ui.label(RichText::new(format_age(event.time.elapsed())).weak());
+                }
+                if muted {
+                    ui.label(RichText::new("(muted)").weak());
+                }
+                self.render_log_text(ui, &event.rich_text());
+            })
+            .response
+            .hovered()
+        } else {
+            self.render_log_text(ui, &event.rich_text()).hovered()
+        }
+    }
+
+    /// Function to render the text of a log entry, truncated or wrapped on at most
+    /// `LOG_WRAPPED_ROWS` lines, so that every entry has a bounded height
+    ///
+    /// The full text of the elided entries is shown on hover.
+    fn render_log_text(&self, ui: &mut egui::Ui, text: &RichText) -> egui::Response {
+        if !self.wrap_log_lines {
+            return ui.add(egui::Label::new(text.clone()).truncate());
+        }
+        let mut job = egui::WidgetText::from(text.clone()).into_layout_job(
+            ui.style(),
+            egui::FontSelection::Default,
+            ui.text_valign(),
+        );
+        job.wrap = egui::text::TextWrapping {
+            max_rows: LOG_WRAPPED_ROWS,
+            ..egui::text::TextWrapping::wrap_at_width(ui.available_width())
+        };
+        let galley = ui.fonts(|fonts| fonts.layout_job(job));
+        ui.add(egui::Label::new(galley))
+    }
+
+    /// Function to highlight the nodes of the hovered log entry, clearing the
+    /// highlight when the hover ends
+    fn set_log_hover(&mut self, nodes: Vec<NodeId>) {
+        if nodes != self.log_hover {
+            self.log_hover = nodes;
+            self.refresh_labels();
+        }
+    }
+
+    /// Function to render the filters of the event log, by node and by category
+    ///
+    /// The filters only apply when the log is rendered, the queue keeps every event.
+    fn render_log_filters(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let selected = self
+                .log_node_filter
+                .map_or("Any node".to_string(), |id| self.node_name(id));
+            let mut nodes = self
+                .graph
+                .nodes_iter()
+                .map(|(_, node)| node.payload().get_id_helper())
+                .map(|id| (id, self.node_name(id)))
+                .collect::<Vec<(NodeId, String)>>();
+            nodes.sort_unstable();
+            egui::ComboBox::from_id_salt("Log_node_filter")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.log_node_filter, None, "Any node");
+                    for (id, name) in nodes {
+                        ui.selectable_value(&mut self.log_node_filter, Some(id), name);
+                    }
+                });
+            ui.checkbox(&mut self.log_filter_follows_selection, "Follow selection")
+                .on_hover_text("Filter the log by the node selected in the graph");
+            ui.separator();
+            for category in LogCategory::ALL {
+                let mut shown = !self.hidden_log_categories.contains(&category);
+                if ui.checkbox(&mut shown, category.name()).changed() {
+                    if shown {
+                        self.hidden_log_categories.remove(&category);
+                    } else {
+                        self.hidden_log_categories.insert(category);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Function to get the entries of the event log that pass the filters
+    fn visible_log_events(&self) -> Vec<&LogEvent> {
+        self.events
+            .get()
+            .into_iter()
+            .filter(|event| !self.show_only_shortcuts || event.kind == EventKind::Shortcut)
+            .filter(|event| !self.hidden_log_categories.contains(&event.category()))
+            .filter(|event| match self.log_node_filter {
+                Some(id) => event.nodes().contains(&id),
+                None => true,
+            })
+            .filter(|event| {
+                event.level > LogLevel::Info
+                    || !event
+                        .source
+                        .is_some_and(|id| self.muted_nodes.contains(&id))
+            })
+            .collect()
+    }
+
+    /// Function to render the event log grouped by session
+    ///
+    /// Events carrying a session id are nested under a collapsible header per
+    /// session, while session-less events stay in a flat tail.
+    /// Only the bodies of the open headers are rendered, so the log stays cheap
+    /// to draw even with many sessions.
+    /// Returns the nodes of the hovered entry, if any.
+    fn render_grouped_log(&self, ui: &mut egui::Ui) -> Vec<NodeId> {
+        let mut hovered = Vec::new();
+        let mut sessions: BTreeMap<u64, Vec<&LogEvent>> = BTreeMap::new();
+        let mut tail: Vec<&LogEvent> = Vec::new();
+        for event in self.visible_log_events() {
+            match event.session_id {
+                Some(session_id) => sessions.entry(session_id).or_default().push(event),
+                None => tail.push(event),
+            }
+        }
+
+        ScrollArea::vertical()
+            .id_salt("Grouped_log")
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (session_id, events) in &sessions {
+                    let first = events.first().map_or(0, |e| e.time.elapsed().as_secs());
+                    let last = events.last().map_or(0, |e| e.time.elapsed().as_secs());
+                    let title = format!(
+                        "Session {session_id}: {} events (first {first}s ago, last {last}s ago)",
+                        events.len()
+                    );
+                    egui::CollapsingHeader::new(title)
+                        .id_salt(session_id)
+                        .show(ui, |ui| {
+                            for event in events {
+                                if self.render_log_event(ui, event) {
+                                    hovered = event.nodes();
+                                }
+                            }
+                        });
+                }
+                for event in tail {
+                    if self.render_log_event(ui, event) {
+                        hovered = event.nodes();
+                    }
+                }
+            });
+        hovered
+    }
+
+    /// Function to render the traces window
+    ///
+    /// Each trace lists the nodes that forwarded a fragment, in arrival order,
+    /// and can be highlighted in the graph.
+    fn render_traces(&mut self, ctx: &egui::Context) {
+        let mut highlight = self.highlighted_trace;
+        egui::Window::new("Traces")
+            .open(&mut self.show_traces)
+            .show(ctx, |ui| {
+                let traces = self.tracer.traces();
+                if traces.is_empty() {
+                    ui.label("No fragments traced");
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for ((session_id, fragment_index), trace) in traces {
+                        let key = (session_id, fragment_index);
+                        let title = format!("Session {session_id}, fragment {fragment_index}");
+                        egui::CollapsingHeader::new(title).show(ui, |ui| {
+                            ui.label(format!("Source route: {:?}", trace.route));
+                            let start = trace.hops.first().map(|(_, time)| *time);
+                            for (i, (hop, time)) in trace.hops.iter().enumerate() {
+                                let elapsed =
+                                    start.map_or(0, |start| time.duration_since(start).as_millis());
+                                ui.label(format!("Hop {i}: node {hop} (+{elapsed} ms)"));
+                            }
+                            if !trace.matches_route() {
+                                ui.label(
+                                    RichText::new("The path differs from the source route")
+                                        .color(Color32::ORANGE),
+                                );
+                            }
+                            let mut highlighted = highlight == Some(key);
+                            if ui
+                                .checkbox(&mut highlighted, "Highlight in graph")
+                                .changed()
+                            {
+                                highlight = highlighted.then_some(key);
+                            }
+                        });
+                    }
+                });
+            });
+        if highlight != self.highlighted_trace {
+            self.highlighted_trace = highlight;
+            self.refresh_labels();
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    pub(crate) fn render(&mut self, ctx: &egui::Context) {
+        self.render_status_bar(ctx);
+        SidePanel::right("Panel").show(ctx, |ui| {
+            if let Some(idx) = self.selected_node {
+                let node_id = self.graph.node(idx).unwrap().payload().get_id_helper();
+                if self.unresponsive_nodes.contains(&node_id) {
+                    let label = self.graph.node(idx).unwrap().payload().get_label_helper();
+                    ui.label(RichText::new(format!("{label} is unresponsive")).color(Color32::RED));
+                    if ui.button("Retry").clicked() {
+                        self.unresponsive_nodes.remove(&node_id);
+                        self.refresh_label(idx);
+                    }
+                } else {
+                    // The widget is rendered in place, so that its state persists between frames
+                    match self.graph.node_mut(idx).unwrap().payload_mut() {
+                        WidgetType::Drone(drone_widget) => {
+                            let drone_id = drone_widget.id();
+                            ui.add(&mut *drone_widget);
+
+                            // Apply the PDR requested through the widget
+                            if let Some(pdr) = drone_widget.take_pdr_request() {
+                                if let Err(error) =
+                                    self.apply_op(TopologyOp::SetPdr { drone_id, pdr })
+                                {
+                                    self.log(LogLevel::Error, error.to_string());
+                                }
+                            }
+                            self.render_pdr_history(ui, drone_id);
+
+                            ui.separator();
+                            ui.label("Crash the drone");
+                            let red_btn = ui.add(
+                                Button::new(RichText::new("Crash").color(Color32::BLACK))
+                                    .fill(Color32::RED),
+                            );
+                            if red_btn.clicked() {
+                                // check if the drone can crash
+                                match self.apply_op(TopologyOp::Crash(drone_id)) {
+                                    Ok(()) => {
+                                        self.crash_repair = None;
+                                        self.drone_crash_error.clear();
+                                    }
+                                    Err(error) => {
+                                        self.drone_crash_error = error.to_string();
+                                        self.crash_repair = self
+                                            .plan_crash_repair(idx)
+                                            .filter(|plan| !plan.is_empty())
+                                            .map(|plan| (drone_id, plan));
+                                    }
+                                }
+                            }
+
+                            if !self.drone_crash_error.is_empty() {
+                                ui.label(
+                                    RichText::new(&self.drone_crash_error)
+                                        .color(egui::Color32::RED),
+                                );
+                            }
+
+                            // Offer to add the edges that would make the crash legal
+                            let repair = self.crash_repair.clone().filter(|(id, _)| {
+                                *id == drone_id && self.graph.node(idx).is_some()
+                            });
+                            if let Some((_, plan)) = repair {
+                                let repair_btn = ui
+                                    .button(format!("Add these {} edges and crash", plan.len()))
+                                    .on_hover_text(format_pairs(&plan));
+                                if repair_btn.clicked() {
+                                    self.crash_repair = None;
+                                    match self.repair_and_crash(drone_id, &plan) {
+                                        Ok(()) => self.drone_crash_error.clear(),
+                                        Err(error) => self.drone_crash_error = error,
+                                    }
+                                }
+                            }
+
+                            ui.separator();
+                            ui.label("Remove all the edges of the drone");
+                            if ui.button("Isolate").clicked() && self.graph.node(idx).is_some() {
+                                self.isolate_errors = match self.validate_isolation(idx) {
+                                    Ok(neighbors) => self
+                                        .isolate_drone(drone_id, &neighbors)
+                                        .err()
+                                        .unwrap_or_default(),
+                                    Err(blockers) => blockers,
+                                };
+                            }
+                            for error in &self.isolate_errors {
+                                ui.label(RichText::new(error).color(Color32::RED));
+                            }
+
+                            ui.separator();
+                            ui.label("Implementation");
+                            egui::ComboBox::from_id_salt("Drone_implementation")
+                                .selected_text(DRONE_FACTORY[self.selected_implementation].0)
+                                .show_ui(ui, |ui| {
+                                    for (i, (name, _)) in DRONE_FACTORY.iter().enumerate() {
+                                        ui.selectable_value(
+                                            &mut self.selected_implementation,
+                                            i,
+                                            *name,
+                                        );
+                                    }
+                                });
+                            let (swap, duplicate) = ui
+                                .horizontal(|ui| (ui.button("Swap"), ui.button("Duplicate")))
+                                .inner;
+                            if swap.clicked() && self.graph.node(idx).is_some() {
+                                match self.swap_drone(idx, self.selected_implementation) {
+                                    Ok(()) => self.implementation_error.clear(),
+                                    Err(error) => self.implementation_error = error,
+                                }
+                            }
+                            if duplicate.clicked() && self.graph.node(idx).is_some() {
+                                match self.duplicate_drone(idx, self.selected_implementation) {
+                                    Ok(warnings) => {
+                                        self.implementation_error.clear();
+                                        if !warnings.is_empty() {
+                                            self.toast =
+                                                Some((warnings.join("\n"), Instant::now()));
+                                        }
+                                    }
+                                    Err(error) => self.implementation_error = error,
+                                }
+                            }
+                            if !self.implementation_error.is_empty() {
+                                ui.label(
+                                    RichText::new(&self.implementation_error).color(Color32::RED),
+                                );
+                            }
+                        }
+                        widget => {
+                            widget.widget_mut().show(ui);
+                        }
+                    }
+
+                    // Report the commands the widget failed to send
+                    let send_error = self
+                        .graph
+                        .node_mut(idx)
+                        .and_then(|n| n.payload_mut().take_send_error_helper());
+                    if let Some(error) = send_error {
+                        self.mark_unresponsive(node_id, &error);
+                    }
+                }
+
+                // The node may have been removed (e.g. crashed) while rendering its widget
+                if self.graph.node(idx).is_some() {
+                    self.render_neighbors(ui, idx);
+                }
+                if self.graph.node(idx).is_some() {
+                    let node_id = self.graph.node(idx).unwrap().payload().get_id_helper();
+                    ui.separator();
+                    ui.label("Alias");
+                    let alias = self.aliases.entry(node_id).or_default();
+                    let alias_changed = ui.text_edit_singleline(alias).changed();
+                    if alias.is_empty() {
+                        self.aliases.remove(&node_id);
+                    }
+                    if alias_changed {
+                        self.refresh_label(idx);
+                    }
+                    let mut muted = self.muted_nodes.contains(&node_id);
+                    if ui.checkbox(&mut muted, "Mute in the log").changed() {
+                        if muted {
+                            self.muted_nodes.insert(node_id);
+                        } else {
+                            self.muted_nodes.remove(&node_id);
+                        }
+                    }
+                    ui.label("Notes");
+                    let note = self.notes.entry(node_id).or_default();
+                    let note_changed = ui.text_edit_multiline(note).changed();
+                    if note.is_empty() {
+                        self.notes.remove(&node_id);
+                    }
+                    if note_changed {
+                        self.refresh_label(idx);
+                    }
+                }
+            } else {
+                ui.label("No node selected");
+            }
+
+            ui.with_layout(Layout::bottom_up(egui::Align::Center), |ui| {
+                ui.add_space(10.0);
+                ui.vertical(|ui| self.render_spawn_form(ui));
+                if ui.button("Transfers").clicked() {
+                    self.show_transfers = !self.show_transfers;
+                }
+                if ui.button("Traces").clicked() {
+                    self.show_traces = !self.show_traces;
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Re-layout").clicked() {
+                        self.relayout();
+                    }
+                    egui::ComboBox::from_id_salt("Layout_kind")
+                        .selected_text(self.layout_kind.name())
+                        .show_ui(ui, |ui| {
+                            for kind in [LayoutKind::Random, LayoutKind::ForceDirected] {
+                                ui.selectable_value(&mut self.layout_kind, kind, kind.name());
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.auto_relayout, "Auto re-layout");
+                    ui.checkbox(
+                        &mut self.keep_pinned,
+                        format!("Keep dragged nodes ({})", self.pinned_nodes.len()),
+                    );
+                    if ui.button("Unpin all").clicked() {
+                        self.pinned_nodes.clear();
+                    }
+                });
+                if ui.button("Changes").clicked() {
+                    self.show_changes = !self.show_changes;
+                }
+                if ui.button("Reconnect").clicked() {
+                    self.show_reconnect = !self.show_reconnect;
+                }
+                if ui.button("Groups").clicked() {
+                    self.show_clusters = !self.show_clusters;
+                }
+                if self.debug_tools && ui.button("Packet forge").clicked() {
+                    self.show_forge = !self.show_forge;
+                }
+                if ui.checkbox(&mut self.debug_tools, "Debug tools").changed() {
+                    self.show_forge &= self.debug_tools;
+                }
+                if ui.button("Stats").clicked() {
+                    self.show_stats = !self.show_stats;
+                }
+                if ui.button("Stress test").clicked() {
+                    self.show_stress_test = !self.show_stress_test;
+                }
+                if ui.button("Export stats").clicked() {
+                    self.export_stats();
+                }
+                if ui
+                    .checkbox(&mut self.show_notes_in_labels, "Show notes in labels")
+                    .changed()
+                {
+                    self.refresh_labels();
+                }
+                ui.collapsing("Settings", |ui| self.render_settings(ui));
+            });
+        });
+        TopBottomPanel::bottom("Bottom_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                let text_style = TextStyle::Body;
+                let row_height = ui.text_style_height(&text_style);
+                ui.columns_const(|[left, right]| {
+                    // Left column should containt the add sender and remove edge buttons
+                    left.horizontal(|ui| {
+                        if let Some(idx) = self.selected_node {
+                            ui.vertical(|ui| {
+                                ui.label(format!(
+                                    "Selected node: {:?}",
+                                    self.graph.node(idx).unwrap().payload().get_id_helper()
+                                ));
+                                ui.set_max_width(71.0); // Width of the add button
+
+                                // Computed every frame, so it follows the selection and the topology
+                                let source_id =
+                                    self.graph.node(idx).unwrap().payload().get_id_helper();
+                                let candidates = self.neighbor_candidates(source_id);
+                                let selected_text = if self.add_neighbor_input.is_empty() {
+                                    "Neighbor".to_string()
+                                } else {
+                                    self.add_neighbor_input.clone()
+                                };
+                                egui::ComboBox::from_id_salt("Add_sender_neighbor")
+                                    .width(71.0)
+                                    .selected_text(selected_text)
+                                    .show_ui(ui, |ui| {
+                                        for (id, check) in &candidates {
+                                            match check {
+                                                Ok(()) => {
+                                                    ui.selectable_value(
+                                                        &mut self.add_neighbor_input,
+                                                        id.to_string(),
+                                                        id.to_string(),
+                                                    );
+                                                }
+                                                Err(rejection) => {
+                                                    ui.add_enabled(
+                                                        false,
+                                                        egui::SelectableLabel::new(
+                                                            false,
+                                                            id.to_string(),
+                                                        ),
+                                                    )
+                                                    .on_disabled_hover_text(rejection.to_string());
+                                                }
+                                            }
+                                        }
+                                    });
+                                // The id can still be typed, e.g. by automation
+                                ui.checkbox(&mut self.type_neighbor_id, "Type id")
+                                    .on_hover_text("Type the id of the neighbor instead of picking it");
+                                if self.type_neighbor_id {
+                                    ui.text_edit_singleline(&mut self.add_neighbor_input)
+                                        .on_hover_text(
+                                            "Any id can be typed, the invalid ones are rejected by Add sender",
+                                        );
+                                }
+                                let add_btn = ui.add(Button::new("Add sender"));
+                                if add_btn.clicked() {
+                                    self.add_sender(source_id, &self.add_neighbor_input.clone());
+                                }
+                                // Connect the first two nodes selected in the graph
+                                if let [first, second] = self.selected_nodes[..] {
+                                    let connect_btn = ui.button("Connect selected").on_hover_text(
+                                        "Connect the two nodes selected in the graph",
+                                    );
+                                    if connect_btn.clicked() {
+                                        let source_id = self
+                                            .graph
+                                            .node(first)
+                                            .unwrap()
+                                            .payload()
+                                            .get_id_helper();
+                                        let neighbor_id = self
+                                            .graph
+                                            .node(second)
+                                            .unwrap()
+                                            .payload()
+                                            .get_id_helper();
+                                        self.add_sender(source_id, &neighbor_id.to_string());
+                                    }
+                                }
+
+                                // Connecting nodes already connected is harmless, so it is
+                                // only a warning
+                                match &self.add_neighbor_error {
+                                    Some(rejection @ ConnectionRejection::AlreadyConnected(..)) => {
+                                        ui.label(
+                                            RichText::new(rejection.to_string())
+                                                .color(Color32::YELLOW),
+                                        );
+                                    }
+                                    Some(rejection) => {
+                                        ui.label(
+                                            RichText::new(rejection.to_string())
+                                                .color(egui::Color32::RED),
+                                        );
+                                    }
+                                    None => {}
+                                }
+                            });
+                        }
+
+                        ui.add_space(15.0);
+
+                        // Actions on every selected node
+                        if self.selected_nodes.len() > 1 {
+                            ui.vertical(|ui| self.render_batch_actions(ui));
+                            ui.add_space(15.0);
+                        }
+
+                        // Remove edge area
+                        // The edge is shown by its endpoints, e.g. `Drone 3 ↔ Server 12`
+                        let endpoints = self
+                            .selected_edge
+                            .and_then(|edge_idx| self.graph.edge_endpoints(edge_idx))
+                            .map(|(node_1, node_2)| {
+                                let widget_1 = self.graph.node(node_1).unwrap().payload();
+                                let widget_2 = self.graph.node(node_2).unwrap().payload();
+                                (
+                                    (widget_1.get_id_helper(), widget_1.get_label_helper()),
+                                    (widget_2.get_id_helper(), widget_2.get_label_helper()),
+                                )
+                            });
+                        if let Some(((id_1, label_1), (id_2, label_2))) = endpoints {
+                            ui.vertical(|ui| {
+                                let packets = self.stats.edge_count(id_1, id_2);
+                                ui.label(format!("Edge: {label_1} ↔ {label_2}"))
+                                    .on_hover_text(format!(
+                                        "{packets} packets sent between {id_1} and {id_2}"
+                                    ));
+                                ui.label(format!("Packets: {packets}"));
+                                let remove_btn = ui.add(Button::new("Remove edge"));
+                                if remove_btn.clicked() {
+                                    match self.remove_edge(id_1, id_2) {
+                                        Ok(()) => self.rm_neighbor_error.clear(),
+                                        Err(errors) => self.rm_neighbor_error = errors,
+                                    }
+                                }
+
+                                // Display a label for each error
+                                for error in &self.rm_neighbor_error {
+                                    ui.label(RichText::new(error).color(egui::Color32::RED));
+                                }
+                            });
+                        }
+                        // ui.add(Separator::default().vertical());
+                    }); // End of left column
+
+                    // Right column should contain the event logger
+                    let stats = *self.events.stats();
+                    right
+                        .horizontal(|ui| {
+                            ui.label(RichText::new("Stats").strong());
+                            ui.label(format!("Sent: {}", format_count(stats.packets_sent)));
+                            ui.label(format!("Dropped: {}", format_count(stats.packets_dropped)));
+                            ui.label(format!("Shortcuts: {}", format_count(stats.shortcuts)));
+                            ui.label(format!(
+                                "Unsupported requests: {}",
+                                format_count(stats.unsupported_requests)
+                            ));
+                        })
+                        .response
+                        .on_hover_text("Events logged since startup, including the discarded ones");
+                    right.horizontal(|ui| {
+                        let pause_label = match &self.paused_events {
+                            Some(buffer) => format!("Resume ({} new)", buffer.len()),
+                            None => "Pause".to_string(),
+                        };
+                        if ui.button(pause_label).clicked() {
+                            self.set_log_paused(self.paused_events.is_none());
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.events.clear();
+                            if let Some(buffer) = &mut self.paused_events {
+                                buffer.clear();
+                            }
+                        }
+                        if ui
+                            .button("Export CSV…")
+                            .on_hover_text("Save the entries of the log as CSV")
+                            .clicked()
+                        {
+                            if let Some(path) =
+                                platform::save_dialog(&self.output_dir, "events.csv")
+                            {
+                                let result = self.export_csv(&path);
+                                self.log_export_result(&path, result);
+                            }
+                        }
+                        if ui
+                            .button("Export JSON…")
+                            .on_hover_text("Save the entries of the log as JSON")
+                            .clicked()
+                        {
+                            if let Some(path) =
+                                platform::save_dialog(&self.output_dir, "events.json")
+                            {
+                                let result = self.export_json(&path);
+                                self.log_export_result(&path, result);
+                            }
+                        }
+                        ui.checkbox(&mut self.group_log_by_session, "Group by session");
+                        ui.checkbox(&mut self.show_event_offset, "Show event time")
+                            .on_hover_text("Time since startup");
+                        ui.checkbox(&mut self.show_event_age, "Show event age");
+                        ui.checkbox(&mut self.show_only_shortcuts, "Shortcuts only");
+                        ui.checkbox(&mut self.wrap_log_lines, "Wrap lines")
+                            .on_hover_text("Wrap long entries instead of truncating them");
+                        let mut policy = self.events.policy();
+                        egui::ComboBox::from_id_salt("Log_overflow_policy")
+                            .selected_text(policy.name())
+                            .show_ui(ui, |ui| {
+                                for option in [
+                                    OverflowPolicy::DropOldest,
+                                    OverflowPolicy::DropNewest,
+                                    OverflowPolicy::Grow {
+                                        max: LOG_MAX_GROWTH,
+                                    },
+                                    OverflowPolicy::KeepErrors,
+                                ] {
+                                    ui.selectable_value(&mut policy, option, option.name());
+                                }
+                            })
+                            .response
+                            .on_hover_text("What to discard when the log is full");
+                        if policy != self.events.policy() {
+                            self.events.set_policy(policy);
+                        }
+                        let mut min_level = self.events.min_level();
+                        egui::ComboBox::from_id_salt("Log_min_level")
+                            .selected_text(format!("Min level: {}", min_level.name()))
+                            .show_ui(ui, |ui| {
+                                for level in LogLevel::ALL {
+                                    ui.selectable_value(&mut min_level, level, level.name());
+                                }
+                            })
+                            .response
+                            .on_hover_text("Events below this level are not logged");
+                        self.events.set_min_level(min_level);
+                        ui.label("Capacity:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.log_capacity_input)
+                                .desired_width(60.0)
+                                .hint_text(self.events.capacity().to_string()),
+                        )
+                        .on_hover_text("Number of entries kept in the log");
+                        if ui.button("Apply").clicked() {
+                            match self.log_capacity_input.trim().parse::<usize>() {
+                                Ok(capacity) if (1..=LOG_MAX_GROWTH).contains(&capacity) => {
+                                    self.set_log_capacity(capacity);
+                                    self.log_capacity_input.clear();
+                                    self.log_capacity_error.clear();
+                                }
+                                _ => {
+                                    self.log_capacity_error =
+                                        format!("Enter a capacity from 1 to {LOG_MAX_GROWTH}");
+                                }
+                            }
+                        }
+                        if !self.log_capacity_error.is_empty() {
+                            ui.label(RichText::new(&self.log_capacity_error).color(Color32::RED));
+                        }
+                        ui.checkbox(&mut self.limit_log_age, "Max age");
+                        ui.add_enabled(
+                            self.limit_log_age,
+                            egui::DragValue::new(&mut self.log_max_age_minutes)
+                                .range(1..=24 * 60)
+                                .suffix(" min"),
+                        );
+                    });
+                    self.render_log_filters(right);
+                    // Marker of the trimmed history
+                    let discarded = self.events.discarded();
+                    if discarded > 0 {
+                        right.label(
+                            RichText::new(format!(
+                                "{} older events discarded",
+                                format_count(discarded)
+                            ))
+                            .weak()
+                            .italics(),
+                        );
+                    }
+                    // Muted nodes, click to unmute
+                    if !self.muted_nodes.is_empty() {
+                        let mut unmuted = None;
+                        right.horizontal_wrapped(|ui| {
+                            ui.label("Muted:");
+                            for id in &self.muted_nodes {
+                                if ui
+                                    .small_button(format!("{} x", self.node_name(*id)))
+                                    .on_hover_text("Unmute")
+                                    .clicked()
+                                {
+                                    unmuted = Some(*id);
+                                }
+                            }
+                        });
+                        if let Some(id) = unmuted {
+                            self.muted_nodes.remove(&id);
+                        }
+                    }
+                    let hovered = if self.group_log_by_session {
+                        self.render_grouped_log(right)
+                    } else {
+                        let mut hovered = Vec::new();
+                        let events = self.visible_log_events();
+                        // Every row gets the height of a wrapped entry, so that the
+                        // rows stay fixed and only the visible ones are laid out
+                        #[allow(clippy::cast_precision_loss)]
+                        let log_row_height = if self.wrap_log_lines {
+                            row_height * LOG_WRAPPED_ROWS as f32
+                        } else {
+                            row_height
+                        };
+                        ScrollArea::vertical().stick_to_bottom(true).show_rows(
+                            right,
+                            log_row_height,
+                            events.len(),
+                            |ui, row_range| {
+                                for row in row_range {
+                                    let row_hovered = ui
+                                        .allocate_ui(
+                                            egui::vec2(ui.available_width(), log_row_height),
+                                            |ui| {
+                                                ui.set_min_height(log_row_height);
+                                                self.render_log_event(ui, events[row])
+                                            },
+                                        )
+                                        .inner;
+                                    if row_hovered {
+                                        hovered = events[row].nodes();
+                                    }
+                                }
+                            },
+                        );
+                        hovered
+                    };
+                    self.set_log_hover(hovered);
+                });
+            });
+        egui::Window::new("Transfers")
+            .open(&mut self.show_transfers)
+            .show(ctx, |ui| {
+                let sessions = self.transfers.sessions();
+                if sessions.is_empty() {
+                    ui.label("No transfers observed");
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (session_id, status) in sessions {
+                        let line = format!(
+                            "Session {session_id}: {} fragments, {} acked, {} outstanding, {} dropped",
+                            status.total_fragments,
+                            status.acked.len(),
+                            status.outstanding(),
+                            status.dropped.len()
+                        );
+                        let color = if status.is_complete() {
+                            Color32::GREEN
+                        } else if status.nacked.is_empty() {
+                            Color32::GRAY
+                        } else {
+                            Color32::ORANGE
+                        };
+                        ui.label(RichText::new(line).color(color));
+                        for (reason, count) in &status.nack_reasons {
+                            ui.label(format!("    Nack {reason}: {count}"));
+                        }
+                    }
+                });
+            });
+        self.render_traces(ctx);
+        self.render_stress_test(ctx);
+        self.render_stats(ctx);
+        self.render_changes(ctx);
+        self.render_reconnect(ctx);
+        self.render_clusters(ctx);
+        self.render_forge(ctx);
+        self.render_import(ctx);
+        self.render_toast(ctx);
+        CentralPanel::default().show(ctx, |ui| {
+            // Shift adds to the selection, and drags a selection rectangle instead of panning
+            let shift = ui.input(|i| i.modifiers.shift);
+            let graph_widget: &mut GraphView<
+                '_,
+                WidgetType,
+                (),
+                petgraph::Undirected,
+                u32,
+                egui_graphs::DefaultNodeShape,
+                egui_graphs::DefaultEdgeShape,
+                LayoutStateRandom,
+                LayoutRandom,
+            > = &mut GraphView::new(&mut self.graph)
+                .with_interactions(
+                    &SettingsInteraction::new()
+                        .with_node_selection_enabled(true)
+                        .with_node_selection_multi_enabled(shift)
+                        .with_dragging_enabled(true)
+                        .with_edge_selection_enabled(true),
+                )
+                .with_styles(&SettingsStyle::new().with_labels_always(true))
+                .with_navigations(&SettingsNavigation::new().with_zoom_and_pan_enabled(!shift));
+            let graph_response = ui.add(graph_widget);
+            self.handle_selection_rect(ui, &graph_response, shift);
+
+            // Pin the nodes dragged by the user, so that the layout keeps them in place
+            for (_, node) in self.graph.nodes_iter() {
+                if node.dragged() {
+                    self.pinned_nodes.insert(node.payload().get_id_helper());
+                }
+            }
+
+            // Keep the members of the collapsed groups on top of each other,
+            // double-clicking a collapsed group expands it
+            self.stack_collapsed_groups();
+            if ui.input(|i| {
+                i.pointer
+                    .button_double_clicked(egui::PointerButton::Primary)
+            }) {
+                let group = self
+                    .graph
+                    .hovered_node()
+                    .map(|idx| self.graph.node(idx).unwrap().payload().get_id_helper())
+                    .and_then(|id| self.clusters.group_of(id))
+                    .filter(|name| self.clusters.is_collapsed(name))
+                    .map(str::to_string);
+                if let Some(name) = group {
+                    self.expand_group(&name);
+                }
+            }
+
+            // Show the note of the hovered node in a tooltip
+            if let Some(hovered_idx) = self.graph.hovered_node() {
+                let hovered_id = self
+                    .graph
+                    .node(hovered_idx)
+                    .unwrap()
+                    .payload()
+                    .get_id_helper();
+                if let Some(note) = self.notes.get(&hovered_id) {
+                    egui::show_tooltip_at_pointer(
+                        ui.ctx(),
+                        ui.layer_id(),
+                        egui::Id::new("node_note_tooltip"),
+                        |ui| {
+                            ui.label(note);
+                        },
+                    );
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,202 @@
+//! Live stream of the controller events as JSON Lines, for external analysis
+//!
+//! Every event of the log is serialized as one JSON object per line, e.g.
+//! `{"timestamp_ms":1700000000000,"kind":"packet_dropped","node_id":3,"severity":"warn",...}`.
+//! The lines are written by a worker thread, so the UI thread never waits for
+//! the writer. On the web, where threads are not available, they are written
+//! by the UI thread.
+
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::JoinHandle;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crossbeam_channel::{RecvTimeoutError, Sender};
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+use wg_2024::network::NodeId;
+
+use crate::utils::{LogLevel, NodeKind};
+
+/// Interval between the flushes of the writer
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A structured controller event, as streamed by the `EventSink`
+#[derive(Clone, Debug)]
+pub struct SinkRecord {
+    /// Kind of event: `packet`, `shortcut`, `controller`, `unresponsive` or
+    /// `unsupported_request`
+    pub kind: &'static str,
+    /// Node that reported the event, if any
+    pub node_id: Option<NodeId>,
+    pub node_kind: Option<NodeKind>,
+    /// Destination of the shortcut, if any
+    pub destination: Option<NodeId>,
+    pub severity: LogLevel,
+    /// Packet type and route of the packet the event refers to, if any
+    pub packet: Option<String>,
+    pub session_id: Option<u64>,
+    /// Text of the entry in the log
+    pub text: String,
+}
+
+impl SinkRecord {
+    /// Serialize the record as a single JSON line, stamped with the current time
+    fn to_json_line(&self) -> String {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        serde_json::json!({
+            "timestamp_ms": timestamp_ms,
+            "kind": self.kind,
+            "node_id": self.node_id,
+            "node_kind": self.node_kind.map(NodeKind::name),
+            "destination": self.destination,
+            "severity": self.severity.name(),
+            "packet": self.packet,
+            "session_id": self.session_id,
+            "text": self.text,
+        })
+        .to_string()
+    }
+}
+
+/// Handle of the worker thread writing the events
+///
+/// Dropping the sink stops the worker after a final flush.
+/// On the web there are no threads: the lines are written by `send` itself.
+pub struct EventSink {
+    #[cfg(not(target_arch = "wasm32"))]
+    sender: Option<Sender<String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    worker: Option<JoinHandle<()>>,
+    #[cfg(target_arch = "wasm32")]
+    writer: RefCell<LineWriter>,
+}
+
+/// Writer of the JSON lines, flushed every `FLUSH_INTERVAL`
+struct LineWriter {
+    writer: Box<dyn Write + Send>,
+    last_flush: Instant,
+    /// Set on the first error, the controller keeps running without the stream
+    failed: bool,
+}
+
+impl LineWriter {
+    fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            last_flush: Instant::now(),
+            failed: false,
+        }
+    }
+
+    /// Write `line`, if any, then flush if the interval has elapsed
+    fn write(&mut self, line: Option<&str>) {
+        if self.failed {
+            return;
+        }
+        let mut written = match line {
+            Some(line) => writeln!(self.writer, "{line}"),
+            None => Ok(()),
+        };
+        if written.is_ok() && self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.last_flush = Instant::now();
+            written = self.writer.flush();
+        }
+        self.failed = written.is_err();
+    }
+
+    fn flush(&mut self) {
+        if !self.failed {
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+impl EventSink {
+    /// Start streaming the events to `writer`
+    ///
+    /// The writer is flushed every second by the worker, and when the sink is dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded::<String>();
+        let mut writer = LineWriter::new(writer);
+        let worker = std::thread::spawn(move || {
+            loop {
+                match receiver.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(line) => writer.write(Some(&line)),
+                    Err(RecvTimeoutError::Timeout) => writer.write(None),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                if writer.failed {
+                    return;
+                }
+            }
+            writer.flush();
+        });
+        Self {
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+
+    /// Start streaming the events to `writer`
+    ///
+    /// The writer is flushed at most every second, when a line is sent, and when
+    /// the sink is dropped.
+    #[cfg(target_arch = "wasm32")]
+    #[must_use]
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: RefCell::new(LineWriter::new(writer)),
+        }
+    }
+
+    /// Start streaming the events to the file at `path`, replacing it
+    ///
+    /// # Errors
+    /// If the file can't be created
+    pub fn to_file(path: &Path) -> Result<Self, String> {
+        let file =
+            File::create(path).map_err(|e| format!("Cannot create {}: {e}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// Queue a record for the worker, without blocking
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn send(&self, record: &SinkRecord) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(record.to_json_line());
+        }
+    }
+
+    /// Write a record to the writer
+    #[cfg(target_arch = "wasm32")]
+    pub fn send(&self, record: &SinkRecord) {
+        self.writer.borrow_mut().write(Some(&record.to_json_line()));
+    }
+}
+
+impl Drop for EventSink {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn drop(&mut self) {
+        // Closing the channel lets the worker write the queued lines and flush
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn drop(&mut self) {
+        self.writer.get_mut().flush();
+    }
+}
@@ -0,0 +1,630 @@
+//! Drones spawned at runtime, their threads, and the shutdown of the network
+
+use std::{
+    collections::{HashMap, HashSet},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use petgraph::stable_graph::NodeIndex;
+use rand::Rng;
+use web_time::Instant;
+use wg_2024::{
+    config::{Client, Drone, Server},
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+use crate::{
+    build_node, platform,
+    topology::Change,
+    utils::{LogLevel, NodeKind},
+    validation::{ConnectionRejection, ControllerError, ValidationError},
+    widgets::{
+        chat_client_widget::ChatClientWidget, drone_widget::DroneWidget,
+        server_widget::ServerWidget, web_client_widget::WebClientWidget, NodeWidget, WidgetType,
+    },
+    DroneFactory, RetiredThread, SimulationController, UpdateType, DRONE_FACTORY,
+    DRONE_JOIN_TIMEOUT,
+};
+
+impl SimulationController {
+    /// Function to stop the network, as done when the window is closed
+    ///
+    /// Every drone is sent the crash command, then the events are handled until
+    /// the event channels are drained and the threads spawned by the controller
+    /// stop, for up to `DRONE_JOIN_TIMEOUT`; the threads still running after it
+    /// are detached. Clients and servers have no
+    /// command to stop them, they see their channels close when the controller
+    /// is dropped.
+    /// The outcome is logged, then the event stream and the log file are flushed and closed.
+    pub fn shutdown(&mut self) {
+        let mut crashed = 0;
+        let mut unreachable = Vec::new();
+        for (_, node) in self.graph.nodes_iter() {
+            if let WidgetType::Drone(drone_widget) = node.payload() {
+                match drone_widget.send_crash_command() {
+                    Ok(()) => crashed += 1,
+                    Err(_) => unreachable.push(drone_widget.id()),
+                }
+            }
+        }
+
+        let mut threads = self
+            .drone_threads
+            .drain()
+            .map(|(_, thread)| thread)
+            .chain(self.retired_threads.drain(..).map(|retired| retired.thread))
+            .collect::<Vec<JoinHandle<()>>>();
+        let deadline = Instant::now() + DRONE_JOIN_TIMEOUT;
+        loop {
+            // The events sent by the nodes before stopping still reach the log
+            self.handle_event();
+            threads = join_finished(threads);
+            let drained = threads.is_empty() && self.event_channels_empty();
+            if drained || Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        self.drones_channels.clear();
+
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] Shutdown: {crashed} drones sent the crash command"),
+        );
+        if !unreachable.is_empty() {
+            unreachable.sort_unstable();
+            let ids = unreachable
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            self.log(
+                LogLevel::Warn,
+                format!("[CONTROLLER] Shutdown: drones {ids} could not be sent the crash command"),
+            );
+        }
+        if !threads.is_empty() {
+            self.log(
+                LogLevel::Warn,
+                format!(
+                    "[CONTROLLER] Shutdown: {} drone threads still running, detached",
+                    threads.len()
+                ),
+            );
+        }
+        self.event_sink = None;
+        self.close_log_file();
+    }
+
+    /// Helper function to check that no event is waiting in the channels of the nodes
+    fn event_channels_empty(&self) -> bool {
+        self.crashed_receivers.is_empty()
+            && self.drones_channels.values().all(|ch| ch.1.is_empty())
+            && self.web_clients_channels.values().all(|ch| ch.1.is_empty())
+            && self
+                .chat_clients_channels
+                .values()
+                .all(|ch| ch.1.is_empty())
+            && self.servers_channels.values().all(|ch| ch.1.is_empty())
+    }
+
+    /// Helper function to validate and apply `TopologyOp::Spawn`
+    ///
+    /// The neighbors are validated before anything is spawned; the ones that
+    /// can't receive the `AddSender` command once the drone runs are reported
+    /// as a failure, while the drone stays up.
+    pub(crate) fn apply_spawn(
+        &mut self,
+        drone_id: NodeId,
+        implementation: Option<usize>,
+        pdr: f32,
+        neighbors: &[NodeId],
+    ) -> Result<(), ValidationError> {
+        if !platform::CAN_SPAWN_DRONES {
+            return Err(ValidationError::Failed(
+                "Drones can't be spawned on this platform".to_string(),
+            ));
+        }
+        if self.get_node_idx(drone_id).is_some() {
+            return Err(ValidationError::Violations(vec![
+                ControllerError::IdAlreadyUsed(drone_id),
+            ]));
+        }
+        if !(0.0..=1.0).contains(&pdr) {
+            return Err(ValidationError::Violations(vec![
+                ControllerError::PdrOutOfRange(pdr),
+            ]));
+        }
+        let mut neighbor_indices = Vec::new();
+        for neighbor_id in neighbors {
+            let neighbor_idx = self
+                .get_node_idx(*neighbor_id)
+                .ok_or(ValidationError::Rejected(ConnectionRejection::UnknownId(
+                    *neighbor_id,
+                )))?;
+            if neighbor_indices.contains(&neighbor_idx) {
+                return Err(ValidationError::Rejected(
+                    ConnectionRejection::AlreadyConnected(drone_id, *neighbor_id),
+                ));
+            }
+            self.can_add_spawn_neighbor(neighbor_idx)
+                .map_err(ValidationError::Rejected)?;
+            neighbor_indices.push(neighbor_idx);
+        }
+        let implementation =
+            implementation.unwrap_or_else(|| rand::rng().random_range(0..DRONE_FACTORY.len()));
+        let Some((name, drone_factory)) = DRONE_FACTORY.get(implementation).copied() else {
+            return Err(ValidationError::Violations(vec![
+                ControllerError::UnknownImplementation(implementation),
+            ]));
+        };
+        let (_, lost_neighbors) =
+            self.spawn_drone_with(drone_id, (name, drone_factory), pdr, &neighbor_indices);
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] Drone {drone_id} spawned ({name})"),
+        );
+        // The id of a crashed drone may be reused on purpose
+        self.retired_ids.remove(&drone_id);
+
+        if lost_neighbors.is_empty() {
+            Ok(())
+        } else {
+            let lost = lost_neighbors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(ValidationError::Failed(format!(
+                "Drone {drone_id} spawned, but could not be connected to {lost}"
+            )))
+        }
+    }
+
+    /// Helper function to validate and apply `TopologyOp::SpawnNode` for clients and servers
+    ///
+    /// Clients need 1 or 2 drone neighbors and servers at least 2; as for the
+    /// drones, the neighbors that can't be sent the `AddSender` command are
+    /// reported as a failure, while the node stays up.
+    pub(crate) fn apply_spawn_node(
+        &mut self,
+        node_id: NodeId,
+        kind: NodeKind,
+        neighbors: &[NodeId],
+    ) -> Result<(), ValidationError> {
+        if !platform::CAN_SPAWN_DRONES {
+            return Err(ValidationError::Failed(
+                "Nodes can't be spawned on this platform".to_string(),
+            ));
+        }
+        let name = match kind {
+            NodeKind::Drone => "Drone",
+            NodeKind::WebClient => "Web client",
+            NodeKind::ChatClient => "Chat client",
+            NodeKind::Server => "Server",
+        };
+        if self.get_node_idx(node_id).is_some() {
+            return Err(ValidationError::Violations(vec![
+                ControllerError::IdAlreadyUsed(node_id),
+            ]));
+        }
+        match kind {
+            NodeKind::WebClient | NodeKind::ChatClient if neighbors.is_empty() => {
+                return Err(ValidationError::Violations(vec![
+                    ControllerError::ClientMinConnections(node_id),
+                ]));
+            }
+            NodeKind::WebClient | NodeKind::ChatClient if neighbors.len() > 2 => {
+                return Err(ValidationError::Violations(vec![
+                    ControllerError::ClientMaxConnections(node_id),
+                ]));
+            }
+            NodeKind::Server if neighbors.len() < 2 => {
+                return Err(ValidationError::Violations(vec![
+                    ControllerError::ServerMinConnections(node_id),
+                ]));
+            }
+            _ => {}
+        }
+        let mut neighbor_indices = Vec::new();
+        for neighbor_id in neighbors {
+            let neighbor_idx = self
+                .get_node_idx(*neighbor_id)
+                .ok_or(ValidationError::Rejected(ConnectionRejection::UnknownId(
+                    *neighbor_id,
+                )))?;
+            if neighbor_indices.contains(&neighbor_idx) {
+                return Err(ValidationError::Rejected(
+                    ConnectionRejection::AlreadyConnected(node_id, *neighbor_id),
+                ));
+            }
+            let widget = self.graph.node(neighbor_idx).unwrap().payload();
+            match (kind, widget) {
+                (_, WidgetType::Drone(_)) => {}
+                (NodeKind::Server, _) => {
+                    return Err(ValidationError::Rejected(
+                        ConnectionRejection::ServerToNonDrone,
+                    ))
+                }
+                (_, WidgetType::Server(_)) => {
+                    return Err(ValidationError::Rejected(
+                        ConnectionRejection::ClientToServer,
+                    ))
+                }
+                (_, _) => {
+                    return Err(ValidationError::Rejected(
+                        ConnectionRejection::ClientToClient,
+                    ))
+                }
+            }
+            self.can_add_spawn_neighbor(neighbor_idx)
+                .map_err(ValidationError::Rejected)?;
+            neighbor_indices.push(neighbor_idx);
+        }
+
+        let nbrs = neighbor_indices
+            .iter()
+            .map(|idx| self.get_sender_channel(*idx))
+            .collect::<HashMap<NodeId, Sender<Packet>>>();
+        // Nothing was changed so far, a missing factory leaves the network as it was
+        let no_factory = || {
+            ValidationError::Failed(format!(
+                "No factory was given for the {} nodes",
+                kind.name()
+            ))
+        };
+        let (widget, packet_send, node) = match kind {
+            NodeKind::WebClient => {
+                let factory = self.web_client_factory.as_ref().ok_or_else(no_factory)?;
+                let (channels, node) =
+                    build_node(factory, node_id, crossbeam_channel::unbounded(), nbrs);
+                let widget =
+                    WidgetType::WebClient(WebClientWidget::new(node_id, channels.0.clone()));
+                let packet_send = channels.2.clone();
+                self.web_clients_channels.insert(node_id, channels);
+                self.web_clients.push(Client {
+                    id: node_id,
+                    connected_drone_ids: vec![],
+                });
+                (widget, packet_send, node)
+            }
+            NodeKind::ChatClient => {
+                let factory = self.chat_client_factory.as_ref().ok_or_else(no_factory)?;
+                let (channels, node) =
+                    build_node(factory, node_id, crossbeam_channel::unbounded(), nbrs);
+                let widget =
+                    WidgetType::ChatClient(ChatClientWidget::new(node_id, channels.0.clone()));
+                let packet_send = channels.2.clone();
+                self.chat_clients_channels.insert(node_id, channels);
+                self.chat_clients.push(Client {
+                    id: node_id,
+                    connected_drone_ids: vec![],
+                });
+                (widget, packet_send, node)
+            }
+            NodeKind::Server => {
+                let factory = self.server_factory.as_ref().ok_or_else(no_factory)?;
+                let (channels, node) =
+                    build_node(factory, node_id, crossbeam_channel::unbounded(), nbrs);
+                let widget = WidgetType::Server(ServerWidget::new(node_id, channels.0.clone()));
+                let packet_send = channels.2.clone();
+                self.servers_channels.insert(node_id, channels);
+                self.servers.push(Server {
+                    id: node_id,
+                    connected_drone_ids: vec![],
+                });
+                (widget, packet_send, node)
+            }
+            NodeKind::Drone => return Err(no_factory()),
+        };
+        let node_idx = self.graph.add_node(widget);
+        self.node_indices.insert(node_id, node_idx);
+        self.refresh_label(node_idx);
+        self.started_at.insert(node_id, Instant::now());
+        platform::run_node(node);
+        let lost_neighbors =
+            self.link_spawned_node(node_id, node_idx, &packet_send, &neighbor_indices);
+        self.topology_changed();
+        self.retired_ids.remove(&node_id);
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] {name} {node_id} spawned"),
+        );
+
+        if lost_neighbors.is_empty() {
+            Ok(())
+        } else {
+            let lost = lost_neighbors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(ValidationError::Failed(format!(
+                "{name} {node_id} spawned, but could not be connected to {lost}"
+            )))
+        }
+    }
+
+    /// Function to spawn a drone with the implementation `(name, drone_factory)`
+    /// of `DRONE_FACTORY`, connected to the already validated `neighbors`
+    ///
+    /// Returns the index of the node of the new drone in the graph, with the
+    /// neighbors that could not be sent the `AddSender` command: they are
+    /// flagged as unresponsive and left out.
+    fn spawn_drone_with(
+        &mut self,
+        new_id: NodeId,
+        (name, drone_factory): (&'static str, DroneFactory),
+        pdr: f32,
+        neighbors: &[NodeIndex],
+    ) -> (NodeIndex, Vec<NodeId>) {
+        let (sender_command, receiver_command): (Sender<DroneCommand>, Receiver<DroneCommand>) =
+            crossbeam_channel::unbounded();
+        let (send_event, receive_event): (Sender<DroneEvent>, Receiver<DroneEvent>) =
+            crossbeam_channel::unbounded();
+        let (packet_send, packet_recv): (Sender<Packet>, Receiver<Packet>) =
+            crossbeam_channel::unbounded();
+        let nbrs = neighbors
+            .iter()
+            .map(|idx| self.get_sender_channel(*idx))
+            .collect::<HashMap<NodeId, Sender<Packet>>>();
+        let new_drone = drone_factory(
+            new_id,
+            send_event,
+            receiver_command,
+            packet_recv.clone(),
+            nbrs,
+            pdr,
+        );
+
+        self.drones_channels.insert(
+            new_id,
+            (
+                sender_command.clone(),
+                receive_event,
+                packet_send.clone(),
+                packet_recv,
+            ),
+        );
+        self.drones.push(Drone {
+            id: new_id,
+            connected_node_ids: vec![],
+            pdr,
+        });
+        let mut drone_widget = DroneWidget::new(new_id, sender_command.clone(), pdr);
+        drone_widget.set_implementation(name);
+        let drone_idx = self.graph.add_node(WidgetType::Drone(drone_widget));
+        self.node_indices.insert(new_id, drone_idx);
+        self.graph
+            .node_mut(drone_idx)
+            .unwrap()
+            .set_label(format!("Drone {new_id}"));
+        self.started_at.insert(new_id, Instant::now());
+        self.pdr_history.record_pdr(new_id, pdr);
+        self.operations.record(Change::DroneSpawned(new_id));
+        self.track_drone_thread(new_id, platform::run_drone(new_drone));
+
+        let lost_neighbors = self.link_spawned_node(new_id, drone_idx, &packet_send, neighbors);
+        self.topology_changed();
+        (drone_idx, lost_neighbors)
+    }
+
+    /// Function to give the `neighbors` of a node just spawned its packet sender,
+    /// adding the edges
+    ///
+    /// The new node already knows its neighbors from its factory. Returns the
+    /// neighbors that could not be sent the `AddSender` command: they are
+    /// flagged as unresponsive and removed from the new node.
+    fn link_spawned_node(
+        &mut self,
+        new_id: NodeId,
+        new_idx: NodeIndex,
+        packet_send: &Sender<Packet>,
+        neighbors: &[NodeIndex],
+    ) -> Vec<NodeId> {
+        let mut lost_neighbors = Vec::new();
+        for neighbor_idx in neighbors {
+            let neighbor_widget = self.graph.node_mut(*neighbor_idx).unwrap().payload_mut();
+            let neighbor_id = neighbor_widget.get_id_helper();
+            if let Err(error) = neighbor_widget.add_neighbor_helper(new_id, packet_send.clone()) {
+                let new_widget = self.graph.node(new_idx).unwrap().payload();
+                let _ = new_widget.rm_neighbor_helper(neighbor_id);
+                self.mark_unresponsive(neighbor_id, &error);
+                lost_neighbors.push(neighbor_id);
+                continue;
+            }
+            self.update_neighborhood(&UpdateType::Add, new_id, new_idx, neighbor_id);
+            self.update_neighborhood(&UpdateType::Add, neighbor_id, *neighbor_idx, new_id);
+            self.graph.add_edge(new_idx, *neighbor_idx, ());
+            self.operations
+                .record(Change::EdgeAdded(new_id, neighbor_id));
+        }
+        lost_neighbors
+    }
+
+    /// Function to keep the handle of the thread of a drone spawned by the controller
+    ///
+    /// The thread of the drone previously running with the same id, if any, is retired.
+    pub(crate) fn track_drone_thread(&mut self, drone_id: NodeId, thread: Option<JoinHandle<()>>) {
+        let Some(thread) = thread else {
+            return;
+        };
+        self.retire_drone_thread(drone_id);
+        self.drone_threads.insert(drone_id, thread);
+    }
+
+    /// Function to move the thread of a drone that was sent the crash command
+    /// to the retired threads, if the controller spawned it
+    pub(crate) fn retire_drone_thread(&mut self, drone_id: NodeId) {
+        if let Some(thread) = self.drone_threads.remove(&drone_id) {
+            self.retired_threads.push(RetiredThread {
+                drone_id,
+                retired_at: Instant::now(),
+                thread,
+                reported: false,
+            });
+        }
+    }
+
+    /// Function to join the threads of the drones that finished, without blocking
+    ///
+    /// A running drone whose thread stopped is flagged as unresponsive, a panic
+    /// is reported in the log, as well as the retired drones still running
+    /// `DRONE_JOIN_TIMEOUT` after the crash command. Done at each frame of the GUI.
+    pub fn reap_drone_threads(&mut self) {
+        let stopped = self
+            .drone_threads
+            .iter()
+            .filter(|(_, thread)| thread.is_finished())
+            .map(|(id, _)| *id)
+            .collect::<Vec<NodeId>>();
+        for drone_id in stopped {
+            let Some(thread) = self.drone_threads.remove(&drone_id) else {
+                continue;
+            };
+            let reason = if thread.join().is_err() {
+                "panicked"
+            } else {
+                "stopped running"
+            };
+            self.flag_unresponsive(drone_id, &format!("[CONTROLLER] Drone {drone_id} {reason}"));
+        }
+
+        let mut running = Vec::new();
+        for mut retired in std::mem::take(&mut self.retired_threads) {
+            if retired.thread.is_finished() {
+                if retired.thread.join().is_err() {
+                    self.log(
+                        LogLevel::Error,
+                        format!("[CONTROLLER] Drone {} panicked", retired.drone_id),
+                    );
+                }
+                continue;
+            }
+            if !retired.reported && retired.retired_at.elapsed() >= DRONE_JOIN_TIMEOUT {
+                retired.reported = true;
+                self.log(
+                    LogLevel::Warn,
+                    format!(
+                        "[CONTROLLER] Drone {} failed to terminate",
+                        retired.drone_id
+                    ),
+                );
+            }
+            running.push(retired);
+        }
+        self.retired_threads = running;
+    }
+
+    /// Function to get the number of threads of spawned drones not joined yet,
+    /// running or retired
+    #[must_use]
+    pub fn unjoined_drone_threads(&self) -> usize {
+        self.drone_threads.len() + self.retired_threads.len()
+    }
+
+    /// Function to allocate the id of a new node: the lowest id not used by any node
+    ///
+    /// The ids of the crashed drones are reassigned only if `recycle_crashed` is set,
+    /// so that the events and stats of a crashed drone are not mixed with a new one.
+    ///
+    /// # Errors
+    /// If every id is in use
+    pub fn allocate_node_id(&self, recycle_crashed: bool) -> Result<NodeId, String> {
+        let used = self
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload().get_id_helper())
+            .chain(self.drones_channels.keys().copied())
+            .chain(self.web_clients_channels.keys().copied())
+            .chain(self.chat_clients_channels.keys().copied())
+            .chain(self.servers_channels.keys().copied())
+            .collect::<HashSet<NodeId>>();
+        (0..=NodeId::MAX)
+            .find(|id| !used.contains(id) && (recycle_crashed || !self.retired_ids.contains(id)))
+            .ok_or_else(|| {
+                format!(
+                    "All the {} node ids are in use",
+                    usize::from(NodeId::MAX) + 1
+                )
+            })
+    }
+
+    /// Function to spawn a copy of a drone, with a new id, the same PDR and the same neighbors
+    ///
+    /// The copy is connected to the neighbors through the validated path.
+    /// Neighbors that can't be connected (e.g. clients that already have two
+    /// drones) are skipped, and a warning is returned for each of them.
+    pub(crate) fn duplicate_drone(
+        &mut self,
+        drone_idx: NodeIndex,
+        implementation: usize,
+    ) -> Result<Vec<String>, String> {
+        let drone_id = self
+            .graph
+            .node(drone_idx)
+            .unwrap()
+            .payload()
+            .get_id_helper();
+        let Some(pdr) = self.drones.iter().find(|d| d.id == drone_id).map(|d| d.pdr) else {
+            return Err(format!("Drone {drone_id} not found"));
+        };
+        if !platform::CAN_SPAWN_DRONES {
+            return Err("Drones can't be spawned on this platform".to_string());
+        }
+        let Some((name, drone_factory)) = DRONE_FACTORY.get(implementation).copied() else {
+            return Err(format!("Unknown drone implementation {implementation}"));
+        };
+        let new_id = self.allocate_node_id(false)?;
+        let neighbors = self
+            .graph
+            .g
+            .neighbors(drone_idx)
+            .collect::<Vec<NodeIndex>>();
+
+        let (new_idx, _) = self.spawn_drone_with(new_id, (name, drone_factory), pdr, &[]);
+        self.log(
+            LogLevel::Info,
+            format!("[CONTROLLER] Drone {drone_id} duplicated as drone {new_id} ({name})"),
+        );
+
+        let mut warnings = Vec::new();
+        for neighbor_idx in neighbors {
+            let result = self
+                .can_add_sender(new_idx, neighbor_idx)
+                .map_err(|rejection| rejection.to_string())
+                .and_then(|(source_idx, neighbor_idx)| {
+                    self.connect_nodes(source_idx, neighbor_idx)
+                });
+            if let Err(error) = result {
+                let neighbor_id = self
+                    .graph
+                    .node(neighbor_idx)
+                    .unwrap()
+                    .payload()
+                    .get_id_helper();
+                let warning = format!(
+                    "Drone {new_id} not connected to {}: {error}",
+                    self.node_name(neighbor_id)
+                );
+                self.log(LogLevel::Warn, &warning);
+                warnings.push(warning);
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+/// This function joins the finished threads, returning the ones still running
+fn join_finished(threads: Vec<JoinHandle<()>>) -> Vec<JoinHandle<()>> {
+    let (finished, running): (Vec<_>, Vec<_>) =
+        threads.into_iter().partition(JoinHandle::is_finished);
+    for thread in finished {
+        let _ = thread.join();
+    }
+    running
+}
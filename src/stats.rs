@@ -0,0 +1,452 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    path::Path,
+    time::Duration,
+};
+
+use wg_2024::{network::NodeId, packet::Packet};
+
+use crate::platform;
+
+/// Normalize an edge so that `(a, b)` and `(b, a)` map to the same key
+#[must_use]
+pub fn edge_key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Minimum, average and maximum of the durations, `None` if there are none
+#[must_use]
+pub fn duration_summary(durations: &[Duration]) -> Option<(Duration, Duration, Duration)> {
+    let min = *durations.iter().min()?;
+    let max = *durations.iter().max()?;
+    let total: Duration = durations.iter().sum();
+    let avg = total / u32::try_from(durations.len()).unwrap_or(u32::MAX);
+    Some((min, avg, max))
+}
+
+/// Counters of the packets handled by a single node
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeCounters {
+    /// Number of packets the node reported as sent
+    pub sent: u64,
+    /// Number of packets the node reported as dropped
+    pub dropped: u64,
+    /// Number of shortcuts the node requested to the controller
+    pub shortcuts: u64,
+}
+
+impl NodeCounters {
+    /// Fraction of the handled packets that were dropped, `0.0` if none was handled
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn observed_pdr(&self) -> f64 {
+        let total = self.sent + self.dropped;
+        if total == 0 {
+            0.0
+        } else {
+            self.dropped as f64 / total as f64
+        }
+    }
+}
+
+/// Information about a node that is not collected from the events, exported
+/// along with its counters
+#[derive(Clone, Debug)]
+pub struct NodeInfo {
+    pub id: NodeId,
+    /// Alias given by the user to the node, if any
+    pub alias: Option<String>,
+    pub kind: &'static str,
+    /// Configured PDR, `None` for nodes other than drones
+    pub configured_pdr: Option<f32>,
+    pub neighbors: usize,
+    pub uptime: Duration,
+}
+
+/// Snapshot of the statistics collected by the simulation controller
+#[derive(Clone, Debug, Default)]
+pub struct StatsSnapshot {
+    /// Packet counters of each node that produced at least one event
+    pub nodes: HashMap<NodeId, NodeCounters>,
+    /// Number of packets sent over each edge, keyed by the normalized edge
+    ///
+    /// Edges that no longer exist keep their historical counts
+    pub edge_traffic: HashMap<(NodeId, NodeId), u64>,
+    /// Time elapsed since the simulation started, used to normalize the rates
+    pub uptime: Duration,
+}
+
+/// Statistics collected from the events received by the simulation controller
+#[derive(Debug, Default)]
+pub struct Stats {
+    nodes: HashMap<NodeId, NodeCounters>,
+    edge_traffic: HashMap<(NodeId, NodeId), u64>,
+    /// Round-trip times of the client requests that received a response
+    round_trips: Vec<Duration>,
+    /// Number of client requests that timed out
+    lost_requests: u64,
+}
+
+impl Stats {
+    /// Record a packet sent by the node with `node_id`
+    pub fn record_sent(&mut self, node_id: NodeId) {
+        self.nodes.entry(node_id).or_default().sent += 1;
+    }
+
+    /// Record a packet dropped by the node with `node_id`
+    pub fn record_dropped(&mut self, node_id: NodeId) {
+        self.nodes.entry(node_id).or_default().dropped += 1;
+    }
+
+    /// Record a shortcut requested by the node with `node_id`
+    pub fn record_shortcut(&mut self, node_id: NodeId) {
+        self.nodes.entry(node_id).or_default().shortcuts += 1;
+    }
+
+    /// Record a packet sent by the drone with `drone_id`
+    ///
+    /// The traversed edge is derived from the routing header: it connects the
+    /// drone to the hop that follows it in the route.
+    pub fn record_drone_sent(&mut self, drone_id: NodeId, packet: &Packet) {
+        let hops = &packet.routing_header.hops;
+        let Some(pos) = hops.iter().position(|hop| *hop == drone_id) else {
+            return;
+        };
+        if let Some(next_hop) = hops.get(pos + 1) {
+            *self
+                .edge_traffic
+                .entry(edge_key(drone_id, *next_hop))
+                .or_default() += 1;
+        }
+    }
+
+    /// Record the round-trip time of a client request
+    pub fn record_round_trip(&mut self, rtt: Duration) {
+        self.round_trips.push(rtt);
+    }
+
+    /// Record client requests that timed out without a response
+    pub fn record_lost_requests(&mut self, count: usize) {
+        self.lost_requests += count as u64;
+    }
+
+    /// Minimum, average and maximum round-trip time of the client requests
+    #[must_use]
+    pub fn round_trip_summary(&self) -> Option<(Duration, Duration, Duration)> {
+        duration_summary(&self.round_trips)
+    }
+
+    /// Number of client requests answered and timed out
+    #[must_use]
+    pub fn request_outcomes(&self) -> (usize, u64) {
+        (self.round_trips.len(), self.lost_requests)
+    }
+
+    /// Get the packet counters of the node with `node_id`
+    #[must_use]
+    pub fn counters(&self, node_id: NodeId) -> NodeCounters {
+        self.nodes.get(&node_id).copied().unwrap_or_default()
+    }
+
+    /// Get the number of packets sent over the edge between `a` and `b`
+    #[must_use]
+    pub fn edge_count(&self, a: NodeId, b: NodeId) -> u64 {
+        self.edge_traffic
+            .get(&edge_key(a, b))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Take a snapshot of the collected statistics, after `uptime` of simulation
+    #[must_use]
+    pub fn snapshot(&self, uptime: Duration) -> StatsSnapshot {
+        StatsSnapshot {
+            nodes: self.nodes.clone(),
+            edge_traffic: self.edge_traffic.clone(),
+            uptime,
+        }
+    }
+}
+
+/// Number of queued events above which the channel of a node is backed up
+const LAG_QUEUE_LIMIT: usize = 100;
+
+/// Number of consecutive frames a channel must be backed up to be reported as lagging
+const LAG_FRAMES: u32 = 30;
+
+/// Monitor of the event channels, to detect nodes emitting events faster
+/// than the controller drains them
+///
+/// It only looks at the length of the channels after each drain, which is a
+/// constant time operation, so it adds no measurable cost to the frame.
+#[derive(Debug, Default)]
+pub struct IntakeMonitor {
+    /// Number of consecutive frames the channel of each node was backed up
+    backed_up_frames: HashMap<NodeId, u32>,
+    /// Nodes currently lagging, with the number of events left in their channel
+    lagging: BTreeMap<NodeId, usize>,
+    /// Number of times a node started lagging
+    lag_episodes: u64,
+}
+
+impl IntakeMonitor {
+    /// Record the number of events left in the channel of `node_id` after a drain
+    pub fn observe(&mut self, node_id: NodeId, queued: usize) {
+        if queued <= LAG_QUEUE_LIMIT {
+            self.backed_up_frames.remove(&node_id);
+            self.lagging.remove(&node_id);
+            return;
+        }
+        let frames = self.backed_up_frames.entry(node_id).or_default();
+        *frames = frames.saturating_add(1);
+        if *frames >= LAG_FRAMES && self.lagging.insert(node_id, queued).is_none() {
+            self.lag_episodes += 1;
+        }
+    }
+
+    /// Forget the node, e.g. when it crashed
+    pub fn forget(&mut self, node_id: NodeId) {
+        self.backed_up_frames.remove(&node_id);
+        self.lagging.remove(&node_id);
+    }
+
+    /// Nodes currently lagging, with the number of queued events
+    #[must_use]
+    pub fn lagging(&self) -> &BTreeMap<NodeId, usize> {
+        &self.lagging
+    }
+
+    /// Number of times a node started lagging
+    #[must_use]
+    pub fn lag_episodes(&self) -> u64 {
+        self.lag_episodes
+    }
+}
+
+/// Quote a CSV field if it contains a separator, a quote or a newline
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Export the statistics as CSV files in `dir`
+///
+/// `nodes.csv` contains one row per node in `nodes`, while `edges.csv`
+/// contains the traffic of every edge in the snapshot.
+/// Rates are normalized by the uptime of the simulation in the snapshot.
+/// Rows are sorted by id, so that exports of the same scenario can be diffed.
+///
+/// # Errors
+/// If the directory or the files cannot be written
+#[allow(clippy::cast_precision_loss)]
+pub fn export_csv(dir: &Path, nodes: &[NodeInfo], snapshot: &StatsSnapshot) -> Result<(), String> {
+    let uptime_secs = snapshot.uptime.as_secs_f64().max(f64::EPSILON);
+    let mut nodes = nodes.to_vec();
+    nodes.sort_by_key(|node| node.id);
+    let mut nodes_csv = String::from(
+        "id,alias,kind,sent,dropped,shortcuts,observed_pdr,configured_pdr,neighbors,uptime_secs,sent_per_sec\n",
+    );
+    for node in nodes {
+        let counters = snapshot.nodes.get(&node.id).copied().unwrap_or_default();
+        let configured_pdr = node
+            .configured_pdr
+            .map(|pdr| pdr.to_string())
+            .unwrap_or_default();
+        let _ = writeln!(
+            nodes_csv,
+            "{},{},{},{},{},{},{:.4},{},{},{},{:.4}",
+            node.id,
+            csv_field(node.alias.as_deref().unwrap_or_default()),
+            node.kind,
+            counters.sent,
+            counters.dropped,
+            counters.shortcuts,
+            counters.observed_pdr(),
+            configured_pdr,
+            node.neighbors,
+            node.uptime.as_secs(),
+            counters.sent as f64 / uptime_secs
+        );
+    }
+
+    let mut edges = snapshot.edge_traffic.iter().collect::<Vec<_>>();
+    edges.sort_by_key(|(edge, _)| **edge);
+    let mut edges_csv = String::from("node_1,node_2,packets,packets_per_sec\n");
+    for ((a, b), count) in edges {
+        let _ = writeln!(
+            edges_csv,
+            "{a},{b},{count},{:.4}",
+            *count as f64 / uptime_secs
+        );
+    }
+
+    for (name, content) in [("nodes.csv", nodes_csv), ("edges.csv", edges_csv)] {
+        platform::save_file(&dir.join(name), content.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wg_2024::{
+        network::SourceRoutingHeader,
+        packet::{Ack, PacketType},
+    };
+
+    use super::*;
+
+    /// An ack travelling along `hops`
+    fn ack(hops: &[NodeId]) -> Packet {
+        Packet {
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: hops.to_vec(),
+            },
+            session_id: 1,
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+        }
+    }
+
+    #[test]
+    fn edges_are_counted_in_both_directions() {
+        let mut stats = Stats::default();
+        stats.record_drone_sent(2, &ack(&[10, 2, 3, 20]));
+        stats.record_drone_sent(3, &ack(&[20, 3, 2, 10]));
+        // The last hop and the drones off the route traverse no edge
+        stats.record_drone_sent(3, &ack(&[10, 2, 3]));
+        stats.record_drone_sent(4, &ack(&[10, 2, 3]));
+
+        assert_eq!(edge_key(3, 2), (2, 3));
+        assert_eq!(stats.edge_count(2, 3), 2);
+        assert_eq!(stats.edge_count(3, 2), 2);
+        assert_eq!(stats.edge_count(3, 20), 0);
+        assert_eq!(stats.snapshot(Duration::ZERO).edge_traffic.len(), 1);
+    }
+
+    #[test]
+    fn node_counters_give_the_observed_pdr() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.counters(1).observed_pdr(), 0.0);
+
+        for _ in 0..3 {
+            stats.record_sent(1);
+        }
+        stats.record_dropped(1);
+        stats.record_shortcut(1);
+
+        let counters = stats.counters(1);
+        assert_eq!(
+            (counters.sent, counters.dropped, counters.shortcuts),
+            (3, 1, 1)
+        );
+        assert_eq!(counters.observed_pdr(), 0.25);
+    }
+
+    #[test]
+    fn round_trips_are_summarized() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.round_trip_summary(), None);
+
+        for millis in [10, 40, 20, 30] {
+            stats.record_round_trip(Duration::from_millis(millis));
+        }
+        stats.record_lost_requests(2);
+
+        assert_eq!(
+            stats.round_trip_summary(),
+            Some((
+                Duration::from_millis(10),
+                Duration::from_millis(25),
+                Duration::from_millis(40)
+            ))
+        );
+        assert_eq!(stats.request_outcomes(), (4, 2));
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("drone 1"), "drone 1");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn statistics_are_exported_sorted_by_id() {
+        let mut stats = Stats::default();
+        stats.record_sent(3);
+        stats.record_dropped(3);
+        stats.record_drone_sent(3, &ack(&[10, 3, 1]));
+        let node = |id, kind, configured_pdr| NodeInfo {
+            id,
+            alias: None,
+            kind,
+            configured_pdr,
+            neighbors: 2,
+            uptime: Duration::from_secs(4),
+        };
+        let mut client = node(10, "web_client", None);
+        client.alias = Some("home, sweet home".to_string());
+        let nodes = [client, node(3, "drone", Some(0.5))];
+        let dir = tempfile::tempdir().unwrap();
+
+        export_csv(dir.path(), &nodes, &stats.snapshot(Duration::from_secs(2))).unwrap();
+
+        let nodes_csv = std::fs::read_to_string(dir.path().join("nodes.csv")).unwrap();
+        let rows = nodes_csv.lines().skip(1).collect::<Vec<&str>>();
+        assert_eq!(
+            rows,
+            [
+                "3,,drone,1,1,0,0.5000,0.5,2,4,0.5000",
+                "10,\"home, sweet home\",web_client,0,0,0,0.0000,,2,4,0.0000",
+            ]
+        );
+        let edges_csv = std::fs::read_to_string(dir.path().join("edges.csv")).unwrap();
+        assert_eq!(
+            edges_csv,
+            "node_1,node_2,packets,packets_per_sec\n1,3,1,0.5000\n"
+        );
+    }
+
+    #[test]
+    fn nodes_backed_up_for_long_enough_are_lagging() {
+        let mut monitor = IntakeMonitor::default();
+        for _ in 1..LAG_FRAMES {
+            monitor.observe(1, LAG_QUEUE_LIMIT + 1);
+        }
+        assert!(monitor.lagging().is_empty());
+
+        monitor.observe(1, LAG_QUEUE_LIMIT + 5);
+        monitor.observe(1, LAG_QUEUE_LIMIT + 9);
+
+        assert_eq!(monitor.lagging().get(&1), Some(&(LAG_QUEUE_LIMIT + 5)));
+        assert_eq!(monitor.lag_episodes(), 1);
+    }
+
+    #[test]
+    fn draining_the_channel_ends_the_lag() {
+        let mut monitor = IntakeMonitor::default();
+        for _ in 0..LAG_FRAMES {
+            monitor.observe(1, LAG_QUEUE_LIMIT + 1);
+            monitor.observe(2, LAG_QUEUE_LIMIT + 1);
+        }
+
+        monitor.observe(1, LAG_QUEUE_LIMIT);
+        monitor.forget(2);
+        assert!(monitor.lagging().is_empty());
+
+        // A new episode needs the full number of frames again
+        for _ in 0..LAG_FRAMES {
+            monitor.observe(1, LAG_QUEUE_LIMIT + 1);
+        }
+        assert_eq!(monitor.lag_episodes(), 3);
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use common::slc_commands::WebClientCommand;
+use web_time::Instant;
+use wg_2024::network::NodeId;
+
+use crate::{stats::duration_summary, widgets::web_client_widget::PendingRequest};
+
+/// Request fired by a stress test
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StressRequest {
+    /// `AskListOfFiles` to the server
+    ListOfFiles,
+    /// `RequestFile` of the given file to the server
+    File(String),
+}
+
+/// A burst of requests sent by a web client to a server
+///
+/// The test is driven by the controller, which calls `due` every frame and
+/// sends the next command when it returns `true`, so the UI is never blocked
+/// between two sends.
+/// Responses are correlated through the pending requests of the web client.
+#[derive(Clone, Debug)]
+pub struct StressTest {
+    pub client_id: NodeId,
+    pub server_id: NodeId,
+    pub request: StressRequest,
+    /// Number of requests to send
+    pub count: usize,
+    /// Time between two requests
+    interval: Duration,
+    /// Number of requests sent so far
+    pub sent: usize,
+    /// Round-trip time of the responses received so far
+    pub latencies: Vec<Duration>,
+    /// Flag to indicate that the test was cancelled before sending every request
+    pub cancelled: bool,
+    next_send: Instant,
+}
+
+impl StressTest {
+    /// Create a new stress test, whose first request is due immediately
+    #[must_use]
+    pub fn new(
+        client_id: NodeId,
+        server_id: NodeId,
+        request: StressRequest,
+        count: usize,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            client_id,
+            server_id,
+            request,
+            count,
+            interval,
+            sent: 0,
+            latencies: Vec::new(),
+            cancelled: false,
+            next_send: Instant::now(),
+        }
+    }
+
+    /// Check if the test is still sending requests
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        !self.cancelled && self.sent < self.count
+    }
+
+    /// Check if the next request should be sent now
+    #[must_use]
+    pub fn due(&self) -> bool {
+        self.is_running() && Instant::now() >= self.next_send
+    }
+
+    /// Time left before the next request is due, if the test is running
+    #[must_use]
+    pub fn time_to_next_send(&self) -> Option<Duration> {
+        self.is_running()
+            .then(|| self.next_send.saturating_duration_since(Instant::now()))
+    }
+
+    /// Command to send to the web client for the next request
+    #[must_use]
+    pub fn command(&self) -> WebClientCommand {
+        match &self.request {
+            StressRequest::ListOfFiles => WebClientCommand::AskListOfFiles(self.server_id),
+            StressRequest::File(file) => {
+                WebClientCommand::RequestFile(file.clone(), self.server_id)
+            }
+        }
+    }
+
+    /// Pending request used to correlate the responses of the test
+    #[must_use]
+    pub fn pending_request(&self) -> PendingRequest {
+        match self.request {
+            StressRequest::ListOfFiles => PendingRequest::ListOfFiles(self.server_id),
+            StressRequest::File(_) => PendingRequest::File(self.server_id),
+        }
+    }
+
+    /// Record that the next request was sent
+    pub fn mark_sent(&mut self) {
+        self.sent += 1;
+        self.next_send += self.interval;
+    }
+
+    /// Record a response to `request` from the web client with `client_id`,
+    /// if it belongs to the test
+    pub fn record_response(&mut self, client_id: NodeId, request: &PendingRequest, rtt: Duration) {
+        if client_id == self.client_id && *request == self.pending_request() {
+            self.latencies.push(rtt);
+        }
+    }
+
+    /// Stop sending requests, the responses to the sent ones are still recorded
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+
+    /// Minimum, average and maximum round-trip time, if any response arrived
+    #[must_use]
+    pub fn latency_summary(&self) -> Option<(Duration, Duration, Duration)> {
+        duration_summary(&self.latencies)
+    }
+}
+
+/// Inputs of the stress test dialog
+#[derive(Clone, Debug)]
+pub struct StressTestForm {
+    pub client_id: Option<NodeId>,
+    pub server_id: Option<NodeId>,
+    /// Flag to request a file instead of the list of files
+    pub request_file: bool,
+    /// Path of the file to request
+    pub file: String,
+    pub count: usize,
+    pub interval_ms: u64,
+}
+
+impl Default for StressTestForm {
+    fn default() -> Self {
+        Self {
+            client_id: None,
+            server_id: None,
+            request_file: false,
+            file: String::default(),
+            count: 10,
+            interval_ms: 100,
+        }
+    }
+}
+
+impl StressTestForm {
+    /// Build the stress test described by the form
+    ///
+    /// # Errors
+    /// If the client or the server is not selected, or the file path is empty
+    pub fn build(&self) -> Result<StressTest, String> {
+        let client_id = self.client_id.ok_or("Select a web client")?;
+        let server_id = self.server_id.ok_or("Select a server")?;
+        let request = if self.request_file {
+            if self.file.is_empty() {
+                return Err("Empty file path".to_string());
+            }
+            StressRequest::File(self.file.clone())
+        } else {
+            StressRequest::ListOfFiles
+        };
+        Ok(StressTest::new(
+            client_id,
+            server_id,
+            request,
+            self.count,
+            Duration::from_millis(self.interval_ms),
+        ))
+    }
+}
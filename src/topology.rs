@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use web_time::Instant;
+use wg_2024::network::NodeId;
+
+use crate::{stats::edge_key, utils::NodeKind};
+
+/// Snapshot of the topology of the network
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TopologySnapshot {
+    /// Drones in the network with their PDR
+    pub drones: BTreeMap<NodeId, f32>,
+    /// Edges of the network, keyed by the normalized edge
+    pub edges: BTreeSet<(NodeId, NodeId)>,
+}
+
+/// A change applied to the topology
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    DroneCrashed(NodeId),
+    DroneSpawned(NodeId),
+    EdgeAdded(NodeId, NodeId),
+    EdgeRemoved(NodeId, NodeId),
+    PdrChanged {
+        drone_id: NodeId,
+        from: f32,
+        to: f32,
+    },
+}
+
+impl Change {
+    /// Check if `other` changes the same drone or edge as `self`
+    fn same_subject(&self, other: &Change) -> bool {
+        match (self, other) {
+            (
+                Change::DroneCrashed(a) | Change::DroneSpawned(a),
+                Change::DroneCrashed(b) | Change::DroneSpawned(b),
+            ) => a == b,
+            (
+                Change::EdgeAdded(a1, a2) | Change::EdgeRemoved(a1, a2),
+                Change::EdgeAdded(b1, b2) | Change::EdgeRemoved(b1, b2),
+            ) => edge_key(*a1, *a2) == edge_key(*b1, *b2),
+            (Change::PdrChanged { drone_id: a, .. }, Change::PdrChanged { drone_id: b, .. }) => {
+                a == b
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::DroneCrashed(id) => write!(f, "Drone {id} crashed"),
+            Change::DroneSpawned(id) => write!(f, "Drone {id} spawned"),
+            Change::EdgeAdded(a, b) => write!(f, "Edge {a}-{b} added"),
+            Change::EdgeRemoved(a, b) => write!(f, "Edge {a}-{b} removed"),
+            Change::PdrChanged { drone_id, from, to } => {
+                write!(f, "PDR of drone {drone_id} changed from {from} to {to}")
+            }
+        }
+    }
+}
+
+/// An operation applied to the topology that can be undone, see `SimulationController::undo`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TopologyOperation {
+    AddEdge(NodeId, NodeId),
+    RemoveEdge(NodeId, NodeId),
+    /// The drone crashed, with what it takes to spawn it again: the index of its
+    /// implementation in `DRONE_FACTORY`, if known, and its neighbors at the crash
+    CrashDrone {
+        drone_id: NodeId,
+        pdr: f32,
+        implementation: Option<usize>,
+        neighbors: Vec<NodeId>,
+    },
+}
+
+impl fmt::Display for TopologyOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyOperation::AddEdge(a, b) => write!(f, "add edge {a}-{b}"),
+            TopologyOperation::RemoveEdge(a, b) => write!(f, "remove edge {a}-{b}"),
+            TopologyOperation::CrashDrone { drone_id, .. } => write!(f, "crash drone {drone_id}"),
+        }
+    }
+}
+
+/// An operation requested on the topology, applied through `SimulationController::apply_op`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TopologyOp {
+    Connect(NodeId, NodeId),
+    Disconnect(NodeId, NodeId),
+    Crash(NodeId),
+    /// Spawn a drone connected to `neighbors`, with a random implementation if
+    /// `implementation` is `None`
+    Spawn {
+        drone_id: NodeId,
+        implementation: Option<usize>,
+        pdr: f32,
+        neighbors: Vec<NodeId>,
+    },
+    SetPdr {
+        drone_id: NodeId,
+        pdr: f32,
+    },
+    /// Spawn a node of any kind connected to `neighbors`, built by the factory given
+    /// to `SimulationControllerBuilder`; drones get a random implementation and PDR 0
+    SpawnNode {
+        node_id: NodeId,
+        kind: NodeKind,
+        neighbors: Vec<NodeId>,
+    },
+}
+
+/// Compute the changes that turn the `initial` topology into the `current` one
+///
+/// Changes that cancel out (e.g. an edge removed and added back) do not appear.
+#[must_use]
+pub fn diff(initial: &TopologySnapshot, current: &TopologySnapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (id, pdr) in &initial.drones {
+        match current.drones.get(id) {
+            None => changes.push(Change::DroneCrashed(*id)),
+            Some(current_pdr) if (current_pdr - pdr).abs() > f32::EPSILON => {
+                changes.push(Change::PdrChanged {
+                    drone_id: *id,
+                    from: *pdr,
+                    to: *current_pdr,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    for id in current.drones.keys() {
+        if !initial.drones.contains_key(id) {
+            changes.push(Change::DroneSpawned(*id));
+        }
+    }
+    for (a, b) in initial.edges.difference(&current.edges) {
+        changes.push(Change::EdgeRemoved(*a, *b));
+    }
+    for (a, b) in current.edges.difference(&initial.edges) {
+        changes.push(Change::EdgeAdded(*a, *b));
+    }
+    changes
+}
+
+/// Log of the changes applied by the controller, with the time they were applied
+#[derive(Debug, Default)]
+pub struct OperationLog {
+    operations: Vec<(Instant, Change)>,
+}
+
+impl OperationLog {
+    /// Record a change applied now
+    pub fn record(&mut self, change: Change) {
+        self.operations.push((Instant::now(), change));
+    }
+
+    /// Time of the last operation on the same drone or edge as `change`
+    #[must_use]
+    pub fn last_time(&self, change: &Change) -> Option<Instant> {
+        self.operations
+            .iter()
+            .rev()
+            .find(|(_, op)| op.same_subject(change))
+            .map(|(time, _)| *time)
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{HashMap, VecDeque};
+
+use web_time::Instant;
+use wg_2024::{
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+/// Key of a trace: the session id and the fragment index of the traced fragment
+pub type TraceKey = (u64, u64);
+
+/// Path actually taken by a fragment, reconstructed from the `PacketSent` events
+#[derive(Clone, Debug)]
+pub struct Trace {
+    /// Route computed by the source, as found in the routing header
+    pub route: Vec<NodeId>,
+    /// Nodes that reported sending the fragment, in arrival order, with the
+    /// time the event was received
+    pub hops: Vec<(NodeId, Instant)>,
+}
+
+impl Trace {
+    /// Check if the nodes that forwarded the fragment follow the source route
+    #[must_use]
+    pub fn matches_route(&self) -> bool {
+        self.hops
+            .iter()
+            .zip(self.route.iter())
+            .all(|((hop, _), expected)| hop == expected)
+    }
+}
+
+/// Tracks the path of the fragments across the network
+///
+/// Events are correlated by `(session_id, fragment_index)` in arrival order.
+/// Only the last `capacity` traces are retained.
+pub struct PacketTracer {
+    traces: HashMap<TraceKey, Trace>,
+    order: VecDeque<TraceKey>,
+    capacity: usize,
+}
+
+impl PacketTracer {
+    /// Create a new tracer that retains at most `capacity` traces
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            traces: HashMap::new(),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record a fragment sent by the node with `node_id`
+    ///
+    /// Packets other than `MsgFragment` are ignored.
+    pub fn record(&mut self, node_id: NodeId, packet: &Packet) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
+        let key = (packet.session_id, fragment.fragment_index);
+        if !self.traces.contains_key(&key) {
+            if self.order.len() == self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.traces.remove(&oldest);
+                }
+            }
+            self.order.push_back(key);
+            self.traces.insert(
+                key,
+                Trace {
+                    route: packet.routing_header.hops.clone(),
+                    hops: Vec::new(),
+                },
+            );
+        }
+        if let Some(trace) = self.traces.get_mut(&key) {
+            trace.hops.push((node_id, Instant::now()));
+        }
+    }
+
+    /// Get a trace given its key
+    #[must_use]
+    pub fn get(&self, key: TraceKey) -> Option<&Trace> {
+        self.traces.get(&key)
+    }
+
+    /// Get the retained traces, the most recent first
+    #[must_use]
+    pub fn traces(&self) -> Vec<(TraceKey, &Trace)> {
+        self.order
+            .iter()
+            .rev()
+            .filter_map(|key| self.traces.get(key).map(|trace| (*key, trace)))
+            .collect()
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Duration,
+};
+
+use web_time::Instant;
+use wg_2024::packet::{Packet, PacketType};
+
+/// Delivery status of a single session
+///
+/// The session is identified by the `session_id` of the `MsgFragment` packets.
+/// The sets contain the indices of the fragments seen in the events received
+/// by the controller.
+#[derive(Clone, Debug)]
+pub struct SessionStatus {
+    /// Total number of fragments of the message, as declared by the fragments
+    pub total_fragments: u64,
+    /// Indices of the fragments seen sent
+    pub sent: BTreeSet<u64>,
+    /// Indices of the fragments seen dropped
+    pub dropped: BTreeSet<u64>,
+    /// Indices of the fragments acknowledged
+    pub acked: BTreeSet<u64>,
+    /// Indices of the fragments that received a `Nack`
+    pub nacked: BTreeSet<u64>,
+    /// Reasons of the received `Nack`s with the number of occurrences
+    pub nack_reasons: BTreeMap<String, usize>,
+    /// Last time a packet of the session was observed
+    last_activity: Instant,
+}
+
+impl SessionStatus {
+    fn new() -> Self {
+        Self {
+            total_fragments: 0,
+            sent: BTreeSet::new(),
+            dropped: BTreeSet::new(),
+            acked: BTreeSet::new(),
+            nacked: BTreeSet::new(),
+            nack_reasons: BTreeMap::new(),
+            last_activity: Instant::now(),
+        }
+    }
+
+    /// Number of fragments not acknowledged yet
+    #[must_use]
+    pub fn outstanding(&self) -> u64 {
+        self.total_fragments.saturating_sub(self.acked.len() as u64)
+    }
+
+    /// A session is complete when every fragment has been acknowledged
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.total_fragments > 0 && self.outstanding() == 0
+    }
+}
+
+/// Tracks the delivery status of the sessions observed in the events
+///
+/// Sessions are forgotten after `timeout` of inactivity to bound memory.
+pub struct TransferTracker {
+    sessions: HashMap<u64, SessionStatus>,
+    timeout: Duration,
+}
+
+impl TransferTracker {
+    /// Create a new tracker that forgets sessions inactive for more than `timeout`
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Record a packet reported as sent by a node
+    ///
+    /// Fragments are marked as sent, while `Ack`s and `Nack`s update the
+    /// status of the fragment they refer to.
+    pub fn record_sent(&mut self, packet: &Packet) {
+        match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                let session = self.session_mut(packet.session_id);
+                session.total_fragments = fragment.total_n_fragments;
+                session.sent.insert(fragment.fragment_index);
+            }
+            PacketType::Ack(ack) => {
+                let session = self.session_mut(packet.session_id);
+                session.acked.insert(ack.fragment_index);
+            }
+            PacketType::Nack(nack) => {
+                let session = self.session_mut(packet.session_id);
+                session.nacked.insert(nack.fragment_index);
+                *session
+                    .nack_reasons
+                    .entry(format!("{:?}", nack.nack_type))
+                    .or_default() += 1;
+            }
+            PacketType::FloodRequest(_) | PacketType::FloodResponse(_) => {}
+        }
+    }
+
+    /// Record a packet reported as dropped by a drone
+    pub fn record_dropped(&mut self, packet: &Packet) {
+        if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+            let session = self.session_mut(packet.session_id);
+            session.total_fragments = fragment.total_n_fragments;
+            session.dropped.insert(fragment.fragment_index);
+        }
+    }
+
+    /// Remove the sessions inactive for more than the configured timeout
+    pub fn expire(&mut self) {
+        let timeout = self.timeout;
+        self.sessions
+            .retain(|_, session| session.last_activity.elapsed() < timeout);
+    }
+
+    /// Get the tracked sessions, sorted by session id
+    #[must_use]
+    pub fn sessions(&self) -> Vec<(u64, &SessionStatus)> {
+        let mut sessions = self
+            .sessions
+            .iter()
+            .map(|(id, session)| (*id, session))
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|(id, _)| *id);
+        sessions
+    }
+
+    fn session_mut(&mut self, session_id: u64) -> &mut SessionStatus {
+        let session = self
+            .sessions
+            .entry(session_id)
+            .or_insert_with(SessionStatus::new);
+        session.last_activity = Instant::now();
+        session
+    }
+}
@@ -1,28 +1,392 @@
-#![allow(clippy::len_without_is_empty)]
-use std::collections::VecDeque;
+use std::{collections::VecDeque, time::Duration};
+
+use crossbeam_channel::{Receiver, Sender};
+use egui::{Color32, RichText};
+use web_time::Instant;
+use wg_2024::network::NodeId;
+
+/// Severity of an entry of the event log
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Routine events, e.g. the packets sent by the nodes
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    /// Name of the level, as used in the streamed events
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+/// Kind of node that reported an event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Drone,
+    WebClient,
+    ChatClient,
+    Server,
+}
+
+impl NodeKind {
+    /// Name of the kind, as used in the streamed events
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            NodeKind::Drone => "drone",
+            NodeKind::WebClient => "web_client",
+            NodeKind::ChatClient => "chat_client",
+            NodeKind::Server => "server",
+        }
+    }
+}
+
+/// What an entry of the event log is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// A packet sent by a node
+    Packet,
+    /// A packet dropped by a drone
+    PacketDropped,
+    /// A packet injected into a node by the controller
+    PacketInjected,
+    /// A shortcut requested by a node or delivered by the controller
+    Shortcut,
+    /// An action of the controller
+    Controller,
+    /// A node that stopped responding
+    Unresponsive,
+    /// A request of a client that its servers don't support
+    UnsupportedRequest,
+}
+
+impl EventKind {
+    /// Name of the kind, as used in the streamed events
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Packet => "packet",
+            EventKind::PacketDropped => "packet_dropped",
+            EventKind::PacketInjected => "packet_injected",
+            EventKind::Shortcut => "shortcut",
+            EventKind::Controller => "controller",
+            EventKind::Unresponsive => "unresponsive",
+            EventKind::UnsupportedRequest => "unsupported_request",
+        }
+    }
+}
+
+/// Category of the entries of the event log, each can be hidden from the log
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogCategory {
+    /// Packets sent by the drones
+    Sent,
+    /// Packets dropped by the drones
+    Dropped,
+    Shortcut,
+    /// Packets sent by the clients
+    Client,
+    /// Packets sent by the servers
+    Server,
+    /// Actions of the controller and unresponsive nodes
+    Controller,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 6] = [
+        LogCategory::Sent,
+        LogCategory::Dropped,
+        LogCategory::Shortcut,
+        LogCategory::Client,
+        LogCategory::Server,
+        LogCategory::Controller,
+    ];
+
+    /// Name of the category, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            LogCategory::Sent => "Sent",
+            LogCategory::Dropped => "Dropped",
+            LogCategory::Shortcut => "Shortcut",
+            LogCategory::Client => "Client",
+            LogCategory::Server => "Server",
+            LogCategory::Controller => "Controller",
+        }
+    }
+}
+
+/// An entry of the event log
+///
+/// The entry only holds data: its color is chosen when it's rendered.
+#[derive(Clone, Debug)]
+pub struct LogEvent {
+    /// Time the event was logged
+    pub time: Instant,
+    pub kind: EventKind,
+    /// Session id of the packet the event refers to, if any
+    pub session_id: Option<u64>,
+    /// Node that reported the event, if any
+    pub source: Option<NodeId>,
+    /// Kind of the node that reported the event, if any
+    pub node_kind: Option<NodeKind>,
+    /// Type of the packet the event refers to, if any
+    pub packet_type: Option<String>,
+    /// Destination of the shortcut the event refers to, if any
+    pub destination: Option<NodeId>,
+    /// Severity of the event, the info events of muted nodes are hidden
+    pub level: LogLevel,
+    /// Text shown in the log
+    pub text: String,
+}
+
+impl Timestamped for LogEvent {
+    fn time(&self) -> Instant {
+        self.time
+    }
+}
+
+impl Severity for LogEvent {
+    fn level(&self) -> LogLevel {
+        self.level
+    }
+}
+
+impl Counted for LogEvent {
+    fn count(&self, stats: &mut EventStats) {
+        match self.kind {
+            EventKind::Packet => stats.packets_sent += 1,
+            EventKind::PacketDropped => stats.packets_dropped += 1,
+            EventKind::Shortcut => stats.shortcuts += 1,
+            EventKind::UnsupportedRequest => stats.unsupported_requests += 1,
+            EventKind::PacketInjected | EventKind::Controller | EventKind::Unresponsive => {}
+        }
+    }
+}
+
+impl LogEvent {
+    /// Nodes the event refers to, highlighted when the entry is hovered
+    #[must_use]
+    pub fn nodes(&self) -> Vec<NodeId> {
+        self.source.into_iter().chain(self.destination).collect()
+    }
+
+    /// Category of the entry, used to filter the log
+    #[must_use]
+    pub fn category(&self) -> LogCategory {
+        match (self.kind, self.node_kind) {
+            (EventKind::Shortcut, _) => LogCategory::Shortcut,
+            (EventKind::PacketDropped, _) => LogCategory::Dropped,
+            (EventKind::PacketInjected | EventKind::Controller | EventKind::Unresponsive, _) => {
+                LogCategory::Controller
+            }
+            (EventKind::Packet, Some(NodeKind::WebClient | NodeKind::ChatClient))
+            | (EventKind::UnsupportedRequest, _) => LogCategory::Client,
+            (EventKind::Packet, Some(NodeKind::Server)) => LogCategory::Server,
+            (EventKind::Packet, _) => LogCategory::Sent,
+        }
+    }
+
+    /// Color of the entry: errors and dropped packets in red, shortcuts and
+    /// other warnings in orange
+    #[must_use]
+    pub fn color(&self) -> Option<Color32> {
+        match (self.level, self.kind) {
+            (LogLevel::Error, _) | (_, EventKind::PacketDropped) => Some(Color32::RED),
+            (LogLevel::Warn, _) | (LogLevel::Info, EventKind::Shortcut) => Some(Color32::ORANGE),
+            (LogLevel::Debug | LogLevel::Info, _) => None,
+        }
+    }
+
+    /// Text of the entry as shown in the log
+    #[must_use]
+    pub fn rich_text(&self) -> RichText {
+        let text = RichText::new(&self.text);
+        match self.color() {
+            Some(color) => text.color(color),
+            None => text,
+        }
+    }
+}
+
+/// An entry that records the time it was created
+pub trait Timestamped {
+    fn time(&self) -> Instant;
+}
+
+/// An event stamped with the time it was pushed, see `EventQueue::push_timed`
+///
+/// `LogEvent` records its own time, the wrapper gives one to payloads that
+/// don't, e.g. plain text.
+#[derive(Clone, Debug)]
+pub struct TimestampedEvent<T> {
+    pub timestamp: Instant,
+    pub payload: T,
+}
+
+impl<T> Timestamped for TimestampedEvent<T> {
+    fn time(&self) -> Instant {
+        self.timestamp
+    }
+}
+
+/// Queue of text entries stamped with the time they were pushed
+pub type TimedEventQueue = EventQueue<TimestampedEvent<RichText>>;
+
+/// An entry that can be an error, kept longer by `OverflowPolicy::KeepErrors`
+pub trait Severity {
+    fn level(&self) -> LogLevel;
+
+    fn is_error(&self) -> bool {
+        self.level() == LogLevel::Error
+    }
+}
+
+/// An entry counted in the `EventStats` of the queue
+pub trait Counted {
+    /// Increment the counters the entry contributes to
+    fn count(&self, stats: &mut EventStats);
+}
+
+/// Number of events of each category pushed to an `EventQueue`
+///
+/// The counters are kept when the events are discarded or cleared, so they
+/// cover the whole session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventStats {
+    pub packets_sent: usize,
+    pub packets_dropped: usize,
+    pub shortcuts: usize,
+    pub unsupported_requests: usize,
+}
+
+/// What the queue does when an event is pushed while it's full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Remove the oldest event, the right choice for a live log
+    DropOldest,
+    /// Reject the new event, keeping the first `capacity` events
+    DropNewest,
+    /// Grow past the capacity up to `max` events, then remove the oldest
+    Grow { max: usize },
+    /// Remove the oldest event that isn't an error, or the oldest error if all of them are
+    KeepErrors,
+}
+
+impl OverflowPolicy {
+    /// Name of the policy, as shown in the UI
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "Drop oldest",
+            OverflowPolicy::DropNewest => "Drop newest",
+            OverflowPolicy::Grow { .. } => "Grow",
+            OverflowPolicy::KeepErrors => "Keep errors",
+        }
+    }
+}
+
+/// Content of an `EventQueue` at some point, see `EventQueue::snapshot`
+#[derive(Clone, Debug)]
+pub struct EventQueueSnapshot<T>(VecDeque<T>);
 
 /// A simple event queue that stores the last `capacity` events.
+///
+/// The queue is owned by the UI thread, other threads push their events through
+/// the senders returned by `producer`.
 pub struct EventQueue<T> {
     queue: VecDeque<T>,
     capacity: usize,
+    policy: OverflowPolicy,
+    /// Number of events removed by the capacity or age limits
+    discarded: usize,
+    /// Events below this level are not added to the queue
+    min_level: LogLevel,
+    /// Counters of all the events pushed, including the ones below `min_level`
+    stats: EventStats,
+    /// Channel of the events pushed from other threads, moved into the queue by `drain_producers`
+    incoming: (Sender<T>, Receiver<T>),
 }
 
 impl<T> EventQueue<T> {
     /// Create a new event queue with the given capacity.
+    ///
+    /// A capacity of 0 is raised to 1, so that the queue always keeps the newest event.
     #[must_use]
     pub fn new(capacity: usize) -> Self {
+        Self::with_policy(capacity, OverflowPolicy::DropOldest)
+    }
+
+    /// Create a new event queue with the given capacity and overflow policy.
+    #[must_use]
+    pub fn with_policy(capacity: usize, policy: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
         EventQueue {
             queue: VecDeque::with_capacity(capacity),
             capacity,
+            policy,
+            discarded: 0,
+            min_level: LogLevel::Debug,
+            stats: EventStats::default(),
+            incoming: crossbeam_channel::unbounded(),
         }
     }
 
-    /// Push a new event to the queue. If the queue is full, the oldest event will be removed.
-    pub fn push(&mut self, event: T) {
-        if self.queue.len() == self.capacity {
-            self.queue.pop_front();
+    /// Get a handle to push events from another thread.
+    ///
+    /// The channel is unbounded, so pushing never blocks the producer. The events
+    /// show up in the queue after the next `drain_producers`.
+    #[must_use]
+    pub fn producer(&self) -> Sender<T> {
+        self.incoming.0.clone()
+    }
+
+    /// Get the capacity of the queue, after raising a capacity of 0 to 1.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[must_use]
+    pub fn min_level(&self) -> LogLevel {
+        self.min_level
+    }
+
+    /// Set the minimum level of the events added to the queue.
+    ///
+    /// The events below the level are dropped when they are pushed, without being
+    /// counted as discarded; the events already in the queue are kept.
+    pub fn set_min_level(&mut self, level: LogLevel) {
+        self.min_level = level;
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Max number of events the queue holds with its policy
+    fn limit(&self) -> usize {
+        match self.policy {
+            OverflowPolicy::Grow { max } => max.max(self.capacity),
+            _ => self.capacity,
         }
-        self.queue.push_back(event);
     }
 
     /// Get all events in the queue.
@@ -36,6 +400,230 @@ impl<T> EventQueue<T> {
     pub fn len(&self) -> usize {
         self.queue.len()
     }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Take all the events out of the queue, oldest first, leaving it empty.
+    ///
+    /// The events are not counted as discarded.
+    pub fn drain(&mut self) -> Vec<T> {
+        self.queue.drain(..).collect()
+    }
+
+    /// Replace the events with the ones of `snapshot`, e.g. to roll back the history.
+    ///
+    /// If the snapshot holds more events than the queue can hold now, only the
+    /// newest ones are kept.
+    pub fn restore(&mut self, snapshot: EventQueueSnapshot<T>) {
+        self.queue = snapshot.0;
+        let excess = self.queue.len().saturating_sub(self.limit());
+        self.queue.drain(..excess);
+    }
+
+    /// Push `event` at `level`, for payloads without a level of their own.
+    ///
+    /// The event is dropped silently if it's below the minimum level. It's not
+    /// counted in the stats, and when the queue is full it's handled by the
+    /// policy as an event that isn't an error.
+    pub fn push_with_level(&mut self, level: LogLevel, event: T) {
+        if level >= self.min_level {
+            self.push_unleveled(event);
+        }
+    }
+
+    /// Helper function to push an event without level, see `push_with_level`
+    fn push_unleveled(&mut self, event: T) {
+        if self.queue.len() >= self.limit() {
+            self.discarded += 1;
+            if self.policy == OverflowPolicy::DropNewest {
+                return;
+            }
+            self.queue.pop_front();
+        }
+        self.queue.push_back(event);
+    }
+
+    /// Remove all the events from the queue, without counting them as discarded.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Get the number of events discarded so far, either because the queue was full or
+    /// because they were too old.
+    #[must_use]
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// Get the counters of the events pushed so far, by category.
+    #[must_use]
+    pub fn stats(&self) -> &EventStats {
+        &self.stats
+    }
+}
+
+impl<T: Clone> EventQueue<T> {
+    /// Save the events currently in the queue, to be restored with `restore`.
+    #[must_use]
+    pub fn snapshot(&self) -> EventQueueSnapshot<T> {
+        EventQueueSnapshot(self.queue.clone())
+    }
+}
+
+impl<T: Severity + Counted> EventQueue<T> {
+    /// Move the events pushed through the producers into the queue, in the order they were sent.
+    pub fn drain_producers(&mut self) {
+        while let Ok(event) = self.incoming.1.try_recv() {
+            self.push(event);
+        }
+    }
+
+    /// Push a new event to the queue, unless it's below the minimum level. If the
+    /// queue is full, an event is discarded according to the overflow policy.
+    ///
+    /// The event is counted in the stats in any case.
+    pub fn push(&mut self, event: T) {
+        event.count(&mut self.stats);
+        if event.level() < self.min_level {
+            return;
+        }
+        if self.queue.len() >= self.limit() {
+            if self.policy == OverflowPolicy::DropNewest {
+                self.discarded += 1;
+                return;
+            }
+            self.evict();
+        }
+        self.queue.push_back(event);
+    }
+
+    /// Count an event discarded before reaching the queue, e.g. by a full buffer
+    /// of the paused log, in the stats and as discarded
+    pub fn discard(&mut self, event: &T) {
+        event.count(&mut self.stats);
+        self.discarded += 1;
+    }
+
+    /// Change the capacity, discarding the events past the new limit following the policy.
+    ///
+    /// A capacity of 0 is raised to 1, as in `new`.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.queue.len() > self.limit() {
+            self.evict();
+        }
+    }
+
+    /// Change the capacity, keeping the events that still fit.
+    ///
+    /// If the queue holds more events than the new limit, they're evicted
+    /// following the policy, as in `set_capacity`.
+    pub fn resize_capacity(&mut self, new_capacity: usize) {
+        self.set_capacity(new_capacity);
+    }
+
+    /// Change the overflow policy, discarding the events past the new limit
+    pub fn set_policy(&mut self, policy: OverflowPolicy) {
+        self.policy = policy;
+        while self.queue.len() > self.limit() {
+            self.evict();
+        }
+    }
+
+    /// Helper function to remove an event to make room, following the policy
+    fn evict(&mut self) {
+        let position = match self.policy {
+            OverflowPolicy::KeepErrors => self
+                .queue
+                .iter()
+                .position(|event| !event.is_error())
+                .unwrap_or(0),
+            // `DropNewest` rejects the new event instead, but when the policy
+            // changes the queue is trimmed from the oldest
+            _ => 0,
+        };
+        if self.queue.remove(position).is_some() {
+            self.discarded += 1;
+        }
+    }
+}
+
+impl<T> EventQueue<TimestampedEvent<T>> {
+    /// Push `event` stamped with the current time.
+    ///
+    /// The payload has no level nor category, so it's never filtered by the
+    /// minimum level nor counted in the stats; when the queue is full it's
+    /// handled by the policy as an event that isn't an error.
+    pub fn push_timed(&mut self, event: T) {
+        self.push_unleveled(TimestampedEvent {
+            timestamp: Instant::now(),
+            payload: event,
+        });
+    }
+}
+
+impl<T: Timestamped> EventQueue<T> {
+    /// Remove the events older than `max_age`, returns the number of removed events.
+    ///
+    /// Events are pushed in chronological order, so only the front of the queue is checked.
+    pub fn prune_older_than(&mut self, max_age: Duration) -> usize {
+        let mut pruned = 0;
+        while self
+            .queue
+            .front()
+            .is_some_and(|event| event.time().elapsed() > max_age)
+        {
+            self.queue.pop_front();
+            pruned += 1;
+        }
+        self.discarded += pruned;
+        pruned
+    }
+
+    /// Get the events that occurred at `cutoff` or later, oldest first.
+    #[must_use]
+    pub fn get_since(&self, cutoff: Instant) -> Vec<&T> {
+        self.queue
+            .iter()
+            .filter(|event| event.time() >= cutoff)
+            .collect()
+    }
+}
+
+/// Format a duration as `HH:MM:SS`, e.g. the uptime of the simulation
+#[must_use]
+pub fn format_hms(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Format the time elapsed since an event, e.g. `12s ago` or `3m ago`
+#[must_use]
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{secs}s ago")
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else {
+        format!("{}h ago", secs / 3600)
+    }
+}
+
+/// Format a count in a compact form, e.g. `950` or `1.2k`
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_count(count: usize) -> String {
+    if count < 1000 {
+        count.to_string()
+    } else if count < 1_000_000 {
+        format!("{:.1}k", count as f64 / 1000.0)
+    } else {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    }
 }
 
 #[macro_export]
@@ -59,3 +647,462 @@ macro_rules! create_boxed_drone {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: EventKind, level: LogLevel) -> LogEvent {
+        LogEvent {
+            time: Instant::now(),
+            kind,
+            session_id: None,
+            source: Some(1),
+            node_kind: Some(NodeKind::Drone),
+            packet_type: None,
+            destination: None,
+            level,
+            text: format!("{} {}", kind.name(), level.name()),
+        }
+    }
+
+    #[test]
+    fn stats_survive_discarded_events() {
+        let mut queue = EventQueue::new(3);
+        for _ in 0..5 {
+            queue.push(event(EventKind::Packet, LogLevel::Debug));
+            queue.push(event(EventKind::PacketDropped, LogLevel::Warn));
+        }
+        queue.push(event(EventKind::Shortcut, LogLevel::Info));
+        queue.push(event(EventKind::UnsupportedRequest, LogLevel::Warn));
+        queue.push(event(EventKind::Controller, LogLevel::Error));
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.discarded(), 10);
+        let stats = queue.stats();
+        assert_eq!(stats.packets_sent, 5);
+        assert_eq!(stats.packets_dropped, 5);
+        assert_eq!(stats.shortcuts, 1);
+        assert_eq!(stats.unsupported_requests, 1);
+    }
+
+    #[test]
+    fn events_discarded_before_the_queue_are_counted() {
+        let mut queue = EventQueue::new(2);
+        queue.discard(&event(EventKind::PacketDropped, LogLevel::Warn));
+        queue.discard(&event(EventKind::Packet, LogLevel::Debug));
+
+        assert!(queue.is_empty());
+        assert_eq!(queue.discarded(), 2);
+        assert_eq!(queue.stats().packets_dropped, 1);
+        assert_eq!(queue.stats().packets_sent, 1);
+    }
+
+    /// Texts of the events in the queue, oldest first
+    fn texts(queue: &EventQueue<LogEvent>) -> Vec<&str> {
+        queue.get().into_iter().map(|e| e.text.as_str()).collect()
+    }
+
+    /// A queue holding the events `0` to `count - 1`, oldest first
+    fn numbered_queue(capacity: usize, count: usize) -> EventQueue<LogEvent> {
+        let mut queue = EventQueue::new(capacity);
+        for i in 0..count {
+            let mut event = event(EventKind::Controller, LogLevel::Info);
+            event.text = i.to_string();
+            queue.push(event);
+        }
+        queue
+    }
+
+    #[test]
+    fn a_queue_created_without_capacity_keeps_the_newest_event() {
+        let queue = numbered_queue(0, 3);
+
+        assert_eq!(queue.capacity(), 1);
+        assert_eq!(texts(&queue), ["2"]);
+        assert_eq!(queue.discarded(), 2);
+    }
+
+    #[test]
+    fn a_queue_of_one_keeps_the_newest_event() {
+        let mut queue = numbered_queue(1, 1);
+        assert_eq!(texts(&queue), ["0"]);
+
+        queue.push(event(EventKind::Packet, LogLevel::Debug));
+
+        assert_eq!(texts(&queue), ["packet debug"]);
+        assert_eq!(queue.discarded(), 1);
+    }
+
+    #[test]
+    fn a_full_queue_evicts_the_first_events() {
+        let queue = numbered_queue(5, 7);
+
+        assert_eq!(queue.len(), 5);
+        assert_eq!(texts(&queue), ["2", "3", "4", "5", "6"]);
+        assert_eq!(queue.discarded(), 2);
+    }
+
+    #[test]
+    fn growing_the_capacity_keeps_the_events() {
+        let mut queue = numbered_queue(3, 3);
+
+        queue.resize_capacity(10);
+        for i in 3..6 {
+            let mut event = event(EventKind::Controller, LogLevel::Info);
+            event.text = i.to_string();
+            queue.push(event);
+        }
+
+        assert_eq!(queue.capacity(), 10);
+        assert_eq!(texts(&queue), ["0", "1", "2", "3", "4", "5"]);
+        assert_eq!(queue.discarded(), 0);
+    }
+
+    #[test]
+    fn shrinking_the_capacity_evicts_the_oldest_events() {
+        let mut queue = numbered_queue(10, 6);
+
+        queue.resize_capacity(2);
+
+        assert_eq!(texts(&queue), ["4", "5"]);
+        assert_eq!(queue.discarded(), 4);
+    }
+
+    #[test]
+    fn shrinking_a_keep_errors_queue_evicts_the_non_errors_first() {
+        let mut queue = EventQueue::with_policy(10, OverflowPolicy::KeepErrors);
+        queue.push(event(EventKind::Controller, LogLevel::Error));
+        queue.push(event(EventKind::Packet, LogLevel::Info));
+        queue.push(event(EventKind::Unresponsive, LogLevel::Error));
+        queue.push(event(EventKind::Packet, LogLevel::Debug));
+
+        queue.resize_capacity(2);
+
+        assert_eq!(texts(&queue), ["controller error", "unresponsive error"]);
+        assert_eq!(queue.discarded(), 2);
+
+        queue.resize_capacity(1);
+
+        assert_eq!(texts(&queue), ["unresponsive error"]);
+        assert_eq!(queue.discarded(), 3);
+    }
+
+    #[test]
+    fn set_capacity_grows_the_queue_without_discarding() {
+        let mut queue = numbered_queue(2, 2);
+
+        queue.set_capacity(4);
+        push_numbered(&mut queue, 2, &[]);
+
+        assert_eq!(queue.capacity(), 4);
+        assert_eq!(texts(&queue), ["0", "1", "0", "1"]);
+        assert_eq!(queue.discarded(), 0);
+    }
+
+    #[test]
+    fn set_capacity_shrinks_the_queue_counting_the_evicted_events() {
+        let mut queue = numbered_queue(10, 5);
+
+        queue.set_capacity(3);
+
+        assert_eq!(queue.capacity(), 3);
+        assert_eq!(texts(&queue), ["2", "3", "4"]);
+        assert_eq!(queue.discarded(), 2);
+        queue.set_capacity(0);
+        assert_eq!(texts(&queue), ["4"]);
+        assert_eq!(queue.discarded(), 4);
+    }
+
+    #[test]
+    fn a_capacity_of_zero_is_raised_to_one() {
+        let mut queue = numbered_queue(5, 5);
+
+        queue.resize_capacity(0);
+
+        assert_eq!(queue.capacity(), 1);
+        assert_eq!(texts(&queue), ["4"]);
+    }
+
+    #[test]
+    fn only_evicted_events_are_counted_as_discarded() {
+        let mut queue = numbered_queue(4, 4);
+        assert_eq!(queue.discarded(), 0);
+
+        queue.drain();
+        push_numbered(&mut queue, 3, &[]);
+        queue.clear();
+        assert_eq!(queue.discarded(), 0);
+
+        push_numbered(&mut queue, 6, &[]);
+        assert_eq!(queue.discarded(), 2);
+        queue.resize_capacity(3);
+        assert_eq!(queue.discarded(), 3);
+    }
+
+    #[test]
+    fn drain_takes_every_event_in_order() {
+        let mut queue = numbered_queue(10, 5);
+
+        let drained = queue.drain();
+
+        let drained = drained.iter().map(|e| e.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(drained, ["0", "1", "2", "3", "4"]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.len(), 0);
+        assert!(queue.drain().is_empty());
+        assert_eq!(queue.discarded(), 0);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_discards_the_later_events() {
+        let mut queue = numbered_queue(10, 3);
+        let snapshot = queue.snapshot();
+
+        push_numbered(&mut queue, 2, &[]);
+        assert_eq!(queue.len(), 5);
+        queue.restore(snapshot.clone());
+        assert_eq!(texts(&queue), ["0", "1", "2"]);
+
+        // The snapshot can be restored again after more changes
+        queue.drain();
+        queue.restore(snapshot);
+        assert_eq!(texts(&queue), ["0", "1", "2"]);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_keeps_the_newest_events_that_fit() {
+        let mut queue = numbered_queue(10, 6);
+        let snapshot = queue.snapshot();
+
+        queue.resize_capacity(4);
+        queue.restore(snapshot);
+
+        assert_eq!(texts(&queue), ["2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn timed_events_are_kept_in_push_order() {
+        let mut queue: TimedEventQueue = EventQueue::new(2);
+        let before = Instant::now();
+        for text in ["first", "second", "third"] {
+            queue.push_timed(RichText::new(text));
+        }
+
+        let events = queue.get();
+        let texts = events.iter().map(|e| e.payload.text()).collect::<Vec<_>>();
+        assert_eq!(texts, ["second", "third"]);
+        assert!(events[0].timestamp >= before);
+        assert!(events[0].timestamp <= events[1].timestamp);
+        assert_eq!(queue.discarded(), 1);
+    }
+
+    #[test]
+    fn get_since_keeps_the_events_from_the_cutoff() {
+        let mut queue = EventQueue::new(10);
+        queue.push_timed("old");
+        std::thread::sleep(Duration::from_millis(5));
+        let cutoff = Instant::now();
+        queue.push_timed("new");
+        queue.push_timed("newer");
+
+        let recent = queue
+            .get_since(cutoff)
+            .into_iter()
+            .map(|e| e.payload)
+            .collect::<Vec<_>>();
+        assert_eq!(recent, ["new", "newer"]);
+        let all = queue.get_since(queue.get()[0].timestamp);
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn events_below_the_minimum_level_are_rejected() {
+        let mut queue = EventQueue::new(10);
+        queue.set_min_level(LogLevel::Warn);
+
+        queue.push_with_level(LogLevel::Debug, "debug");
+        queue.push_with_level(LogLevel::Info, "info");
+        queue.push_with_level(LogLevel::Warn, "warn");
+        queue.push_with_level(LogLevel::Error, "error");
+
+        assert_eq!(queue.get(), [&"warn", &"error"]);
+        assert_eq!(queue.discarded(), 0);
+    }
+
+    #[test]
+    fn log_events_below_the_minimum_level_are_only_counted() {
+        let mut queue = EventQueue::new(10);
+        queue.set_min_level(LogLevel::Warn);
+
+        queue.push(event(EventKind::Packet, LogLevel::Info));
+        queue.push(event(EventKind::PacketDropped, LogLevel::Warn));
+        queue.push(event(EventKind::Controller, LogLevel::Error));
+
+        assert_eq!(texts(&queue), ["packet_dropped warn", "controller error"]);
+        assert_eq!(queue.stats().packets_sent, 1);
+    }
+
+    #[test]
+    fn producers_never_wait_for_the_queue() {
+        let mut queue = EventQueue::new(10);
+        let producers = (0..4)
+            .map(|_| {
+                let producer = queue.producer();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        producer
+                            .send(event(EventKind::Packet, LogLevel::Debug))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // The producers finish before the queue is drained even once
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        queue.drain_producers();
+
+        assert_eq!(queue.stats().packets_sent, 4000);
+        assert_eq!(queue.len(), 10);
+        assert_eq!(queue.discarded(), 3990);
+    }
+
+    #[test]
+    fn the_queue_is_read_while_producers_push() {
+        let mut queue = EventQueue::new(100_000);
+        let producers = (1..=4)
+            .map(|source| {
+                let producer = queue.producer();
+                std::thread::spawn(move || {
+                    for i in 0..2000 {
+                        let mut event = event(EventKind::Packet, LogLevel::Debug);
+                        event.source = Some(source);
+                        event.text = i.to_string();
+                        producer.send(event).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let sent_by = |queue: &EventQueue<LogEvent>, source| {
+            queue
+                .get()
+                .into_iter()
+                .filter(|e| e.source == Some(source))
+                .map(|e| e.text.parse::<usize>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        while producers.iter().any(|producer| !producer.is_finished()) {
+            queue.drain_producers();
+            // Reading between the drains sees a consistent prefix of each producer
+            for source in 1..=4 {
+                let texts = sent_by(&queue, source);
+                assert_eq!(texts, (0..texts.len()).collect::<Vec<_>>());
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        queue.drain_producers();
+
+        assert_eq!(queue.len(), 8000);
+        // The events of each producer keep the order they were sent in
+        for source in 1..=4 {
+            assert_eq!(sent_by(&queue, source), (0..2000).collect::<Vec<_>>());
+        }
+    }
+
+    /// Push the events `0` to `count - 1` to `queue`, the ones in `errors` as errors
+    fn push_numbered(queue: &mut EventQueue<LogEvent>, count: usize, errors: &[usize]) {
+        for i in 0..count {
+            let level = if errors.contains(&i) {
+                LogLevel::Error
+            } else {
+                LogLevel::Info
+            };
+            let mut event = event(EventKind::Controller, level);
+            event.text = i.to_string();
+            queue.push(event);
+        }
+    }
+
+    #[test]
+    fn drop_oldest_evicts_from_the_front() {
+        let mut queue = EventQueue::with_policy(3, OverflowPolicy::DropOldest);
+        push_numbered(&mut queue, 5, &[0]);
+
+        assert_eq!(texts(&queue), ["2", "3", "4"]);
+        assert_eq!(queue.discarded(), 2);
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_first_events() {
+        let mut queue = EventQueue::with_policy(3, OverflowPolicy::DropNewest);
+        push_numbered(&mut queue, 5, &[]);
+
+        assert_eq!(texts(&queue), ["0", "1", "2"]);
+        assert_eq!(queue.discarded(), 2);
+    }
+
+    #[test]
+    fn grow_evicts_only_past_the_max() {
+        let mut queue = EventQueue::with_policy(2, OverflowPolicy::Grow { max: 4 });
+        push_numbered(&mut queue, 4, &[]);
+        assert_eq!(texts(&queue), ["0", "1", "2", "3"]);
+        assert_eq!(queue.discarded(), 0);
+
+        push_numbered(&mut queue, 1, &[]);
+        assert_eq!(texts(&queue), ["1", "2", "3", "0"]);
+        assert_eq!(queue.discarded(), 1);
+    }
+
+    #[test]
+    fn keep_errors_evicts_the_oldest_event_that_isnt_an_error() {
+        let mut queue = EventQueue::with_policy(3, OverflowPolicy::KeepErrors);
+        push_numbered(&mut queue, 5, &[0, 2]);
+        assert_eq!(texts(&queue), ["0", "2", "4"]);
+
+        // With only errors left, the oldest error goes
+        let mut queue = EventQueue::with_policy(2, OverflowPolicy::KeepErrors);
+        push_numbered(&mut queue, 3, &[0, 1, 2]);
+        assert_eq!(texts(&queue), ["1", "2"]);
+        assert_eq!(queue.discarded(), 1);
+    }
+
+    #[test]
+    fn changing_the_policy_trims_the_queue() {
+        let mut queue = EventQueue::with_policy(2, OverflowPolicy::Grow { max: 5 });
+        push_numbered(&mut queue, 5, &[1]);
+
+        queue.set_policy(OverflowPolicy::KeepErrors);
+
+        assert_eq!(texts(&queue), ["1", "4"]);
+        assert_eq!(queue.discarded(), 3);
+    }
+
+    #[test]
+    fn dropped_packets_are_counted_at_any_level() {
+        let mut queue = EventQueue::new(10);
+        queue.push(event(EventKind::PacketDropped, LogLevel::Info));
+        queue.push(event(EventKind::PacketInjected, LogLevel::Info));
+        queue.push(event(EventKind::Packet, LogLevel::Warn));
+
+        assert_eq!(queue.stats().packets_dropped, 1);
+        assert_eq!(queue.stats().packets_sent, 1);
+        let categories = queue
+            .get()
+            .into_iter()
+            .map(LogEvent::category)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            categories,
+            [
+                LogCategory::Dropped,
+                LogCategory::Controller,
+                LogCategory::Sent
+            ]
+        );
+    }
+}
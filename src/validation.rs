@@ -0,0 +1,226 @@
+use std::fmt;
+
+use wg_2024::network::NodeId;
+
+/// Reason why two nodes cannot be connected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionRejection {
+    /// The input field for the neighbor id is empty
+    EmptyInput,
+    /// The input for the neighbor id is not a valid `NodeId`
+    WrongIdFormat,
+    /// No node in the graph has the given id
+    UnknownId(NodeId),
+    /// The node is flagged as unresponsive, the string is the label of the node
+    Unresponsive(String),
+    SelfLoop(NodeId),
+    AlreadyConnected(NodeId, NodeId),
+    ClientToClient,
+    ClientToServer,
+    ServerToNonDrone,
+    /// The client already has the maximum number of connections
+    ClientAtMaxLinks(NodeId),
+    /// The client is not in the configuration of the controller
+    ClientNotFound(NodeId),
+    /// The connection was valid, but the `AddSender` command could not be sent
+    CommandFailed(String),
+}
+
+impl fmt::Display for ConnectionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionRejection::EmptyInput => write!(f, "The input field cannot be empty"),
+            ConnectionRejection::WrongIdFormat => write!(f, "Wrong ID format"),
+            ConnectionRejection::UnknownId(id) => write!(f, "ID {id} not found in the graph"),
+            ConnectionRejection::Unresponsive(label) => write!(f, "{label} is unresponsive"),
+            ConnectionRejection::SelfLoop(_) => write!(f, "Can't create a connection to itself"),
+            ConnectionRejection::AlreadyConnected(a, b) => {
+                write!(f, "Nodes {a} and {b} are already connected")
+            }
+            ConnectionRejection::ClientToClient => {
+                write!(f, "Client cannot be connected directly to other client")
+            }
+            ConnectionRejection::ClientToServer => {
+                write!(f, "Client cannot be connected directly to a server")
+            }
+            ConnectionRejection::ServerToNonDrone => write!(
+                f,
+                "Server cannot be connected directly to other client nor server"
+            ),
+            ConnectionRejection::ClientAtMaxLinks(id) => {
+                write!(f, "Client {id} reached its max connections")
+            }
+            ConnectionRejection::ClientNotFound(id) => write!(f, "Client {id} not found"),
+            ConnectionRejection::CommandFailed(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+/// Constraint of the network that an operation on the topology would violate
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControllerError {
+    /// No node in the graph has the given id
+    NodeNotFound(NodeId),
+    /// The drone is not in the graph
+    DroneNotFound(NodeId),
+    /// The two nodes are not connected
+    NoEdge(NodeId, NodeId),
+    /// The node is flagged as unresponsive, the string is the label of the node
+    Unresponsive(String),
+    /// The drone would lose its last connection
+    DroneMinConnections(NodeId),
+    /// The client would lose its last connection
+    ClientMinConnections(NodeId),
+    /// The client would have more than 2 connections
+    ClientMaxConnections(NodeId),
+    /// The server would have less than 2 connections
+    ServerMinConnections(NodeId),
+    /// The client would not reach every server
+    ServerUnreachable(NodeId),
+    /// The graph would have more than one connected component
+    WouldDisconnectGraph,
+    /// The id is already used by a node of the graph
+    IdAlreadyUsed(NodeId),
+    PdrOutOfRange(f32),
+    /// No drone implementation has the given index
+    UnknownImplementation(usize),
+}
+
+impl fmt::Display for ControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControllerError::NodeNotFound(id) => write!(f, "Node {id} not found"),
+            ControllerError::DroneNotFound(id) => write!(f, "Drone {id} not found"),
+            ControllerError::NoEdge(a, b) => write!(f, "No edge between {a} and {b}"),
+            ControllerError::Unresponsive(label) => write!(f, "{label} is unresponsive"),
+            ControllerError::DroneMinConnections(id) => {
+                write!(f, "Drone {id} must have at least 1 connection")
+            }
+            ControllerError::ClientMinConnections(id) => {
+                write!(f, "Client {id} must have at least 1 connection")
+            }
+            ControllerError::ClientMaxConnections(id) => {
+                write!(f, "Client {id} can have at most 2 connections")
+            }
+            ControllerError::ServerMinConnections(id) => {
+                write!(f, "Server {id} must have at least 2 connections")
+            }
+            ControllerError::ServerUnreachable(id) => {
+                write!(f, "Client {id} wouldn't reach every server")
+            }
+            ControllerError::WouldDisconnectGraph => {
+                write!(f, "The graph would become disconnected")
+            }
+            ControllerError::IdAlreadyUsed(id) => write!(f, "ID {id} is already used"),
+            ControllerError::PdrOutOfRange(pdr) => write!(f, "PDR {pdr} is not between 0 and 1"),
+            ControllerError::UnknownImplementation(index) => {
+                write!(f, "Unknown implementation {index}")
+            }
+        }
+    }
+}
+
+/// Policy applied when the configuration violates a constraint of the network
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConstraintPolicy {
+    /// Invalid configurations are rejected
+    #[default]
+    Strict,
+    /// Invalid configurations are fixed where possible, logging a warning
+    Sandbox,
+}
+
+/// Reason why an operation on the topology was not applied
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// The connection was rejected, see `ConnectionRejection`
+    Rejected(ConnectionRejection),
+    /// The operation violates one or more constraints of the network
+    Violations(Vec<ControllerError>),
+    /// The operation was valid, but a command could not be sent
+    Failed(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Rejected(rejection) => write!(f, "{rejection}"),
+            ValidationError::Violations(errors) => {
+                let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "{}", errors.join(", "))
+            }
+            ValidationError::Failed(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_controller_error_has_a_message() {
+        let cases = [
+            (ControllerError::NodeNotFound(7), "Node 7 not found"),
+            (ControllerError::DroneNotFound(7), "Drone 7 not found"),
+            (ControllerError::NoEdge(1, 2), "No edge between 1 and 2"),
+            (
+                ControllerError::Unresponsive("Drone 7".to_string()),
+                "Drone 7 is unresponsive",
+            ),
+            (
+                ControllerError::DroneMinConnections(7),
+                "Drone 7 must have at least 1 connection",
+            ),
+            (
+                ControllerError::ClientMinConnections(7),
+                "Client 7 must have at least 1 connection",
+            ),
+            (
+                ControllerError::ClientMaxConnections(7),
+                "Client 7 can have at most 2 connections",
+            ),
+            (
+                ControllerError::ServerMinConnections(7),
+                "Server 7 must have at least 2 connections",
+            ),
+            (
+                ControllerError::ServerUnreachable(7),
+                "Client 7 wouldn't reach every server",
+            ),
+            (
+                ControllerError::WouldDisconnectGraph,
+                "The graph would become disconnected",
+            ),
+            (ControllerError::IdAlreadyUsed(7), "ID 7 is already used"),
+            (
+                ControllerError::PdrOutOfRange(1.5),
+                "PDR 1.5 is not between 0 and 1",
+            ),
+            (
+                ControllerError::UnknownImplementation(9),
+                "Unknown implementation 9",
+            ),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn violations_are_listed_together() {
+        let error = ValidationError::Violations(vec![
+            ControllerError::ClientMinConnections(10),
+            ControllerError::ServerMinConnections(20),
+        ]);
+
+        assert_eq!(
+            error.to_string(),
+            "Client 10 must have at least 1 connection, Server 20 must have at least 2 connections"
+        );
+        assert_eq!(
+            ValidationError::Rejected(ConnectionRejection::SelfLoop(3)).to_string(),
+            "Can't create a connection to itself"
+        );
+    }
+}
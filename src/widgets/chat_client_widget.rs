@@ -1,19 +1,22 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
 
 use common::slc_commands::{ChatClientCommand, ServerType};
 use crossbeam_channel::Sender;
 use egui::{Align, Label, Layout, Sense, Widget};
 use wg_2024::{network::NodeId, packet::Packet};
 
+use super::NodeWidget;
+
 #[derive(Debug, Clone)]
 pub struct ChatClientWidget {
     id: NodeId,
     command_ch: Sender<ChatClientCommand>,
     servers_types: HashMap<NodeId, ServerType>,
     list_connected_clients: HashMap<NodeId, Vec<u8>>,
-    open_chat: Rc<RefCell<bool>>,
-    chat_input: Rc<RefCell<String>>,
-    chat_messages: Rc<RefCell<Vec<(bool, String)>>>,
+    open_chat: bool,
+    chat_input: String,
+    chat_messages: Vec<(bool, String)>,
+    send_error: Option<String>,
 }
 
 impl ChatClientWidget {
@@ -24,35 +27,13 @@ impl ChatClientWidget {
             command_ch,
             servers_types: HashMap::default(),
             list_connected_clients: HashMap::default(),
-            open_chat: Rc::new(RefCell::new(false)),
-            chat_input: Rc::new(RefCell::new(String::new())),
-            chat_messages: Rc::new(RefCell::new(Vec::new())),
+            open_chat: false,
+            chat_input: String::new(),
+            chat_messages: Vec::new(),
+            send_error: None,
         }
     }
 
-    /// Utility function to send a `ChatClientCommand::AddSender` command to the chat client
-    /// Adds a new neighbor with `neighbor_id` to the chat client's neighbor list
-    /// Furthermore, a clone of the `Sender<Packet>` channel is stored in the chat client
-    ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn add_neighbor(&mut self, neighbor_id: u8, neighbor_ch: Sender<Packet>) {
-        self.command_ch
-            .send(ChatClientCommand::AddSender(neighbor_id, neighbor_ch))
-            .expect("msg not sent");
-    }
-
-    /// Utility function to send a `ChatClientCommand::RemoveSender` command to the chat client
-    /// Removes a the neighbor with `neighbor_id` from the chat client's neighbor list
-    ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn remove_neighbor(&self, neighbor_id: u8) {
-        self.command_ch
-            .send(ChatClientCommand::RemoveSender(neighbor_id))
-            .expect("msg not sent");
-    }
-
     /// Function to add the server types to the chat client
     /// The server type is associated with the `server_id`
     /// The response is received from the mimicked chat client through the `ChatClientEvent::ServersTypes` event
@@ -65,7 +46,7 @@ impl ChatClientWidget {
     }
 
     pub fn update_chat(&mut self, msg: String) {
-        self.chat_messages.borrow_mut().push((false, msg));
+        self.chat_messages.push((false, msg));
     }
 
     /// Function to update the list of connected clients to a specific chat server
@@ -75,22 +56,28 @@ impl ChatClientWidget {
             .insert(server_id, connected_clients);
     }
 
-    #[must_use]
-    pub fn get_id(&self) -> NodeId {
-        self.id
+    /// Utility function to send a command to the chat client from the UI
+    /// If the message is not sent, the error is stored to be reported by the controller
+    fn send_command(&mut self, cmd: ChatClientCommand) {
+        if self.command_ch.send(cmd).is_err() {
+            self.send_error = Some("channel closed".to_string());
+        }
     }
 }
 
 /// Implementation of the `egui::Widget` trait for the `ChatClientWidget`
 ///
-/// This allows the `ChatClientWidget` to be rendered as an egui widget
+/// This allows the `ChatClientWidget` to be rendered as an egui widget.
+/// The widget is rendered by mutable reference, so that the chat state
+/// persists between frames.
 ///
 /// # Example
 /// ```no_run
 /// use egui::Ui;
-/// ui.add(ChatClientWidget::new(1, command_ch));
+/// let mut chat_client_widget = ChatClientWidget::new(1, command_ch);
+/// ui.add(&mut chat_client_widget);
 /// ```
-impl Widget for ChatClientWidget {
+impl Widget for &mut ChatClientWidget {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         ui.vertical(|ui| {
             ui.label(format!("Chat Client {}", self.id));
@@ -99,22 +86,24 @@ impl Widget for ChatClientWidget {
             ui.label("Ask for Server types");
             if ui.button("Send").clicked() {
                 let cmd = ChatClientCommand::AskServersTypes;
-                self.command_ch.send(cmd).expect("msg not sent");
+                self.send_command(cmd);
             }
 
             // Display the list of chat servers
             // Clicking on a server will open a new window with the chat
             ui.label("Chat servers:");
-            for id in self.servers_types.keys() {
+            let server_ids = self.servers_types.keys().copied().collect::<Vec<NodeId>>();
+            let mut outgoing_message = None;
+            for id in server_ids {
                 if ui
                     .add(Label::new(format!("Server {id}")).sense(Sense::click()))
                     .clicked()
                 {
-                    *self.open_chat.borrow_mut() = true;
+                    self.open_chat = true;
                 }
 
                 egui::Window::new(format!("Chat Server {id}"))
-                    .open(&mut self.open_chat.borrow_mut())
+                    .open(&mut self.open_chat)
                     .resizable(false)
                     .scroll(true)
                     .show(ui.ctx(), |ui| {
@@ -124,7 +113,7 @@ impl Widget for ChatClientWidget {
                                 .stick_to_bottom(true)
                                 .show(ui, |ui| {
                                     ui.label("Chat messages:");
-                                    for (is_sender, msg) in self.chat_messages.borrow().iter() {
+                                    for (is_sender, msg) in &self.chat_messages {
                                         if *is_sender {
                                             ui.with_layout(
                                                 Layout::right_to_left(Align::TOP),
@@ -147,25 +136,87 @@ impl Widget for ChatClientWidget {
                         ui.with_layout(Layout::bottom_up(egui::Align::Center), |ui| {
                             ui.add_space(10.0);
                             ui.horizontal(|ui| {
-                                ui.text_edit_singleline(&mut *self.chat_input.borrow_mut());
-                                if ui.button("Send").clicked()
-                                    && !self.chat_input.borrow().is_empty()
-                                {
-                                    self.chat_messages
-                                        .borrow_mut()
-                                        .push((true, self.chat_input.borrow().clone()));
-                                    let cmd = ChatClientCommand::SendMessage(
-                                        self.chat_input.borrow().clone(),
-                                    );
-                                    self.command_ch.send(cmd).expect("msg not sent");
-                                    self.chat_input.borrow_mut().clear();
+                                ui.text_edit_singleline(&mut self.chat_input);
+                                if ui.button("Send").clicked() && !self.chat_input.is_empty() {
+                                    let message = std::mem::take(&mut self.chat_input);
+                                    self.chat_messages.push((true, message.clone()));
+                                    outgoing_message = Some(message);
                                 }
                             });
                         });
                     });
             }
+            if let Some(message) = outgoing_message {
+                self.send_command(ChatClientCommand::SendMessage(message));
+            }
             ui.separator();
         })
         .response
     }
 }
+
+/// Implementation of the `NodeWidget` trait, to command the node like any other
+impl NodeWidget for ChatClientWidget {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn kind(&self) -> &'static str {
+        "chat_client"
+    }
+
+    fn name(&self) -> &'static str {
+        "Chat Client"
+    }
+
+    fn add_neighbor(
+        &mut self,
+        neighbor_id: NodeId,
+        neighbor_ch: Sender<Packet>,
+    ) -> Result<(), String> {
+        self.command_ch
+            .send(ChatClientCommand::AddSender(neighbor_id, neighbor_ch))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn remove_neighbor(&self, neighbor_id: NodeId) -> Result<(), String> {
+        self.command_ch
+            .send(ChatClientCommand::RemoveSender(neighbor_id))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn take_send_error(&mut self) -> Option<String> {
+        self.send_error.take()
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        ui.add(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbors_are_commanded_through_the_command_channel() {
+        let (command_ch, command_recv) = crossbeam_channel::unbounded();
+        let mut widget = ChatClientWidget::new(11, command_ch);
+        assert_eq!((widget.id(), widget.kind()), (11, "chat_client"));
+
+        let (packet_ch, _packet_recv) = crossbeam_channel::unbounded();
+        widget.add_neighbor(5, packet_ch).unwrap();
+        widget.remove_neighbor(5).unwrap();
+
+        let commands = command_recv.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                ChatClientCommand::AddSender(5, _),
+                ChatClientCommand::RemoveSender(5)
+            ]
+        ));
+        drop(command_recv);
+        assert_eq!(widget.remove_neighbor(5), Err("channel closed".to_string()));
+    }
+}
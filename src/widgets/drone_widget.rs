@@ -1,82 +1,99 @@
-use std::{cell::RefCell, rc::Rc};
-
 use crossbeam_channel::Sender;
 use egui::{Color32, RichText, Ui, Widget};
 use wg_2024::{controller::DroneCommand, network::NodeId, packet::Packet};
 
+use super::NodeWidget;
+
 #[derive(Clone, Debug)]
 /// Represents a drone widget
 ///
-/// This struct stores the `NodeId` and the `Sender<DroneCommand>` of the
-/// represented drone.
+/// This struct stores the `NodeId`, the `Sender<DroneCommand>` and the current
+/// PDR of the represented drone.
 /// Furthermore, it stores the input for the packet drop rate (PDR) and a flag
 /// to indicate if the input is invalid.
+/// The validated PDR is not sent directly, but stored as a request that the
+/// simulation controller applies, so that its state stays in sync.
 pub struct DroneWidget {
     /// The `NodeId` of the drone
     id: NodeId,
     /// The `Sender<DroneCommand>` channel to send commands to the drone
     command_ch: Sender<DroneCommand>,
+    /// The current packet drop rate (PDR) of the drone, as applied by the controller
+    pdr: f32,
     /// The input field for the packet drop rate (PDR)
-    pdr_input: Rc<RefCell<String>>,
+    pdr_input: String,
     /// Flag to indicate if the input for the PDR is invalid
-    pdr_invalid: Rc<RefCell<String>>,
+    pdr_invalid: String,
+    /// The PDR requested by the user, waiting to be applied by the controller
+    pdr_request: Option<f32>,
+    /// Name of the implementation running the drone, if it was spawned by the controller
+    implementation: Option<&'static str>,
 }
 
 impl DroneWidget {
-    /// Creates a new `DroneWidget` with the given `id` and `command_ch`
+    /// Creates a new `DroneWidget` with the given `id`, `command_ch` and initial `pdr`
     #[must_use]
-    pub fn new(id: NodeId, command_ch: Sender<DroneCommand>) -> Self {
+    pub fn new(id: NodeId, command_ch: Sender<DroneCommand>, pdr: f32) -> Self {
         Self {
             id,
             command_ch,
-            pdr_input: Rc::new(RefCell::new(String::default())),
-            pdr_invalid: Rc::new(RefCell::new(String::default())),
+            pdr,
+            pdr_input: String::default(),
+            pdr_invalid: String::default(),
+            pdr_request: None,
+            implementation: None,
         }
     }
 
-    /// Utility function to send a `DroneCommand::AddSender` command to the drone
-    /// Adds a new neighbor with `neighbor_id` to the drone's neighbor list
-    /// Furthermore, a clone of the `Sender<Packet>` channel is stored in the drone
-    ///
-    /// # Panics
-    /// If the message is not sent
-    pub fn add_neighbor(&mut self, neighbor_id: u8, neighbor_ch: Sender<Packet>) {
-        self.command_ch
-            .send(DroneCommand::AddSender(neighbor_id, neighbor_ch))
-            .expect("msg not sent");
+    /// Function to get the current PDR of the drone
+    #[must_use]
+    pub fn pdr(&self) -> f32 {
+        self.pdr
     }
 
-    /// Utility function to send a `DroneCommand::RemoveSender` command to the drone
-    /// Removes a the neighbor with `neighbor_id` from the drone's neighbor list
-    ///
-    /// # Panics
-    /// If the message is not sent
-    pub fn remove_neighbor(&self, neighbor_id: u8) {
-        self.command_ch
-            .send(DroneCommand::RemoveSender(neighbor_id))
-            .expect("msg not sent");
+    /// Function to record the PDR applied to the drone by the controller
+    pub fn set_pdr(&mut self, pdr: f32) {
+        self.pdr = pdr;
     }
 
-    /// Utility function to get the `NodeId` of the drone
+    /// Function to get the name of the implementation running the drone, if known
     #[must_use]
-    pub fn get_id(&self) -> NodeId {
-        self.id
+    pub fn implementation(&self) -> Option<&'static str> {
+        self.implementation
+    }
+
+    /// Function to record the implementation running the drone, e.g. after a swap
+    pub fn set_implementation(&mut self, implementation: &'static str) {
+        self.implementation = Some(implementation);
+    }
+
+    /// Function to take the PDR requested by the user, if any
+    ///
+    /// The request is cleared, so that it is applied only once
+    pub fn take_pdr_request(&mut self) -> Option<f32> {
+        self.pdr_request.take()
+    }
+
+    /// Function to replace the command channel, when the drone is respawned
+    /// with a different implementation
+    pub fn set_command_channel(&mut self, command_ch: Sender<DroneCommand>) {
+        self.command_ch = command_ch;
     }
 
     /// Utility function to send a `DroneCommand::Crash` command to the drone
     ///
-    /// # Panics
-    /// If the message is not sent
-    pub fn send_crash_command(&self) {
+    /// # Errors
+    /// If the message is not sent, because the command channel is closed
+    pub fn send_crash_command(&self) -> Result<(), String> {
         self.command_ch
             .send(DroneCommand::Crash)
-            .expect("msg not sent");
+            .map_err(|_| "channel closed".to_string())
     }
 
     /// Function that validates the input for the PDR
     ///
-    /// The input is considered valid if it is not empty and can be parsed as a float
-    /// between 0.0 and 1.0.
+    /// The input is considered valid if it is not empty and can be parsed as a finite
+    /// float between 0.0 and 1.0.
     ///
     /// # Example
     /// ```no_run
@@ -86,17 +103,20 @@ impl DroneWidget {
     /// let pdr = "1.5".to_string();
     /// assert_eq!(validate_parse_pdr(&pdr), None);
     /// ```
-    fn validate_parse_pdr(input_pdr: &str) -> Result<f32, String> {
+    pub fn validate_parse_pdr(input_pdr: &str) -> Result<f32, String> {
         if input_pdr.is_empty() {
-            return Err("Empty ID field".to_string());
+            return Err("Empty PDR field".to_string());
         }
         let pdr = input_pdr.parse::<f32>();
 
         if pdr.is_err() {
-            return Err("Wrong ID format".to_string());
+            return Err("Invalid PDR format".to_string());
         }
         let pdr = pdr.unwrap();
-        // Bro....this is crazy
+        // `parse` accepts "NaN" and "inf"
+        if !pdr.is_finite() {
+            return Err("PDR must be a finite number".to_string());
+        }
         if !(0.0..=1.0).contains(&pdr) {
             return Err("PDR must be between 0.0 and 1.0".to_string());
         }
@@ -107,34 +127,146 @@ impl DroneWidget {
 
 /// Implement the `egui::Widget` trait for `DroneWidget`
 ///
-/// This allows the `DroneWidget` to be rendered as an egui widget
+/// This allows the `DroneWidget` to be rendered as an egui widget.
+/// The widget is rendered by mutable reference, so that the state of its
+/// input fields persists between frames.
 ///
 /// # Example
 /// ```no_run
 /// use egui::Ui;
-/// ui.add(DroneWidget::new(1, command_ch));
+/// let mut drone_widget = DroneWidget::new(1, command_ch, 0.1);
+/// ui.add(&mut drone_widget);
 /// ```
-impl Widget for DroneWidget {
+impl Widget for &mut DroneWidget {
     fn ui(self, ui: &mut Ui) -> egui::Response {
         ui.vertical(|ui| {
             ui.label(format!("Drone {}", self.id));
+            ui.label(format!("Current PDR: {}", self.pdr));
+            if let Some(implementation) = self.implementation {
+                ui.label(format!("Implementation: {implementation}"));
+            }
             ui.label("Change PDR");
-            ui.text_edit_singleline(&mut *self.pdr_input.borrow_mut());
+            ui.text_edit_singleline(&mut self.pdr_input);
             if ui.button("Send").clicked() {
-                match DroneWidget::validate_parse_pdr(&self.pdr_input.borrow()) {
+                match DroneWidget::validate_parse_pdr(&self.pdr_input) {
                     Ok(pdr) => {
-                        self.pdr_invalid.borrow_mut().clear();
-                        let cmd = DroneCommand::SetPacketDropRate(pdr);
-                        self.command_ch.send(cmd).expect("msg not sent");
+                        self.pdr_invalid.clear();
+                        self.pdr_request = Some(pdr);
                     }
-                    Err(error) => *self.pdr_invalid.borrow_mut() = error,
+                    Err(error) => self.pdr_invalid = error,
                 }
             }
 
-            if !self.pdr_invalid.borrow().is_empty() {
-                ui.label(RichText::new(&*self.pdr_invalid.borrow()).color(Color32::RED));
+            if !self.pdr_invalid.is_empty() {
+                ui.label(RichText::new(&self.pdr_invalid).color(Color32::RED));
             }
         })
         .response
     }
 }
+
+/// Implementation of the `NodeWidget` trait, to command the node like any other
+impl NodeWidget for DroneWidget {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn kind(&self) -> &'static str {
+        "drone"
+    }
+
+    fn name(&self) -> &'static str {
+        "Drone"
+    }
+
+    fn add_neighbor(
+        &mut self,
+        neighbor_id: NodeId,
+        neighbor_ch: Sender<Packet>,
+    ) -> Result<(), String> {
+        self.command_ch
+            .send(DroneCommand::AddSender(neighbor_id, neighbor_ch))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn remove_neighbor(&self, neighbor_id: NodeId) -> Result<(), String> {
+        self.command_ch
+            .send(DroneCommand::RemoveSender(neighbor_id))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        ui.add(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_widget_shows_the_pdr_applied_by_the_controller() {
+        let (command_ch, _command_recv) = crossbeam_channel::unbounded();
+        let mut widget = DroneWidget::new(3, command_ch, 0.25);
+        assert_eq!(widget.pdr(), 0.25);
+
+        widget.set_pdr(0.5);
+
+        assert_eq!(widget.pdr(), 0.5);
+    }
+
+    #[test]
+    fn neighbors_are_commanded_through_the_command_channel() {
+        let (command_ch, command_recv) = crossbeam_channel::unbounded();
+        let mut widget = DroneWidget::new(3, command_ch, 0.25);
+        assert_eq!((widget.id(), widget.kind()), (3, "drone"));
+
+        let (packet_ch, _packet_recv) = crossbeam_channel::unbounded();
+        widget.add_neighbor(5, packet_ch).unwrap();
+        widget.remove_neighbor(5).unwrap();
+
+        let commands = command_recv.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            commands.as_slice(),
+            [DroneCommand::AddSender(5, _), DroneCommand::RemoveSender(5)]
+        ));
+        drop(command_recv);
+        assert_eq!(widget.remove_neighbor(5), Err("channel closed".to_string()));
+    }
+
+    #[test]
+    fn pdr_input_must_be_finite() {
+        for input in ["NaN", "inf", "-inf"] {
+            assert_eq!(
+                DroneWidget::validate_parse_pdr(input),
+                Err("PDR must be a finite number".to_string()),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn pdr_input_must_be_between_zero_and_one() {
+        assert_eq!(DroneWidget::validate_parse_pdr("1.0"), Ok(1.0));
+        assert_eq!(DroneWidget::validate_parse_pdr("0.0"), Ok(0.0));
+        for input in ["1.1", "-0.1"] {
+            assert_eq!(
+                DroneWidget::validate_parse_pdr(input),
+                Err("PDR must be between 0.0 and 1.0".to_string()),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn pdr_input_must_be_a_number() {
+        assert_eq!(
+            DroneWidget::validate_parse_pdr(""),
+            Err("Empty PDR field".to_string())
+        );
+        assert_eq!(
+            DroneWidget::validate_parse_pdr("half"),
+            Err("Invalid PDR format".to_string())
+        );
+    }
+}
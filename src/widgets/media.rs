@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use crossbeam_channel::Receiver;
+use egui::{ColorImage, TextureHandle, TextureOptions, Ui};
+
+use crate::platform;
+
+/// Side of the thumbnails shown in the download registry
+const THUMBNAIL_SIZE: f32 = 64.0;
+
+/// Format of the images that can be previewed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageKind {
+    Png,
+    Jpeg,
+}
+
+impl ImageKind {
+    /// Detect the format of an image from its magic bytes
+    #[must_use]
+    pub fn sniff(bytes: &[u8]) -> Option<ImageKind> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageKind::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageKind::Jpeg)
+        } else {
+            None
+        }
+    }
+
+    fn format(self) -> image::ImageFormat {
+        match self {
+            ImageKind::Png => image::ImageFormat::Png,
+            ImageKind::Jpeg => image::ImageFormat::Jpeg,
+        }
+    }
+}
+
+/// Preview of a media file
+#[derive(Clone, Debug)]
+enum Preview {
+    /// The image is being decoded by a worker
+    Decoding(Receiver<Result<ColorImage, String>>),
+    Ready(TextureHandle),
+    Failed(String),
+    /// The media is not an image, so it can only be opened externally
+    External(Vec<u8>),
+}
+
+/// A media file received by a web client
+#[derive(Clone, Debug)]
+pub struct MediaEntry {
+    name: String,
+    /// Path the media was saved to
+    path: PathBuf,
+    preview: Preview,
+}
+
+impl MediaEntry {
+    /// Create the entry of a media file, decoding it off the UI thread if it's an image
+    #[must_use]
+    pub fn new(name: String, path: PathBuf, content: Vec<u8>) -> Self {
+        let preview = match ImageKind::sniff(&content) {
+            Some(kind) => {
+                let (sender, receiver) = crossbeam_channel::bounded(1);
+                platform::run_in_background(move || {
+                    let _ = sender.send(decode(&content, kind));
+                });
+                Preview::Decoding(receiver)
+            }
+            None => Preview::External(content),
+        };
+        Self {
+            name,
+            path,
+            preview,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Function to upload the decoded image to the GPU, once the worker is done
+    fn poll(&mut self, ui: &Ui) {
+        let Preview::Decoding(receiver) = &self.preview else {
+            return;
+        };
+        self.preview = match receiver.try_recv() {
+            Ok(Ok(image)) => Preview::Ready(ui.ctx().load_texture(
+                self.path.display().to_string(),
+                image,
+                TextureOptions::default(),
+            )),
+            Ok(Err(error)) => Preview::Failed(error),
+            Err(crossbeam_channel::TryRecvError::Empty) => return,
+            Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                Preview::Failed("decoder stopped".to_string())
+            }
+        };
+    }
+
+    /// Render the entry, returns true if the thumbnail was clicked
+    pub fn show(&mut self, ui: &mut Ui) -> bool {
+        self.poll(ui);
+        let mut clicked = false;
+        ui.horizontal(|ui| {
+            match &self.preview {
+                Preview::Decoding(_) => {
+                    ui.spinner();
+                }
+                Preview::Ready(texture) => {
+                    let thumbnail = egui::Image::new(texture)
+                        .max_size(egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+                        .sense(egui::Sense::click());
+                    clicked = ui.add(thumbnail).on_hover_text("Click to zoom").clicked();
+                }
+                Preview::Failed(error) => {
+                    ui.label(
+                        egui::RichText::new(format!("Cannot decode: {error}"))
+                            .color(egui::Color32::RED),
+                    );
+                }
+                Preview::External(content) => {
+                    if ui.button("Open").clicked() {
+                        let _ = platform::open_page(&self.path, content);
+                    }
+                }
+            }
+            ui.label(&self.name);
+        });
+        clicked
+    }
+
+    /// Render the image at its full size, if it was decoded
+    pub fn show_full(&self, ui: &mut Ui) {
+        if let Preview::Ready(texture) = &self.preview {
+            egui::ScrollArea::both().show(ui, |ui| {
+                ui.image(texture);
+            });
+        }
+    }
+}
+
+/// Decode an image of the given kind to RGBA pixels
+fn decode(content: &[u8], kind: ImageKind) -> Result<ColorImage, String> {
+    let image = image::load_from_memory_with_format(content, kind.format())
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
@@ -1,15 +1,62 @@
-use chat_client_widget::ChatClientWidget;
+//! Widgets used to render and command the nodes of the network
+//!
+//! Every kind of node has its own widget, rendered by mutable reference in the
+//! side panel; `WidgetType` is the payload stored in the graph.
+
 use crossbeam_channel::Sender;
-use drone_widget::DroneWidget;
-use server_widget::ServerWidget;
-use web_client_widget::WebClientWidget;
+use egui::{Response, Ui};
 use wg_2024::{network::NodeId, packet::Packet};
 
+use crate::utils::NodeKind;
+
+pub use chat_client_widget::ChatClientWidget;
+pub use drone_widget::DroneWidget;
+pub use server_widget::ServerWidget;
+pub use web_client_widget::{PendingRequest, WebClientWidget};
+
 pub mod chat_client_widget;
 pub mod drone_widget;
+pub mod media;
 pub mod server_widget;
 pub mod web_client_widget;
 
+/// Operations shared by the widgets of every kind of node
+pub trait NodeWidget {
+    /// The `NodeId` of the represented node
+    fn id(&self) -> NodeId;
+
+    /// Kind of the node, as used in the exported statistics, e.g. `web_client`
+    fn kind(&self) -> &'static str;
+
+    /// Name of the kind of node, as shown in the UI, e.g. `Web Client`
+    fn name(&self) -> &'static str;
+
+    /// Sends the command to add the neighbor `neighbor_id` to the node, along
+    /// with the channel to send it packets
+    ///
+    /// # Errors
+    /// If the command channel of the node is closed
+    fn add_neighbor(
+        &mut self,
+        neighbor_id: NodeId,
+        neighbor_ch: Sender<Packet>,
+    ) -> Result<(), String>;
+
+    /// Sends the command to remove the neighbor `neighbor_id` from the node
+    ///
+    /// # Errors
+    /// If the command channel of the node is closed
+    fn remove_neighbor(&self, neighbor_id: NodeId) -> Result<(), String>;
+
+    /// Takes the error of the last command the widget failed to send from the UI, if any
+    fn take_send_error(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Renders the widget in the side panel
+    fn show(&mut self, ui: &mut Ui) -> Response;
+}
+
 #[derive(Clone, Debug)]
 pub enum WidgetType {
     Drone(DroneWidget),
@@ -19,35 +66,82 @@ pub enum WidgetType {
 }
 
 impl WidgetType {
+    /// The widget of the node, to run the operations shared by every kind of node
     #[must_use]
-    pub fn get_id_helper(&self) -> NodeId {
+    pub fn widget(&self) -> &dyn NodeWidget {
         match self {
-            WidgetType::Drone(drone_widget) => drone_widget.get_id(),
-            WidgetType::WebClient(web_client_widget) => web_client_widget.get_id(),
-            WidgetType::ChatClient(chat_client_widget) => chat_client_widget.get_id(),
-            WidgetType::Server(server_widget) => server_widget.get_id(),
+            WidgetType::Drone(drone_widget) => drone_widget,
+            WidgetType::WebClient(web_client_widget) => web_client_widget,
+            WidgetType::ChatClient(chat_client_widget) => chat_client_widget,
+            WidgetType::Server(server_widget) => server_widget,
         }
     }
 
-    pub fn add_neighbor_helper(&mut self, nid: u8, nch: Sender<Packet>) {
+    pub fn widget_mut(&mut self) -> &mut dyn NodeWidget {
         match self {
-            WidgetType::Drone(drone_widget) => drone_widget.add_neighbor(nid, nch),
-            WidgetType::WebClient(web_client_widget) => web_client_widget.add_neighbor(nid, nch),
-            WidgetType::ChatClient(chat_client_widget) => chat_client_widget.add_neighbor(nid, nch),
-            WidgetType::Server(server_widget) => server_widget.add_neighbor(nid, nch),
+            WidgetType::Drone(drone_widget) => drone_widget,
+            WidgetType::WebClient(web_client_widget) => web_client_widget,
+            WidgetType::ChatClient(chat_client_widget) => chat_client_widget,
+            WidgetType::Server(server_widget) => server_widget,
         }
     }
 
-    pub fn rm_neighbor_helper(&self, neighbor_id: u8) {
+    #[must_use]
+    pub fn get_id_helper(&self) -> NodeId {
+        self.widget().id()
+    }
+
+    /// Returns the label shown in the graph for the node, e.g. `Drone 3`
+    #[must_use]
+    pub fn get_label_helper(&self) -> String {
+        format!("{} {}", self.widget().name(), self.widget().id())
+    }
+
+    /// Returns the shape of the node in the exported DOT graph
+    #[must_use]
+    pub fn get_dot_shape_helper(&self) -> &'static str {
         match self {
-            WidgetType::Drone(drone_widget) => drone_widget.remove_neighbor(neighbor_id),
-            WidgetType::WebClient(web_client_widget) => {
-                web_client_widget.remove_neighbor(neighbor_id);
-            }
-            WidgetType::ChatClient(chat_client_widget) => {
-                chat_client_widget.remove_neighbor(neighbor_id);
-            }
-            WidgetType::Server(server_widget) => server_widget.remove_neighbor(neighbor_id),
+            WidgetType::Drone(_) => "circle",
+            WidgetType::WebClient(_) | WidgetType::ChatClient(_) => "diamond",
+            WidgetType::Server(_) => "box",
         }
     }
+
+    /// Returns the kind of the node, as used in the exported statistics
+    #[must_use]
+    pub fn get_kind_helper(&self) -> &'static str {
+        self.widget().kind()
+    }
+
+    /// Returns the kind of the node, as recorded in the event log
+    #[must_use]
+    pub fn get_node_kind_helper(&self) -> NodeKind {
+        match self {
+            WidgetType::Drone(_) => NodeKind::Drone,
+            WidgetType::WebClient(_) => NodeKind::WebClient,
+            WidgetType::ChatClient(_) => NodeKind::ChatClient,
+            WidgetType::Server(_) => NodeKind::Server,
+        }
+    }
+
+    /// Sends the command to add the neighbor `nid` to the node
+    ///
+    /// # Errors
+    /// If the command channel of the node is closed
+    pub fn add_neighbor_helper(&mut self, nid: u8, nch: Sender<Packet>) -> Result<(), String> {
+        self.widget_mut().add_neighbor(nid, nch)
+    }
+
+    /// Sends the command to remove the neighbor `neighbor_id` from the node
+    ///
+    /// # Errors
+    /// If the command channel of the node is closed
+    pub fn rm_neighbor_helper(&self, neighbor_id: u8) -> Result<(), String> {
+        self.widget().remove_neighbor(neighbor_id)
+    }
+
+    /// Takes the error of the last command the widget failed to send from the UI, if any
+    pub fn take_send_error_helper(&mut self) -> Option<String> {
+        self.widget_mut().take_send_error()
+    }
 }
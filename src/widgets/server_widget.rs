@@ -3,6 +3,8 @@ use crossbeam_channel::Sender;
 use egui::{Ui, Widget};
 use wg_2024::{network::NodeId, packet::Packet};
 
+use super::NodeWidget;
+
 #[derive(Clone, Debug)]
 /// Represents a server widget
 ///
@@ -21,35 +23,6 @@ impl ServerWidget {
     pub fn new(id: NodeId, command_ch: Sender<ServerCommand>) -> Self {
         Self { id, command_ch }
     }
-
-    /// Utility function to send a `ServerCommand::AddSender` command to the server
-    /// Adds a new neighbor with `neighbor_id` to the server's neighbor list
-    /// Furthermore, a clone of the `Sender<Packet>` channel is stored in the server
-    ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn add_neighbor(&mut self, neighbor_id: u8, neighbor_ch: Sender<Packet>) {
-        self.command_ch
-            .send(ServerCommand::AddSender(neighbor_id, neighbor_ch))
-            .expect("msg not sent");
-    }
-
-    /// Utility function to send a `ServerCommand::RemoveSender` command to the server
-    /// Removes a the neighbor with `neighbor_id` from the server's neighbor list
-    ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn remove_neighbor(&self, neighbor_id: u8) {
-        self.command_ch
-            .send(ServerCommand::RemoveSender(neighbor_id))
-            .expect("msg not sent");
-    }
-
-    /// Utility function to get the `NodeId` of the server
-    #[must_use]
-    pub fn get_id(&self) -> NodeId {
-        self.id
-    }
 }
 
 /// Implement the `egui::Widget` trait for `ServerWidget`
@@ -59,9 +32,10 @@ impl ServerWidget {
 /// # Example
 /// ```no_run
 /// use egui::Ui;
-/// ui.add(ServerWidget::new(1, command_ch));
+/// let mut server_widget = ServerWidget::new(1, command_ch);
+/// ui.add(&mut server_widget);
 /// ```
-impl Widget for ServerWidget {
+impl Widget for &mut ServerWidget {
     fn ui(self, ui: &mut Ui) -> egui::Response {
         ui.vertical_centered(|ui| {
             ui.label(format!("Server {}", self.id));
@@ -69,3 +43,65 @@ impl Widget for ServerWidget {
         .response
     }
 }
+
+/// Implementation of the `NodeWidget` trait, to command the node like any other
+impl NodeWidget for ServerWidget {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn kind(&self) -> &'static str {
+        "server"
+    }
+
+    fn name(&self) -> &'static str {
+        "Server"
+    }
+
+    fn add_neighbor(
+        &mut self,
+        neighbor_id: NodeId,
+        neighbor_ch: Sender<Packet>,
+    ) -> Result<(), String> {
+        self.command_ch
+            .send(ServerCommand::AddSender(neighbor_id, neighbor_ch))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn remove_neighbor(&self, neighbor_id: NodeId) -> Result<(), String> {
+        self.command_ch
+            .send(ServerCommand::RemoveSender(neighbor_id))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        ui.add(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbors_are_commanded_through_the_command_channel() {
+        let (command_ch, command_recv) = crossbeam_channel::unbounded();
+        let mut widget = ServerWidget::new(20, command_ch);
+        assert_eq!((widget.id(), widget.kind()), (20, "server"));
+
+        let (packet_ch, _packet_recv) = crossbeam_channel::unbounded();
+        widget.add_neighbor(5, packet_ch).unwrap();
+        widget.remove_neighbor(5).unwrap();
+
+        let commands = command_recv.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                ServerCommand::AddSender(5, _),
+                ServerCommand::RemoveSender(5)
+            ]
+        ));
+        drop(command_recv);
+        assert_eq!(widget.remove_neighbor(5), Err("channel closed".to_string()));
+    }
+}
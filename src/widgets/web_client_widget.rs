@@ -1,10 +1,53 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    path::PathBuf,
+    time::Duration,
+};
 
 use common::slc_commands::{ServerType, WebClientCommand};
 use crossbeam_channel::Sender;
 use egui::{Label, RichText, Sense, Ui, Widget};
+use web_time::Instant;
 use wg_2024::{network::NodeId, packet::Packet};
 
+use super::{media::MediaEntry, NodeWidget};
+
+/// Number of completed requests kept in the request history
+const REQUEST_HISTORY_LEN: usize = 20;
+
+/// A request sent to the web client that expects a response event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PendingRequest {
+    /// `AskServersTypes`, answered by `ServersTypes`
+    ServersTypes,
+    /// `AskListOfFiles` to the server, answered by `ListOfFiles`
+    ListOfFiles(NodeId),
+    /// `RequestFile` to the server, answered by `FileFromClient`
+    File(NodeId),
+}
+
+impl PendingRequest {
+    /// Id of the server the request is addressed to, if any
+    #[must_use]
+    pub fn server_id(&self) -> Option<NodeId> {
+        match self {
+            PendingRequest::ServersTypes => None,
+            PendingRequest::ListOfFiles(id) | PendingRequest::File(id) => Some(*id),
+        }
+    }
+}
+
+impl fmt::Display for PendingRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PendingRequest::ServersTypes => write!(f, "AskServersTypes"),
+            PendingRequest::ListOfFiles(id) => write!(f, "AskListOfFiles({id})"),
+            PendingRequest::File(id) => write!(f, "RequestFile({id})"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 /// Represents a web client widget
 ///
@@ -14,6 +57,8 @@ use wg_2024::{network::NodeId, packet::Packet};
 /// the input is invalid.
 /// It also stores the discovered servers with their types and the list of files
 /// they have.
+/// In advanced mode, the list of files can be requested to any id, even if it
+/// is not a known server, to see how the client handles it.
 pub struct WebClientWidget {
     /// The `NodeId` of the web client
     id: NodeId,
@@ -22,13 +67,27 @@ pub struct WebClientWidget {
     /// The discovered servers with their types
     servers_types: HashMap<NodeId, ServerType>,
     /// The input field for the server id
-    id_input: Rc<RefCell<String>>,
+    id_input: String,
     /// Flag to indicate if the input for the server id is invalid
-    id_input_error: Rc<RefCell<String>>,
+    id_input_error: String,
     /// The list of files contained on the servers
     list_of_files: HashMap<NodeId, Vec<String>>,
     /// The error message for an unsupported request
-    unsupported_request_error: Rc<RefCell<String>>,
+    unsupported_request_error: String,
+    /// Flag to allow requesting the list of files to ids that are not known servers
+    advanced: bool,
+    /// Warning shown when the list of files is requested to an unknown server
+    id_input_warning: String,
+    /// The requests waiting for a response, with the time they were sent
+    pending_requests: Vec<(PendingRequest, Instant)>,
+    /// The last completed requests, with their round-trip time or `None` if they timed out
+    request_history: VecDeque<(PendingRequest, Option<Duration>)>,
+    /// The error of the last command that could not be sent
+    send_error: Option<String>,
+    /// The media files received with the requested files
+    media: Vec<MediaEntry>,
+    /// Index in `media` of the image shown in the preview window
+    zoomed_media: Option<usize>,
 }
 
 impl WebClientWidget {
@@ -39,64 +98,121 @@ impl WebClientWidget {
             id,
             command_ch,
             servers_types: HashMap::default(),
-            id_input: Rc::new(RefCell::new(String::default())),
-            id_input_error: Rc::new(RefCell::new(String::default())),
+            id_input: String::default(),
+            id_input_error: String::default(),
             list_of_files: HashMap::default(),
-            unsupported_request_error: Rc::new(RefCell::new(String::default())),
+            unsupported_request_error: String::default(),
+            advanced: false,
+            id_input_warning: String::default(),
+            pending_requests: Vec::new(),
+            request_history: VecDeque::new(),
+            send_error: None,
+            media: Vec::new(),
+            zoomed_media: None,
         }
     }
 
-    /// Utility function to send a `WebClientCommand::AddSender` command to the web client
-    /// Adds a new neighbor with `neighbor_id` to the web client's neighbor list
-    /// Furthermore, a clone of the `Sender<Packet>` channel is stored in the web client
+    /// Function to add a list of files to the web client
+    /// The list of files is associated with the server with the given `server_id`
+    /// The response is received from the mimicked client through the `WebClientEvent::ListOfFiles` event
     ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn add_neighbor(&mut self, neighbor_id: u8, neighbor_ch: Sender<Packet>) {
-        self.command_ch
-            .send(WebClientCommand::AddSender(neighbor_id, neighbor_ch))
-            .expect("msg not sent");
+    /// Returns the round-trip time of the matching `AskListOfFiles` request, if any
+    pub fn add_list_of_files(&mut self, server_id: NodeId, files: Vec<String>) -> Option<Duration> {
+        self.list_of_files.insert(server_id, files);
+        self.resolve_request(&PendingRequest::ListOfFiles(server_id))
     }
 
-    /// Utility function to send a `WebClientCommand::RemoveSender` command to the web client
-    /// Removes a the neighbor with `neighbor_id` from the web client's neighbor list
+    /// Function to add a media file received with a requested file
     ///
-    /// # Panics
-    /// The function panics if the message is not sent
-    pub fn remove_neighbor(&self, neighbor_id: u8) {
-        self.command_ch
-            .send(WebClientCommand::RemoveSender(neighbor_id))
-            .expect("msg not sent");
+    /// Images are decoded in the background and shown as thumbnails.
+    pub fn add_media(&mut self, name: String, path: PathBuf, content: Vec<u8>) {
+        self.media.push(MediaEntry::new(name, path, content));
     }
 
-    /// Function to add a list of files to the web client
-    /// The list of files is associated with the server with the given `server_id`
-    /// The response is received from the mimicked client through the `WebClientEvent::ListOfFiles` event
-    pub fn add_list_of_files(&mut self, server_id: NodeId, files: Vec<String>) {
-        self.list_of_files.insert(server_id, files);
+    /// Function to track a request sent to the web client, until its response arrives
+    pub fn track_request(&mut self, request: PendingRequest) {
+        self.pending_requests.push((request, Instant::now()));
+    }
+
+    /// Function to resolve the oldest pending request equal to `request`
+    ///
+    /// Requests are matched by kind and server, so overlapping requests to
+    /// different servers are resolved independently.
+    /// Returns the round-trip time of the request, or `None` if no such request is pending
+    #[must_use]
+    pub fn resolve_request(&mut self, request: &PendingRequest) -> Option<Duration> {
+        let pos = self
+            .pending_requests
+            .iter()
+            .position(|(r, _)| r == request)?;
+        let (request, sent_at) = self.pending_requests.remove(pos);
+        let rtt = sent_at.elapsed();
+        self.push_history(request, Some(rtt));
+        Some(rtt)
+    }
+
+    /// Function to drop the pending requests older than `timeout`
+    ///
+    /// The dropped requests are recorded in the history as lost.
+    /// Returns the number of dropped requests
+    pub fn expire_requests(&mut self, timeout: Duration) -> usize {
+        let (expired, pending): (Vec<_>, Vec<_>) = self
+            .pending_requests
+            .drain(..)
+            .partition(|(_, sent_at)| sent_at.elapsed() > timeout);
+        self.pending_requests = pending;
+        let lost = expired.len();
+        for (request, _) in expired {
+            self.push_history(request, None);
+        }
+        lost
+    }
+
+    /// Utility function to add a completed request to the history, dropping the oldest one if full
+    fn push_history(&mut self, request: PendingRequest, rtt: Option<Duration>) {
+        if self.request_history.len() == REQUEST_HISTORY_LEN {
+            self.request_history.pop_front();
+        }
+        self.request_history.push_back((request, rtt));
     }
 
     /// Function to add a servers type to the web client
     /// The server type is associated with the server with the given `server_id`
     /// The response is received from the mimicked client through the `WebClientEvent::ServersTypes` event
-    pub fn add_server_type(&mut self, server_types: HashMap<NodeId, ServerType>) {
+    ///
+    /// Returns the round-trip time of the matching `AskServersTypes` request, if any
+    pub fn add_server_type(
+        &mut self,
+        server_types: HashMap<NodeId, ServerType>,
+    ) -> Option<Duration> {
         self.servers_types = server_types;
+        self.resolve_request(&PendingRequest::ServersTypes)
     }
 
+    /// Function to show the error for an unsupported request
+    /// The error is attributed to the oldest pending request, if any
     pub fn add_unsupported_request_error(&mut self, error: String) {
-        *self.unsupported_request_error.borrow_mut() = error;
+        if self.pending_requests.is_empty() {
+            self.unsupported_request_error = error;
+        } else {
+            let (request, _) = self.pending_requests.remove(0);
+            self.unsupported_request_error = format!("{error} ({request})");
+        }
     }
 
-    /// Utility function to get the `NodeId` of the web client
-    #[must_use]
-    pub fn get_id(&self) -> NodeId {
-        self.id
+    /// Utility function to send a command to the web client from the UI
+    /// If the message is not sent, the error is stored to be reported by the controller
+    fn send_command(&mut self, cmd: WebClientCommand) {
+        if self.command_ch.send(cmd).is_err() {
+            self.send_error = Some("channel closed".to_string());
+        }
     }
 
     /// Function that validates the input for the server id
     ///
     /// The function checks if the input is empty, if the input can be parsed to a `NodeId`
     /// and if the parsed `NodeId` is a valid server id.
+    /// In advanced mode, any parsable `NodeId` is accepted.
     ///
     /// # Example
     /// ```no_run
@@ -118,7 +234,7 @@ impl WebClientWidget {
         }
 
         let id = id.unwrap();
-        if self.servers_types.contains_key(&id) {
+        if self.servers_types.contains_key(&id) || self.advanced {
             Ok(id)
         } else {
             Err("Server ID not found".to_string())
@@ -128,14 +244,17 @@ impl WebClientWidget {
 
 /// Implementation of the `egui::Widget` trait for the `WebClientWidget`
 ///
-/// This allows the `WebClientWidget` to be rendered as an egui widget
+/// This allows the `WebClientWidget` to be rendered as an egui widget.
+/// The widget is rendered by mutable reference, so that the inputs and the
+/// pending requests persist between frames.
 ///
 /// # Example
 /// ```no_run
 /// use egui::Ui;
-/// ui.add(WebClientWidget::new(1, command_ch));
+/// let mut web_client_widget = WebClientWidget::new(1, command_ch);
+/// ui.add(&mut web_client_widget);
 /// ```
-impl Widget for WebClientWidget {
+impl Widget for &mut WebClientWidget {
     fn ui(self, ui: &mut Ui) -> egui::Response {
         ui.vertical(|ui| {
             ui.label(format!("Web Client {}", self.id));
@@ -144,7 +263,8 @@ impl Widget for WebClientWidget {
             ui.label("Ask for Server types");
             if ui.button("Send").clicked() {
                 let cmd = WebClientCommand::AskServersTypes;
-                self.command_ch.send(cmd).expect("msg not sent");
+                self.send_command(cmd);
+                self.track_request(PendingRequest::ServersTypes);
             }
 
             ui.label("Servers types:");
@@ -156,32 +276,63 @@ impl Widget for WebClientWidget {
 
             // Send command to ask for files
             ui.label("Ask for Server files");
-            ui.text_edit_singleline(&mut *self.id_input.borrow_mut());
+            ui.checkbox(&mut self.advanced, "Advanced (allow any id)");
+            ui.text_edit_singleline(&mut self.id_input);
             if ui.button("Send").clicked() {
-                match self.validate_parse_id(&self.id_input.borrow()) {
+                match self.validate_parse_id(&self.id_input) {
                     Ok(id) => {
-                        self.id_input_error.borrow_mut().clear();
+                        self.id_input_error.clear();
+                        if self.servers_types.contains_key(&id) {
+                            self.id_input_warning.clear();
+                        } else {
+                            self.id_input_warning = format!("ID {id} is not a known server");
+                        }
                         let cmd = WebClientCommand::AskListOfFiles(id);
-                        self.command_ch.send(cmd).expect("msg not sent");
+                        self.send_command(cmd);
+                        self.track_request(PendingRequest::ListOfFiles(id));
                     }
-                    Err(error) => *self.id_input_error.borrow_mut() = error,
+                    Err(error) => self.id_input_error = error,
                 }
-                self.id_input.borrow_mut().clear();
+                self.id_input.clear();
             }
 
-            if !self.id_input_error.borrow().is_empty() {
-                ui.label(RichText::new(&*self.id_input_error.borrow()).color(egui::Color32::RED));
+            if !self.id_input_error.is_empty() {
+                ui.label(RichText::new(&self.id_input_error).color(egui::Color32::RED));
             }
 
-            if !self.unsupported_request_error.borrow().is_empty() {
-                ui.label(
-                    RichText::new(&*self.unsupported_request_error.borrow())
-                        .color(egui::Color32::RED),
-                );
+            if !self.id_input_warning.is_empty() {
+                ui.label(RichText::new(&self.id_input_warning).color(egui::Color32::YELLOW));
+            }
+
+            if !self.unsupported_request_error.is_empty() {
+                ui.label(RichText::new(&self.unsupported_request_error).color(egui::Color32::RED));
+            }
+
+            ui.separator();
+            ui.label("Pending requests:");
+            for (request, sent_at) in &self.pending_requests {
+                let waiting = sent_at.elapsed();
+                let known = match request.server_id() {
+                    Some(id) if !self.servers_types.contains_key(&id) => " (unknown server)",
+                    _ => "",
+                };
+                ui.label(format!("{request}{known}: waiting {}s", waiting.as_secs()));
+            }
+
+            ui.separator();
+            ui.label("Request history:");
+            for (request, rtt) in self.request_history.iter().rev() {
+                match rtt {
+                    Some(rtt) => ui.label(format!("{request}: {} ms", rtt.as_millis())),
+                    None => ui.label(
+                        RichText::new(format!("{request}: timed out")).color(egui::Color32::YELLOW),
+                    ),
+                };
             }
 
             ui.separator();
             ui.label("Received files:");
+            let mut requested_file = None;
             for (server_id, server_files) in &self.list_of_files {
                 ui.label(format!("Server {server_id}: "));
                 for file in server_files {
@@ -190,12 +341,99 @@ impl Widget for WebClientWidget {
                         .add(Label::new(file_name).sense(Sense::click()))
                         .clicked()
                     {
-                        let cmd = WebClientCommand::RequestFile(file.to_string(), *server_id);
-                        self.command_ch.send(cmd).expect("msg not sent");
+                        requested_file = Some((file.to_string(), *server_id));
                     }
                 }
             }
+            if let Some((file, server_id)) = requested_file {
+                let cmd = WebClientCommand::RequestFile(file, server_id);
+                self.send_command(cmd);
+                self.track_request(PendingRequest::File(server_id));
+            }
+
+            ui.separator();
+            ui.label("Downloaded media:");
+            for (i, entry) in self.media.iter_mut().enumerate() {
+                if entry.show(ui) {
+                    self.zoomed_media = Some(i);
+                }
+            }
+            if let Some(entry) = self.zoomed_media.and_then(|i| self.media.get(i)) {
+                let mut open = true;
+                egui::Window::new(entry.name())
+                    .open(&mut open)
+                    .show(ui.ctx(), |ui| entry.show_full(ui));
+                if !open {
+                    self.zoomed_media = None;
+                }
+            }
         })
         .response
     }
 }
+
+/// Implementation of the `NodeWidget` trait, to command the node like any other
+impl NodeWidget for WebClientWidget {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn kind(&self) -> &'static str {
+        "web_client"
+    }
+
+    fn name(&self) -> &'static str {
+        "Web Client"
+    }
+
+    fn add_neighbor(
+        &mut self,
+        neighbor_id: NodeId,
+        neighbor_ch: Sender<Packet>,
+    ) -> Result<(), String> {
+        self.command_ch
+            .send(WebClientCommand::AddSender(neighbor_id, neighbor_ch))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn remove_neighbor(&self, neighbor_id: NodeId) -> Result<(), String> {
+        self.command_ch
+            .send(WebClientCommand::RemoveSender(neighbor_id))
+            .map_err(|_| "channel closed".to_string())
+    }
+
+    fn take_send_error(&mut self) -> Option<String> {
+        self.send_error.take()
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        ui.add(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbors_are_commanded_through_the_command_channel() {
+        let (command_ch, command_recv) = crossbeam_channel::unbounded();
+        let mut widget = WebClientWidget::new(10, command_ch);
+        assert_eq!((widget.id(), widget.kind()), (10, "web_client"));
+
+        let (packet_ch, _packet_recv) = crossbeam_channel::unbounded();
+        widget.add_neighbor(5, packet_ch).unwrap();
+        widget.remove_neighbor(5).unwrap();
+
+        let commands = command_recv.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                WebClientCommand::AddSender(5, _),
+                WebClientCommand::RemoveSender(5)
+            ]
+        ));
+        drop(command_recv);
+        assert_eq!(widget.remove_neighbor(5), Err("channel closed".to_string()));
+    }
+}
@@ -0,0 +1,641 @@
+//! Scenarios driving the controller through its public operations, without the GUI
+
+mod support;
+
+use std::collections::HashMap;
+
+use common::slc_commands::{ChatClientEvent, WebClientEvent};
+use simulation_controller::{
+    topology::TopologyOp,
+    utils::NodeKind,
+    validation::{ConnectionRejection, ControllerError, ValidationError},
+};
+use support::{ack, neighbors, sample_config, sample_network, TestNetwork, SAMPLE_CHAT_CLIENTS};
+use wg_2024::{
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+};
+
+#[test]
+fn builds_the_graph_of_the_configuration() {
+    let network = sample_network();
+    let snapshot = network.controller.topology_snapshot();
+
+    assert_eq!(
+        snapshot.drones.keys().copied().collect::<Vec<_>>(),
+        [1, 2, 3, 4]
+    );
+    assert_eq!(snapshot.edges.len(), 12);
+    assert!(snapshot.edges.contains(&(1, 3)));
+    assert!(snapshot.edges.contains(&(4, 11)));
+    assert_eq!(neighbors(&network.controller, 20), Some(vec![1, 2, 4]));
+}
+
+#[test]
+fn rejects_a_one_sided_edge() {
+    let mut config = sample_config();
+    config.drone[1].connected_node_ids.retain(|id| *id != 20);
+
+    assert!(TestNetwork::try_new(config, &SAMPLE_CHAT_CLIENTS).is_err());
+}
+
+#[test]
+fn crashing_a_drone_removes_it_and_notifies_its_neighbors() {
+    let mut network = sample_network();
+
+    network.controller.apply_op(TopologyOp::Crash(2)).unwrap();
+
+    let snapshot = network.controller.topology_snapshot();
+    assert!(!snapshot.drones.contains_key(&2));
+    assert!(snapshot.edges.iter().all(|(a, b)| *a != 2 && *b != 2));
+    assert_eq!(neighbors(&network.controller, 2), None);
+    assert_eq!(neighbors(&network.controller, 20), Some(vec![1, 4]));
+    assert_eq!(neighbors(&network.controller, 1), Some(vec![3, 4, 10, 20]));
+
+    let commands = network.drone(2).take_commands();
+    assert!(matches!(commands.as_slice(), [DroneCommand::Crash]));
+    for neighbor in [1, 3] {
+        let commands = network.drone(neighbor).take_commands();
+        assert!(matches!(
+            commands.as_slice(),
+            [DroneCommand::RemoveSender(2)]
+        ));
+    }
+}
+
+#[test]
+fn a_crashed_drone_leaves_no_channels_nor_configuration() {
+    let mut network = sample_network();
+    let events = network.drone(2).events.clone();
+
+    network.controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    network.controller.process_events();
+
+    assert_eq!(network.controller.drone_pdr(2), None);
+    let config = network.controller.export_config();
+    assert!(config.drone.iter().all(|d| d.id != 2));
+    // The event receiver is dropped after the grace frame
+    assert!(events
+        .send(DroneEvent::PacketSent(ack(&[1, 2], 1)))
+        .is_err());
+    // The packet sender is gone too, shortcuts can't reach the drone anymore
+    network
+        .controller
+        .inject_event(1, DroneEvent::ControllerShortcut(ack(&[10, 1, 2], 1)));
+    assert!(network
+        .controller
+        .log_lines()
+        .iter()
+        .any(|line| line.ends_with("failed: unknown destination")));
+    assert_eq!(network.drone(2).packets.try_iter().count(), 0);
+}
+
+#[test]
+fn crash_that_breaks_a_constraint_is_rejected() {
+    let mut network = sample_network();
+    network.controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    network.clear_commands();
+
+    // Server 20 would be left with drone 1 only
+    let error = network
+        .controller
+        .apply_op(TopologyOp::Crash(4))
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        ValidationError::Violations(vec![ControllerError::ServerMinConnections(20)])
+    );
+    assert!(network
+        .controller
+        .topology_snapshot()
+        .drones
+        .contains_key(&4));
+    assert!(network.drone(4).take_commands().is_empty());
+}
+
+#[test]
+fn only_drones_can_crash() {
+    let mut network = sample_network();
+
+    let error = network
+        .controller
+        .apply_op(TopologyOp::Crash(20))
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        ValidationError::Violations(vec![ControllerError::DroneNotFound(20)])
+    );
+}
+
+#[test]
+fn adding_an_edge_connects_both_nodes() {
+    let mut network = sample_network();
+
+    network
+        .controller
+        .apply_op(TopologyOp::Connect(2, 4))
+        .unwrap();
+
+    assert!(network
+        .controller
+        .topology_snapshot()
+        .edges
+        .contains(&(2, 4)));
+    assert_eq!(neighbors(&network.controller, 2), Some(vec![1, 3, 4, 20]));
+    assert_eq!(
+        neighbors(&network.controller, 4),
+        Some(vec![1, 2, 3, 11, 20])
+    );
+    let commands = network.drone(2).take_commands();
+    assert!(matches!(
+        commands.as_slice(),
+        [DroneCommand::AddSender(4, _)]
+    ));
+    let commands = network.drone(4).take_commands();
+    assert!(matches!(
+        commands.as_slice(),
+        [DroneCommand::AddSender(2, _)]
+    ));
+}
+
+#[test]
+fn eligible_neighbors_pass_the_checks_of_connect() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    // Clients can't get more than 2 connections, nor be connected to servers
+    assert_eq!(controller.eligible_neighbors(2), [4]);
+    assert_eq!(controller.eligible_neighbors(20), [3]);
+    assert!(controller.eligible_neighbors(1).is_empty());
+    assert!(controller.eligible_neighbors(11).is_empty());
+    assert!(controller.eligible_neighbors(99).is_empty());
+
+    controller.apply_op(TopologyOp::Disconnect(11, 3)).unwrap();
+    assert_eq!(controller.eligible_neighbors(11), [1, 2, 3]);
+    for id in controller.eligible_neighbors(11) {
+        assert!(controller.apply_op(TopologyOp::Connect(11, id)).is_ok());
+        controller.apply_op(TopologyOp::Disconnect(11, id)).unwrap();
+    }
+}
+
+#[test]
+fn invalid_edges_are_rejected() {
+    let mut network = sample_network();
+    let before = network.controller.topology_snapshot().edges;
+
+    let cases = [
+        (
+            TopologyOp::Connect(1, 2),
+            ValidationError::Rejected(ConnectionRejection::AlreadyConnected(1, 2)),
+        ),
+        (
+            TopologyOp::Connect(10, 2),
+            ValidationError::Rejected(ConnectionRejection::ClientAtMaxLinks(10)),
+        ),
+        (
+            TopologyOp::Connect(10, 11),
+            ValidationError::Rejected(ConnectionRejection::ClientToClient),
+        ),
+        (
+            TopologyOp::Connect(20, 10),
+            ValidationError::Rejected(ConnectionRejection::ServerToNonDrone),
+        ),
+        (
+            TopologyOp::Connect(3, 99),
+            ValidationError::Rejected(ConnectionRejection::UnknownId(99)),
+        ),
+    ];
+    for (op, expected) in cases {
+        assert_eq!(network.controller.apply_op(op), Err(expected));
+    }
+    assert_eq!(network.controller.topology_snapshot().edges, before);
+    network
+        .drones
+        .values()
+        .for_each(|drone| assert!(drone.take_commands().is_empty()));
+}
+
+#[test]
+fn removing_an_edge_disconnects_both_nodes() {
+    let mut network = sample_network();
+
+    network
+        .controller
+        .apply_op(TopologyOp::Disconnect(11, 3))
+        .unwrap();
+
+    assert_eq!(neighbors(&network.controller, 11), Some(vec![4]));
+    assert_eq!(neighbors(&network.controller, 3), Some(vec![1, 2, 4, 10]));
+    let commands = network.drone(3).take_commands();
+    assert!(matches!(
+        commands.as_slice(),
+        [DroneCommand::RemoveSender(11)]
+    ));
+}
+
+#[test]
+fn edge_removal_that_breaks_a_constraint_is_rejected() {
+    let mut network = sample_network();
+    network
+        .controller
+        .apply_op(TopologyOp::Disconnect(11, 3))
+        .unwrap();
+
+    // Chat client 11 would be cut from the network
+    let error = network
+        .controller
+        .apply_op(TopologyOp::Disconnect(11, 4))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::Violations(vec![ControllerError::ServerUnreachable(11)])
+    );
+
+    let error = network
+        .controller
+        .apply_op(TopologyOp::Disconnect(2, 4))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::Violations(vec![ControllerError::NoEdge(2, 4)])
+    );
+    assert_eq!(neighbors(&network.controller, 11), Some(vec![4]));
+}
+
+#[test]
+fn setting_a_pdr_updates_the_configuration() {
+    let mut network = sample_network();
+
+    network
+        .controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 3,
+            pdr: 0.5,
+        })
+        .unwrap();
+
+    assert_eq!(network.controller.topology_snapshot().drones[&3], 0.5);
+    let config = network.controller.export_config();
+    assert_eq!(config.drone.iter().find(|d| d.id == 3).unwrap().pdr, 0.5);
+    let commands = network.drone(3).take_commands();
+    assert!(matches!(
+        commands.as_slice(),
+        [DroneCommand::SetPacketDropRate(pdr)] if *pdr == 0.5
+    ));
+}
+
+#[test]
+fn the_pdr_of_a_drone_follows_its_changes() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    assert_eq!(controller.drone_pdr(2), Some(0.0));
+
+    controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 2,
+            pdr: 0.3,
+        })
+        .unwrap();
+
+    assert_eq!(controller.drone_pdr(2), Some(0.3));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.json");
+    controller.export_json_topology(&path).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let drone = json["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|node| node["id"] == 2)
+        .unwrap();
+    assert!((drone["pdr"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+
+    controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    assert_eq!(controller.drone_pdr(2), None);
+    assert_eq!(controller.drone_pdr(20), None);
+}
+
+#[test]
+fn undoing_a_crash_restores_the_pdr_shown_for_the_drone() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 2,
+            pdr: 0.4,
+        })
+        .unwrap();
+    controller.apply_op(TopologyOp::Crash(2)).unwrap();
+
+    controller.undo();
+
+    assert_eq!(controller.drone_pdr(2), Some(0.4));
+    assert_eq!(controller.topology_snapshot().drones[&2], 0.4);
+    assert_eq!(neighbors(controller, 2), Some(vec![1, 3, 20]));
+}
+
+#[test]
+fn invalid_pdrs_are_rejected() {
+    let mut network = sample_network();
+
+    let error = network
+        .controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 3,
+            pdr: 1.5,
+        })
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::Violations(vec![ControllerError::PdrOutOfRange(1.5)])
+    );
+    assert!(network
+        .controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 20,
+            pdr: 0.5,
+        })
+        .is_err());
+
+    assert_eq!(network.controller.topology_snapshot().drones[&3], 0.0);
+    assert!(network.drone(3).take_commands().is_empty());
+}
+
+#[test]
+fn spawns_that_break_a_constraint_are_rejected() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    let spawn = |drone_id, implementation| TopologyOp::Spawn {
+        drone_id,
+        implementation,
+        pdr: 0.0,
+        neighbors: vec![2],
+    };
+    let spawn_node = |kind, neighbors: &[NodeId]| TopologyOp::SpawnNode {
+        node_id: 50,
+        kind,
+        neighbors: neighbors.to_vec(),
+    };
+    let violation =
+        |error| -> Result<(), ValidationError> { Err(ValidationError::Violations(vec![error])) };
+
+    assert_eq!(
+        controller.apply_op(spawn(1, None)),
+        violation(ControllerError::IdAlreadyUsed(1))
+    );
+    assert_eq!(
+        controller.apply_op(spawn(50, Some(usize::MAX))),
+        violation(ControllerError::UnknownImplementation(usize::MAX))
+    );
+    assert_eq!(
+        controller.apply_op(spawn_node(NodeKind::WebClient, &[])),
+        violation(ControllerError::ClientMinConnections(50))
+    );
+    assert_eq!(
+        controller.apply_op(spawn_node(NodeKind::ChatClient, &[1, 2, 3])),
+        violation(ControllerError::ClientMaxConnections(50))
+    );
+    assert_eq!(
+        controller.apply_op(spawn_node(NodeKind::Server, &[1])),
+        violation(ControllerError::ServerMinConnections(50))
+    );
+    assert_eq!(controller.topology_snapshot().drones.len(), 4);
+}
+
+#[test]
+fn removing_the_last_edge_of_a_drone_is_rejected() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller
+        .apply_op(TopologyOp::Spawn {
+            drone_id: 50,
+            implementation: None,
+            pdr: 0.0,
+            neighbors: vec![2],
+        })
+        .unwrap();
+
+    assert_eq!(
+        controller.apply_op(TopologyOp::Disconnect(50, 2)),
+        Err(ValidationError::Violations(vec![
+            ControllerError::WouldDisconnectGraph
+        ]))
+    );
+}
+
+#[test]
+fn commands_to_a_stopped_drone_are_reported() {
+    let mut network = sample_network();
+    // The drone thread is gone: its end of the channels is dropped
+    network.drones.remove(&2);
+
+    let error = network
+        .controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 2,
+            pdr: 0.5,
+        })
+        .unwrap_err();
+
+    assert!(matches!(error, ValidationError::Failed(_)));
+    assert_eq!(network.controller.topology_snapshot().drones[&2], 0.0);
+}
+
+#[test]
+fn events_of_the_nodes_reach_the_log() {
+    let mut network = sample_network();
+    let packet = ack(&[10, 1, 2, 20], 2);
+
+    network
+        .drone(1)
+        .events
+        .send(DroneEvent::PacketSent(packet.clone()))
+        .unwrap();
+    network
+        .drone(2)
+        .events
+        .send(DroneEvent::PacketDropped(packet))
+        .unwrap();
+    network.controller.process_events();
+
+    let lines = network.controller.log_lines();
+    assert!(lines
+        .iter()
+        .any(|line| line == "[DRONE: 1] Sent Ack packet"));
+    assert!(lines
+        .iter()
+        .any(|line| line == "[DRONE: 2] Dropped Ack packet"));
+}
+
+#[test]
+fn shortcuts_are_delivered_to_the_destination() {
+    let mut network = sample_network();
+    network
+        .drone(3)
+        .events
+        .send(DroneEvent::ControllerShortcut(ack(&[20, 4, 3, 11], 2)))
+        .unwrap();
+    network.controller.process_events();
+    network
+        .controller
+        .inject_event(1, DroneEvent::ControllerShortcut(ack(&[20, 1, 10], 1)));
+
+    let delivered = network.chat_clients[&11]
+        .packets
+        .try_iter()
+        .collect::<Vec<_>>();
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].routing_header.hops, [20, 4, 3, 11]);
+    assert_eq!(delivered[0].session_id, 7);
+    assert_eq!(network.web_clients[&10].packets.try_iter().count(), 1);
+    assert_eq!(network.servers[&20].packets.try_iter().count(), 0);
+}
+
+#[test]
+fn shortcuts_to_unknown_nodes_are_logged_as_errors() {
+    let mut network = sample_network();
+
+    network
+        .controller
+        .inject_event(3, DroneEvent::ControllerShortcut(ack(&[20, 3, 99], 1)));
+
+    assert!(network
+        .controller
+        .log_lines()
+        .iter()
+        .any(|line| line.ends_with("failed: unknown destination")));
+    assert_eq!(network.chat_clients[&11].packets.try_iter().count(), 0);
+}
+
+#[test]
+fn late_events_of_a_crashed_drone_are_only_logged() {
+    let mut network = sample_network();
+    let events = network.drone(2).events.clone();
+    events
+        .send(DroneEvent::PacketSent(ack(&[20, 2, 1, 10], 2)))
+        .unwrap();
+    events
+        .send(DroneEvent::PacketDropped(ack(&[20, 2, 1, 10], 1)))
+        .unwrap();
+    events
+        .send(DroneEvent::ControllerShortcut(ack(&[20, 2, 3, 11], 1)))
+        .unwrap();
+    events
+        .send(DroneEvent::ControllerShortcut(ack(&[], 0)))
+        .unwrap();
+
+    network.controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    network.controller.process_events();
+    // The receiver is dropped after the grace frame
+    assert!(events
+        .send(DroneEvent::PacketSent(ack(&[20, 2], 1)))
+        .is_err());
+    network.controller.process_events();
+
+    let late = network
+        .controller
+        .log_lines()
+        .into_iter()
+        .filter(|line| line.ends_with("(late, node crashed)"))
+        .collect::<Vec<String>>();
+    assert_eq!(late.len(), 4);
+    assert!(late[0].starts_with("[DRONE: 2] Sent Ack packet"));
+    assert!(late[1].starts_with("[DRONE: 2] Dropped Ack packet"));
+    assert!(late[3].contains("without destination"));
+    assert!(!network
+        .controller
+        .topology_snapshot()
+        .drones
+        .contains_key(&2));
+    assert_eq!(network.chat_clients[&11].packets.try_iter().count(), 1);
+}
+
+#[test]
+fn client_events_from_unknown_nodes_are_dropped_with_a_warning() {
+    let mut network = sample_network();
+
+    network.controller.inject_event(
+        99,
+        WebClientEvent::ListOfFiles(vec!["index.html".to_string()], 20),
+    );
+    network
+        .controller
+        .inject_event(99, WebClientEvent::ServersTypes(HashMap::new()));
+    network
+        .controller
+        .inject_event(99, WebClientEvent::UnsupportedRequest);
+    network
+        .controller
+        .inject_event(98, ChatClientEvent::ServersTypes(HashMap::new()));
+    network
+        .controller
+        .inject_event(98, ChatClientEvent::UnsupportedRequest);
+
+    let lines = network.controller.log_lines();
+    for (id, event) in [
+        (99, "ListOfFiles"),
+        (99, "ServersTypes"),
+        (99, "UnsupportedRequest"),
+        (98, "ServersTypes"),
+        (98, "UnsupportedRequest"),
+    ] {
+        let warning = format!("[CONTROLLER] Dropped {event} event from unknown node {id}");
+        assert!(lines.contains(&warning), "missing: {warning}");
+    }
+    assert_eq!(network.controller.topology_snapshot().edges.len(), 12);
+}
+
+#[test]
+fn the_pdr_is_set_on_every_selected_drone() {
+    let mut network = sample_network();
+    network.clear_commands();
+
+    network.controller.select_nodes(&[1, 2, 3, 10]);
+    let summary = network.controller.set_selection_pdr(0.5).to_string();
+
+    assert_eq!(summary, "PDR set on 3 drones, 1 non-drone nodes skipped");
+    for id in [1, 2, 3] {
+        let commands = network.drone(id).take_commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [DroneCommand::SetPacketDropRate(pdr)] if *pdr == 0.5
+            ),
+            "drone {id}"
+        );
+        assert_eq!(network.controller.drone_pdr(id), Some(0.5));
+    }
+    assert!(network.drone(4).take_commands().is_empty());
+    assert!(network.web_clients[&10].take_commands().is_empty());
+}
+
+#[test]
+fn shutdown_crashes_every_drone_and_logs_their_last_events() {
+    let mut network = sample_network();
+    network.clear_commands();
+    network
+        .drone(3)
+        .events
+        .send(DroneEvent::PacketSent(ack(&[10, 1, 3, 20], 2)))
+        .unwrap();
+
+    network.controller.shutdown();
+
+    for id in [1, 2, 3, 4] {
+        let commands = network.drone(id).take_commands();
+        assert!(
+            matches!(commands.as_slice(), [DroneCommand::Crash]),
+            "drone {id}"
+        );
+    }
+    let lines = network.controller.log_lines();
+    assert!(lines
+        .iter()
+        .any(|line| line == "[DRONE: 3] Sent Ack packet"));
+    assert!(lines
+        .iter()
+        .any(|line| line == "[CONTROLLER] Shutdown: 4 drones sent the crash command"));
+}
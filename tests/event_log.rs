@@ -0,0 +1,121 @@
+//! Event log of the controller: pausing, capacity and discarded entries
+
+mod support;
+
+use simulation_controller::{topology::TopologyOp, SimulationController};
+use support::sample_network;
+
+#[test]
+fn the_paused_log_keeps_at_most_its_capacity() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller.set_log_capacity(5);
+    let discarded = controller.log_discarded();
+
+    controller.set_log_paused(true);
+    for step in 1..=8_u8 {
+        controller
+            .apply_op(TopologyOp::SetPdr {
+                drone_id: 1,
+                pdr: f32::from(step) / 100.0,
+            })
+            .unwrap();
+    }
+    assert!(controller
+        .log_lines()
+        .iter()
+        .all(|line| !line.contains("Set PDR")));
+    // The 3 oldest entries of the buffer are dropped while paused
+    assert_eq!(controller.log_discarded(), discarded + 3);
+
+    controller.set_log_paused(false);
+
+    let lines = controller.log_lines();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[0].ends_with("Set PDR of drone 1 to 0.04"));
+    assert!(lines[4].ends_with("Set PDR of drone 1 to 0.08"));
+}
+
+#[test]
+fn resizing_the_log_keeps_the_newest_entries() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller.set_log_capacity(100);
+    for step in 1..=4_u8 {
+        controller
+            .apply_op(TopologyOp::SetPdr {
+                drone_id: 1,
+                pdr: f32::from(step) / 100.0,
+            })
+            .unwrap();
+    }
+    let lines = controller.log_lines();
+
+    controller.set_log_capacity(1000);
+    assert_eq!(controller.log_lines(), lines);
+
+    controller.set_log_capacity(2);
+    assert_eq!(controller.log_lines(), lines[lines.len() - 2..]);
+    controller.set_log_capacity(0);
+    assert_eq!(controller.log_lines(), lines[lines.len() - 1..]);
+}
+
+#[test]
+fn entries_evicted_from_the_log_are_counted() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller.set_log_capacity(3);
+    let discarded = controller.log_discarded();
+    let kept = controller.log_lines().len();
+
+    for step in 1..=5_u8 {
+        controller
+            .apply_op(TopologyOp::SetPdr {
+                drone_id: 1,
+                pdr: f32::from(step) / 100.0,
+            })
+            .unwrap();
+    }
+    assert_eq!(controller.log_lines().len(), 3);
+    assert_eq!(controller.log_discarded(), discarded + kept + 5 - 3);
+
+    controller.set_log_capacity(1);
+    assert_eq!(controller.log_discarded(), discarded + kept + 5 - 1);
+}
+
+#[test]
+fn the_log_file_gets_a_line_per_entry() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    let set_pdrs = |controller: &mut SimulationController, steps: std::ops::RangeInclusive<u8>| {
+        for step in steps {
+            controller
+                .apply_op(TopologyOp::SetPdr {
+                    drone_id: 1,
+                    pdr: f32::from(step) / 100.0,
+                })
+                .unwrap();
+        }
+    };
+
+    controller.set_log_file(&path).unwrap();
+    set_pdrs(controller, 1..=3);
+    controller.close_log_file();
+    // Entries logged while the file is closed are not written
+    set_pdrs(controller, 4..=4);
+    // The file is appended to when it's set again
+    controller.set_log_file(&path).unwrap();
+    set_pdrs(controller, 5..=6);
+    controller.close_log_file();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 5);
+    for (line, pdr) in lines.iter().zip(["0.01", "0.02", "0.03", "0.05", "0.06"]) {
+        let (timestamp_ms, text) = line.split_once(' ').unwrap();
+        assert!(timestamp_ms.parse::<u128>().is_ok());
+        assert_eq!(text, format!("[CONTROLLER] Set PDR of drone 1 to {pdr}"));
+    }
+}
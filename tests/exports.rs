@@ -0,0 +1,123 @@
+//! Exports of the event log and of the topology
+
+mod support;
+
+use simulation_controller::{
+    topology::TopologyOp,
+    utils::{EventKind, LogLevel},
+};
+use support::{log_event, push_events, sample_network};
+
+#[test]
+fn csv_export_has_a_row_per_event() {
+    let mut network = sample_network();
+    let events = (0..10)
+        .map(|i| {
+            log_event(
+                1,
+                EventKind::Packet,
+                LogLevel::Info,
+                &format!("[DRONE: 1] Sent Ack packet {i}"),
+            )
+        })
+        .collect();
+    push_events(&mut network.controller, events);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.csv");
+
+    network.controller.export_csv(&path).unwrap();
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let rows = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(rows.len(), 11);
+    assert_eq!(
+        rows[0],
+        "timestamp_secs,node_id,node_type,event_type,detail"
+    );
+    assert!(rows[1].ends_with(",1,drone,packet,[DRONE: 1] Sent Ack packet 0"));
+    assert!(rows[10].ends_with(",1,drone,packet,[DRONE: 1] Sent Ack packet 9"));
+}
+
+#[test]
+fn csv_export_creates_the_missing_folders() {
+    let network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("exports").join("events.csv");
+
+    network.controller.export_csv(&path).unwrap();
+
+    assert!(path.exists());
+}
+
+#[test]
+fn json_export_has_the_fields_of_each_event() {
+    let mut network = sample_network();
+    push_events(
+        &mut network.controller,
+        vec![
+            log_event(
+                2,
+                EventKind::PacketDropped,
+                LogLevel::Warn,
+                "[DRONE: 2] Dropped Ack packet",
+            ),
+            log_event(
+                3,
+                EventKind::Shortcut,
+                LogLevel::Info,
+                "[DRONE: 3] Shortcut",
+            ),
+        ],
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.json");
+
+    network.controller.export_json(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&content).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["node_id"], 2);
+    assert_eq!(entries[0]["event_type"], "packet_dropped");
+    assert_eq!(entries[0]["message"], "[DRONE: 2] Dropped Ack packet");
+    assert_eq!(entries[1]["node_id"], 3);
+    assert_eq!(entries[1]["event_type"], "shortcut");
+    assert_eq!(entries[1]["message"], "[DRONE: 3] Shortcut");
+    let elapsed = entries
+        .iter()
+        .map(|entry| entry["elapsed_secs"].as_f64().unwrap())
+        .collect::<Vec<f64>>();
+    assert!(elapsed[0] >= 0.0 && elapsed[0] <= elapsed[1]);
+}
+
+#[test]
+fn dot_export_has_a_line_per_node_and_edge() {
+    let mut network = sample_network();
+    network.controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.dot");
+
+    network.controller.export_dot(&path).unwrap();
+
+    let dot = std::fs::read_to_string(&path).unwrap();
+    let lines = dot.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.first(), Some(&"graph G {"));
+    assert_eq!(lines.last(), Some(&"}"));
+    let node_lines = lines
+        .iter()
+        .filter(|line| line.contains("[label="))
+        .collect::<Vec<_>>();
+    let edge_lines = lines.iter().filter(|line| line.contains(" -- ")).count();
+    let snapshot = network.controller.topology_snapshot();
+    // Drones 1, 3 and 4, clients 10 and 11, server 20
+    assert_eq!(node_lines.len(), 6);
+    assert_eq!(edge_lines, snapshot.edges.len());
+    assert!(node_lines.contains(&&"    1 [label=\"Drone 1\", shape=circle];"));
+    assert!(lines.contains(&"    1 -- 3;"));
+    assert!(node_lines
+        .iter()
+        .any(|line| line.starts_with("    20 ") && line.ends_with("shape=box];")));
+    assert!(node_lines
+        .iter()
+        .any(|line| line.starts_with("    10 ") && line.ends_with("shape=diamond];")));
+}
@@ -0,0 +1,245 @@
+//! Events of every kind of node, injected with `inject_event` as if they were
+//! received from the channels
+
+mod support;
+
+use common::slc_commands::{ChatClientEvent, ServerEvent, WebClientEvent};
+use serde_json::{json, Value};
+use simulation_controller::sink::EventSink;
+use support::{ack, sample_network};
+use wg_2024::controller::DroneEvent;
+
+#[test]
+fn packets_sent_by_every_kind_of_node_are_logged() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    controller.inject_event(1, DroneEvent::PacketSent(ack(&[20, 1, 10], 1)));
+    controller.inject_event(10, WebClientEvent::PacketSent(ack(&[10, 1, 20], 0)));
+    controller.inject_event(11, ChatClientEvent::PacketSent(ack(&[11, 4, 20], 0)));
+    controller.inject_event(20, ServerEvent::PacketSent(ack(&[20, 4, 11], 0)));
+
+    let lines = controller.log_lines();
+    for expected in [
+        "[DRONE: 1] Sent Ack packet",
+        "[WEB CLIENT: 10] Sent Ack packet",
+        "[CHAT CLIENT: 11] Sent Ack packet",
+        "[SERVER: 20] Sent Ack packet",
+    ] {
+        assert!(
+            lines.iter().any(|line| line == expected),
+            "missing: {expected}"
+        );
+    }
+}
+
+#[test]
+fn shortcuts_of_every_kind_of_node_are_delivered() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    controller.inject_event(1, DroneEvent::ControllerShortcut(ack(&[20, 1, 10], 1)));
+    controller.inject_event(10, WebClientEvent::Shortcut(ack(&[10, 1, 20], 0)));
+    controller.inject_event(11, ChatClientEvent::Shortcut(ack(&[11, 4, 20], 0)));
+    controller.inject_event(20, ServerEvent::ShortCut(ack(&[20, 4, 11], 0)));
+
+    assert_eq!(network.web_clients[&10].packets.try_iter().count(), 1);
+    assert_eq!(network.chat_clients[&11].packets.try_iter().count(), 1);
+    assert_eq!(network.servers[&20].packets.try_iter().count(), 2);
+    let lines = network.controller.log_lines();
+    for prefix in [
+        "[DRONE: 1] Requested shortcut for packet Ack to 10",
+        "[WEB CLIENT: 10] Requested shortcut for packet Ack to 20",
+        "[CHAT CLIENT: 11] Requested shortcut for packet Ack to 20",
+        "[SERVER: 20] Requested shortcut for packet Ack to 11",
+    ] {
+        assert!(
+            lines.iter().any(|line| line.starts_with(prefix)),
+            "missing: {prefix}"
+        );
+    }
+}
+
+#[test]
+fn shortcuts_without_destination_are_logged_as_errors() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    controller.inject_event(1, DroneEvent::ControllerShortcut(ack(&[], 0)));
+    controller.inject_event(10, WebClientEvent::Shortcut(ack(&[], 0)));
+    controller.inject_event(11, ChatClientEvent::Shortcut(ack(&[], 0)));
+    controller.inject_event(20, ServerEvent::ShortCut(ack(&[], 0)));
+
+    let lines = controller.log_lines();
+    for prefix in [
+        "[DRONE: 1]",
+        "[WEB CLIENT: 10]",
+        "[CHAT CLIENT: 11]",
+        "[SERVER: 20]",
+    ] {
+        let expected = format!("{prefix} Requested shortcut for packet Ack without destination");
+        assert!(
+            lines.iter().any(|line| line.starts_with(&expected)),
+            "missing: {expected}"
+        );
+    }
+    // The controller keeps working after the invalid shortcuts
+    controller.inject_event(1, DroneEvent::PacketSent(ack(&[20, 1, 10], 1)));
+    assert_eq!(
+        controller.log_lines().last().map(String::as_str),
+        Some("[DRONE: 1] Sent Ack packet")
+    );
+}
+
+#[test]
+fn unsupported_requests_are_logged() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    controller.inject_event(10, WebClientEvent::UnsupportedRequest);
+    controller.inject_event(11, ChatClientEvent::UnsupportedRequest);
+
+    let lines = controller.log_lines();
+    assert!(lines.contains(&"[WEB CLIENT: 10] Unsupported request".to_string()));
+    assert!(lines.contains(&"[CHAT CLIENT: 11] Unsupported request".to_string()));
+}
+
+#[test]
+fn every_pending_event_is_handled_in_one_frame() {
+    let mut network = sample_network();
+    for i in 0..50 {
+        let hop = [1, 2, 3, 4][i % 4];
+        network
+            .drone(hop)
+            .events
+            .send(DroneEvent::PacketSent(ack(&[20, hop, 10], 1)))
+            .unwrap();
+    }
+
+    network.controller.process_events();
+
+    let sent = network
+        .controller
+        .log_lines()
+        .into_iter()
+        .filter(|line| line.ends_with("Sent Ack packet"))
+        .count();
+    assert_eq!(sent, 50);
+}
+
+#[test]
+fn events_past_the_cap_wait_for_the_next_frame() {
+    let mut network = sample_network();
+    network.controller.set_max_events_per_frame(20);
+    for _ in 0..50 {
+        network
+            .drone(1)
+            .events
+            .send(DroneEvent::PacketSent(ack(&[20, 1, 10], 1)))
+            .unwrap();
+    }
+    let sent = |network: &support::TestNetwork| {
+        network
+            .controller
+            .log_lines()
+            .into_iter()
+            .filter(|line| line == "[DRONE: 1] Sent Ack packet")
+            .count()
+    };
+
+    network.controller.process_events();
+    assert_eq!(sent(&network), 20);
+    network.controller.process_events();
+    network.controller.process_events();
+    assert_eq!(sent(&network), 50);
+}
+
+#[test]
+fn shortcuts_reach_every_category_of_destination() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+
+    controller.inject_event(1, DroneEvent::ControllerShortcut(ack(&[10, 1, 2], 1)));
+    controller.inject_event(1, DroneEvent::ControllerShortcut(ack(&[20, 1, 10], 1)));
+    controller.inject_event(4, DroneEvent::ControllerShortcut(ack(&[20, 4, 11], 1)));
+    controller.inject_event(4, DroneEvent::ControllerShortcut(ack(&[11, 4, 20], 1)));
+    controller.inject_event(4, DroneEvent::ControllerShortcut(ack(&[11, 4, 99], 1)));
+
+    let delivered = |packets: &crossbeam_channel::Receiver<_>| {
+        packets
+            .try_iter()
+            .map(|packet: wg_2024::packet::Packet| packet.routing_header.hops)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(delivered(&network.drone(2).packets), [vec![10, 1, 2]]);
+    assert_eq!(
+        delivered(&network.web_clients[&10].packets),
+        [vec![20, 1, 10]]
+    );
+    assert_eq!(
+        delivered(&network.chat_clients[&11].packets),
+        [vec![20, 4, 11]]
+    );
+    assert_eq!(delivered(&network.servers[&20].packets), [vec![11, 4, 20]]);
+    let failures = network
+        .controller
+        .log_lines()
+        .into_iter()
+        .filter(|line| line.contains("Shortcut delivery of Ack"))
+        .collect::<Vec<_>>();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].ends_with("failed: unknown destination"));
+}
+
+#[test]
+fn logged_events_keep_the_fields_of_the_event() {
+    let mut network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.jsonl");
+    let file = std::fs::File::create(&path).unwrap();
+    let controller = &mut network.controller;
+    controller.set_event_sink(Some(EventSink::new(file)));
+
+    controller.inject_event(1, DroneEvent::PacketSent(ack(&[20, 1, 10], 1)));
+    controller.inject_event(2, DroneEvent::PacketDropped(ack(&[20, 2, 1, 10], 1)));
+    controller.inject_event(10, WebClientEvent::Shortcut(ack(&[10, 1, 20], 0)));
+    controller.inject_event(11, ChatClientEvent::UnsupportedRequest);
+    controller.inject_event(20, ServerEvent::PacketSent(ack(&[20, 4, 11], 0)));
+    // Dropping the sink writes the queued records
+    controller.set_event_sink(None);
+
+    let records = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .filter(|record| record["node_id"] != Value::Null)
+        .collect::<Vec<Value>>();
+    let fields = records
+        .iter()
+        .map(|record| {
+            let fields = [
+                "kind",
+                "node_id",
+                "node_kind",
+                "destination",
+                "severity",
+                "session_id",
+            ];
+            Value::from(fields.map(|field| record[field].clone()).to_vec())
+        })
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        fields,
+        [
+            json!(["packet", 1, "drone", null, "debug", 7]),
+            json!(["packet_dropped", 2, "drone", null, "warn", 7]),
+            json!(["shortcut", 10, "web_client", 20, "info", 7]),
+            json!(["unsupported_request", 11, "chat_client", null, "warn", null]),
+            json!(["packet", 20, "server", null, "debug", 7]),
+        ]
+    );
+    for record in records.iter().filter(|record| record["session_id"] == 7) {
+        assert!(record["packet"].as_str().unwrap().starts_with("Ack "));
+    }
+    assert_eq!(records[3]["packet"], Value::Null);
+}
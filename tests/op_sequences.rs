@@ -0,0 +1,100 @@
+//! Random sequences of topology operations, checking the invariants of the
+//! controller after each step
+
+mod support;
+
+use std::collections::BTreeSet;
+
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+use simulation_controller::{stats::edge_key, topology::TopologyOp, SimulationController};
+use support::{ack, config_nodes, sample_network, TestNetwork};
+use wg_2024::{controller::DroneEvent, network::NodeId};
+
+const IDS: [NodeId; 7] = [1, 2, 3, 4, 10, 11, 20];
+const DRONES: [NodeId; 4] = [1, 2, 3, 4];
+
+fn random_op(rng: &mut StdRng) -> TopologyOp {
+    let id = |rng: &mut StdRng| *IDS.choose(rng).unwrap();
+    match rng.random_range(0..10) {
+        0..=3 => TopologyOp::Connect(id(rng), id(rng)),
+        4..=7 => TopologyOp::Disconnect(id(rng), id(rng)),
+        8 => TopologyOp::Crash(id(rng)),
+        _ => TopologyOp::SetPdr {
+            drone_id: id(rng),
+            pdr: rng.random_range(-0.5..1.5),
+        },
+    }
+}
+
+/// Check that the configuration and the graph describe the same network
+fn assert_consistent(controller: &SimulationController) {
+    let snapshot = controller.topology_snapshot();
+    let config = controller.export_config();
+    let mut edges = BTreeSet::new();
+    for (id, neighbors, _) in config_nodes(&config) {
+        let unique = neighbors.iter().collect::<BTreeSet<_>>();
+        assert_eq!(unique.len(), neighbors.len(), "duplicated neighbor of {id}");
+        for neighbor in neighbors {
+            edges.insert(edge_key(id, neighbor));
+        }
+    }
+    assert_eq!(edges, snapshot.edges);
+    // Every edge is listed by both nodes
+    for (a, b) in &snapshot.edges {
+        for (id, neighbor) in [(a, b), (b, a)] {
+            let listed = config_nodes(&config)
+                .into_iter()
+                .any(|(node, neighbors, _)| node == *id && neighbors.contains(neighbor));
+            assert!(listed, "edge {a}-{b} missing from node {id}");
+        }
+    }
+    let drones = config.drone.iter().map(|d| d.id).collect::<Vec<_>>();
+    assert_eq!(drones, snapshot.drones.keys().copied().collect::<Vec<_>>());
+}
+
+/// Check that the controller listens only to the drones that are still alive
+fn assert_channels_follow_the_drones(network: &mut TestNetwork) {
+    // The receivers of the crashed drones are dropped after a grace frame
+    network.controller.process_events();
+    network.controller.process_events();
+    let alive = network.controller.topology_snapshot().drones;
+    for id in DRONES {
+        let sent = network
+            .drone(id)
+            .events
+            .send(DroneEvent::PacketSent(ack(&[id, 20], 0)))
+            .is_ok();
+        assert_eq!(sent, alive.contains_key(&id), "channel of drone {id}");
+    }
+    network.controller.process_events();
+}
+
+#[test]
+fn random_operations_keep_the_network_consistent() {
+    for seed in 0..20 {
+        let mut network = sample_network();
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..40 {
+            let op = random_op(&mut rng);
+            let snapshot = network.controller.topology_snapshot();
+            let config = config_nodes(&network.controller.export_config());
+
+            let result = network.controller.apply_op(op.clone());
+
+            if result.is_err() {
+                assert_eq!(
+                    network.controller.topology_snapshot(),
+                    snapshot,
+                    "seed {seed}: rejected {op:?} changed the graph"
+                );
+                assert_eq!(
+                    config_nodes(&network.controller.export_config()),
+                    config,
+                    "seed {seed}: rejected {op:?} changed the configuration"
+                );
+            }
+            assert_consistent(&network.controller);
+        }
+        assert_channels_follow_the_drones(&mut network);
+    }
+}
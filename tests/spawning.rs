@@ -0,0 +1,121 @@
+//! Allocation of the ids of the drones spawned at runtime, and their threads
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use simulation_controller::{platform, topology::TopologyOp};
+use support::{sample_network, TestNetwork};
+use wg_2024::{
+    config::{Config, Drone},
+    network::NodeId,
+};
+
+/// Spawn a drone connected to drone 1 with a newly allocated id
+fn spawn(network: &mut TestNetwork, recycle_crashed: bool) -> NodeId {
+    let drone_id = network
+        .controller
+        .allocate_node_id(recycle_crashed)
+        .unwrap();
+    network
+        .controller
+        .apply_op(TopologyOp::Spawn {
+            drone_id,
+            implementation: None,
+            pdr: 0.0,
+            neighbors: vec![1],
+        })
+        .unwrap();
+    drone_id
+}
+
+#[test]
+fn crashed_ids_are_recycled_only_on_request() {
+    let mut network = sample_network();
+    let spawned = spawn(&mut network, false);
+    assert_eq!(spawned, 0);
+
+    network
+        .controller
+        .apply_op(TopologyOp::Crash(spawned))
+        .unwrap();
+
+    assert_eq!(network.controller.allocate_node_id(false), Ok(5));
+    assert_eq!(network.controller.allocate_node_id(true), Ok(0));
+    assert_eq!(spawn(&mut network, true), 0);
+    let drones = network.controller.export_config().drone;
+    let ids = drones.iter().map(|d| d.id).collect::<Vec<_>>();
+    assert_eq!(ids.iter().filter(|id| **id == 0).count(), 1);
+}
+
+#[test]
+fn allocation_fails_when_every_id_is_in_use() {
+    // A chain of drones using every id
+    let drone = |id: NodeId| Drone {
+        id,
+        connected_node_ids: [id.checked_sub(1), id.checked_add(1)]
+            .into_iter()
+            .flatten()
+            .collect(),
+        pdr: 0.0,
+    };
+    let config = Config {
+        drone: (0..=NodeId::MAX).map(drone).collect(),
+        client: Vec::new(),
+        server: Vec::new(),
+    };
+    let network = TestNetwork::new(config, &[]);
+
+    let error = network.controller.allocate_node_id(true).unwrap_err();
+    assert_eq!(error, "All the 256 node ids are in use");
+}
+
+#[test]
+fn drones_spawned_in_a_row_get_distinct_new_ids() {
+    let mut network = sample_network();
+    let existing = network.controller.topology_snapshot();
+    let existing_ids = [1, 2, 3, 4, 10, 11, 20];
+
+    let spawned = (0..5)
+        .map(|_| spawn(&mut network, false))
+        .collect::<Vec<NodeId>>();
+
+    assert_eq!(spawned, [0, 5, 6, 7, 8]);
+    assert!(spawned.iter().all(|id| !existing_ids.contains(id)));
+    assert!(spawned.iter().all(|id| !existing.drones.contains_key(id)));
+    let drones = network.controller.topology_snapshot().drones;
+    assert_eq!(drones.len(), 4 + spawned.len());
+    for id in &spawned {
+        assert!(drones.contains_key(id), "drone {id} missing");
+    }
+}
+
+#[test]
+fn the_thread_of_a_crashed_drone_is_joined() {
+    if !platform::CAN_SPAWN_DRONES {
+        return;
+    }
+    let mut network = sample_network();
+    let spawned = spawn(&mut network, false);
+    // The `AddSender` command received by drone 1 holds a sender to the new drone
+    network.clear_commands();
+    assert_eq!(network.controller.unjoined_drone_threads(), 1);
+
+    network
+        .controller
+        .apply_op(TopologyOp::Crash(spawned))
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while network.controller.unjoined_drone_threads() > 0 && Instant::now() < deadline {
+        network.controller.process_events();
+        network.controller.reap_drone_threads();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(network.controller.unjoined_drone_threads(), 0);
+    assert!(!network
+        .controller
+        .log_lines()
+        .iter()
+        .any(|line| line.contains("failed to terminate")));
+}
@@ -0,0 +1,287 @@
+//! Network of fake nodes driving a `SimulationController` without the GUI
+//!
+//! The tests hold the node side of every channel: they read the commands sent
+//! by the controller and send events in place of the nodes.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use common::slc_commands::{
+    ChatClientCommand, ChatClientEvent, ServerCommand, ServerEvent, WebClientCommand,
+    WebClientEvent,
+};
+use crossbeam_channel::{Receiver, Sender};
+use simulation_controller::{
+    utils::{EventKind, LogEvent, LogLevel, NodeKind},
+    validation::ConstraintPolicy,
+    SimulationController,
+};
+use web_time::Instant;
+use wg_2024::{
+    config::{Client, Config, Drone, Server},
+    controller::{DroneCommand, DroneEvent},
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Ack, Packet, PacketType},
+};
+
+/// Ends of the channels of a node, held by the test in place of the node
+pub struct NodeEnds<C, E> {
+    /// Commands sent by the controller to the node
+    pub commands: Receiver<C>,
+    /// Events of the node, received by the controller
+    pub events: Sender<E>,
+    /// Packets delivered to the node, e.g. the shortcuts
+    pub packets: Receiver<Packet>,
+}
+
+impl<C, E> NodeEnds<C, E> {
+    /// Take the commands received so far
+    pub fn take_commands(&self) -> Vec<C> {
+        self.commands.try_iter().collect()
+    }
+}
+
+type ControllerEnds<C, E> = (Sender<C>, Receiver<E>, Sender<Packet>, Receiver<Packet>);
+
+/// Create the channels of a node, returning the controller and the node side
+fn channels<C, E>() -> (ControllerEnds<C, E>, NodeEnds<C, E>) {
+    let (command_send, command_recv) = crossbeam_channel::unbounded();
+    let (event_send, event_recv) = crossbeam_channel::unbounded();
+    let (packet_send, packet_recv) = crossbeam_channel::unbounded();
+    (
+        (command_send, event_recv, packet_send, packet_recv.clone()),
+        NodeEnds {
+            commands: command_recv,
+            events: event_send,
+            packets: packet_recv,
+        },
+    )
+}
+
+/// A controller with the node side of its channels
+pub struct TestNetwork {
+    pub controller: SimulationController,
+    pub drones: HashMap<NodeId, NodeEnds<DroneCommand, DroneEvent>>,
+    pub web_clients: HashMap<NodeId, NodeEnds<WebClientCommand, WebClientEvent>>,
+    pub chat_clients: HashMap<NodeId, NodeEnds<ChatClientCommand, ChatClientEvent>>,
+    pub servers: HashMap<NodeId, NodeEnds<ServerCommand, ServerEvent>>,
+}
+
+impl TestNetwork {
+    /// Build a controller for `config`, the clients in `chat_clients` are chat
+    /// clients and the others web clients
+    ///
+    /// # Panics
+    /// If the controller rejects the configuration
+    pub fn new(config: Config, chat_clients: &[NodeId]) -> Self {
+        Self::try_new(config, chat_clients).expect("valid configuration")
+    }
+
+    /// Build a controller for `config`, see `new`
+    ///
+    /// # Errors
+    /// If the controller rejects the configuration
+    pub fn try_new(config: Config, chat_clients: &[NodeId]) -> Result<Self, String> {
+        let mut drones_channels = HashMap::new();
+        let mut web_clients_channels = HashMap::new();
+        let mut chat_clients_channels = HashMap::new();
+        let mut servers_channels = HashMap::new();
+        let mut drones = HashMap::new();
+        let mut web_clients = HashMap::new();
+        let mut chat_clients_ends = HashMap::new();
+        let mut servers = HashMap::new();
+        for drone in &config.drone {
+            let (controller_side, node_side) = channels();
+            drones_channels.insert(drone.id, controller_side);
+            drones.insert(drone.id, node_side);
+        }
+        for client in &config.client {
+            if chat_clients.contains(&client.id) {
+                let (controller_side, node_side) = channels();
+                chat_clients_channels.insert(client.id, controller_side);
+                chat_clients_ends.insert(client.id, node_side);
+            } else {
+                let (controller_side, node_side) = channels();
+                web_clients_channels.insert(client.id, controller_side);
+                web_clients.insert(client.id, node_side);
+            }
+        }
+        for server in &config.server {
+            let (controller_side, node_side) = channels();
+            servers_channels.insert(server.id, controller_side);
+            servers.insert(server.id, node_side);
+        }
+        let controller = SimulationController::new(
+            drones_channels,
+            web_clients_channels,
+            chat_clients_channels,
+            servers_channels,
+            config.drone,
+            config.client,
+            config.server,
+            ConstraintPolicy::Strict,
+        )?;
+        Ok(TestNetwork {
+            controller,
+            drones,
+            web_clients,
+            chat_clients: chat_clients_ends,
+            servers,
+        })
+    }
+
+    pub fn drone(&self, id: NodeId) -> &NodeEnds<DroneCommand, DroneEvent> {
+        &self.drones[&id]
+    }
+
+    /// Drop the commands received so far by every node
+    pub fn clear_commands(&self) {
+        for drone in self.drones.values() {
+            drone.take_commands();
+        }
+        for client in self.web_clients.values() {
+            client.take_commands();
+        }
+        for client in self.chat_clients.values() {
+            client.take_commands();
+        }
+        for server in self.servers.values() {
+            server.take_commands();
+        }
+    }
+}
+
+/// Configuration of a small network:
+/// - drones 1-2-3-4 in a ring, with the chord 1-3
+/// - web client 10 connected to 1 and 3
+/// - chat client 11 connected to 3 and 4
+/// - server 20 connected to 1, 2 and 4
+pub fn sample_config() -> Config {
+    let drone = |id, connected_node_ids: &[NodeId]| Drone {
+        id,
+        connected_node_ids: connected_node_ids.to_vec(),
+        pdr: 0.0,
+    };
+    Config {
+        drone: vec![
+            drone(1, &[2, 4, 3, 10, 20]),
+            drone(2, &[1, 3, 20]),
+            drone(3, &[2, 4, 1, 10, 11]),
+            drone(4, &[3, 1, 11, 20]),
+        ],
+        client: vec![
+            Client {
+                id: 10,
+                connected_drone_ids: vec![1, 3],
+            },
+            Client {
+                id: 11,
+                connected_drone_ids: vec![3, 4],
+            },
+        ],
+        server: vec![Server {
+            id: 20,
+            connected_drone_ids: vec![2, 4, 1],
+        }],
+    }
+}
+
+/// Chat clients of `sample_config`
+pub const SAMPLE_CHAT_CLIENTS: [NodeId; 1] = [11];
+
+/// Build the controller of `sample_config`
+pub fn sample_network() -> TestNetwork {
+    TestNetwork::new(sample_config(), &SAMPLE_CHAT_CLIENTS)
+}
+
+/// Build an ack travelling along `hops`, at the hop `hop_index`
+pub fn ack(hops: &[NodeId], hop_index: usize) -> Packet {
+    Packet {
+        routing_header: SourceRoutingHeader {
+            hop_index,
+            hops: hops.to_vec(),
+        },
+        session_id: 7,
+        pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+    }
+}
+
+/// Sorted neighbors of `id` in the configuration exported by the controller,
+/// `None` if the node is not in the network
+pub fn neighbors(controller: &SimulationController, id: NodeId) -> Option<Vec<NodeId>> {
+    let config = controller.export_config();
+    let mut neighbors = config
+        .drone
+        .iter()
+        .find(|d| d.id == id)
+        .map(|d| d.connected_node_ids.clone())
+        .or_else(|| {
+            config
+                .client
+                .iter()
+                .find(|c| c.id == id)
+                .map(|c| c.connected_drone_ids.clone())
+        })
+        .or_else(|| {
+            config
+                .server
+                .iter()
+                .find(|s| s.id == id)
+                .map(|s| s.connected_drone_ids.clone())
+        })?;
+    neighbors.sort_unstable();
+    Some(neighbors)
+}
+
+/// Build an entry of the event log reported by `source`
+pub fn log_event(source: NodeId, kind: EventKind, level: LogLevel, text: &str) -> LogEvent {
+    LogEvent {
+        time: Instant::now(),
+        kind,
+        session_id: None,
+        source: Some(source),
+        node_kind: Some(NodeKind::Drone),
+        packet_type: None,
+        destination: None,
+        level,
+        text: text.to_string(),
+    }
+}
+
+/// Append `events` to the event log of `controller`, as another thread would
+pub fn push_events(controller: &mut SimulationController, events: Vec<LogEvent>) {
+    let producer = controller.log_producer();
+    for event in events {
+        producer.send(event).unwrap();
+    }
+    controller.process_events();
+}
+
+/// Nodes of `config` as sorted `(id, sorted neighbors, pdr)` tuples, clients
+/// and servers with a PDR of 0, to compare configurations
+pub fn config_nodes(config: &Config) -> Vec<(NodeId, Vec<NodeId>, f32)> {
+    let sorted = |ids: &[NodeId]| {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids
+    };
+    let mut nodes = config
+        .drone
+        .iter()
+        .map(|d| (d.id, sorted(&d.connected_node_ids), d.pdr))
+        .chain(
+            config
+                .client
+                .iter()
+                .map(|c| (c.id, sorted(&c.connected_drone_ids), 0.0)),
+        )
+        .chain(
+            config
+                .server
+                .iter()
+                .map(|s| (s.id, sorted(&s.connected_drone_ids), 0.0)),
+        )
+        .collect::<Vec<_>>();
+    nodes.sort_by_key(|(id, _, _)| *id);
+    nodes
+}
@@ -0,0 +1,260 @@
+//! Saving and loading the topology of the network
+
+mod support;
+
+use crossbeam_channel::TryRecvError;
+use simulation_controller::{
+    import::{self, ImportError, LoadError},
+    topology::TopologyOp,
+};
+use support::{config_nodes, sample_config, sample_network, TestNetwork};
+use wg_2024::{
+    config::{Config, Drone},
+    controller::DroneCommand,
+};
+
+/// Three drones connected in a triangle, without clients and servers, so that
+/// the nodes can be spawned without factories
+fn triangle_network() -> TestNetwork {
+    let drone = |id, connected_node_ids: &[u8]| Drone {
+        id,
+        connected_node_ids: connected_node_ids.to_vec(),
+        pdr: 0.1,
+    };
+    let config = Config {
+        drone: vec![drone(1, &[2, 3]), drone(2, &[1, 3]), drone(3, &[1, 2])],
+        client: Vec::new(),
+        server: Vec::new(),
+    };
+    TestNetwork::new(config, &[])
+}
+
+#[test]
+fn saved_topology_parses_back_to_the_same_configuration() {
+    let network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.toml");
+
+    network.controller.save_topology(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let saved: Config = toml::from_str(&content).unwrap();
+    assert_eq!(config_nodes(&saved), config_nodes(&sample_config()));
+    assert_eq!(
+        saved.client.iter().map(|c| c.id).collect::<Vec<_>>(),
+        [10, 11]
+    );
+    assert_eq!(saved.server.iter().map(|s| s.id).collect::<Vec<_>>(), [20]);
+}
+
+#[test]
+fn saving_to_an_unwritable_path_is_an_io_error() {
+    let network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    // A file can't be used as a folder
+    let blocker = dir.path().join("blocker");
+    std::fs::write(&blocker, "").unwrap();
+
+    let error = network
+        .controller
+        .save_topology(&blocker.join("topology.toml"))
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        simulation_controller::import::SaveError::Io(_)
+    ));
+}
+
+#[test]
+fn exported_configuration_follows_the_runtime_changes() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    controller.apply_op(TopologyOp::Crash(2)).unwrap();
+    controller.apply_op(TopologyOp::Disconnect(11, 3)).unwrap();
+    controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 4,
+            pdr: 0.25,
+        })
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.toml");
+
+    controller.save_topology(&path).unwrap();
+
+    let saved: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        config_nodes(&saved),
+        config_nodes(&controller.export_config())
+    );
+    assert_eq!(
+        config_nodes(&saved),
+        [
+            (1, vec![3, 4, 10, 20], 0.0),
+            (3, vec![1, 4, 10], 0.0),
+            (4, vec![1, 3, 11, 20], 0.25),
+            (10, vec![1, 3], 0.0),
+            (11, vec![4], 0.0),
+            (20, vec![1, 4], 0.0),
+        ]
+    );
+}
+
+#[test]
+fn loading_a_saved_topology_restores_the_network() {
+    let mut network = triangle_network();
+    let controller = &mut network.controller;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.toml");
+    controller.save_topology(&path).unwrap();
+    let saved = controller.topology_snapshot();
+
+    controller.apply_op(TopologyOp::Disconnect(1, 2)).unwrap();
+    controller
+        .apply_op(TopologyOp::SetPdr {
+            drone_id: 3,
+            pdr: 0.5,
+        })
+        .unwrap();
+    assert_ne!(controller.topology_snapshot(), saved);
+
+    controller.load_topology(&path).unwrap();
+
+    let loaded = controller.topology_snapshot();
+    assert_eq!(loaded.drones.keys().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(loaded, saved);
+}
+
+#[test]
+fn a_failed_load_leaves_the_network_intact() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.toml");
+    std::fs::write(&path, "drone = 3").unwrap();
+    let before = controller.topology_snapshot();
+
+    let error = controller.load_topology(&path).unwrap_err();
+    assert!(matches!(error, LoadError::Parse(_)));
+    let error = controller
+        .load_topology(&dir.path().join("missing.toml"))
+        .unwrap_err();
+    assert!(matches!(error, LoadError::Read(_)));
+
+    assert_eq!(controller.topology_snapshot(), before);
+    assert_eq!(
+        config_nodes(&controller.export_config()),
+        config_nodes(&sample_config())
+    );
+}
+
+#[test]
+fn json_topology_parses_back_to_the_same_configuration() {
+    let network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.json");
+
+    network.controller.export_json_topology(&path).unwrap();
+
+    let topology = import::parse_json_topology(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        config_nodes(&topology.config),
+        config_nodes(&sample_config())
+    );
+    assert_eq!(topology.chat_clients.into_iter().collect::<Vec<_>>(), [11]);
+}
+
+#[test]
+fn importing_an_exported_json_topology_restores_the_network() {
+    let mut network = triangle_network();
+    let controller = &mut network.controller;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.json");
+    controller.export_json_topology(&path).unwrap();
+    let saved = controller.topology_snapshot();
+
+    controller.apply_op(TopologyOp::Disconnect(2, 3)).unwrap();
+    controller.import_json_topology(&path).unwrap();
+
+    assert_eq!(controller.topology_snapshot(), saved);
+}
+
+#[test]
+fn json_import_errors_tell_parse_errors_from_invalid_topologies() {
+    let mut network = sample_network();
+    let controller = &mut network.controller;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.json");
+    let before = controller.topology_snapshot();
+    let mut import_content = |content: &str| {
+        std::fs::write(&path, content).unwrap();
+        controller.import_json_topology(&path).unwrap_err()
+    };
+
+    assert!(matches!(
+        import_content("{\"nodes\": ["),
+        ImportError::Parse(_)
+    ));
+    assert!(matches!(
+        import_content(r#"{"nodes": [{"id": 1, "type": "Router"}], "edges": []}"#),
+        ImportError::Parse(_)
+    ));
+    let two_clients = r#"{"nodes": [
+        {"id": 10, "type": "WebClient"}, {"id": 11, "type": "ChatClient"}
+    ], "edges": [[10, 11]]}"#;
+    assert!(matches!(
+        import_content(two_clients),
+        ImportError::InvalidTopology(_)
+    ));
+    assert!(matches!(
+        import_content(r#"{"nodes": [{"id": 1, "type": "Drone"}], "edges": [[1, 2]]}"#),
+        ImportError::InvalidTopology(_)
+    ));
+    let error = controller
+        .import_json_topology(&dir.path().join("missing.json"))
+        .unwrap_err();
+    assert!(matches!(error, ImportError::Read(_)));
+
+    assert_eq!(controller.topology_snapshot(), before);
+}
+
+#[test]
+fn loading_a_topology_stops_the_old_nodes() {
+    let mut network = sample_network();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("topology.toml");
+    triangle_network().controller.save_topology(&path).unwrap();
+
+    network.controller.load_topology(&path).unwrap();
+
+    for (id, drone) in &network.drones {
+        let commands = drone.take_commands();
+        assert!(
+            matches!(commands.last(), Some(DroneCommand::Crash)),
+            "drone {id} got {commands:?}"
+        );
+    }
+    // Clients and servers are cut off from every neighbor, then their channels close
+    assert_eq!(network.web_clients[&10].take_commands().len(), 2);
+    assert_eq!(network.chat_clients[&11].take_commands().len(), 2);
+    assert_eq!(network.servers[&20].take_commands().len(), 3);
+    assert!(matches!(
+        network.chat_clients[&11].commands.try_recv(),
+        Err(TryRecvError::Disconnected)
+    ));
+    assert!(matches!(
+        network.servers[&20].commands.try_recv(),
+        Err(TryRecvError::Disconnected)
+    ));
+    assert_eq!(
+        network
+            .controller
+            .topology_snapshot()
+            .drones
+            .keys()
+            .copied()
+            .collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+}